@@ -7,10 +7,11 @@ use rust_sandbox::engine::platform;
 
 pub struct GLTFModel {
     engine_runtime: platform::EngineRuntime,
-    render_pipeline_handle: handle::ResourceHandle,
+    //one pipeline per topology and index format used by the meshes of the file
+    render_pipeline_handles: Vec<(graphics::bindings::PipelineVariant, handle::ResourceHandle)>,
     camera: graphics::camera::Camera,
     uniform_buffer: wgpu::Buffer,
-    uniform_bind_group: wgpu::BindGroup,
+    uniform_bind_group_handle: handle::ResourceHandle,
     size: winit::dpi::PhysicalSize<u32>,
     color: f64,
     camera_controller: graphics::camera::CameraControllerFPS,
@@ -29,7 +30,7 @@ impl platform::Application for GLTFModel {
         let color = 0.0;
         let gpu_interfaces = &engine_runtime.gpu_interfaces;
 
-        let camera = graphics::camera::Camera {
+        let mut camera = graphics::camera::Camera {
             // position the camera one unit up and 2 units back
             // +z is out of the screen
             eye: (3.0, 1.0, 2.0).into(),
@@ -49,7 +50,7 @@ impl platform::Application for GLTFModel {
         per_frame_data.update_view_proj(&camera);
 
         let uniform_buffer = gpu_interfaces.device.create_buffer_with_data(
-            bytemuck::cast_slice(&[per_frame_data]),
+            per_frame_data.as_bytes(),
             wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
         );
 
@@ -61,43 +62,60 @@ impl platform::Application for GLTFModel {
 
         let default_depth_format = wgpu::TextureFormat::Depth32Float;
 
-        let render_pipeline_handle = engine_runtime
+        let uniform_bind_group_handle = engine_runtime
             .resource_managers
-            .pipeline_manager
-            .load_pipeline(
-                "resources/examples/gltf-model/gltf_model.pipeline",
-                &mut engine_runtime.resource_managers.shader_manager,
+            .bind_group_manager
+            .create_bind_group(
+                "uniform_bind_group",
+                &layout_handle,
+                &[graphics::bind_group::BindGroupEntry {
+                    slot: graphics::bind_group::BindingSlot::Name("frame_data"),
+                    resource: graphics::bind_group::BindGroupResource::Buffer {
+                        buffer: &uniform_buffer,
+                        range: 0..per_frame_data.as_bytes().len() as wgpu::BufferAddress,
+                    },
+                }],
+                &engine_runtime.resource_managers.pipeline_manager,
                 &engine_runtime.gpu_interfaces,
-                default_depth_format,
             )
-            .await;
-
-        let bg_layout = engine_runtime
-            .resource_managers
-            .pipeline_manager
-            .get_bind_group_from_handle(layout_handle);
-
-        let uniform_bind_group =
-            gpu_interfaces
-                .device
-                .create_bind_group(&wgpu::BindGroupDescriptor {
-                    layout: &bg_layout.unwrap(),
-                    bindings: &[wgpu::Binding {
-                        binding: 0,
-                        resource: wgpu::BindingResource::Buffer {
-                            buffer: &uniform_buffer,
-                            // FYI: you can share a single buffer between bindings.
-                            range: 0..std::mem::size_of_val(&per_frame_data) as wgpu::BufferAddress,
-                        },
-                    }],
-                    label: Some("uniform_bind_group"),
-                });
+            .unwrap();
 
         let gltf_file = graphics::model::load_gltf_file(
             "resources/examples/gltf-model/Suzanne.gltf",
             &gpu_interfaces,
+            &mut engine_runtime.resource_managers.upload_manager,
+            &mut engine_runtime.resource_managers.texture_manager,
+            &Default::default(),
         )
-        .await;
+        .await
+        .unwrap();
+
+        let mut render_pipeline_handles = Vec::new();
+        for variant in gltf_file.get_pipeline_variants() {
+            let pipeline_handle = engine_runtime
+                .resource_managers
+                .pipeline_manager
+                .load_pipeline_variant(
+                    "resources/examples/gltf-model/gltf_model.pipeline",
+                    variant,
+                    &mut engine_runtime.resource_managers.shader_manager,
+                    &engine_runtime.gpu_interfaces,
+                    default_depth_format,
+                )
+                .await;
+            render_pipeline_handles.push((variant, pipeline_handle));
+        }
+
+        //start with the whole model on screen
+        let model_bounds: Vec<graphics::bounds::Bounds> =
+            gltf_file.models.iter().map(|model| model.bounds).collect();
+        let scene_graph = &gltf_file.scene_graph;
+        if let Some(scene) = scene_graph.get_active_scene() {
+            let scene_bounds = scene_graph.get_scene_bounds(scene, &model_bounds[..]);
+            if !scene_bounds.is_empty() {
+                camera.frame_bounding_sphere(&scene_bounds.sphere);
+            }
+        }
 
         let depth_texture = graphics::texture::Texture::create_depth_texture(
             &engine_runtime.gpu_interfaces.device,
@@ -107,10 +125,10 @@ impl platform::Application for GLTFModel {
 
         Self {
             engine_runtime,
-            render_pipeline_handle,
+            render_pipeline_handles,
             camera,
             uniform_buffer,
-            uniform_bind_group,
+            uniform_bind_group_handle,
             size,
             color,
             camera_controller,
@@ -151,22 +169,11 @@ impl platform::Application for GLTFModel {
                 label: Some("update encoder"),
             });
 
-        let staging_buffer = self
-            .engine_runtime
-            .gpu_interfaces
-            .device
-            .create_buffer_with_data(
-                bytemuck::cast_slice(&[self.per_frame_data]),
-                wgpu::BufferUsage::COPY_SRC,
-            );
-
-        encoder.copy_buffer_to_buffer(
-            &staging_buffer,
-            0,
-            &self.uniform_buffer,
-            0,
-            std::mem::size_of::<graphics::FrameData>() as wgpu::BufferAddress,
-        );
+        let upload_manager = &mut self.engine_runtime.resource_managers.upload_manager;
+        upload_manager.begin_frame();
+        let mut uploads = upload_manager.begin_batch();
+        uploads.write_buffer(&self.uniform_buffer, 0, self.per_frame_data.as_bytes());
+        uploads.flush(&self.engine_runtime.gpu_interfaces.device, &mut encoder);
 
         command_buffers.push(encoder.finish());
     }
@@ -215,40 +222,30 @@ impl platform::Application for GLTFModel {
                 depth_stencil_attachment,
             });
 
-            let render_pipeline = self
+            let uniform_bind_group = self
                 .engine_runtime
                 .resource_managers
-                .pipeline_manager
-                .get_pipeline_from_handle(&self.render_pipeline_handle);
-            render_pass.set_pipeline(&render_pipeline.unwrap());
-            render_pass.set_bind_group(0, &self.uniform_bind_group, &[]);
-
-            let model = self.gltf_file.models.get(0).unwrap();
-            let mesh = model.meshes.get(0).unwrap();
-            let pos_mapper =
-                mesh.get_buffer_from_semantic(graphics::model::MeshBufferSemantic::Positions);
-            let pos_idx = pos_mapper.buffer_idx;
-            let pos_buff = self.gltf_file.buffers.get(&pos_idx).unwrap();
-
-            let n_mapper =
-                mesh.get_buffer_from_semantic(graphics::model::MeshBufferSemantic::Normals);
-            let n_idx = n_mapper.buffer_idx;
-            let n_buff = self.gltf_file.buffers.get(&n_idx).unwrap();
-
-            let mut idx_count = 0;
-            match &mesh.index_buffer {
-                Some(idx_buff_map) => {
-                    let idx = idx_buff_map.buffer_idx;
-                    let idx_buff = self.gltf_file.buffers.get(&idx).unwrap();
-                    render_pass.set_index_buffer(idx_buff, idx_buff_map.offset as u64, 0);
-                    idx_count = idx_buff_map.count;
+                .bind_group_manager
+                .get_bind_group_from_handle(&self.uniform_bind_group_handle)
+                .unwrap();
+            render_pass.set_bind_group(0, uniform_bind_group, &[]);
+
+            for (variant, pipeline_handle) in self.render_pipeline_handles.iter() {
+                let pipeline_manager = &self.engine_runtime.resource_managers.pipeline_manager;
+                let render_pipeline = pipeline_manager
+                    .get_pipeline_from_handle(pipeline_handle)
+                    .unwrap();
+                let vertex_streams = pipeline_manager
+                    .get_pipeline_vertex_streams_from_handle(pipeline_handle)
+                    .unwrap();
+                render_pass.set_pipeline(render_pipeline);
+                if let Err(message) =
+                    self.gltf_file
+                        .draw_all(*variant, &mut render_pass, vertex_streams)
+                {
+                    platform::core::to_console(&message[..]);
                 }
-                None => {}
             }
-
-            render_pass.set_vertex_buffer(0, pos_buff, pos_mapper.offset as u64, 0);
-            render_pass.set_vertex_buffer(1, n_buff, n_mapper.offset as u64, 0);
-            render_pass.draw_indexed(0..idx_count, 0, 0..1);
         }
 
         self.color += 0.001;
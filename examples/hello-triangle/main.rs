@@ -12,7 +12,7 @@ pub struct HelloTriangle {
     render_pipeline_handle: handle::ResourceHandle,
     camera: graphics::camera::Camera,
     uniform_buffer: wgpu::Buffer,
-    uniform_bind_group: wgpu::BindGroup,
+    uniform_bind_group_handle: handle::ResourceHandle,
     size: winit::dpi::PhysicalSize<u32>,
     color: f64,
     camera_controller: graphics::camera::CameraControllerFPS,
@@ -49,7 +49,7 @@ impl platform::Application for HelloTriangle {
         per_frame_data.update_view_proj(&camera);
 
         let uniform_buffer = gpu_interfaces.device.create_buffer_with_data(
-            bytemuck::cast_slice(&[per_frame_data]),
+            per_frame_data.as_bytes(),
             wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
         );
 
@@ -70,26 +70,23 @@ impl platform::Application for HelloTriangle {
             )
             .await;
 
-        let bg_layout = engine_runtime
+        let uniform_bind_group_handle = engine_runtime
             .resource_managers
-            .pipeline_manager
-            .get_bind_group_from_handle(layout_handle);
-
-        let uniform_bind_group =
-            gpu_interfaces
-                .device
-                .create_bind_group(&wgpu::BindGroupDescriptor {
-                    layout: &bg_layout.unwrap(),
-                    bindings: &[wgpu::Binding {
-                        binding: 0,
-                        resource: wgpu::BindingResource::Buffer {
-                            buffer: &uniform_buffer,
-                            // FYI: you can share a single buffer between bindings.
-                            range: 0..std::mem::size_of_val(&per_frame_data) as wgpu::BufferAddress,
-                        },
-                    }],
-                    label: Some("uniform_bind_group"),
-                });
+            .bind_group_manager
+            .create_bind_group(
+                "uniform_bind_group",
+                &layout_handle,
+                &[graphics::bind_group::BindGroupEntry {
+                    slot: graphics::bind_group::BindingSlot::Name("frame_data"),
+                    resource: graphics::bind_group::BindGroupResource::Buffer {
+                        buffer: &uniform_buffer,
+                        range: 0..per_frame_data.as_bytes().len() as wgpu::BufferAddress,
+                    },
+                }],
+                &engine_runtime.resource_managers.pipeline_manager,
+                &engine_runtime.gpu_interfaces,
+            )
+            .unwrap();
 
 
         Self {
@@ -97,7 +94,7 @@ impl platform::Application for HelloTriangle {
             render_pipeline_handle,
             camera,
             uniform_buffer,
-            uniform_bind_group,
+            uniform_bind_group_handle,
             size,
             color,
             camera_controller,
@@ -137,22 +134,11 @@ impl platform::Application for HelloTriangle {
                 label: Some("update encoder"),
             });
 
-        let staging_buffer = self
-            .engine_runtime
-            .gpu_interfaces
-            .device
-            .create_buffer_with_data(
-                bytemuck::cast_slice(&[self.per_frame_data]),
-                wgpu::BufferUsage::COPY_SRC,
-            );
-
-        encoder.copy_buffer_to_buffer(
-            &staging_buffer,
-            0,
-            &self.uniform_buffer,
-            0,
-            std::mem::size_of::<graphics::FrameData>() as wgpu::BufferAddress,
-        );
+        let upload_manager = &mut self.engine_runtime.resource_managers.upload_manager;
+        upload_manager.begin_frame();
+        let mut uploads = upload_manager.begin_batch();
+        uploads.write_buffer(&self.uniform_buffer, 0, self.per_frame_data.as_bytes());
+        uploads.flush(&self.engine_runtime.gpu_interfaces.device, &mut encoder);
 
         command_buffers.push(encoder.finish());
     }
@@ -198,7 +184,13 @@ impl platform::Application for HelloTriangle {
                 .pipeline_manager
                 .get_pipeline_from_handle(&self.render_pipeline_handle);
             render_pass.set_pipeline(&render_pipeline.unwrap());
-            render_pass.set_bind_group(0, &self.uniform_bind_group, &[]);
+            let uniform_bind_group = self
+                .engine_runtime
+                .resource_managers
+                .bind_group_manager
+                .get_bind_group_from_handle(&self.uniform_bind_group_handle)
+                .unwrap();
+            render_pass.set_bind_group(0, uniform_bind_group, &[]);
             render_pass.draw(0..3, 0..1);
         }
         self.color += 0.001;
@@ -0,0 +1,21 @@
+#version 450
+
+layout(location=0) in vec3 a_position;
+layout(location=1) in vec3 a_normal;
+
+layout(location=0) out vec3 v_color;
+
+layout(set=0, binding=0)
+uniform Uniforms {
+    mat4 u_view_proj;
+};
+
+layout(set=0, binding=1)
+uniform ObjectUniforms {
+    mat4 u_world;
+};
+
+void main() {
+    v_color = normalize(mat3(u_world) * a_normal);
+    gl_Position = u_view_proj * u_world * vec4(a_position, 1.0);
+}
@@ -0,0 +1,31 @@
+#[cfg(not(target_arch = "wasm32"))]
+use rust_sandbox::engine::graphics::lint;
+
+//usage: cargo run --bin asset_linter -- [resource_root]
+//must run from the same working directory the sandbox runs from, since assets
+//reference each other with paths relative to it
+#[cfg(not(target_arch = "wasm32"))]
+fn main() {
+    let root = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| String::from("resources"));
+
+    let report = lint::lint_resource_root(&root[..]);
+    for issue in report.issues.iter() {
+        println!("[Error]: {}: {}", issue.file, issue.message);
+    }
+    println!(
+        "checked {} files under {}, found {} issues",
+        report.files_checked,
+        root,
+        report.issues.len()
+    );
+
+    if !report.issues.is_empty() {
+        std::process::exit(1);
+    }
+}
+
+//the linter reads the file system, there is nothing to run on the web
+#[cfg(target_arch = "wasm32")]
+fn main() {}
@@ -1,31 +1,101 @@
 pub mod shader;
 pub mod camera;
+pub mod animation;
 pub mod api;
 pub mod bindings;
+pub mod bind_group;
+pub mod block_layout;
+pub mod bounds;
+pub mod material;
+pub mod mipmap;
 pub mod model;
+pub mod morph;
+pub mod scene;
 pub mod texture;
+pub mod uniform;
+pub mod upload;
+//walks the resource folders with std::fs, there is nothing to walk on the web
+#[cfg(not(target_arch = "wasm32"))]
+pub mod lint;
 
 
 
-#[repr(C)] // We need this for Rust to store our data correctly for the shaders
-#[derive(Debug, Copy, Clone)] // This is so we can store this in a buffer
+//data of the Uniforms block of the shaders, the bytes are laid out by block_layout
+//so the std140 padding stays right when fields get added
+#[derive(Debug, Clone)]
 pub struct FrameData {
-    view_proj: cgmath::Matrix4<f32>,
+    layout: block_layout::BlockLayout,
+    data: Vec<u8>,
 }
 
 impl FrameData {
+    pub fn get_layout() -> block_layout::BlockLayout {
+        block_layout::BlockLayoutBuilder::new(block_layout::LayoutRule::Std140)
+            .field("u_view_proj", block_layout::FieldType::Mat4)
+            .build()
+    }
+
     pub fn new() -> Self {
         use cgmath::SquareMatrix;
-        Self {
-            view_proj: cgmath::Matrix4::identity(),
-        }
+        let layout = Self::get_layout();
+        let data = layout.create_data();
+        let mut frame_data = Self { layout, data };
+        frame_data.set_view_proj(cgmath::Matrix4::identity());
+        frame_data
     }
 
     pub fn update_view_proj(&mut self, camera: &camera::Camera) {
-        self.view_proj = camera.build_view_projection_matrix();
+        self.set_view_proj(camera.build_view_projection_matrix());
+    }
+
+    //the field is in the layout we build, setting it cannot fail
+    fn set_view_proj(&mut self, view_proj: cgmath::Matrix4<f32>) {
+        self.layout
+            .set(
+                &mut self.data,
+                "u_view_proj",
+                block_layout::BlockValue::Mat4(view_proj),
+            )
+            .unwrap();
     }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.data[..]
+    }
+}
+
+//per draw data of the ObjectUniforms block, the world matrix of the scene node being
+//rendered. Laid out by block_layout like FrameData, one can be refilled for every draw
+#[derive(Debug, Clone)]
+pub struct ObjectData {
+    layout: block_layout::BlockLayout,
+    data: Vec<u8>,
 }
 
-unsafe impl bytemuck::Pod for FrameData {}
-unsafe impl bytemuck::Zeroable for FrameData {}
+impl ObjectData {
+    pub fn get_layout() -> block_layout::BlockLayout {
+        block_layout::BlockLayoutBuilder::new(block_layout::LayoutRule::Std140)
+            .field("u_world", block_layout::FieldType::Mat4)
+            .build()
+    }
+
+    pub fn new(world: cgmath::Matrix4<f32>) -> Self {
+        let layout = Self::get_layout();
+        let data = layout.create_data();
+        let mut object_data = Self { layout, data };
+        object_data.set_world(world);
+        object_data
+    }
+
+    //the field is in the layout we build, setting it cannot fail
+    pub fn set_world(&mut self, world: cgmath::Matrix4<f32>) {
+        self.layout
+            .set(&mut self.data, "u_world", block_layout::BlockValue::Mat4(world))
+            .unwrap();
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.data[..]
+    }
+}
 
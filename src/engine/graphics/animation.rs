@@ -0,0 +1,287 @@
+use super::scene;
+use cgmath::SquareMatrix;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AnimationProperty {
+    Translation,
+    Rotation,
+    Scale,
+    //morph target weights, one value per target of the mesh of the node
+    Weights,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Interpolation {
+    Step,
+    Linear,
+    //hermite spline, every keyframe stores in tangent, value and out tangent
+    CubicSpline,
+}
+
+//one track of the animation, keyframe values are stored flat so the same code
+//samples vectors, quaternions (x,y,z,w) and any number of weights
+#[derive(Clone, Debug, PartialEq)]
+pub struct AnimationChannel {
+    pub node: usize,
+    pub property: AnimationProperty,
+    pub interpolation: Interpolation,
+    pub times: Vec<f32>,
+    pub values: Vec<f32>,
+}
+
+impl AnimationChannel {
+    pub fn new(
+        node: usize,
+        property: AnimationProperty,
+        interpolation: Interpolation,
+        times: Vec<f32>,
+        values: Vec<f32>,
+    ) -> Result<Self, String> {
+        if times.is_empty() {
+            return Err(format!(
+                "animation channel of node {} has no keyframes",
+                node
+            ));
+        }
+        if times.windows(2).any(|pair| pair[0] >= pair[1]) {
+            return Err(format!(
+                "animation channel of node {} keyframe times are not increasing",
+                node
+            ));
+        }
+        let values_per_key = match interpolation {
+            Interpolation::CubicSpline => times.len() * 3,
+            _ => times.len(),
+        };
+        let expected_components = match property {
+            AnimationProperty::Translation | AnimationProperty::Scale => Some(3),
+            AnimationProperty::Rotation => Some(4),
+            AnimationProperty::Weights => None,
+        };
+        let valid = match expected_components {
+            Some(components) => values.len() == values_per_key * components,
+            None => {
+                let components = values.len() / values_per_key;
+                components > 0 && components * values_per_key == values.len()
+            }
+        };
+        if !valid {
+            return Err(format!(
+                "animation channel of node {} has {} values for {} keyframes of {:?}",
+                node,
+                values.len(),
+                times.len(),
+                property
+            ));
+        }
+        Ok(Self {
+            node,
+            property,
+            interpolation,
+            times,
+            values,
+        })
+    }
+
+    //number of floats of a single sampled value
+    pub fn get_component_count(&self) -> usize {
+        match self.interpolation {
+            Interpolation::CubicSpline => self.values.len() / (self.times.len() * 3),
+            _ => self.values.len() / self.times.len(),
+        }
+    }
+
+    pub fn get_duration(&self) -> f32 {
+        *self.times.last().unwrap()
+    }
+
+    //part is 0 for the in tangent, 1 for the value and 2 for the out tangent of a
+    //cubic spline keyframe, it is ignored by the other interpolations
+    fn get_key(&self, key: usize, part: usize) -> &[f32] {
+        let count = self.get_component_count();
+        let start = match self.interpolation {
+            Interpolation::CubicSpline => (key * 3 + part) * count,
+            _ => key * count,
+        };
+        &self.values[start..start + count]
+    }
+
+    //times outside of the keyframes are clamped to the first and last one
+    pub fn sample(&self, time: f32) -> Vec<f32> {
+        let last = self.times.len() - 1;
+        if time <= self.times[0] {
+            return self.get_key(0, 1).to_vec();
+        }
+        if time >= self.times[last] {
+            return self.get_key(last, 1).to_vec();
+        }
+        let key = self.times.iter().rposition(|t| *t <= time).unwrap();
+        let delta = self.times[key + 1] - self.times[key];
+        let amount = (time - self.times[key]) / delta;
+
+        match self.interpolation {
+            Interpolation::Step => self.get_key(key, 1).to_vec(),
+            Interpolation::Linear => {
+                let start = self.get_key(key, 1);
+                let end = self.get_key(key + 1, 1);
+                if self.property == AnimationProperty::Rotation {
+                    let start = to_quaternion(start);
+                    let mut end = to_quaternion(end);
+                    //going through the shortest arc
+                    if cgmath::InnerSpace::dot(start, end) < 0.0 {
+                        end = -end;
+                    }
+                    from_quaternion(start.slerp(end, amount))
+                } else {
+                    start
+                        .iter()
+                        .zip(end.iter())
+                        .map(|(a, b)| a + (b - a) * amount)
+                        .collect()
+                }
+            }
+            Interpolation::CubicSpline => {
+                let amount2 = amount * amount;
+                let amount3 = amount2 * amount;
+                let start_weight = 2.0 * amount3 - 3.0 * amount2 + 1.0;
+                let out_weight = (amount3 - 2.0 * amount2 + amount) * delta;
+                let end_weight = -2.0 * amount3 + 3.0 * amount2;
+                let in_weight = (amount3 - amount2) * delta;
+
+                let start = self.get_key(key, 1);
+                let start_out = self.get_key(key, 2);
+                let end_in = self.get_key(key + 1, 0);
+                let end = self.get_key(key + 1, 1);
+                let value: Vec<f32> = (0..start.len())
+                    .map(|i| {
+                        start[i] * start_weight
+                            + start_out[i] * out_weight
+                            + end[i] * end_weight
+                            + end_in[i] * in_weight
+                    })
+                    .collect();
+                if self.property == AnimationProperty::Rotation {
+                    from_quaternion(cgmath::InnerSpace::normalize(to_quaternion(&value[..])))
+                } else {
+                    value
+                }
+            }
+        }
+    }
+}
+
+fn to_quaternion(value: &[f32]) -> cgmath::Quaternion<f32> {
+    cgmath::Quaternion::new(value[3], value[0], value[1], value[2])
+}
+
+fn from_quaternion(quaternion: cgmath::Quaternion<f32>) -> Vec<f32> {
+    vec![quaternion.v.x, quaternion.v.y, quaternion.v.z, quaternion.s]
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct AnimationClip {
+    pub name: Option<String>,
+    pub channels: Vec<AnimationChannel>,
+}
+
+impl AnimationClip {
+    pub fn get_duration(&self) -> f32 {
+        self.channels
+            .iter()
+            .map(|channel| channel.get_duration())
+            .fold(0.0, f32::max)
+    }
+
+    //writes the state of the clip at the given time in the animated nodes, world
+    //matrices need to be updated afterwards. Animated nodes are switched to a
+    //decomposed transform, nodes with a matrix start from the identity
+    pub fn sample(&self, time: f32, scene_graph: &mut scene::SceneGraph) {
+        for channel in self.channels.iter() {
+            let node = match scene_graph.nodes.get_mut(channel.node) {
+                Some(node) => node,
+                None => continue,
+            };
+            let value = channel.sample(time);
+            let (mut translation, mut rotation, mut scale) = match node.transform {
+                scene::NodeTransform::Decomposed {
+                    translation,
+                    rotation,
+                    scale,
+                } => (translation, rotation, scale),
+                scene::NodeTransform::Matrix(_) => (
+                    cgmath::Vector3::new(0.0, 0.0, 0.0),
+                    cgmath::Quaternion::new(1.0, 0.0, 0.0, 0.0),
+                    cgmath::Vector3::new(1.0, 1.0, 1.0),
+                ),
+            };
+            match channel.property {
+                AnimationProperty::Translation => {
+                    translation = cgmath::Vector3::new(value[0], value[1], value[2])
+                }
+                AnimationProperty::Rotation => rotation = to_quaternion(&value[..]),
+                AnimationProperty::Scale => {
+                    scale = cgmath::Vector3::new(value[0], value[1], value[2])
+                }
+                AnimationProperty::Weights => {
+                    node.weights = value;
+                    continue;
+                }
+            }
+            node.transform = scene::NodeTransform::Decomposed {
+                translation,
+                rotation,
+                scale,
+            };
+        }
+    }
+}
+
+//column major, laid out as a mat4 array in both uniform and storage buffers
+pub type JointMatrix = [[f32; 4]; 4];
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Skin {
+    pub name: Option<String>,
+    //scene graph nodes acting as joints, the vertex joint indices point in here
+    pub joints: Vec<usize>,
+    //one per joint, brings the mesh in the space of the joint at bind time
+    pub inverse_bind_matrices: Vec<cgmath::Matrix4<f32>>,
+    pub skeleton: Option<usize>,
+}
+
+impl Skin {
+    //the matrices a vertex shader needs to skin the mesh of the given node, world
+    //matrices of the scene graph must be up to date. The transform of the skinned
+    //node is removed since it is applied by the object data already
+    pub fn get_joint_palette(
+        &self,
+        scene_graph: &scene::SceneGraph,
+        skinned_node: usize,
+    ) -> Vec<JointMatrix> {
+        let inverse_world = scene_graph.nodes[skinned_node]
+            .world_matrix
+            .invert()
+            .unwrap_or_else(cgmath::Matrix4::identity);
+        self.joints
+            .iter()
+            .zip(self.inverse_bind_matrices.iter())
+            .map(|(joint, inverse_bind)| {
+                let matrix = inverse_world * scene_graph.nodes[*joint].world_matrix * inverse_bind;
+                matrix.into()
+            })
+            .collect()
+    }
+}
+
+//evaluates a clip at the given time straight into a joint palette
+pub fn sample_joint_palette(
+    clip: &AnimationClip,
+    time: f32,
+    skin: &Skin,
+    skinned_node: usize,
+    scene_graph: &mut scene::SceneGraph,
+) -> Vec<JointMatrix> {
+    clip.sample(time, scene_graph);
+    scene_graph.update_world_matrices();
+    skin.get_joint_palette(scene_graph, skinned_node)
+}
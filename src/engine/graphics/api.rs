@@ -4,6 +4,9 @@ use winit::{
 
 use super::shader::ShaderManager;
 use super::bindings::PipelineManager;
+use super::bind_group::BindGroupManager;
+use super::texture::TextureManager;
+use super::upload::UploadManager;
 
 pub struct GPUInterfaces {
     pub _instance: wgpu::Instance,
@@ -20,6 +23,9 @@ pub struct ResourceManagers
 {
     pub shader_manager : ShaderManager,
     pub pipeline_manager : PipelineManager, 
+    pub bind_group_manager : BindGroupManager,
+    pub upload_manager : UploadManager,
+    pub texture_manager : TextureManager,
 }
 
 impl GPUInterfaces {
@@ -0,0 +1,184 @@
+use std::collections::HashMap;
+
+use super::super::handle;
+use super::api;
+use super::bindings;
+
+#[derive(Clone, Copy, Debug)]
+pub enum BindingSlot<'a> {
+    Index(u32),
+    //name declared in the binding group layout file
+    Name(&'a str),
+}
+
+pub enum BindGroupResource<'a> {
+    Buffer {
+        buffer: &'a wgpu::Buffer,
+        range: std::ops::Range<wgpu::BufferAddress>,
+    },
+    TextureView(&'a wgpu::TextureView),
+    Sampler(&'a wgpu::Sampler),
+}
+
+//what kind of resource we are binding, split from the resource itself so the
+//validation can run without any gpu object around
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BindGroupResourceKind {
+    Buffer {
+        offset: wgpu::BufferAddress,
+        size: wgpu::BufferAddress,
+    },
+    TextureView,
+    Sampler,
+}
+
+impl<'a> BindGroupResource<'a> {
+    pub fn kind(&self) -> BindGroupResourceKind {
+        match self {
+            BindGroupResource::Buffer { range, .. } => BindGroupResourceKind::Buffer {
+                offset: range.start,
+                size: range.end.saturating_sub(range.start),
+            },
+            BindGroupResource::TextureView(_) => BindGroupResourceKind::TextureView,
+            BindGroupResource::Sampler(_) => BindGroupResourceKind::Sampler,
+        }
+    }
+}
+
+pub struct BindGroupEntry<'a> {
+    pub slot: BindingSlot<'a>,
+    pub resource: BindGroupResource<'a>,
+}
+
+//resolves the requested slots against the layout and makes sure every resource is
+//of the type the layout expects, returns the binding index of each request in order
+pub fn resolve_bind_group_slots(
+    description: &bindings::BindGroupLayoutDescription,
+    requests: &[(BindingSlot, BindGroupResourceKind)],
+) -> Result<Vec<u32>, String> {
+    let mut resolved: Vec<u32> = Vec::with_capacity(requests.len());
+    for (slot, kind) in requests.iter() {
+        let binding = match slot {
+            BindingSlot::Index(index) => *index,
+            BindingSlot::Name(name) => match description.names.get(*name) {
+                Some(index) => *index,
+                None => return Err(format!("layout has no binding named {}", name)),
+            },
+        };
+
+        let entry = match description.entries.iter().find(|e| e.binding == binding) {
+            Some(entry) => entry,
+            None => return Err(format!("layout has no binding at slot {}", binding)),
+        };
+
+        match (&entry.ty, kind) {
+            (wgpu::BindingType::UniformBuffer { .. }, BindGroupResourceKind::Buffer { offset, size })
+            | (wgpu::BindingType::StorageBuffer { .. }, BindGroupResourceKind::Buffer { offset, size }) => {
+                if *size == 0 {
+                    return Err(format!("empty buffer range bound at slot {}", binding));
+                }
+                if offset % wgpu::BIND_BUFFER_ALIGNMENT != 0 {
+                    return Err(format!(
+                        "buffer range at slot {} starts at {}, which is not aligned to {}",
+                        binding,
+                        offset,
+                        wgpu::BIND_BUFFER_ALIGNMENT
+                    ));
+                }
+            }
+            (wgpu::BindingType::SampledTexture { .. }, BindGroupResourceKind::TextureView) => {}
+            (wgpu::BindingType::Sampler { .. }, BindGroupResourceKind::Sampler) => {}
+            _ => {
+                return Err(format!(
+                    "slot {} expects {:?} but a {:?} was provided",
+                    binding, entry.ty, kind
+                ))
+            }
+        }
+
+        if resolved.contains(&binding) {
+            return Err(format!("slot {} bound more than once", binding));
+        }
+        resolved.push(binding);
+    }
+
+    //wgpu wants every binding of the layout to be provided
+    for entry in description.entries.iter() {
+        if !resolved.contains(&entry.binding) {
+            return Err(format!("no resource provided for slot {}", entry.binding));
+        }
+    }
+
+    Ok(resolved)
+}
+
+#[derive(Default)]
+pub struct BindGroupManager {
+    group_mapper: HashMap<u64, wgpu::BindGroup>,
+    handle_counter: u64,
+}
+
+impl BindGroupManager {
+    pub fn create_bind_group(
+        &mut self,
+        label: &str,
+        layout_handle: &handle::ResourceHandle,
+        entries: &[BindGroupEntry],
+        pipeline_manager: &bindings::PipelineManager,
+        gpu_interfaces: &api::GPUInterfaces,
+    ) -> Result<handle::ResourceHandle, String> {
+        let layout = pipeline_manager.get_bind_group_from_handle(*layout_handle)?;
+        let description = pipeline_manager.get_bind_group_description_from_handle(layout_handle)?;
+
+        let requests: Vec<(BindingSlot, BindGroupResourceKind)> = entries
+            .iter()
+            .map(|entry| (entry.slot, entry.resource.kind()))
+            .collect();
+        let slots = resolve_bind_group_slots(description, &requests[..])?;
+
+        let bindings: Vec<wgpu::Binding> = entries
+            .iter()
+            .zip(slots.iter())
+            .map(|(entry, slot)| wgpu::Binding {
+                binding: *slot,
+                resource: match &entry.resource {
+                    BindGroupResource::Buffer { buffer, range } => wgpu::BindingResource::Buffer {
+                        buffer,
+                        range: range.clone(),
+                    },
+                    BindGroupResource::TextureView(view) => {
+                        wgpu::BindingResource::TextureView(view)
+                    }
+                    BindGroupResource::Sampler(sampler) => wgpu::BindingResource::Sampler(sampler),
+                },
+            })
+            .collect();
+
+        let bind_group = gpu_interfaces
+            .device
+            .create_bind_group(&wgpu::BindGroupDescriptor {
+                layout,
+                bindings: &bindings[..],
+                label: Some(label),
+            });
+
+        self.handle_counter += 1;
+        self.group_mapper.insert(self.handle_counter, bind_group);
+
+        Ok(handle::ResourceHandle::new(
+            handle::ResourceHandleType::BindingGroup,
+            self.handle_counter,
+        ))
+    }
+
+    pub fn get_bind_group_from_handle(
+        &self,
+        handle: &handle::ResourceHandle,
+    ) -> Result<&wgpu::BindGroup, &'static str> {
+        let value = handle.get_value();
+        match self.group_mapper.get(&value) {
+            Some(group) => Ok(group),
+            None => Err("could not find binding group"),
+        }
+    }
+}
@@ -5,11 +5,37 @@ use super::super::handle;
 use crate::engine::graphics;
 use crate::engine::platform::file_system;
 
+//what a binding group layout file declares, kept around so bind groups can be
+//validated against it when created
+pub struct BindGroupLayoutDescription {
+    pub entries: Vec<wgpu::BindGroupLayoutEntry>,
+    pub names: HashMap<String, u32>,
+}
+
+//the state of a pipeline that depends on the mesh it draws rather than on the
+//pipeline file
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct PipelineVariant {
+    pub topology: wgpu::PrimitiveTopology,
+    //only matters for indexed draws, non indexed meshes can use any
+    pub index_format: wgpu::IndexFormat,
+}
+
+//a vertex buffer a pipeline reads, the slot of the buffer is its index in the
+//vertex state and matches the shader location
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct VertexStreamDescription {
+    pub semantic: graphics::model::MeshBufferSemantic,
+    pub format: wgpu::VertexFormat,
+}
+
 #[derive(Default)]
 pub struct PipelineManager {
     bg_mapper: HashMap<u64, wgpu::BindGroupLayout>,
+    bg_description_mapper: HashMap<u64, BindGroupLayoutDescription>,
     bg_path_mapper: HashMap<String, u64>,
     pipe_mapper: HashMap<u64, wgpu::RenderPipeline>,
+    pipe_vertex_stream_mapper: HashMap<u64, Vec<VertexStreamDescription>>,
     pipe_path_mapper: HashMap<String, u64>,
     handle_counter: u64,
 }
@@ -23,19 +49,73 @@ impl PipelineManager {
         default_depth_format: wgpu::TextureFormat,
         //layout: &wgpu::BindGroupLayout,
     ) -> handle::ResourceHandle {
-        let loaded = self.pipe_path_mapper.contains_key(file_name);
-        if loaded {
-            let handle_data = self.pipe_path_mapper[file_name];
-            //we need to check if the handle matches the index buffer size
-            let index_16bit_tag = (1 as u64) << (63 - handle::HANDLE_TYPE_BIT_COUNT);
-            let pipe_correct_index_size = (handle_data & index_16bit_tag) > 0;
-            if pipe_correct_index_size {
-                return handle::ResourceHandle::from_data(handle_data);
-            }
+        self.load_pipeline_file(
+            file_name,
+            String::from(file_name),
+            None,
+            shader_manager,
+            gpu_interfaces,
+            default_depth_format,
+        )
+        .await
+    }
+
+    //same pipeline file with the primitive topology and index format replaced, every
+    //mesh needs the variant matching its own. Each variant of a file is cached on its own
+    pub async fn load_pipeline_variant(
+        &mut self,
+        file_name: &str,
+        variant: PipelineVariant,
+        shader_manager: &mut graphics::shader::ShaderManager,
+        gpu_interfaces: &graphics::api::GPUInterfaces,
+        default_depth_format: wgpu::TextureFormat,
+    ) -> handle::ResourceHandle {
+        let key = format!(
+            "{}#{}#{}",
+            file_name,
+            get_primitive_topology_name(variant.topology),
+            get_index_format_name(variant.index_format)
+        );
+        self.load_pipeline_file(
+            file_name,
+            key,
+            Some(variant),
+            shader_manager,
+            gpu_interfaces,
+            default_depth_format,
+        )
+        .await
+    }
+
+    //loads the pipeline file, with the variant state written over the file's own when
+    //given, and caches the result under the key
+    async fn load_pipeline_file(
+        &mut self,
+        file_name: &str,
+        key: String,
+        variant: Option<PipelineVariant>,
+        shader_manager: &mut graphics::shader::ShaderManager,
+        gpu_interfaces: &graphics::api::GPUInterfaces,
+        default_depth_format: wgpu::TextureFormat,
+    ) -> handle::ResourceHandle {
+        if let Some(handle) = self.pipe_path_mapper.get(&key) {
+            return handle::ResourceHandle::new(
+                handle::ResourceHandleType::RenderPipeline,
+                *handle,
+            );
         }
 
         let pipe_source = file_system::load_file_string(file_name).await.unwrap();
-        let pipe_content_json: Value = serde_json::from_str(&pipe_source[..]).unwrap();
+        let mut pipe_content_json: Value = serde_json::from_str(&pipe_source[..]).unwrap();
+        if let Some(variant) = variant {
+            pipe_content_json["primitive_topology"] =
+                Value::from(get_primitive_topology_name(variant.topology));
+            pipe_content_json["index_format"] =
+                Value::from(get_index_format_name(variant.index_format));
+        }
+
+        let vertex_state_type = pipe_content_json["vertex_state"]["type"].as_str().unwrap();
+        let vertex_streams = get_vertex_stream_descriptions(vertex_state_type).unwrap();
 
         let pipe_type = pipe_content_json["type"].as_str().unwrap();
         let pipe = match pipe_type {
@@ -55,8 +135,9 @@ impl PipelineManager {
         let handle = self.handle_counter;
 
         self.pipe_mapper.insert(handle, pipe);
-        self.pipe_path_mapper
-            .insert(String::from(file_name), handle);
+        self.pipe_vertex_stream_mapper
+            .insert(handle, vertex_streams);
+        self.pipe_path_mapper.insert(key, handle);
 
         handle::ResourceHandle::new(handle::ResourceHandleType::RenderPipeline, handle)
     }
@@ -72,6 +153,18 @@ impl PipelineManager {
         Ok(pipe)
     }
 
+    //the buffers a mesh has to bind to be drawn with the pipeline, in slot order
+    pub fn get_pipeline_vertex_streams_from_handle(
+        &self,
+        handle: &handle::ResourceHandle,
+    ) -> Result<&[VertexStreamDescription], &'static str> {
+        let value = handle.get_value();
+        match self.pipe_vertex_stream_mapper.get(&value) {
+            Some(streams) => Ok(&streams[..]),
+            None => Err("could not find pipeline vertex streams"),
+        }
+    }
+
     pub fn get_bind_group_from_handle(
         &self,
         handle: handle::ResourceHandle,
@@ -84,6 +177,17 @@ impl PipelineManager {
         Ok(group)
     }
 
+    pub fn get_bind_group_description_from_handle(
+        &self,
+        handle: &handle::ResourceHandle,
+    ) -> Result<&BindGroupLayoutDescription, &'static str> {
+        let value = handle.get_value();
+        match self.bg_description_mapper.get(&value) {
+            Some(description) => Ok(description),
+            None => Err("could not find binding group layout description"),
+        }
+    }
+
     pub async fn load_binding_group(
         &mut self,
         file_name: &str,
@@ -96,22 +200,7 @@ impl PipelineManager {
 
         let bg_source = file_system::load_file_string(file_name).await.unwrap();
         let bg_content_js: Value = serde_json::from_str(&bg_source[..]).unwrap();
-        let bindings_values = &bg_content_js["bindings"].as_array().unwrap();
-
-        let mut bindings = Vec::new();
-        for binding in *bindings_values {
-            let slot = binding["slot"].as_u64().unwrap() as u32;
-            let visibility_array = binding["visibility"].as_array().unwrap();
-            let visibility_bitfiled = get_bind_group_visibility(visibility_array);
-            let type_value = binding["type"].as_str().unwrap();
-            let binding_type = get_bind_group_type(type_value, binding);
-
-            bindings.push(wgpu::BindGroupLayoutEntry {
-                binding: slot,
-                visibility: visibility_bitfiled,
-                ty: binding_type,
-            })
-        }
+        let description = get_bind_group_layout_description(&bg_content_js).unwrap();
 
         //oh wow... all this to get the string
         let file_name_no_ext = std::path::Path::new(file_name)
@@ -123,13 +212,15 @@ impl PipelineManager {
             gpu_interfaces
                 .device
                 .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-                    bindings: &bindings[..],
+                    bindings: &description.entries[..],
                     label: Some(&format!("{}_bg", file_name_no_ext)[..]),
                 });
 
         self.handle_counter += 1;
         self.bg_mapper
             .insert(self.handle_counter, bind_group_layout);
+        self.bg_description_mapper
+            .insert(self.handle_counter, description);
         self.bg_path_mapper
             .insert(String::from(file_name), self.handle_counter);
 
@@ -193,26 +284,17 @@ impl PipelineManager {
         };
 
         //next is raster state
-        let raster_state = get_pipeline_raster_state(&pipe_content_json);
+        let raster_state = get_pipeline_raster_state(&pipe_content_json).unwrap();
 
         //depth state
-        let depth_stencil_state = get_depth_stencil_state(&pipe_content_json, default_depth_format);
-
-        let primitive_value = pipe_content_json["primitive_topology"].as_str().unwrap();
-        let primitive_topology = match primitive_value {
-            "pointList" => wgpu::PrimitiveTopology::PointList,
-            "lineList" => wgpu::PrimitiveTopology::LineList,
-            "lineStrip" => wgpu::PrimitiveTopology::LineStrip,
-            "triangleList" => wgpu::PrimitiveTopology::TriangleList,
-            "triangleStrip" => wgpu::PrimitiveTopology::TriangleStrip,
-            _ => panic!(
-                "could not match requested primitive topology {}",
-                primitive_value
-            ),
-        };
+        let depth_stencil_state =
+            get_depth_stencil_state(&pipe_content_json, default_depth_format).unwrap();
+
+        let primitive_topology = get_primitive_topology(&pipe_content_json).unwrap();
+        let index_format = get_index_format(&pipe_content_json).unwrap();
 
         let color_states =
-            get_pipeline_color_states(&pipe_content_json, gpu_interfaces.sc_desc.format);
+            get_pipeline_color_states(&pipe_content_json, gpu_interfaces.sc_desc.format).unwrap();
 
         let layout_name = pipe_content_json["layout"].as_str().unwrap();
         let bg_layout_handle = self
@@ -229,7 +311,7 @@ impl PipelineManager {
                 });
 
         let vertex_state_type = pipe_content_json["vertex_state"]["type"].as_str().unwrap();
-        let desc = get_vertex_attrbibute_descriptor(vertex_state_type);
+        let desc = get_vertex_attrbibute_descriptor(vertex_state_type).unwrap();
         println!("{} {}", vertex_state_type,desc.len());
 
         gpu_interfaces
@@ -244,7 +326,7 @@ impl PipelineManager {
                 primitive_topology,
                 depth_stencil_state,
                 vertex_state: wgpu::VertexStateDescriptor {
-                    index_format: wgpu::IndexFormat::Uint32,
+                    index_format,
                     vertex_buffers: &desc[..],
                 },
                 sample_count: 1,
@@ -254,60 +336,176 @@ impl PipelineManager {
     }
 }
 
-fn get_depth_stencil_state(
+pub fn get_bind_group_layout_entries(
+    bg_content_js: &Value,
+) -> Result<Vec<wgpu::BindGroupLayoutEntry>, String> {
+    let bindings_values = bg_content_js["bindings"]
+        .as_array()
+        .ok_or("missing or non array field \"bindings\"")?;
+
+    let mut bindings = Vec::new();
+    for binding in bindings_values {
+        let slot = binding["slot"]
+            .as_u64()
+            .ok_or("missing or non integer field \"slot\"")? as u32;
+        let visibility_array = binding["visibility"]
+            .as_array()
+            .ok_or("missing or non array field \"visibility\"")?;
+        let visibility_bitfiled = get_bind_group_visibility(visibility_array)?;
+        let type_value = get_json_str(binding, "type")?;
+        let binding_type = get_bind_group_type(type_value, binding)?;
+
+        bindings.push(wgpu::BindGroupLayoutEntry {
+            binding: slot,
+            visibility: visibility_bitfiled,
+            ty: binding_type,
+        })
+    }
+    Ok(bindings)
+}
+
+//binding names are optional, they allow to refer to a slot by name when creating
+//bind groups
+pub fn get_bind_group_layout_description(
+    bg_content_js: &Value,
+) -> Result<BindGroupLayoutDescription, String> {
+    let entries = get_bind_group_layout_entries(bg_content_js)?;
+
+    let mut names = HashMap::new();
+    for (binding, entry) in bg_content_js["bindings"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .zip(entries.iter())
+    {
+        let name_value = &binding["name"];
+        if name_value.is_null() {
+            continue;
+        }
+        let name = name_value
+            .as_str()
+            .ok_or("binding \"name\" field is not a string")?;
+        if names.insert(String::from(name), entry.binding).is_some() {
+            return Err(format!("binding name {} declared twice", name));
+        }
+    }
+
+    Ok(BindGroupLayoutDescription { entries, names })
+}
+
+fn get_json_str<'a>(value: &'a Value, field: &str) -> Result<&'a str, String> {
+    value[field]
+        .as_str()
+        .ok_or_else(|| format!("missing or non string field \"{}\"", field))
+}
+
+pub fn get_depth_stencil_state(
     pipe_content_json: &Value,
     swap_depth_format: wgpu::TextureFormat,
-) -> Option<wgpu::DepthStencilStateDescriptor> {
+) -> Result<Option<wgpu::DepthStencilStateDescriptor>, String> {
     let state_value = &pipe_content_json["depth_state"];
     if state_value.is_null() {
-        return None;
+        return Ok(None);
     }
 
-    let format_str = state_value["format"].as_str().unwrap();
+    let format_str = get_json_str(state_value, "format")?;
 
     let format = match format_str {
         "default" => swap_depth_format,
-        _ => panic!(
-            "unsupported swap chain dept format {:?}, if is a valid type add it to the function",
-            swap_depth_format
-        ),
+        _ => {
+            return Err(format!(
+                "unsupported swap chain depth format {}, if is a valid type add it to the function",
+                format_str
+            ))
+        }
     };
 
-    let depth_write_enabled = state_value["depth_write_enabled"].as_bool().unwrap();
-    let depth_compare = get_compare_function(&state_value["depth_compare"]);
+    let depth_write_enabled = state_value["depth_write_enabled"]
+        .as_bool()
+        .ok_or("missing or non bool field \"depth_write_enabled\"")?;
+    let depth_compare = get_compare_function(&state_value["depth_compare"])?;
 
-    Some(wgpu::DepthStencilStateDescriptor{
+    Ok(Some(wgpu::DepthStencilStateDescriptor {
         format,
         depth_write_enabled,
         depth_compare,
-        stencil_front : wgpu::StencilStateFaceDescriptor::IGNORE, 
-        stencil_back : wgpu::StencilStateFaceDescriptor::IGNORE, 
+        stencil_front: wgpu::StencilStateFaceDescriptor::IGNORE,
+        stencil_back: wgpu::StencilStateFaceDescriptor::IGNORE,
         stencil_read_mask: 0,
-        stencil_write_mask :0,
-    })
+        stencil_write_mask: 0,
+    }))
 }
 
-fn get_compare_function(value: &serde_json::Value)-> wgpu::CompareFunction
-{
-    let compare_str = value.as_str().unwrap();
-    match  compare_str
-    {
-        "Undefined" => wgpu::CompareFunction::Undefined,
-        "Never" => wgpu::CompareFunction::Never,
-        "Less" => wgpu::CompareFunction::Less,
-        "Equal" => wgpu::CompareFunction::Equal,
-        "LessEqual" => wgpu::CompareFunction::LessEqual,
-        "Greater" => wgpu::CompareFunction::Greater,
-        "NotEqual" => wgpu::CompareFunction::NotEqual,
-        "GreaterEqual" => wgpu::CompareFunction::GreaterEqual,
-        "Always" => wgpu::CompareFunction::Always,
-        _ => panic!("Not supported compare fuction {}",  compare_str)
+pub fn get_compare_function(value: &serde_json::Value) -> Result<wgpu::CompareFunction, String> {
+    let compare_str = value.as_str().ok_or("compare function is not a string")?;
+    match compare_str {
+        "Undefined" => Ok(wgpu::CompareFunction::Undefined),
+        "Never" => Ok(wgpu::CompareFunction::Never),
+        "Less" => Ok(wgpu::CompareFunction::Less),
+        "Equal" => Ok(wgpu::CompareFunction::Equal),
+        "LessEqual" => Ok(wgpu::CompareFunction::LessEqual),
+        "Greater" => Ok(wgpu::CompareFunction::Greater),
+        "NotEqual" => Ok(wgpu::CompareFunction::NotEqual),
+        "GreaterEqual" => Ok(wgpu::CompareFunction::GreaterEqual),
+        "Always" => Ok(wgpu::CompareFunction::Always),
+        _ => Err(format!("Not supported compare fuction {}", compare_str)),
     }
 }
 
-fn get_vertex_attrbibute_descriptor(name: &str) -> Vec<wgpu::VertexBufferDescriptor<'static>> {
+pub fn get_primitive_topology(pipe_content_json: &Value) -> Result<wgpu::PrimitiveTopology, String> {
+    let primitive_value = get_json_str(pipe_content_json, "primitive_topology")?;
+    match primitive_value {
+        "pointList" => Ok(wgpu::PrimitiveTopology::PointList),
+        "lineList" => Ok(wgpu::PrimitiveTopology::LineList),
+        "lineStrip" => Ok(wgpu::PrimitiveTopology::LineStrip),
+        "triangleList" => Ok(wgpu::PrimitiveTopology::TriangleList),
+        "triangleStrip" => Ok(wgpu::PrimitiveTopology::TriangleStrip),
+        _ => Err(format!(
+            "could not match requested primitive topology {}",
+            primitive_value
+        )),
+    }
+}
+
+//inverse of get_primitive_topology, the name used in pipeline files
+pub fn get_primitive_topology_name(topology: wgpu::PrimitiveTopology) -> &'static str {
+    match topology {
+        wgpu::PrimitiveTopology::PointList => "pointList",
+        wgpu::PrimitiveTopology::LineList => "lineList",
+        wgpu::PrimitiveTopology::LineStrip => "lineStrip",
+        wgpu::PrimitiveTopology::TriangleList => "triangleList",
+        wgpu::PrimitiveTopology::TriangleStrip => "triangleStrip",
+    }
+}
+
+//optional in pipeline files, 32 bit indices when missing
+pub fn get_index_format(pipe_content_json: &Value) -> Result<wgpu::IndexFormat, String> {
+    if pipe_content_json["index_format"].is_null() {
+        return Ok(wgpu::IndexFormat::Uint32);
+    }
+    let index_value = get_json_str(pipe_content_json, "index_format")?;
+    match index_value {
+        "uint16" => Ok(wgpu::IndexFormat::Uint16),
+        "uint32" => Ok(wgpu::IndexFormat::Uint32),
+        _ => Err(format!(
+            "could not match requested index format {}",
+            index_value
+        )),
+    }
+}
+
+pub fn get_index_format_name(index_format: wgpu::IndexFormat) -> &'static str {
+    match index_format {
+        wgpu::IndexFormat::Uint16 => "uint16",
+        wgpu::IndexFormat::Uint32 => "uint32",
+    }
+}
+
+pub fn get_vertex_attrbibute_descriptor(
+    name: &str,
+) -> Result<Vec<wgpu::VertexBufferDescriptor<'static>>, String> {
     match name {
-        "position_normal" => vec![
+        "position_normal" => Ok(vec![
             wgpu::VertexBufferDescriptor {
                 stride: 12 as wgpu::BufferAddress,
                 step_mode: wgpu::InputStepMode::Vertex,
@@ -326,48 +524,142 @@ fn get_vertex_attrbibute_descriptor(name: &str) -> Vec<wgpu::VertexBufferDescrip
                     format: wgpu::VertexFormat::Float3,
                 }],
             },
-        ],
-        "none" =>  Vec::new(),
-        _ => panic!("could not find {} vertex description", name),
+        ]),
+        //point clouds and other meshes colored per vertex
+        "position_color" => Ok(vec![
+            wgpu::VertexBufferDescriptor {
+                stride: 12 as wgpu::BufferAddress,
+                step_mode: wgpu::InputStepMode::Vertex,
+                attributes: &[wgpu::VertexAttributeDescriptor {
+                    offset: 0,
+                    shader_location: 0,
+                    format: wgpu::VertexFormat::Float3,
+                }],
+            },
+            wgpu::VertexBufferDescriptor {
+                stride: 16 as wgpu::BufferAddress,
+                step_mode: wgpu::InputStepMode::Vertex,
+                attributes: &[wgpu::VertexAttributeDescriptor {
+                    offset: 0,
+                    shader_location: 1,
+                    format: wgpu::VertexFormat::Float4,
+                }],
+            },
+        ]),
+        "none" => Ok(Vec::new()),
+        _ => Err(format!("could not find {} vertex description", name)),
+    }
+}
+
+//the meaning of every buffer of get_vertex_attrbibute_descriptor, the two need to
+//be kept in sync
+pub fn get_vertex_stream_descriptions(name: &str) -> Result<Vec<VertexStreamDescription>, String> {
+    match name {
+        "position_normal" => Ok(vec![
+            VertexStreamDescription {
+                semantic: graphics::model::MeshBufferSemantic::Positions,
+                format: wgpu::VertexFormat::Float3,
+            },
+            VertexStreamDescription {
+                semantic: graphics::model::MeshBufferSemantic::Normals,
+                format: wgpu::VertexFormat::Float3,
+            },
+        ]),
+        "position_color" => Ok(vec![
+            VertexStreamDescription {
+                semantic: graphics::model::MeshBufferSemantic::Positions,
+                format: wgpu::VertexFormat::Float3,
+            },
+            VertexStreamDescription {
+                semantic: graphics::model::MeshBufferSemantic::Colors(0),
+                format: wgpu::VertexFormat::Float4,
+            },
+        ]),
+        "none" => Ok(Vec::new()),
+        _ => Err(format!("could not find {} vertex description", name)),
     }
 }
 
-pub fn get_bind_group_visibility(visibilities: &[Value]) -> wgpu::ShaderStage {
+pub fn get_bind_group_visibility(visibilities: &[Value]) -> Result<wgpu::ShaderStage, String> {
     let mut out_vis = wgpu::ShaderStage::NONE;
     for visibility in visibilities {
-        let visibility_str = visibility.as_str().unwrap();
+        let visibility_str = visibility
+            .as_str()
+            .ok_or("shader stage visibility is not a string")?;
         out_vis |= match visibility_str {
             "vertex" => wgpu::ShaderStage::VERTEX,
             "fragment" => wgpu::ShaderStage::FRAGMENT,
             "compute" => wgpu::ShaderStage::COMPUTE,
-            _ => panic!("Unknown wgpu shader statage {}", visibility_str),
+            _ => return Err(format!("Unknown wgpu shader statage {}", visibility_str)),
         };
     }
 
     //returning built visibility field
-    out_vis
+    Ok(out_vis)
 }
 
-pub fn get_bind_group_type(type_str: &str, binding: &Value) -> wgpu::BindingType {
+pub fn get_bind_group_type(type_str: &str, binding: &Value) -> Result<wgpu::BindingType, String> {
     match type_str {
         //if is a uniform , we extract some extra data and return the built type
-        "uniform" => wgpu::BindingType::UniformBuffer {
-            dynamic: binding["dynamic"].as_bool().unwrap(),
-        },
-        _ => panic!("Unexpected binding group type {}", type_str),
+        "uniform" => Ok(wgpu::BindingType::UniformBuffer {
+            dynamic: binding["dynamic"]
+                .as_bool()
+                .ok_or("missing or non bool field \"dynamic\"")?,
+        }),
+        "storage" => Ok(wgpu::BindingType::StorageBuffer {
+            dynamic: binding["dynamic"]
+                .as_bool()
+                .ok_or("missing or non bool field \"dynamic\"")?,
+            readonly: binding["readonly"].as_bool().unwrap_or(false),
+        }),
+        //texture fields are optional, defaulting to the common 2d float texture
+        "sampled_texture" => Ok(wgpu::BindingType::SampledTexture {
+            dimension: get_texture_view_dimension(binding["dimension"].as_str().unwrap_or("2d"))?,
+            component_type: get_texture_component_type(
+                binding["component_type"].as_str().unwrap_or("float"),
+            )?,
+            multisampled: binding["multisampled"].as_bool().unwrap_or(false),
+        }),
+        "sampler" => Ok(wgpu::BindingType::Sampler {
+            comparison: binding["comparison"].as_bool().unwrap_or(false),
+        }),
+        _ => Err(format!("Unexpected binding group type {}", type_str)),
+    }
+}
+
+fn get_texture_view_dimension(dimension_str: &str) -> Result<wgpu::TextureViewDimension, String> {
+    match dimension_str {
+        "1d" => Ok(wgpu::TextureViewDimension::D1),
+        "2d" => Ok(wgpu::TextureViewDimension::D2),
+        "2d_array" => Ok(wgpu::TextureViewDimension::D2Array),
+        "cube" => Ok(wgpu::TextureViewDimension::Cube),
+        "cube_array" => Ok(wgpu::TextureViewDimension::CubeArray),
+        "3d" => Ok(wgpu::TextureViewDimension::D3),
+        _ => Err(format!("Unexpected texture dimension {}", dimension_str)),
+    }
+}
+
+fn get_texture_component_type(component_str: &str) -> Result<wgpu::TextureComponentType, String> {
+    match component_str {
+        "float" => Ok(wgpu::TextureComponentType::Float),
+        "sint" => Ok(wgpu::TextureComponentType::Sint),
+        "uint" => Ok(wgpu::TextureComponentType::Uint),
+        _ => Err(format!("Unexpected texture component type {}", component_str)),
     }
 }
 
 pub fn get_pipeline_color_states(
     pipe_content_json: &Value,
     swap_chain_format: wgpu::TextureFormat,
-) -> Vec<wgpu::ColorStateDescriptor> {
-    let color_values = pipe_content_json["color_states"].as_array().unwrap();
+) -> Result<Vec<wgpu::ColorStateDescriptor>, String> {
+    let color_values = pipe_content_json["color_states"]
+        .as_array()
+        .ok_or("missing or non array field \"color_states\"")?;
     let mut color_states = Vec::new();
     for color_value in color_values {
-        let format = get_pipeline_color_format(color_value, swap_chain_format);
-        let color_blend = get_pipeline_blend(color_value, "color_blend");
-        let alpha_blend = get_pipeline_blend(color_value, "alpha_blend");
+        let format = get_pipeline_color_format(color_value, swap_chain_format)?;
+        let color_blend = get_pipeline_blend(color_value, "color_blend")?;
+        let alpha_blend = get_pipeline_blend(color_value, "alpha_blend")?;
 
         color_states.push(wgpu::ColorStateDescriptor {
             format,
@@ -377,73 +669,84 @@ pub fn get_pipeline_color_states(
         });
     }
 
-    color_states
+    Ok(color_states)
 }
 
-fn get_pipeline_blend(color_value: &Value, name: &str) -> wgpu::BlendDescriptor {
-    let blend_value = color_value[name].as_str().unwrap();
+fn get_pipeline_blend(color_value: &Value, name: &str) -> Result<wgpu::BlendDescriptor, String> {
+    let blend_value = get_json_str(color_value, name)?;
     match blend_value {
-        "replace" => wgpu::BlendDescriptor::REPLACE,
-        _ => panic!("blend descriptor not supported yet {}", blend_value),
+        "replace" => Ok(wgpu::BlendDescriptor::REPLACE),
+        _ => Err(format!("blend descriptor not supported yet {}", blend_value)),
     }
 }
 
 fn get_pipeline_color_format(
     color_value: &Value,
     swap_chain_format: wgpu::TextureFormat,
-) -> wgpu::TextureFormat {
-    let format_str = color_value["format"].as_str().unwrap();
+) -> Result<wgpu::TextureFormat, String> {
+    let format_str = get_json_str(color_value, "format")?;
     match format_str {
-        "swap_chain_native" => swap_chain_format,
-        _ => panic!(
-            "unsupported swap chain format {:?}, if is a valid type add it to the function",
-            swap_chain_format
-        ),
+        "swap_chain_native" => Ok(swap_chain_format),
+        _ => Err(format!(
+            "unsupported swap chain format {}, if is a valid type add it to the function",
+            format_str
+        )),
     }
 }
 
-pub fn get_pipeline_raster_state(pipe_content_json: &Value) -> wgpu::RasterizationStateDescriptor {
+pub fn get_pipeline_raster_state(
+    pipe_content_json: &Value,
+) -> Result<wgpu::RasterizationStateDescriptor, String> {
     let raster_value = &pipe_content_json["rasterization_state"];
-    let raster_type = raster_value["type"].as_str().unwrap();
+    let raster_type = get_json_str(raster_value, "type")?;
     match raster_type {
-        "default" => wgpu::RasterizationStateDescriptor {
+        "default" => Ok(wgpu::RasterizationStateDescriptor {
             front_face: wgpu::FrontFace::Ccw,
             cull_mode: wgpu::CullMode::Back,
             depth_bias: 0,
             depth_bias_slope_scale: 0.0,
             depth_bias_clamp: 0.0,
-        },
+        }),
         _ => {
             //we parse the raster definition
-            wgpu::RasterizationStateDescriptor {
-                front_face: get_raster_facing(raster_value),
-                cull_mode: get_raster_cull(raster_value),
-                depth_bias: raster_value["depth_bias"].as_i64().unwrap() as i32,
-                depth_bias_slope_scale: raster_value["slope_scale"].as_f64().unwrap() as f32,
-                depth_bias_clamp: raster_value["bias_clamp"].as_f64().unwrap() as f32,
-            }
+            Ok(wgpu::RasterizationStateDescriptor {
+                front_face: get_raster_facing(raster_value)?,
+                cull_mode: get_raster_cull(raster_value)?,
+                depth_bias: raster_value["depth_bias"]
+                    .as_i64()
+                    .ok_or("missing or non integer field \"depth_bias\"")?
+                    as i32,
+                depth_bias_slope_scale: raster_value["slope_scale"]
+                    .as_f64()
+                    .ok_or("missing or non float field \"slope_scale\"")?
+                    as f32,
+                depth_bias_clamp: raster_value["bias_clamp"]
+                    .as_f64()
+                    .ok_or("missing or non float field \"bias_clamp\"")?
+                    as f32,
+            })
         }
     }
 }
 
-fn get_raster_facing(raster_value: &Value) -> wgpu::FrontFace {
-    let front_face_str = raster_value["front_facing"].as_str().unwrap();
+fn get_raster_facing(raster_value: &Value) -> Result<wgpu::FrontFace, String> {
+    let front_face_str = get_json_str(raster_value, "front_facing")?;
     match front_face_str {
-        "ccw" => wgpu::FrontFace::Ccw,
-        "cw" => wgpu::FrontFace::Cw,
-        _ => panic!(
+        "ccw" => Ok(wgpu::FrontFace::Ccw),
+        "cw" => Ok(wgpu::FrontFace::Cw),
+        _ => Err(format!(
             "could not match requessted front facing value {}",
             front_face_str
-        ),
+        )),
     }
 }
 
-fn get_raster_cull(raster_value: &Value) -> wgpu::CullMode {
-    let cull_str = raster_value["cull_mode"].as_str().unwrap();
+fn get_raster_cull(raster_value: &Value) -> Result<wgpu::CullMode, String> {
+    let cull_str = get_json_str(raster_value, "cull_mode")?;
     match cull_str {
-        "none" => wgpu::CullMode::None,
-        "front" => wgpu::CullMode::Front,
-        "back" => wgpu::CullMode::Back,
-        _ => panic!("could not match requested cull facing value {}", cull_str),
+        "none" => Ok(wgpu::CullMode::None),
+        "front" => Ok(wgpu::CullMode::Front),
+        "back" => Ok(wgpu::CullMode::Back),
+        _ => Err(format!("could not match requested cull facing value {}", cull_str)),
     }
 }
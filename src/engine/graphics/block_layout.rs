@@ -0,0 +1,317 @@
+use super::shader;
+
+//glsl packing rules for interface blocks, std140 is what uniform blocks use by
+//default, std430 is available for storage buffers
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LayoutRule {
+    Std140,
+    Std430,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FieldType {
+    Float,
+    Int,
+    Uint,
+    Vec2,
+    Vec3,
+    Vec4,
+    IVec4,
+    UVec4,
+    Mat3,
+    Mat4,
+}
+
+impl FieldType {
+    //size and alignment of the type when not part of an array or matrix
+    fn base_size_align(self) -> (u32, u32) {
+        match self {
+            FieldType::Float | FieldType::Int | FieldType::Uint => (4, 4),
+            FieldType::Vec2 => (8, 8),
+            FieldType::Vec3 => (12, 16),
+            FieldType::Vec4 | FieldType::IVec4 | FieldType::UVec4 => (16, 16),
+            //matrices are laid out as arrays of column vectors
+            FieldType::Mat3 => (12, 16),
+            FieldType::Mat4 => (16, 16),
+        }
+    }
+
+    fn column_count(self) -> u32 {
+        match self {
+            FieldType::Mat3 => 3,
+            FieldType::Mat4 => 4,
+            _ => 1,
+        }
+    }
+}
+
+//all the glsl alignments are powers of two
+fn round_up(value: u32, alignment: u32) -> u32 {
+    (value + alignment - 1) & !(alignment - 1)
+}
+
+#[derive(Clone, Debug)]
+pub struct BlockField {
+    pub name: String,
+    pub field_type: FieldType,
+    //zero for non array fields
+    pub array_count: u32,
+    pub offset: u32,
+    pub size: u32,
+    //distance between array elements, zero for non array fields
+    pub array_stride: u32,
+    //distance between matrix columns, zero for non matrix fields
+    pub matrix_stride: u32,
+}
+
+#[derive(Clone, Debug)]
+pub struct BlockLayout {
+    pub rule: LayoutRule,
+    pub fields: Vec<BlockField>,
+    pub size: u32,
+    pub alignment: u32,
+}
+
+pub struct BlockLayoutBuilder {
+    rule: LayoutRule,
+    fields: Vec<BlockField>,
+    cursor: u32,
+    alignment: u32,
+}
+
+impl BlockLayoutBuilder {
+    pub fn new(rule: LayoutRule) -> Self {
+        Self {
+            rule,
+            fields: Vec::new(),
+            cursor: 0,
+            //std140 structures are always at least vec4 aligned
+            alignment: match rule {
+                LayoutRule::Std140 => 16,
+                LayoutRule::Std430 => 4,
+            },
+        }
+    }
+
+    pub fn field(self, name: &str, field_type: FieldType) -> Self {
+        self.push_field(name, field_type, 0)
+    }
+
+    pub fn array(self, name: &str, field_type: FieldType, count: u32) -> Self {
+        assert!(count > 0, "array fields need at least one element");
+        self.push_field(name, field_type, count)
+    }
+
+    fn push_field(mut self, name: &str, field_type: FieldType, array_count: u32) -> Self {
+        let (base_size, base_align) = field_type.base_size_align();
+        let columns = field_type.column_count();
+        let is_array = array_count > 0;
+        let is_matrix = columns > 1;
+
+        //in std140 every array element and matrix column is rounded up to a vec4
+        let std140_rounding = |value: u32| match self.rule {
+            LayoutRule::Std140 => round_up(value, 16),
+            LayoutRule::Std430 => value,
+        };
+
+        let matrix_stride = if is_matrix {
+            std140_rounding(base_align)
+        } else {
+            0
+        };
+        //size of one element, the full matrix if the field is a matrix
+        let (element_size, element_align) = if is_matrix {
+            (matrix_stride * columns, std140_rounding(base_align))
+        } else {
+            (base_size, base_align)
+        };
+
+        let (align, array_stride, size) = if is_array {
+            let element_align = std140_rounding(element_align);
+            let stride = std140_rounding(round_up(element_size, element_align));
+            (element_align, stride, stride * array_count)
+        } else {
+            (element_align, 0, element_size)
+        };
+
+        let offset = round_up(self.cursor, align);
+        self.cursor = offset + size;
+        self.alignment = self.alignment.max(align);
+        self.fields.push(BlockField {
+            name: String::from(name),
+            field_type,
+            array_count,
+            offset,
+            size,
+            array_stride,
+            matrix_stride,
+        });
+        self
+    }
+
+    pub fn build(self) -> BlockLayout {
+        BlockLayout {
+            rule: self.rule,
+            size: round_up(self.cursor, self.alignment),
+            alignment: self.alignment,
+            fields: self.fields,
+        }
+    }
+}
+
+pub enum BlockValue {
+    Float(f32),
+    Int(i32),
+    Uint(u32),
+    Vec2([f32; 2]),
+    Vec3([f32; 3]),
+    Vec4([f32; 4]),
+    IVec4([i32; 4]),
+    UVec4([u32; 4]),
+    Mat3(cgmath::Matrix3<f32>),
+    Mat4(cgmath::Matrix4<f32>),
+}
+
+impl BlockValue {
+    fn field_type(&self) -> FieldType {
+        match self {
+            BlockValue::Float(_) => FieldType::Float,
+            BlockValue::Int(_) => FieldType::Int,
+            BlockValue::Uint(_) => FieldType::Uint,
+            BlockValue::Vec2(_) => FieldType::Vec2,
+            BlockValue::Vec3(_) => FieldType::Vec3,
+            BlockValue::Vec4(_) => FieldType::Vec4,
+            BlockValue::IVec4(_) => FieldType::IVec4,
+            BlockValue::UVec4(_) => FieldType::UVec4,
+            BlockValue::Mat3(_) => FieldType::Mat3,
+            BlockValue::Mat4(_) => FieldType::Mat4,
+        }
+    }
+
+    //writes the value honouring the column stride, the padding is left untouched
+    fn write(&self, out: &mut [u8], matrix_stride: usize) {
+        fn write_words(out: &mut [u8], words: &[[u8; 4]]) {
+            for (i, word) in words.iter().enumerate() {
+                out[i * 4..i * 4 + 4].copy_from_slice(word);
+            }
+        }
+        let f = |v: &[f32]| v.iter().map(|x| x.to_le_bytes()).collect::<Vec<[u8; 4]>>();
+        match self {
+            BlockValue::Float(v) => write_words(out, &[v.to_le_bytes()]),
+            BlockValue::Int(v) => write_words(out, &[v.to_le_bytes()]),
+            BlockValue::Uint(v) => write_words(out, &[v.to_le_bytes()]),
+            BlockValue::Vec2(v) => write_words(out, &f(v)),
+            BlockValue::Vec3(v) => write_words(out, &f(v)),
+            BlockValue::Vec4(v) => write_words(out, &f(v)),
+            BlockValue::IVec4(v) => {
+                write_words(out, &v.iter().map(|x| x.to_le_bytes()).collect::<Vec<_>>())
+            }
+            BlockValue::UVec4(v) => {
+                write_words(out, &v.iter().map(|x| x.to_le_bytes()).collect::<Vec<_>>())
+            }
+            BlockValue::Mat3(m) => {
+                let columns: [[f32; 3]; 3] = (*m).into();
+                for (i, column) in columns.iter().enumerate() {
+                    write_words(&mut out[i * matrix_stride..], &f(column));
+                }
+            }
+            BlockValue::Mat4(m) => {
+                let columns: [[f32; 4]; 4] = (*m).into();
+                for (i, column) in columns.iter().enumerate() {
+                    write_words(&mut out[i * matrix_stride..], &f(column));
+                }
+            }
+        }
+    }
+}
+
+impl BlockLayout {
+    pub fn get_field(&self, name: &str) -> Option<&BlockField> {
+        self.fields.iter().find(|field| field.name == name)
+    }
+
+    pub fn offset_of(&self, name: &str) -> Option<u32> {
+        self.get_field(name).map(|field| field.offset)
+    }
+
+    //returns a zeroed cpu buffer of the right size, ready to be filled with set
+    pub fn create_data(&self) -> Vec<u8> {
+        vec![0; self.size as usize]
+    }
+
+    pub fn set(&self, data: &mut [u8], name: &str, value: BlockValue) -> Result<(), String> {
+        self.set_element(data, name, 0, value)
+    }
+
+    pub fn set_element(
+        &self,
+        data: &mut [u8],
+        name: &str,
+        index: u32,
+        value: BlockValue,
+    ) -> Result<(), String> {
+        let field = self
+            .get_field(name)
+            .ok_or_else(|| format!("block has no field named {}", name))?;
+        if value.field_type() != field.field_type {
+            return Err(format!(
+                "field {} is a {:?} but a {:?} was provided",
+                name,
+                field.field_type,
+                value.field_type()
+            ));
+        }
+        if index >= field.array_count.max(1) {
+            return Err(format!("index {} out of bounds for field {}", index, name));
+        }
+        if data.len() < self.size as usize {
+            return Err(format!(
+                "data is {} bytes but the block needs {}",
+                data.len(),
+                self.size
+            ));
+        }
+
+        let offset = (field.offset + index * field.array_stride) as usize;
+        value.write(&mut data[offset..], field.matrix_stride as usize);
+        Ok(())
+    }
+
+    //compares the layout against the block the compiler generated, fields are matched by name
+    pub fn check_against_spirv(&self, spirv: &[u32], block_name: &str) -> Result<(), String> {
+        let members = shader::reflect_spirv_block(spirv, block_name)?;
+        for field in self.fields.iter() {
+            let member = members
+                .iter()
+                .find(|member| member.name == field.name)
+                .ok_or_else(|| format!("block {} has no member {}", block_name, field.name))?;
+            if member.offset != field.offset {
+                return Err(format!(
+                    "member {} is at offset {} in the shader but at {} in the layout",
+                    field.name, member.offset, field.offset
+                ));
+            }
+            if field.array_count > 0 && member.array_stride != Some(field.array_stride) {
+                return Err(format!(
+                    "member {} has array stride {:?} in the shader but {} in the layout",
+                    field.name, member.array_stride, field.array_stride
+                ));
+            }
+            if field.matrix_stride > 0 && member.matrix_stride != Some(field.matrix_stride) {
+                return Err(format!(
+                    "member {} has matrix stride {:?} in the shader but {} in the layout",
+                    field.name, member.matrix_stride, field.matrix_stride
+                ));
+            }
+        }
+        if members.len() != self.fields.len() {
+            return Err(format!(
+                "block {} has {} members but the layout has {} fields",
+                block_name,
+                members.len(),
+                self.fields.len()
+            ));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,195 @@
+use cgmath::InnerSpace;
+
+//axis aligned bounding box, min bigger than max means the box is empty
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Aabb {
+    pub min: cgmath::Point3<f32>,
+    pub max: cgmath::Point3<f32>,
+}
+
+impl Aabb {
+    pub fn new(min: cgmath::Point3<f32>, max: cgmath::Point3<f32>) -> Self {
+        Self { min, max }
+    }
+
+    pub fn empty() -> Self {
+        Self {
+            min: cgmath::Point3::new(f32::MAX, f32::MAX, f32::MAX),
+            max: cgmath::Point3::new(f32::MIN, f32::MIN, f32::MIN),
+        }
+    }
+
+    pub fn from_points<I: IntoIterator<Item = cgmath::Point3<f32>>>(points: I) -> Self {
+        let mut aabb = Self::empty();
+        for point in points {
+            aabb.extend_point(point);
+        }
+        aabb
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.min.x > self.max.x || self.min.y > self.max.y || self.min.z > self.max.z
+    }
+
+    pub fn extend_point(&mut self, point: cgmath::Point3<f32>) {
+        self.min = cgmath::Point3::new(
+            self.min.x.min(point.x),
+            self.min.y.min(point.y),
+            self.min.z.min(point.z),
+        );
+        self.max = cgmath::Point3::new(
+            self.max.x.max(point.x),
+            self.max.y.max(point.y),
+            self.max.z.max(point.z),
+        );
+    }
+
+    pub fn union(&self, other: &Aabb) -> Aabb {
+        let mut aabb = *self;
+        if !other.is_empty() {
+            aabb.extend_point(other.min);
+            aabb.extend_point(other.max);
+        }
+        aabb
+    }
+
+    pub fn get_center(&self) -> cgmath::Point3<f32> {
+        cgmath::Point3::new(
+            (self.min.x + self.max.x) * 0.5,
+            (self.min.y + self.max.y) * 0.5,
+            (self.min.z + self.max.z) * 0.5,
+        )
+    }
+
+    //half size on each axis
+    pub fn get_extents(&self) -> cgmath::Vector3<f32> {
+        (self.max - self.min) * 0.5
+    }
+
+    pub fn get_corners(&self) -> [cgmath::Point3<f32>; 8] {
+        let (min, max) = (self.min, self.max);
+        [
+            cgmath::Point3::new(min.x, min.y, min.z),
+            cgmath::Point3::new(max.x, min.y, min.z),
+            cgmath::Point3::new(min.x, max.y, min.z),
+            cgmath::Point3::new(max.x, max.y, min.z),
+            cgmath::Point3::new(min.x, min.y, max.z),
+            cgmath::Point3::new(max.x, min.y, max.z),
+            cgmath::Point3::new(min.x, max.y, max.z),
+            cgmath::Point3::new(max.x, max.y, max.z),
+        ]
+    }
+
+    //box containing the transformed box, it grows with rotations
+    pub fn transform(&self, matrix: &cgmath::Matrix4<f32>) -> Aabb {
+        if self.is_empty() {
+            return *self;
+        }
+        Aabb::from_points(
+            self.get_corners()
+                .iter()
+                .map(|corner| cgmath::Transform::transform_point(matrix, *corner)),
+        )
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BoundingSphere {
+    pub center: cgmath::Point3<f32>,
+    pub radius: f32,
+}
+
+impl BoundingSphere {
+    //sphere through the corners of the box, the best we can do without the points
+    pub fn from_aabb(aabb: &Aabb) -> Self {
+        if aabb.is_empty() {
+            return Self {
+                center: cgmath::Point3::new(0.0, 0.0, 0.0),
+                radius: 0.0,
+            };
+        }
+        Self {
+            center: aabb.get_center(),
+            radius: aabb.get_extents().magnitude(),
+        }
+    }
+
+    //centered on the box of the points but only as big as the farthest point,
+    //tighter than from_aabb for round shapes
+    pub fn from_points(points: &[cgmath::Point3<f32>]) -> Self {
+        let center = Aabb::from_points(points.iter().cloned()).get_center();
+        if points.is_empty() {
+            return Self::from_aabb(&Aabb::empty());
+        }
+        let radius = points
+            .iter()
+            .map(|point| (point - center).magnitude())
+            .fold(0.0, f32::max);
+        Self { center, radius }
+    }
+
+    //the radius is scaled by the biggest axis scale so the sphere still contains
+    //the shape under non uniform scale
+    pub fn transform(&self, matrix: &cgmath::Matrix4<f32>) -> Self {
+        let scale = matrix
+            .x
+            .truncate()
+            .magnitude()
+            .max(matrix.y.truncate().magnitude())
+            .max(matrix.z.truncate().magnitude());
+        Self {
+            center: cgmath::Transform::transform_point(matrix, self.center),
+            radius: self.radius * scale,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Bounds {
+    pub aabb: Aabb,
+    pub sphere: BoundingSphere,
+}
+
+impl Default for Bounds {
+    fn default() -> Self {
+        Self::from_aabb(Aabb::empty())
+    }
+}
+
+impl Bounds {
+    pub fn from_aabb(aabb: Aabb) -> Self {
+        Self {
+            aabb,
+            sphere: BoundingSphere::from_aabb(&aabb),
+        }
+    }
+
+    pub fn from_points(points: &[cgmath::Point3<f32>]) -> Self {
+        Self {
+            aabb: Aabb::from_points(points.iter().cloned()),
+            sphere: BoundingSphere::from_points(points),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.aabb.is_empty()
+    }
+
+    //the sphere of the union is rebuilt from the box, merging spheres is not worth it
+    pub fn union(&self, other: &Bounds) -> Bounds {
+        if self.is_empty() {
+            return *other;
+        }
+        if other.is_empty() {
+            return *self;
+        }
+        Bounds::from_aabb(self.aabb.union(&other.aabb))
+    }
+
+    pub fn transform(&self, matrix: &cgmath::Matrix4<f32>) -> Bounds {
+        Bounds {
+            aabb: self.aabb.transform(matrix),
+            sphere: self.sphere.transform(matrix),
+        }
+    }
+}
@@ -1,3 +1,4 @@
+use super::bounds;
 use winit::event::*;
 
 #[rustfmt::skip] //just to avoid the matrix being formatted
@@ -24,6 +25,38 @@ impl Camera {
         let proj = cgmath::perspective(cgmath::Deg(self.fovy), self.aspect, self.znear, self.zfar);
         OPENGL_TO_WGPU_MATRIX * proj * view
     }
+
+    //looks at the center of the sphere from far enough to have all of it on screen,
+    //keeping the current view direction. Clip planes are left alone
+    pub fn frame_bounding_sphere(&mut self, sphere: &bounds::BoundingSphere) {
+        use cgmath::InnerSpace;
+        let half_fovy = cgmath::Rad::from(cgmath::Deg(self.fovy)).0 * 0.5;
+        //on narrow screens the horizontal field of view is the limit
+        let half_fovx = (half_fovy.tan() * self.aspect).atan();
+        let distance = sphere.radius / half_fovy.min(half_fovx).sin();
+
+        let direction = self.target - self.eye;
+        let direction = if direction.magnitude2() > 0.0 {
+            direction.normalize()
+        } else {
+            cgmath::Vector3::new(0.0, 0.0, -1.0)
+        };
+        self.target = sphere.center;
+        self.eye = sphere.center - direction * distance;
+    }
+
+    //radius in pixels the sphere covers on a screen of the given height, measured as
+    //if the sphere was in the middle of the view. Infinite with the eye inside it
+    pub fn get_projected_radius(&self, sphere: &bounds::BoundingSphere, screen_height: f32) -> f32 {
+        use cgmath::InnerSpace;
+        let distance = (sphere.center - self.eye).magnitude();
+        if distance <= sphere.radius {
+            return f32::INFINITY;
+        }
+        let half_fovy = cgmath::Rad::from(cgmath::Deg(self.fovy)).0 * 0.5;
+        let angular_radius = (sphere.radius / distance).asin();
+        angular_radius.tan() / half_fovy.tan() * screen_height * 0.5
+    }
 }
 
 pub struct CameraControllerFPS {
@@ -0,0 +1,277 @@
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::bindings;
+use super::shader;
+
+//the linter never creates a device, so we feed the parsing functions placeholder
+//formats, the values are irrelevant we only care about the parsing errors
+const LINT_SWAP_CHAIN_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Bgra8Unorm;
+const LINT_DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
+
+pub struct LintIssue {
+    pub file: String,
+    pub message: String,
+}
+
+#[derive(Default)]
+pub struct LintReport {
+    pub files_checked: u32,
+    pub issues: Vec<LintIssue>,
+}
+
+impl LintReport {
+    fn add_issue(&mut self, file: &str, message: String) {
+        self.issues.push(LintIssue {
+            file: String::from(file),
+            message,
+        });
+    }
+}
+
+fn collect_asset_files(dir: &Path, out_files: &mut Vec<PathBuf>) -> Result<(), String> {
+    let entries = fs::read_dir(dir)
+        .map_err(|e| format!("could not read directory {}: {}", dir.display(), e))?;
+    for entry in entries {
+        let path = entry
+            .map_err(|e| format!("could not read entry in {}: {}", dir.display(), e))?
+            .path();
+        if path.is_dir() {
+            collect_asset_files(&path, out_files)?;
+            continue;
+        }
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("pipeline") | Some("bg") => out_files.push(path),
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+fn load_json(file_name: &str) -> Result<Value, String> {
+    let source =
+        fs::read_to_string(file_name).map_err(|e| format!("could not read file: {}", e))?;
+    serde_json::from_str(&source[..]).map_err(|e| format!("invalid json: {}", e))
+}
+
+fn shader_file_exists(file_name: &str) -> bool {
+    Path::new(file_name).exists() || Path::new(&format!("{}.spv", file_name)).exists()
+}
+
+//walks the resource root and checks every pipeline and binding group found, file
+//references inside the assets are resolved exactly as the engine does at runtime,
+//so the linter is expected to run from the same working directory as the sandbox
+pub fn lint_resource_root(root: &str) -> LintReport {
+    let mut report = LintReport::default();
+
+    let mut files = Vec::new();
+    if let Err(message) = collect_asset_files(Path::new(root), &mut files) {
+        report.add_issue(root, message);
+        return report;
+    }
+    //deterministic output, read_dir order is platform dependent
+    files.sort();
+
+    for file in files.iter() {
+        let file_name = file.to_string_lossy().replace('\\', "/");
+        match file.extension().and_then(|ext| ext.to_str()) {
+            Some("pipeline") => lint_pipeline_file(&file_name, &mut report),
+            _ => lint_binding_group_file(&file_name, &mut report),
+        }
+        report.files_checked += 1;
+    }
+    report
+}
+
+pub fn lint_binding_group_file(file_name: &str, report: &mut LintReport) {
+    let bg_content_js = match load_json(file_name) {
+        Ok(value) => value,
+        Err(message) => return report.add_issue(file_name, message),
+    };
+
+    //checking each binding on its own so we can report all of them in one go
+    let bindings_values = match bg_content_js["bindings"].as_array() {
+        Some(values) => values,
+        None => {
+            return report.add_issue(
+                file_name,
+                String::from("missing or non array field \"bindings\""),
+            )
+        }
+    };
+    let mut used_slots = Vec::new();
+    let mut used_names = Vec::new();
+    for binding in bindings_values {
+        let single = serde_json::json!({ "bindings": [binding] });
+        if let Err(message) = bindings::get_bind_group_layout_entries(&single) {
+            report.add_issue(file_name, message);
+            continue;
+        }
+        let slot = binding["slot"].as_u64().unwrap();
+        if used_slots.contains(&slot) {
+            report.add_issue(file_name, format!("binding slot {} declared twice", slot));
+        }
+        used_slots.push(slot);
+
+        match &binding["name"] {
+            Value::Null => {}
+            Value::String(name) => {
+                if used_names.contains(name) {
+                    report.add_issue(file_name, format!("binding name {} declared twice", name));
+                }
+                used_names.push(name.clone());
+            }
+            _ => report.add_issue(file_name, String::from("binding \"name\" field is not a string")),
+        }
+    }
+}
+
+pub fn lint_pipeline_file(file_name: &str, report: &mut LintReport) {
+    let pipe_content_json = match load_json(file_name) {
+        Ok(value) => value,
+        Err(message) => return report.add_issue(file_name, message),
+    };
+
+    match pipe_content_json["type"].as_str() {
+        Some("raster") => {}
+        Some(pipe_type) => {
+            return report.add_issue(file_name, format!("unsupported pipeline type {}", pipe_type))
+        }
+        None => {
+            return report.add_issue(file_name, String::from("missing pipeline \"type\" field"))
+        }
+    }
+
+    match pipe_content_json["layout"].as_str() {
+        Some(layout_name) => {
+            if !Path::new(layout_name).exists() {
+                report.add_issue(
+                    file_name,
+                    format!("layout file {} does not exist", layout_name),
+                );
+            }
+        }
+        None => report.add_issue(file_name, String::from("missing \"layout\" field")),
+    }
+
+    if let Err(message) = bindings::get_pipeline_raster_state(&pipe_content_json) {
+        report.add_issue(file_name, message);
+    }
+    if let Err(message) = bindings::get_depth_stencil_state(&pipe_content_json, LINT_DEPTH_FORMAT)
+    {
+        report.add_issue(file_name, message);
+    }
+    if let Err(message) = bindings::get_primitive_topology(&pipe_content_json) {
+        report.add_issue(file_name, message);
+    }
+    if let Err(message) =
+        bindings::get_pipeline_color_states(&pipe_content_json, LINT_SWAP_CHAIN_FORMAT)
+    {
+        report.add_issue(file_name, message);
+    }
+
+    //fragment is optional, vertex is not
+    let fragment_value = &pipe_content_json["fragment"];
+    if !fragment_value.is_null() {
+        match fragment_value["shader_name"].as_str() {
+            Some(fragment_name) => {
+                let fragment_file = format!("{}.frag", fragment_name);
+                if !shader_file_exists(&fragment_file) {
+                    report.add_issue(
+                        file_name,
+                        format!("fragment shader {} does not exist", fragment_file),
+                    );
+                }
+            }
+            None => report.add_issue(
+                file_name,
+                String::from("missing fragment \"shader_name\" field"),
+            ),
+        }
+    }
+
+    let vertex_name = match pipe_content_json["vertex"]["shader_name"].as_str() {
+        Some(name) => name,
+        None => {
+            return report.add_issue(file_name, String::from("missing vertex \"shader_name\" field"))
+        }
+    };
+    let vertex_file = format!("{}.vert", vertex_name);
+    if !shader_file_exists(&vertex_file) {
+        return report.add_issue(
+            file_name,
+            format!("vertex shader {} does not exist", vertex_file),
+        );
+    }
+
+    let vertex_state_type = match pipe_content_json["vertex_state"]["type"].as_str() {
+        Some(vertex_state_type) => vertex_state_type,
+        None => {
+            return report.add_issue(file_name, String::from("missing vertex_state \"type\" field"))
+        }
+    };
+    let descriptors = match bindings::get_vertex_attrbibute_descriptor(vertex_state_type) {
+        Ok(descriptors) => descriptors,
+        Err(message) => return report.add_issue(file_name, message),
+    };
+
+    //only the glsl source can be checked, if we only ship the spv we trust it
+    let vertex_source = match fs::read_to_string(&vertex_file) {
+        Ok(source) => source,
+        Err(_) => return,
+    };
+    let inputs = match shader::get_glsl_vertex_inputs(&vertex_source[..]) {
+        Ok(inputs) => inputs,
+        Err(message) => return report.add_issue(&vertex_file, message),
+    };
+
+    let mut provided = HashMap::new();
+    for descriptor in descriptors.iter() {
+        for attribute in descriptor.attributes.iter() {
+            provided.insert(attribute.shader_location, attribute.format);
+        }
+    }
+
+    for input in inputs.iter() {
+        match provided.remove(&input.location) {
+            Some(format) => {
+                let expected_type = shader::get_vertex_format_glsl_type(format);
+                if expected_type != input.glsl_type {
+                    report.add_issue(
+                        file_name,
+                        format!(
+                            "vertex_state {} provides {} at location {} but {} reads {} {}",
+                            vertex_state_type,
+                            expected_type,
+                            input.location,
+                            vertex_file,
+                            input.glsl_type,
+                            input.name
+                        ),
+                    );
+                }
+            }
+            None => report.add_issue(
+                file_name,
+                format!(
+                    "vertex_state {} does not provide location {} read by {} as {}",
+                    vertex_state_type, input.location, vertex_file, input.name
+                ),
+            ),
+        }
+    }
+
+    let mut unused: Vec<&u32> = provided.keys().collect();
+    unused.sort();
+    for location in unused {
+        report.add_issue(
+            file_name,
+            format!(
+                "vertex_state {} provides location {} which is not read by {}",
+                vertex_state_type, location, vertex_file
+            ),
+        );
+    }
+}
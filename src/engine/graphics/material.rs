@@ -0,0 +1,69 @@
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AlphaMode {
+    Opaque,
+    //alpha is compared against the cutoff, fragments below it are discarded
+    Mask,
+    Blend,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MaterialTexture {
+    //index in the textures of the file the material comes from
+    pub texture: usize,
+    //which TexCoords set is used to sample the texture
+    pub tex_coord: u32,
+}
+
+//metallic-roughness pbr material, the defaults are the ones of the gltf spec
+#[derive(Clone, Debug, PartialEq)]
+pub struct Material {
+    pub name: Option<String>,
+    pub base_color_factor: [f32; 4],
+    pub base_color_texture: Option<MaterialTexture>,
+    pub metallic_factor: f32,
+    pub roughness_factor: f32,
+    //roughness in the green channel, metalness in the blue one
+    pub metallic_roughness_texture: Option<MaterialTexture>,
+    pub normal_texture: Option<MaterialTexture>,
+    pub normal_scale: f32,
+    pub occlusion_texture: Option<MaterialTexture>,
+    pub occlusion_strength: f32,
+    pub emissive_texture: Option<MaterialTexture>,
+    pub emissive_factor: [f32; 3],
+    pub alpha_mode: AlphaMode,
+    pub alpha_cutoff: f32,
+    pub double_sided: bool,
+}
+
+impl Default for Material {
+    fn default() -> Self {
+        Self {
+            name: None,
+            base_color_factor: [1.0, 1.0, 1.0, 1.0],
+            base_color_texture: None,
+            metallic_factor: 1.0,
+            roughness_factor: 1.0,
+            metallic_roughness_texture: None,
+            normal_texture: None,
+            normal_scale: 1.0,
+            occlusion_texture: None,
+            occlusion_strength: 1.0,
+            emissive_texture: None,
+            emissive_factor: [0.0, 0.0, 0.0],
+            alpha_mode: AlphaMode::Opaque,
+            alpha_cutoff: 0.5,
+            double_sided: false,
+        }
+    }
+}
+
+impl Material {
+    //color data is authored in srgb, everything else is linear
+    pub fn is_srgb_texture(&self, texture: usize) -> bool {
+        let is_texture = |slot: &Option<MaterialTexture>| match slot {
+            Some(slot) => slot.texture == texture,
+            None => false,
+        };
+        is_texture(&self.base_color_texture) || is_texture(&self.emissive_texture)
+    }
+}
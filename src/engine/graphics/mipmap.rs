@@ -0,0 +1,305 @@
+use super::texture;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum MipFilter {
+    //area average of the texels covered by the destination texel, cheap and never rings
+    #[default]
+    Box,
+    //kaiser windowed sinc, keeps more detail in the small levels but can ring a bit
+    Kaiser,
+}
+
+//half width of the kaiser kernel and the shape of its window, in destination texels
+const KAISER_WIDTH: f32 = 3.0;
+const KAISER_ALPHA: f32 = 4.0;
+
+//levels down to 1x1, each level is half the previous one rounded down
+pub fn get_mip_level_count(width: u32, height: u32) -> u32 {
+    32 - width.max(height).max(1).leading_zeros()
+}
+
+pub fn get_mip_size(size: u32, level: u32) -> u32 {
+    (size >> level).max(1)
+}
+
+pub fn srgb_to_linear(value: f32) -> f32 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+pub fn linear_to_srgb(value: f32) -> f32 {
+    if value <= 0.003_130_8 {
+        value * 12.92
+    } else {
+        1.055 * value.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+//zeroth order modified bessel function of the first kind, the series converges
+//quickly for the arguments the kaiser window uses
+fn bessel_i0(x: f32) -> f32 {
+    let mut sum = 1.0;
+    let mut term = 1.0;
+    let half_squared = x * x / 4.0;
+    let mut k = 1.0;
+    while term > sum * 1e-7 {
+        term *= half_squared / (k * k);
+        sum += term;
+        k += 1.0;
+    }
+    sum
+}
+
+fn sinc(x: f32) -> f32 {
+    if x.abs() < 1e-6 {
+        1.0
+    } else {
+        let x = x * std::f32::consts::PI;
+        x.sin() / x
+    }
+}
+
+fn kaiser(x: f32) -> f32 {
+    let ratio = x / KAISER_WIDTH;
+    if ratio.abs() >= 1.0 {
+        return 0.0;
+    }
+    sinc(x) * bessel_i0(KAISER_ALPHA * (1.0 - ratio * ratio).sqrt()) / bessel_i0(KAISER_ALPHA)
+}
+
+//for each destination texel the source texels it reads and their normalized weights.
+//Texels outside of the source are clamped to the edge
+fn get_filter_weights(
+    source_size: u32,
+    destination_size: u32,
+    filter: MipFilter,
+) -> Vec<Vec<(usize, f32)>> {
+    let scale = source_size as f32 / destination_size as f32;
+    let last = source_size as i64 - 1;
+    let mut weights = Vec::with_capacity(destination_size as usize);
+    for i in 0..destination_size {
+        let mut texel_weights: Vec<(usize, f32)> = Vec::new();
+        let mut add_weight = |j: i64, weight: f32| {
+            let j = j.max(0).min(last) as usize;
+            match texel_weights.iter_mut().find(|(index, _)| *index == j) {
+                Some((_, total)) => *total += weight,
+                None => texel_weights.push((j, weight)),
+            }
+        };
+        match filter {
+            MipFilter::Box => {
+                //with odd sizes a destination texel covers parts of the border texels
+                let start = i as f32 * scale;
+                let end = start + scale;
+                for j in start.floor() as i64..end.ceil() as i64 {
+                    let overlap = end.min(j as f32 + 1.0) - start.max(j as f32);
+                    if overlap > 0.0 {
+                        add_weight(j, overlap);
+                    }
+                }
+            }
+            MipFilter::Kaiser => {
+                let center = (i as f32 + 0.5) * scale;
+                let radius = KAISER_WIDTH * scale;
+                let first = (center - radius).floor() as i64;
+                let end = (center + radius).ceil() as i64;
+                for j in first..end {
+                    let weight = kaiser((j as f32 + 0.5 - center) / scale);
+                    if weight != 0.0 {
+                        add_weight(j, weight);
+                    }
+                }
+            }
+        }
+        let total: f32 = texel_weights.iter().map(|(_, weight)| weight).sum();
+        for (_, weight) in texel_weights.iter_mut() {
+            *weight /= total;
+        }
+        weights.push(texel_weights);
+    }
+    weights
+}
+
+//linear rgba texels, the format the filtering is done in
+struct LinearImage {
+    width: u32,
+    height: u32,
+    texels: Vec<[f32; 4]>,
+}
+
+impl LinearImage {
+    fn from_texture_data(data: &texture::TextureData) -> Result<Self, String> {
+        let texel_count = (data.width * data.height) as usize;
+        let expected_size = texel_count * data.bytes_per_pixel() as usize;
+        if data.pixels.len() != expected_size {
+            return Err(format!(
+                "expected {} bytes for a {}x{} texture but got {}",
+                expected_size,
+                data.width,
+                data.height,
+                data.pixels.len()
+            ));
+        }
+        let texels = match data.format {
+            wgpu::TextureFormat::Rgba8Unorm => data
+                .pixels
+                .chunks(4)
+                .map(|t| {
+                    let mut texel = [0.0; 4];
+                    for (value, byte) in texel.iter_mut().zip(t.iter()) {
+                        *value = *byte as f32 / 255.0;
+                    }
+                    texel
+                })
+                .collect(),
+            //alpha is never gamma encoded
+            wgpu::TextureFormat::Rgba8UnormSrgb => data
+                .pixels
+                .chunks(4)
+                .map(|t| {
+                    [
+                        srgb_to_linear(t[0] as f32 / 255.0),
+                        srgb_to_linear(t[1] as f32 / 255.0),
+                        srgb_to_linear(t[2] as f32 / 255.0),
+                        t[3] as f32 / 255.0,
+                    ]
+                })
+                .collect(),
+            wgpu::TextureFormat::Rgba16Float => data
+                .pixels
+                .chunks(8)
+                .map(|t| {
+                    let mut texel = [0.0; 4];
+                    for (value, bytes) in texel.iter_mut().zip(t.chunks(2)) {
+                        *value = texture::f16_to_f32(u16::from_le_bytes([bytes[0], bytes[1]]));
+                    }
+                    texel
+                })
+                .collect(),
+            wgpu::TextureFormat::Rgba32Float => data
+                .pixels
+                .chunks(16)
+                .map(|t| {
+                    let mut texel = [0.0; 4];
+                    for (value, bytes) in texel.iter_mut().zip(t.chunks(4)) {
+                        *value = f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+                    }
+                    texel
+                })
+                .collect(),
+            format => {
+                return Err(format!(
+                    "cannot generate mipmaps for texture format {:?}",
+                    format
+                ))
+            }
+        };
+        Ok(Self {
+            width: data.width,
+            height: data.height,
+            texels,
+        })
+    }
+
+    fn to_texture_data(&self, format: wgpu::TextureFormat) -> texture::TextureData {
+        let to_unorm = |value: f32| (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+        let mut pixels = Vec::with_capacity(self.texels.len() * 16);
+        for texel in self.texels.iter() {
+            match format {
+                wgpu::TextureFormat::Rgba8UnormSrgb => {
+                    for value in texel[0..3].iter() {
+                        pixels.push(to_unorm(linear_to_srgb(value.max(0.0))));
+                    }
+                    pixels.push(to_unorm(texel[3]));
+                }
+                wgpu::TextureFormat::Rgba16Float => {
+                    for value in texel.iter() {
+                        pixels.extend_from_slice(&texture::f32_to_f16(*value).to_le_bytes());
+                    }
+                }
+                wgpu::TextureFormat::Rgba32Float => {
+                    for value in texel.iter() {
+                        pixels.extend_from_slice(&value.to_le_bytes());
+                    }
+                }
+                _ => pixels.extend(texel.iter().map(|value| to_unorm(*value))),
+            }
+        }
+        texture::TextureData {
+            width: self.width,
+            height: self.height,
+            format,
+            pixels,
+        }
+    }
+
+    //separable filter, rows first and then columns
+    fn downsample(&self, width: u32, height: u32, filter: MipFilter) -> Self {
+        let column_weights = get_filter_weights(self.width, width, filter);
+        let row_weights = get_filter_weights(self.height, height, filter);
+
+        let mut horizontal = Vec::with_capacity((width * self.height) as usize);
+        for y in 0..self.height as usize {
+            let row = &self.texels[y * self.width as usize..(y + 1) * self.width as usize];
+            for weights in column_weights.iter() {
+                horizontal.push(weighted_sum(weights, |x| row[x]));
+            }
+        }
+
+        let mut texels = Vec::with_capacity((width * height) as usize);
+        for weights in row_weights.iter() {
+            for x in 0..width as usize {
+                texels.push(weighted_sum(weights, |y| {
+                    horizontal[y * width as usize + x]
+                }));
+            }
+        }
+
+        Self {
+            width,
+            height,
+            texels,
+        }
+    }
+}
+
+fn weighted_sum<F: Fn(usize) -> [f32; 4]>(weights: &[(usize, f32)], texel: F) -> [f32; 4] {
+    let mut sum = [0.0; 4];
+    for (index, weight) in weights.iter() {
+        let value = texel(*index);
+        for (channel, value) in sum.iter_mut().zip(value.iter()) {
+            *channel += value * weight;
+        }
+    }
+    sum
+}
+
+//every level of the chain starting from the given one, in the same format. Levels are
+//filtered from the previous one kept in linear float so the error does not add up
+pub fn generate_mip_chain(
+    data: &texture::TextureData,
+    filter: MipFilter,
+) -> Result<Vec<texture::TextureData>, String> {
+    let mut image = LinearImage::from_texture_data(data)?;
+    let level_count = get_mip_level_count(data.width, data.height);
+
+    let mut levels = Vec::with_capacity(level_count as usize);
+    levels.push(texture::TextureData {
+        width: data.width,
+        height: data.height,
+        format: data.format,
+        pixels: data.pixels.clone(),
+    });
+    for level in 1..level_count {
+        image = image.downsample(
+            get_mip_size(data.width, level),
+            get_mip_size(data.height, level),
+            filter,
+        );
+        levels.push(image.to_texture_data(data.format));
+    }
+    Ok(levels)
+}
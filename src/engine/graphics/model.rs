@@ -1,17 +1,41 @@
+use super::super::handle;
 use super::super::platform;
+use super::animation;
 use super::api;
+use super::bindings;
+use super::bounds;
+use super::material;
+use super::morph;
+use super::scene;
+use super::texture;
+use super::upload;
 use std::collections::HashMap;
 
-#[derive(PartialEq)]
+pub mod mesh_file;
+pub mod obj;
+pub mod ply;
+pub mod procedural;
+pub mod processing;
+pub mod simplify;
+pub mod stl;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum MeshBufferSemantic {
     None,
     Positions,
     Normals,
-    TexCoords,
+    Tangents,
+    //the value is the set index, TEXCOORD_1 is TexCoords(1)
+    TexCoords(u32),
+    Colors(u32),
+    Joints(u32),
+    Weights(u32),
 }
 
 pub struct MeshBufferMapper {
     pub semantic: MeshBufferSemantic,
+    //format of the data as stored in the buffer
+    pub format: wgpu::VertexFormat,
     pub offset: u32,
     pub length: u32,
     pub buffer_idx: u32,
@@ -20,278 +44,1838 @@ pub struct MeshBufferMapper {
 pub struct MeshIndexBufferMapper {
     pub offset: u32,
     pub length: u32,
-    pub is_uint16: bool,
+    //the pipeline drawing the mesh has to be built with the same format
+    pub format: wgpu::IndexFormat,
     pub buffer_idx: u32,
     pub count: u32,
 }
 
-#[derive(Default)]
 pub struct Mesh {
     pub buffers: Vec<MeshBufferMapper>,
     pub index_buffer: Option<MeshIndexBufferMapper>,
+    //the pipeline drawing the mesh needs to be built with the same topology
+    pub topology: wgpu::PrimitiveTopology,
+    //used to draw meshes without an index buffer
+    pub vertex_count: u32,
+    //index in the materials of the file, None means the default material
+    pub material: Option<usize>,
+    //only for primitives with morph targets, blended on the cpu
+    pub morph_targets: Option<morph::MorphTargetSet>,
+    //in the space of the mesh, large enough for any blend of the morph targets
+    pub bounds: bounds::Bounds,
+}
+
+impl Default for Mesh {
+    fn default() -> Self {
+        Self {
+            buffers: Vec::new(),
+            index_buffer: None,
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            vertex_count: 0,
+            material: None,
+            morph_targets: None,
+            bounds: bounds::Bounds::default(),
+        }
+    }
 }
 
 impl Mesh {
-    pub fn get_buffer_from_semantic(&self, semantic: MeshBufferSemantic) -> &MeshBufferMapper {
-        for buffer in self.buffers.iter() {
-            if buffer.semantic == semantic {
-                return buffer;
-            }
+    pub fn get_buffer_from_semantic(
+        &self,
+        semantic: MeshBufferSemantic,
+    ) -> Option<&MeshBufferMapper> {
+        self.buffers
+            .iter()
+            .find(|buffer| buffer.semantic == semantic)
+    }
+
+    //what the pipeline drawing the mesh has to be built with, meshes without indices
+    //are grouped with the 32 bit ones
+    pub fn get_pipeline_variant(&self) -> bindings::PipelineVariant {
+        bindings::PipelineVariant {
+            topology: self.topology,
+            index_format: self
+                .index_buffer
+                .as_ref()
+                .map_or(wgpu::IndexFormat::Uint32, |index_buffer| {
+                    index_buffer.format
+                }),
         }
+    }
+
+    //the buffers of the mesh matching the vertex streams of a pipeline, in slot order
+    pub fn get_vertex_streams(
+        &self,
+        streams: &[bindings::VertexStreamDescription],
+    ) -> Result<Vec<&MeshBufferMapper>, String> {
+        streams
+            .iter()
+            .map(|stream| {
+                let buffer = self
+                    .get_buffer_from_semantic(stream.semantic)
+                    .ok_or_else(|| {
+                        format!(
+                            "mesh has no {:?} buffer required by the pipeline",
+                            stream.semantic
+                        )
+                    })?;
+                if buffer.format != stream.format {
+                    return Err(format!(
+                        "mesh {:?} buffer is {:?} but the pipeline expects {:?}",
+                        stream.semantic, buffer.format, stream.format
+                    ));
+                }
+                Ok(buffer)
+            })
+            .collect()
+    }
 
-        panic!("Could not find buffer with requested semantic");
+    //binds the buffers the pipeline asks for and draws the mesh, the pipeline needs
+    //to be set already and built with the topology of the mesh
+    pub fn draw<'a>(
+        &'a self,
+        render_pass: &mut wgpu::RenderPass<'a>,
+        buffers: &'a HashMap<u32, wgpu::Buffer>,
+        streams: &[bindings::VertexStreamDescription],
+    ) -> Result<(), String> {
+        let get_buffer = |buffer_idx: u32| {
+            buffers
+                .get(&buffer_idx)
+                .ok_or_else(|| format!("could not find mesh buffer {}", buffer_idx))
+        };
+        for (slot, mapper) in self.get_vertex_streams(streams)?.iter().enumerate() {
+            render_pass.set_vertex_buffer(
+                slot as u32,
+                get_buffer(mapper.buffer_idx)?,
+                mapper.offset as u64,
+                0,
+            );
+        }
+        match &self.index_buffer {
+            Some(index_mapper) => {
+                render_pass.set_index_buffer(
+                    get_buffer(index_mapper.buffer_idx)?,
+                    index_mapper.offset as u64,
+                    0,
+                );
+                render_pass.draw_indexed(0..index_mapper.count, 0, 0..1);
+            }
+            None => render_pass.draw(0..self.vertex_count, 0..1),
+        }
+        Ok(())
     }
 }
 
 pub struct Model {
     pub meshes: Vec<Mesh>,
+    //union of the bounds of the meshes
+    pub bounds: bounds::Bounds,
 }
 
-pub struct GltfFile {
-    pub models: Vec<Model>,
-    pub buffers: HashMap<u32, wgpu::Buffer>,
+impl Model {
+    pub fn new(meshes: Vec<Mesh>) -> Self {
+        let bounds = meshes
+            .iter()
+            .fold(bounds::Bounds::default(), |bounds, mesh| {
+                bounds.union(&mesh.bounds)
+            });
+        Self { meshes, bounds }
+    }
 }
 
-fn load_gltf_mesh_primitive(
-    primitive: &gltf::Primitive,
-    gpu_raw_buffers: &mut HashMap<u32, wgpu::Buffer>,
-    raw_buffers: &HashMap<u32, Vec<u8>>,
-    gpu_interfaces: &api::GPUInterfaces,
-) -> Mesh {
-    let attributes = primitive.attributes();
-
-    //cannot get the size of the iterator because in doing so items are consumed
-    //and ownership transfered
-    let mut descriptors = Vec::new();
-    let mut counter: u32 = 0;
-
-    let mut mesh = Mesh::default();
-
-    for attribute in attributes {
-        //mapping the semantic to a vertex format
-        let semantic = attribute.0;
-        let accessor = &attribute.1;
-
-        //converting to wgpu vertex format
-        let (format, wgpu_semantic) = match semantic {
-            gltf::Semantic::Normals => {
-                debug_assert!(accessor.data_type() == gltf::accessor::DataType::F32);
-                (wgpu::VertexFormat::Float3, MeshBufferSemantic::Normals)
+//bytes of a single vertex in the given format
+pub fn get_vertex_format_size(format: wgpu::VertexFormat) -> usize {
+    use wgpu::VertexFormat::*;
+    match format {
+        Uchar2 | Char2 | Uchar2Norm | Char2Norm => 2,
+        Uchar4 | Char4 | Uchar4Norm | Char4Norm | Ushort2 | Short2 | Ushort2Norm | Short2Norm
+        | Half2 | Float | Uint | Int => 4,
+        Ushort4 | Short4 | Ushort4Norm | Short4Norm | Half4 | Float2 | Uint2 | Int2 => 8,
+        Float3 | Uint3 | Int3 => 12,
+        Float4 | Uint4 | Int4 => 16,
+    }
+}
+
+//a vertex attribute kept in the format the gpu reads it with, tightly packed
+#[derive(Clone, Debug, PartialEq)]
+pub struct VertexStreamData {
+    pub semantic: MeshBufferSemantic,
+    pub format: wgpu::VertexFormat,
+    pub data: Vec<u8>,
+}
+
+impl VertexStreamData {
+    pub fn get_vertex_count(&self) -> usize {
+        self.data.len() / get_vertex_format_size(self.format)
+    }
+
+    pub fn get_vertex(&self, vertex: usize) -> &[u8] {
+        let size = get_vertex_format_size(self.format);
+        &self.data[vertex * size..(vertex + 1) * size]
+    }
+}
+
+//cpu side of a mesh, everything needed to create the gpu one without a gpu around.
+//Attributes that are not there are left empty. The usual ones are stored as floats
+//so they can be processed before the upload, the others go in streams as they are
+#[derive(Clone, Debug, PartialEq)]
+pub struct MeshData {
+    pub topology: wgpu::PrimitiveTopology,
+    pub positions: Vec<[f32; 3]>,
+    pub normals: Vec<[f32; 3]>,
+    //first texture coordinate set
+    pub uvs: Vec<[f32; 2]>,
+    //xyz along increasing u, w is the handedness of the bitangent like in gltf
+    pub tangents: Vec<[f32; 4]>,
+    //first color set, linear rgba
+    pub colors: Vec<[f32; 4]>,
+    //skinning data, other texture coordinate and color sets
+    pub streams: Vec<VertexStreamData>,
+    //empty for meshes drawn without indices
+    pub indices: Vec<u32>,
+    //format of the index buffer, 16 bit indices are widened when they cannot reach
+    //every vertex
+    pub index_format: wgpu::IndexFormat,
+    //index in the materials of the file, None means the default material
+    pub material: Option<usize>,
+    pub morph_targets: Option<morph::MorphTargetSet>,
+    //bounds given by the file, None computes them from the positions. Processing only
+    //drops or repeats vertices so they stay valid through it
+    pub bounds: Option<bounds::Bounds>,
+}
+
+impl Default for MeshData {
+    fn default() -> Self {
+        Self {
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            positions: Vec::new(),
+            normals: Vec::new(),
+            uvs: Vec::new(),
+            tangents: Vec::new(),
+            colors: Vec::new(),
+            streams: Vec::new(),
+            indices: Vec::new(),
+            index_format: wgpu::IndexFormat::Uint16,
+            material: None,
+            morph_targets: None,
+            bounds: None,
+        }
+    }
+}
+
+impl MeshData {
+    pub fn get_vertex_count(&self) -> usize {
+        self.positions.len()
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        let count = self.positions.len();
+        let attributes = [
+            ("normals", self.normals.len()),
+            ("uvs", self.uvs.len()),
+            ("tangents", self.tangents.len()),
+            ("colors", self.colors.len()),
+        ];
+        for (name, len) in attributes.iter() {
+            if *len != 0 && *len != count {
+                return Err(format!("mesh has {} {} for {} vertices", len, name, count));
             }
-            gltf::Semantic::Positions => {
-                debug_assert!(accessor.data_type() == gltf::accessor::DataType::F32);
-                (wgpu::VertexFormat::Float3, MeshBufferSemantic::Positions)
+        }
+        for stream in self.streams.iter() {
+            if stream.data.len() != count * get_vertex_format_size(stream.format) {
+                return Err(format!(
+                    "mesh {:?} stream has {} bytes for {} {:?} vertices",
+                    stream.semantic,
+                    stream.data.len(),
+                    count,
+                    stream.format
+                ));
             }
-            //_ => panic!("GLTF atttribute semantic not yet supported {:?}", semantic),
-            _ => {
-                platform::core::to_console(
-                    &format!(
-                        "GLTF atttribute semantic not yet supported {:?} and will be ignored...",
-                        semantic
-                    )[..],
-                );
+        }
+        if let Some(morph_targets) = self.morph_targets.as_ref() {
+            if morph_targets.base.len() != count {
+                return Err(format!(
+                    "mesh has {} morphed vertices for {} vertices",
+                    morph_targets.base.len(),
+                    count
+                ));
+            }
+        }
+        if let Some(index) = self.indices.iter().find(|index| **index as usize >= count) {
+            return Err(format!(
+                "mesh index {} is out of the {} vertices",
+                index, count
+            ));
+        }
+        Ok(())
+    }
+
+    //the bounds of the file when there are some. Otherwise morph targets grow the box
+    //by their displacements, as if all of them had weight one, and without them the
+    //sphere is fitted to the points
+    pub fn get_bounds(&self) -> bounds::Bounds {
+        if let Some(bounds) = self.bounds {
+            return bounds;
+        }
+        let points: Vec<cgmath::Point3<f32>> = self.positions.iter().map(|p| (*p).into()).collect();
+        let morph_targets = match self.morph_targets.as_ref() {
+            Some(morph_targets) if !points.is_empty() => morph_targets,
+            _ => return bounds::Bounds::from_points(&points[..]),
+        };
+        let mut aabb = bounds::Aabb::from_points(points.iter().cloned());
+        for target in morph_targets.targets.iter() {
+            if target.positions.is_empty() {
                 continue;
             }
+            let range = bounds::Aabb::from_points(target.positions.iter().map(|p| (*p).into()));
+            aabb.min += cgmath::Vector3::new(
+                range.min.x.min(0.0),
+                range.min.y.min(0.0),
+                range.min.z.min(0.0),
+            );
+            aabb.max += cgmath::Vector3::new(
+                range.max.x.max(0.0),
+                range.max.y.max(0.0),
+                range.max.z.max(0.0),
+            );
+        }
+        bounds::Bounds::from_aabb(aabb)
+    }
+
+    //rebuilds every vertex attribute from the listed vertices, in that order. A vertex
+    //can be listed more than once to split it, the indices are left to the caller
+    pub fn remap_vertices(&mut self, sources: &[u32]) {
+        fn remap<T: Copy>(values: &mut Vec<T>, sources: &[u32]) {
+            if !values.is_empty() {
+                *values = sources
+                    .iter()
+                    .map(|source| values[*source as usize])
+                    .collect();
+            }
+        }
+        remap(&mut self.positions, sources);
+        remap(&mut self.normals, sources);
+        remap(&mut self.uvs, sources);
+        remap(&mut self.tangents, sources);
+        remap(&mut self.colors, sources);
+        for stream in self.streams.iter_mut() {
+            let data: Vec<u8> = sources
+                .iter()
+                .flat_map(|source| stream.get_vertex(*source as usize).to_vec())
+                .collect();
+            stream.data = data;
+        }
+        if let Some(morph_targets) = self.morph_targets.as_mut() {
+            remap(&mut morph_targets.base.positions, sources);
+            remap(&mut morph_targets.base.normals, sources);
+            remap(&mut morph_targets.base.tangents, sources);
+            for target in morph_targets.targets.iter_mut() {
+                remap(&mut target.positions, sources);
+                remap(&mut target.normals, sources);
+                remap(&mut target.tangents, sources);
+            }
+        }
+    }
+
+    //the mesh drawing this data, one buffer per attribute plus one for the indices.
+    //The content of every buffer is handed to add_buffer which returns the index the
+    //mesh refers to it with
+    pub fn create_mesh<F: FnMut(Vec<u8>) -> u32>(&self, mut add_buffer: F) -> Mesh {
+        let mut mesh = Mesh {
+            topology: self.topology,
+            vertex_count: self.positions.len() as u32,
+            material: self.material,
+            morph_targets: self.morph_targets.clone(),
+            bounds: self.get_bounds(),
+            ..Default::default()
         };
+        let float_streams: [(MeshBufferSemantic, wgpu::VertexFormat, &[u8]); 5] = [
+            (
+                MeshBufferSemantic::Positions,
+                wgpu::VertexFormat::Float3,
+                bytemuck::cast_slice(&self.positions[..]),
+            ),
+            (
+                MeshBufferSemantic::Normals,
+                wgpu::VertexFormat::Float3,
+                bytemuck::cast_slice(&self.normals[..]),
+            ),
+            (
+                MeshBufferSemantic::TexCoords(0),
+                wgpu::VertexFormat::Float2,
+                bytemuck::cast_slice(&self.uvs[..]),
+            ),
+            (
+                MeshBufferSemantic::Tangents,
+                wgpu::VertexFormat::Float4,
+                bytemuck::cast_slice(&self.tangents[..]),
+            ),
+            (
+                MeshBufferSemantic::Colors(0),
+                wgpu::VertexFormat::Float4,
+                bytemuck::cast_slice(&self.colors[..]),
+            ),
+        ];
+        let other_streams = self
+            .streams
+            .iter()
+            .map(|stream| (stream.semantic, stream.format, &stream.data[..]));
+        for (semantic, format, data) in float_streams.iter().cloned().chain(other_streams) {
+            if data.is_empty() {
+                continue;
+            }
+            mesh.buffers.push(MeshBufferMapper {
+                semantic,
+                format,
+                offset: 0,
+                length: data.len() as u32,
+                buffer_idx: add_buffer(data.to_vec()),
+            });
+        }
+        if !self.indices.is_empty() {
+            let (format, data) = match self.index_format {
+                wgpu::IndexFormat::Uint16 => pack_indices(&self.indices[..]),
+                wgpu::IndexFormat::Uint32 => (
+                    wgpu::IndexFormat::Uint32,
+                    bytemuck::cast_slice(&self.indices[..]).to_vec(),
+                ),
+            };
+            mesh.index_buffer = Some(MeshIndexBufferMapper {
+                offset: 0,
+                length: data.len() as u32,
+                format,
+                buffer_idx: add_buffer(data),
+                count: self.indices.len() as u32,
+            });
+        }
+        mesh
+    }
 
-        //building the complete vertex mapper
-        let offset = accessor.offset() as u64;
-        let shader_location = counter;
-        descriptors.push(wgpu::VertexAttributeDescriptor {
-            offset,
-            shader_location,
-            format,
+    //creates the buffers of create_mesh and adds them to the given map with indices
+    //past the ones already there. The data goes up straight away
+    pub fn upload(
+        &self,
+        buffers: &mut HashMap<u32, wgpu::Buffer>,
+        gpu_interfaces: &api::GPUInterfaces,
+        upload_manager: &mut upload::UploadManager,
+    ) -> Result<Mesh, String> {
+        self.validate()?;
+        let first_idx = buffers.keys().max().map_or(0, |idx| idx + 1);
+        let mut uploads: Vec<(u32, wgpu::Buffer, Vec<u8>)> = Vec::new();
+        let mesh = self.create_mesh(|data| {
+            let buffer_idx = first_idx + uploads.len() as u32;
+            let wgpu_buffer = create_gltf_gpu_buffer(data.len() as u64, gpu_interfaces);
+            uploads.push((buffer_idx, wgpu_buffer, data));
+            buffer_idx
         });
 
-        //extracting the buffer data
-        let buffer_view = accessor.view().unwrap();
-        let buffer = buffer_view.buffer();
-        let buffer_idx = buffer.index();
-        //just making sure the buffer is in the raw list
-        assert!(gpu_raw_buffers.contains_key(&(buffer_idx as u32)));
-
-        //need to find the correct slice of the buffer
-        let accessor_offset = accessor.offset();
-        let view_offset = buffer_view.offset();
-        let total_offset = accessor_offset + view_offset;
-        //this is where the view end, but not necessarely the length of the buffer
-        let view_len = buffer_view.length();
-
-        let mesh_buffer = MeshBufferMapper {
-            semantic: wgpu_semantic,
-            offset: total_offset as u32,
-            length: view_len as u32,
-            buffer_idx: buffer_idx as u32,
-        };
+        let mut encoder =
+            gpu_interfaces
+                .device
+                .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                    label: Some("mesh upload encoder"),
+                });
+        let mut batch = upload_manager.begin_batch();
+        for (_, wgpu_buffer, data) in uploads.iter() {
+            batch.write_buffer(wgpu_buffer, 0, &data[..]);
+        }
+        batch.flush(&gpu_interfaces.device, &mut encoder);
+        gpu_interfaces.queue.submit(vec![encoder.finish()]);
 
-        mesh.buffers.push(mesh_buffer);
+        for (buffer_idx, wgpu_buffer, _) in uploads.into_iter() {
+            buffers.insert(buffer_idx, wgpu_buffer);
+        }
+        Ok(mesh)
+    }
+}
+
+pub struct GltfFile {
+    //one model per gltf mesh, in the same order
+    pub models: Vec<Model>,
+    pub buffers: HashMap<u32, wgpu::Buffer>,
+    pub scene_graph: scene::SceneGraph,
+    pub materials: Vec<material::Material>,
+    //one per gltf texture, materials reference them by index
+    pub textures: Vec<GltfTexture>,
+    //scene graph nodes reference skins by index
+    pub skins: Vec<animation::Skin>,
+    pub animations: Vec<animation::AnimationClip>,
+}
+
+impl GltfFile {
+    //the meshes of the model needing the given pipeline variant, a pipeline built
+    //for it needs to be set on the pass already
+    pub fn draw_model<'a>(
+        &'a self,
+        model: usize,
+        variant: bindings::PipelineVariant,
+        render_pass: &mut wgpu::RenderPass<'a>,
+        streams: &[bindings::VertexStreamDescription],
+    ) -> Result<(), String> {
+        let model = self
+            .models
+            .get(model)
+            .ok_or_else(|| format!("could not find model {}", model))?;
+        for mesh in model
+            .meshes
+            .iter()
+            .filter(|mesh| mesh.get_pipeline_variant() == variant)
+        {
+            mesh.draw(render_pass, &self.buffers, streams)?;
+        }
+        Ok(())
+    }
 
-        counter += 1;
+    //every mesh of every model needing the given pipeline variant, without any transform
+    pub fn draw_all<'a>(
+        &'a self,
+        variant: bindings::PipelineVariant,
+        render_pass: &mut wgpu::RenderPass<'a>,
+        streams: &[bindings::VertexStreamDescription],
+    ) -> Result<(), String> {
+        for model in 0..self.models.len() {
+            self.draw_model(model, variant, render_pass, streams)?;
+        }
+        Ok(())
+    }
+
+    //the pipeline variants used by the meshes of the file, one pipeline is needed for each
+    pub fn get_pipeline_variants(&self) -> Vec<bindings::PipelineVariant> {
+        let mut variants = Vec::new();
+        for mesh in self.models.iter().flat_map(|model| model.meshes.iter()) {
+            let variant = mesh.get_pipeline_variant();
+            if !variants.contains(&variant) {
+                variants.push(variant);
+            }
+        }
+        variants
     }
+}
 
-    //next load index buffer if there is one
-    let index_accessor = primitive.indices();
-    match index_accessor {
-        Some(idx_accessor) => {
-            //read the index buffer
-            let buffer_view = idx_accessor.view().unwrap();
-            let buffer = buffer_view.buffer();
-            let mut buffer_idx = buffer.index();
-            //just making sure the buffer is in the raw list
-            assert!(gpu_raw_buffers.contains_key(&(buffer_idx as u32)));
+pub struct GltfTexture {
+    //owned by the texture manager, images used by many files are only loaded once
+    pub texture: handle::ResourceHandle,
+    //the sampler is a property of the gltf texture, not of the image
+    pub sampler: wgpu::Sampler,
+}
 
-            let accessor_offset = idx_accessor.offset();
-            let view_offset = buffer_view.offset();
-            let mut total_offset = accessor_offset + view_offset;
-            let mut view_len = buffer_view.length();
-            let count = idx_accessor.count() as u32;
+//cpu side of a model, one mesh data per primitive
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ModelData {
+    pub meshes: Vec<MeshData>,
+}
 
-            let is_uint16 = match idx_accessor.data_type() {
-                gltf::accessor::DataType::U16 => true,
-                gltf::accessor::DataType::U32 => false,
-                _ => panic!("unexpected datatype for index buffer"),
+impl ModelData {
+    pub fn get_bounds(&self) -> bounds::Bounds {
+        self.meshes
+            .iter()
+            .fold(bounds::Bounds::default(), |bounds, mesh| {
+                bounds.union(&mesh.get_bounds())
+            })
+    }
+}
+
+pub enum TextureSource {
+    //image in its own file, loaded through the texture manager at upload
+    File(String),
+    //image already decoded, embedded in the file or made up by the importer
+    Data(texture::TextureData),
+}
+
+pub struct TextureImportData {
+    pub label: String,
+    pub source: TextureSource,
+    pub color_space: texture::ColorSpace,
+    pub sampler: texture::SamplerSettings,
+}
+
+//an imported file before anything is created on the gpu, upload turns it into the
+//GltfFile the renderer draws. Indices between the parts are the ones of GltfFile
+pub struct ModelFileData {
+    pub models: Vec<ModelData>,
+    pub scene_graph: scene::SceneGraph,
+    pub materials: Vec<material::Material>,
+    pub textures: Vec<TextureImportData>,
+    pub skins: Vec<animation::Skin>,
+    pub animations: Vec<animation::AnimationClip>,
+}
+
+impl ModelFileData {
+    //a texture file that cannot be loaded is replaced by a white texture. All the
+    //buffers and textures go up in a single batch
+    pub async fn upload(
+        self,
+        gpu_interfaces: &api::GPUInterfaces,
+        upload_manager: &mut upload::UploadManager,
+        texture_manager: &mut texture::TextureManager,
+    ) -> GltfFile {
+        let mut textures = Vec::new();
+        for texture_data in self.textures.into_iter() {
+            let texture_handle = match texture_data.source {
+                TextureSource::File(path) => match texture_manager
+                    .load_texture(&path[..], texture_data.color_space, gpu_interfaces)
+                    .await
+                {
+                    Ok(texture_handle) => texture_handle,
+                    Err(message) => {
+                        platform::core::to_console(
+                            &format!("{}, using a white texture instead", message)[..],
+                        );
+                        texture_manager.create_texture_from_data(
+                            &texture_data.label[..],
+                            texture::TextureData::from_color(
+                                [255, 255, 255, 255],
+                                texture_data.color_space,
+                            ),
+                            gpu_interfaces,
+                        )
+                    }
+                },
+                TextureSource::Data(data) => texture_manager.create_texture_from_data(
+                    &texture_data.label[..],
+                    data,
+                    gpu_interfaces,
+                ),
             };
+            let sampler = texture_data
+                .sampler
+                .create_sampler(&gpu_interfaces.device, &texture_data.label[..]);
+            textures.push(GltfTexture {
+                texture: texture_handle,
+                sampler,
+            });
+        }
 
-            //let us convert the buffer to u32
-            if is_uint16 {
-                //lets allocate a buffer big enough
-                let mut idx_buffer_32 = Vec::new();
-                idx_buffer_32.reserve(count as usize);
-                let raw_buffer = raw_buffers.get(&(buffer_idx as u32)).unwrap();
-                let indices: &[u16] = bytemuck::cast_slice(&raw_buffer[total_offset..]);
-                for i in 0..count {
-                    idx_buffer_32.push(indices[i as usize] as u32);
-                }
+        let mut buffer_data: Vec<Vec<u8>> = Vec::new();
+        let models = self
+            .models
+            .iter()
+            .map(|model_data| {
+                let meshes = model_data
+                    .meshes
+                    .iter()
+                    .map(|mesh_data| {
+                        mesh_data.create_mesh(|data| {
+                            buffer_data.push(data);
+                            buffer_data.len() as u32 - 1
+                        })
+                    })
+                    .collect();
+                Model::new(meshes)
+            })
+            .collect();
+        let buffers: HashMap<u32, wgpu::Buffer> = buffer_data
+            .iter()
+            .enumerate()
+            .map(|(buffer_idx, data)| {
+                (
+                    buffer_idx as u32,
+                    create_gltf_gpu_buffer(data.len() as u64, gpu_interfaces),
+                )
+            })
+            .collect();
 
+        let mut encoder =
+            gpu_interfaces
+                .device
+                .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                    label: Some("model upload encoder"),
+                });
+        let mut uploads = upload_manager.begin_batch();
+        for (buffer_idx, wgpu_buffer) in buffers.iter() {
+            uploads.write_buffer(wgpu_buffer, 0, &buffer_data[*buffer_idx as usize][..]);
+        }
+        texture_manager.upload_pending(&mut uploads);
+        uploads.flush(&gpu_interfaces.device, &mut encoder);
+        gpu_interfaces.queue.submit(vec![encoder.finish()]);
 
-                //create a wgpu buffer
-                let wgpu_buffer = gpu_interfaces.device.create_buffer_with_data(
-                    bytemuck::cast_slice(&idx_buffer_32[..]),
-                    wgpu::BufferUsage::INDEX,
-                );
+        GltfFile {
+            models,
+            buffers,
+            scene_graph: self.scene_graph,
+            materials: self.materials,
+            textures,
+            skins: self.skins,
+            animations: self.animations,
+        }
+    }
+}
+
+//maps the component type, count and normalization of an accessor to a vertex format,
+//wgpu has no three components 8 or 16 bit formats so those cannot be mapped
+pub fn get_vertex_format(
+    data_type: gltf::accessor::DataType,
+    dimensions: gltf::accessor::Dimensions,
+    normalized: bool,
+) -> Result<wgpu::VertexFormat, String> {
+    use gltf::accessor::DataType;
+    use gltf::accessor::Dimensions;
+    let format = match (data_type, dimensions, normalized) {
+        (DataType::F32, Dimensions::Scalar, false) => wgpu::VertexFormat::Float,
+        (DataType::F32, Dimensions::Vec2, false) => wgpu::VertexFormat::Float2,
+        (DataType::F32, Dimensions::Vec3, false) => wgpu::VertexFormat::Float3,
+        (DataType::F32, Dimensions::Vec4, false) => wgpu::VertexFormat::Float4,
+        (DataType::U32, Dimensions::Scalar, false) => wgpu::VertexFormat::Uint,
+        (DataType::U32, Dimensions::Vec2, false) => wgpu::VertexFormat::Uint2,
+        (DataType::U32, Dimensions::Vec3, false) => wgpu::VertexFormat::Uint3,
+        (DataType::U32, Dimensions::Vec4, false) => wgpu::VertexFormat::Uint4,
+        (DataType::U8, Dimensions::Vec2, false) => wgpu::VertexFormat::Uchar2,
+        (DataType::U8, Dimensions::Vec4, false) => wgpu::VertexFormat::Uchar4,
+        (DataType::U8, Dimensions::Vec2, true) => wgpu::VertexFormat::Uchar2Norm,
+        (DataType::U8, Dimensions::Vec4, true) => wgpu::VertexFormat::Uchar4Norm,
+        (DataType::I8, Dimensions::Vec2, false) => wgpu::VertexFormat::Char2,
+        (DataType::I8, Dimensions::Vec4, false) => wgpu::VertexFormat::Char4,
+        (DataType::I8, Dimensions::Vec2, true) => wgpu::VertexFormat::Char2Norm,
+        (DataType::I8, Dimensions::Vec4, true) => wgpu::VertexFormat::Char4Norm,
+        (DataType::U16, Dimensions::Vec2, false) => wgpu::VertexFormat::Ushort2,
+        (DataType::U16, Dimensions::Vec4, false) => wgpu::VertexFormat::Ushort4,
+        (DataType::U16, Dimensions::Vec2, true) => wgpu::VertexFormat::Ushort2Norm,
+        (DataType::U16, Dimensions::Vec4, true) => wgpu::VertexFormat::Ushort4Norm,
+        (DataType::I16, Dimensions::Vec2, false) => wgpu::VertexFormat::Short2,
+        (DataType::I16, Dimensions::Vec4, false) => wgpu::VertexFormat::Short4,
+        (DataType::I16, Dimensions::Vec2, true) => wgpu::VertexFormat::Short2Norm,
+        (DataType::I16, Dimensions::Vec4, true) => wgpu::VertexFormat::Short4Norm,
+        _ => {
+            return Err(format!(
+                "no vertex format for {:?} {:?} with normalized {}",
+                data_type, dimensions, normalized
+            ))
+        }
+    };
+    Ok(format)
+}
+
+//converts the gltf attribute to our semantic, and checks the data layout is one
+//the gltf spec allows for that semantic, KHR_mesh_quantization layouts included.
+//The returned format is the one the data is stored with on the gpu: floats and
+//normalized integers keep their own format when wgpu has one, joints stay integers,
+//anything else is converted to floats by the loader
+pub fn get_gltf_attribute_format(
+    semantic: &gltf::Semantic,
+    data_type: gltf::accessor::DataType,
+    dimensions: gltf::accessor::Dimensions,
+    normalized: bool,
+) -> Result<(MeshBufferSemantic, wgpu::VertexFormat), String> {
+    use gltf::accessor::DataType;
+    use gltf::accessor::Dimensions;
+
+    //float data or normalized unsigned integers
+    let is_float_or_unorm = match data_type {
+        DataType::F32 => !normalized,
+        DataType::U8 | DataType::U16 => normalized,
+        _ => false,
+    };
+    //float data or normalized signed integers
+    let is_float_or_snorm = match data_type {
+        DataType::F32 => !normalized,
+        DataType::I8 | DataType::I16 => normalized,
+        _ => false,
+    };
+    //float data or any 8 and 16 bit integer
+    let is_float_or_small_integer = match data_type {
+        DataType::F32 => !normalized,
+        DataType::U32 => false,
+        _ => true,
+    };
+    let (mesh_semantic, is_valid) = match semantic {
+        gltf::Semantic::Positions => (
+            MeshBufferSemantic::Positions,
+            is_float_or_small_integer && dimensions == Dimensions::Vec3,
+        ),
+        gltf::Semantic::Normals => (
+            MeshBufferSemantic::Normals,
+            is_float_or_snorm && dimensions == Dimensions::Vec3,
+        ),
+        gltf::Semantic::Tangents => (
+            MeshBufferSemantic::Tangents,
+            is_float_or_snorm && dimensions == Dimensions::Vec4,
+        ),
+        gltf::Semantic::TexCoords(set) => (
+            MeshBufferSemantic::TexCoords(*set),
+            is_float_or_small_integer && dimensions == Dimensions::Vec2,
+        ),
+        gltf::Semantic::Colors(set) => (
+            MeshBufferSemantic::Colors(*set),
+            is_float_or_unorm && (dimensions == Dimensions::Vec3 || dimensions == Dimensions::Vec4),
+        ),
+        gltf::Semantic::Joints(set) => (
+            MeshBufferSemantic::Joints(*set),
+            (data_type == DataType::U8 || data_type == DataType::U16)
+                && !normalized
+                && dimensions == Dimensions::Vec4,
+        ),
+        gltf::Semantic::Weights(set) => (
+            MeshBufferSemantic::Weights(*set),
+            is_float_or_unorm && dimensions == Dimensions::Vec4,
+        ),
+        #[allow(unreachable_patterns)]
+        _ => {
+            return Err(format!(
+                "gltf attribute semantic {:?} is not supported",
+                semantic
+            ))
+        }
+    };
+    if !is_valid {
+        return Err(format!(
+            "gltf attribute {:?} cannot be stored as {:?} {:?} with normalized {}",
+            semantic, data_type, dimensions, normalized
+        ));
+    }
+
+    let keeps_native_format = match mesh_semantic {
+        MeshBufferSemantic::Joints(_) => true,
+        _ => data_type == DataType::F32 || normalized,
+    };
+    let native_format = get_vertex_format(data_type, dimensions, normalized);
+    let format = match native_format {
+        Ok(format) if keeps_native_format => format,
+        _ => get_vertex_format(DataType::F32, dimensions, false)?,
+    };
+    Ok((mesh_semantic, format))
+}
+
+//elements of the accessor tightly packed in their own component type, following the
+//stride of the view and replacing the values listed by the sparse storage. An accessor
+//without a view starts from zeros
+pub fn read_gltf_accessor(
+    accessor: &gltf::Accessor,
+    raw_buffers: &HashMap<u32, Vec<u8>>,
+) -> Result<Vec<u8>, String> {
+    let element_size = accessor.size();
+    let count = accessor.count();
+    let get_view_data = |view: &gltf::buffer::View, offset: usize| -> Result<&[u8], String> {
+        let buffer = match raw_buffers.get(&(view.buffer().index() as u32)) {
+            Some(buffer) => buffer,
+            None => return Err(format!("buffer {} is not loaded", view.buffer().index())),
+        };
+        match buffer.get(view.offset() + offset..view.offset() + view.length()) {
+            Some(data) => Ok(data),
+            None => Err(format!("view {} is out of bounds", view.index())),
+        }
+    };
+
+    let mut data = vec![0u8; element_size * count];
+    if let Some(view) = accessor.view() {
+        let source = get_view_data(&view, accessor.offset())?;
+        let stride = view.stride().unwrap_or(element_size);
+        if count > 0 && (count - 1) * stride + element_size > source.len() {
+            return Err(format!(
+                "accessor {} does not fit in view {}",
+                accessor.index(),
+                view.index()
+            ));
+        }
+        for (i, element) in data.chunks_mut(element_size).enumerate() {
+            element.copy_from_slice(&source[i * stride..i * stride + element_size]);
+        }
+    }
 
-                //NOTE this is a bit of a quirk, I am mostly not sure of the best way to keep track
-                //of buffer added,I should probably need a higher level structure or similar, for now to keep
-                //it simple I flip the last bit of the 32 bit version of the index, that would basically
-                //signify we re-copied it and converted it. The possibility of a clash is really small,
-                //since this is a mapping per gltf file. if we get to that many buffers we probably have
-                //much bigger problems to deal with. I am not even sure the gpu allows to allocate that many
-                //buffers
-                buffer_idx = buffer_idx | (1 << 31);
-
-                //just making sure the buffer does not already exsits
-                assert!(!gpu_raw_buffers.contains_key(&(buffer_idx as u32)));
-                gpu_raw_buffers.insert(buffer_idx as u32, wgpu_buffer);
-
-                total_offset = 0;
-                view_len = (count * 4) as usize;
+    if let Some(sparse) = accessor.sparse() {
+        let indices = sparse.indices();
+        let values = sparse.values();
+        let index_data = get_view_data(&indices.view(), indices.offset() as usize)?;
+        let value_data = get_view_data(&values.view(), values.offset() as usize)?;
+        let sparse_count = sparse.count() as usize;
+        let index_size = match indices.index_type() {
+            gltf::accessor::sparse::IndexType::U8 => 1,
+            gltf::accessor::sparse::IndexType::U16 => 2,
+            gltf::accessor::sparse::IndexType::U32 => 4,
+        };
+        if index_data.len() < sparse_count * index_size
+            || value_data.len() < sparse_count * element_size
+        {
+            return Err(format!(
+                "sparse storage of accessor {} is out of bounds",
+                accessor.index()
+            ));
+        }
+        for i in 0..sparse_count {
+            let bytes = &index_data[i * index_size..(i + 1) * index_size];
+            let index = match index_size {
+                1 => bytes[0] as usize,
+                2 => u16::from_le_bytes([bytes[0], bytes[1]]) as usize,
+                _ => u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize,
+            };
+            if index >= count {
+                return Err(format!(
+                    "sparse index {} of accessor {} is out of bounds",
+                    index,
+                    accessor.index()
+                ));
             }
+            data[index * element_size..(index + 1) * element_size]
+                .copy_from_slice(&value_data[i * element_size..(i + 1) * element_size]);
+        }
+    }
+    Ok(data)
+}
 
-            let mesh_idx_buffer = MeshIndexBufferMapper {
-                offset: total_offset as u32,
-                length: view_len as u32,
-                is_uint16: false,
-                buffer_idx: buffer_idx as u32,
-                count,
+//packed components to floats, normalized integers are mapped to [0,1] or [-1,1] with
+//the formulas of the gltf spec, the others keep their integer value
+pub fn convert_gltf_components_to_f32(
+    data: &[u8],
+    data_type: gltf::accessor::DataType,
+    normalized: bool,
+) -> Vec<f32> {
+    use gltf::accessor::DataType;
+    match data_type {
+        DataType::F32 => data
+            .chunks(4)
+            .map(|c| f32::from_le_bytes([c[0], c[1], c[2], c[3]]))
+            .collect(),
+        DataType::U8 => data
+            .iter()
+            .map(|v| match normalized {
+                true => *v as f32 / 255.0,
+                false => *v as f32,
+            })
+            .collect(),
+        DataType::I8 => data
+            .iter()
+            .map(|v| match normalized {
+                true => (*v as i8 as f32 / 127.0).max(-1.0),
+                false => *v as i8 as f32,
+            })
+            .collect(),
+        DataType::U16 => data
+            .chunks(2)
+            .map(|c| {
+                let v = u16::from_le_bytes([c[0], c[1]]) as f32;
+                match normalized {
+                    true => v / 65535.0,
+                    false => v,
+                }
+            })
+            .collect(),
+        DataType::I16 => data
+            .chunks(2)
+            .map(|c| {
+                let v = i16::from_le_bytes([c[0], c[1]]) as f32;
+                match normalized {
+                    true => (v / 32767.0).max(-1.0),
+                    false => v,
+                }
+            })
+            .collect(),
+        DataType::U32 => data
+            .chunks(4)
+            .map(|c| u32::from_le_bytes([c[0], c[1], c[2], c[3]]) as f32)
+            .collect(),
+    }
+}
+
+//the attribute tightly packed in the given format, converted to floats when the
+//accessor stores it some other way
+pub fn get_gltf_attribute_data(
+    accessor: &gltf::Accessor,
+    format: wgpu::VertexFormat,
+    raw_buffers: &HashMap<u32, Vec<u8>>,
+) -> Result<Vec<u8>, String> {
+    let native_format = get_vertex_format(
+        accessor.data_type(),
+        accessor.dimensions(),
+        accessor.normalized(),
+    );
+    let data = read_gltf_accessor(accessor, raw_buffers)?;
+    if native_format == Ok(format) {
+        return Ok(data);
+    }
+    let floats =
+        convert_gltf_components_to_f32(&data[..], accessor.data_type(), accessor.normalized());
+    Ok(bytemuck::cast_slice(&floats[..]).to_vec())
+}
+
+//cpu copy of the base vertices and of the displacements of every target, None when
+//the primitive has no targets. Without default weights on the mesh they are all zero
+pub fn load_gltf_morph_targets(
+    primitive: &gltf::Primitive,
+    default_weights: Option<&[f32]>,
+    raw_buffers: &HashMap<u32, Vec<u8>>,
+) -> Result<Option<morph::MorphTargetSet>, String> {
+    use gltf::accessor::Dimensions;
+    //missing attributes are empty, the others go through the same checked reads as the
+    //vertices so quantized data is dequantized the same way
+    let read =
+        |accessor: Option<gltf::Accessor>, dimensions: Dimensions| -> Result<Vec<f32>, String> {
+            let accessor = match accessor {
+                Some(accessor) => accessor,
+                None => return Ok(Vec::new()),
             };
+            if accessor.dimensions() != dimensions {
+                return Err(format!(
+                    "morph accessor {} is not {:?}",
+                    accessor.index(),
+                    dimensions
+                ));
+            }
+            read_gltf_attribute_f32(&accessor, raw_buffers)
+        };
+    let to_vec3 = |values: Vec<f32>| -> Vec<[f32; 3]> {
+        values.chunks(3).map(|v| [v[0], v[1], v[2]]).collect()
+    };
+
+    let mut targets = Vec::new();
+    for target in primitive.morph_targets() {
+        targets.push(morph::MorphTarget {
+            positions: to_vec3(read(target.positions(), Dimensions::Vec3)?),
+            normals: to_vec3(read(target.normals(), Dimensions::Vec3)?),
+            tangents: to_vec3(read(target.tangents(), Dimensions::Vec3)?),
+        });
+    }
+    if targets.is_empty() {
+        return Ok(None);
+    }
+
+    let positions = match primitive.get(&gltf::Semantic::Positions) {
+        Some(positions) => positions,
+        None => return Err(String::from("morphed primitive has no positions")),
+    };
+    let base = morph::MorphVertices {
+        positions: to_vec3(read(Some(positions), Dimensions::Vec3)?),
+        normals: to_vec3(read(
+            primitive.get(&gltf::Semantic::Normals),
+            Dimensions::Vec3,
+        )?),
+        tangents: read(primitive.get(&gltf::Semantic::Tangents), Dimensions::Vec4)?
+            .chunks(4)
+            .map(|v| [v[0], v[1], v[2], v[3]])
+            .collect(),
+    };
+    let default_weights = match default_weights {
+        Some(weights) => weights.to_vec(),
+        None => vec![0.0; targets.len()],
+    };
+    morph::MorphTargetSet::new(base, targets, default_weights).map(Some)
+}
+
+//range of a float vec3 accessor from its min and max, None when the file leaves them
+//out. Integer accessors store them before normalization so they are not used either
+pub fn get_gltf_accessor_aabb(accessor: &gltf::Accessor) -> Option<bounds::Aabb> {
+    if accessor.data_type() != gltf::accessor::DataType::F32
+        || accessor.dimensions() != gltf::accessor::Dimensions::Vec3
+    {
+        return None;
+    }
+    let to_point = |value: Option<serde_json::Value>| -> Option<cgmath::Point3<f32>> {
+        let value = value?;
+        let values = value.as_array()?;
+        if values.len() != 3 {
+            return None;
+        }
+        Some(cgmath::Point3::new(
+            values[0].as_f64()? as f32,
+            values[1].as_f64()? as f32,
+            values[2].as_f64()? as f32,
+        ))
+    };
+    Some(bounds::Aabb::new(
+        to_point(accessor.min())?,
+        to_point(accessor.max())?,
+    ))
+}
+
+//bounds of the decoded positions, the box is the one of the file when it has a usable
+//one. Morph targets are left to MeshData::get_bounds, which grows the box by them
+pub fn get_gltf_primitive_bounds(
+    primitive: &gltf::Primitive,
+    mesh_data: &MeshData,
+) -> Option<bounds::Bounds> {
+    if mesh_data.morph_targets.is_some() {
+        return None;
+    }
+    let points: Vec<cgmath::Point3<f32>> =
+        mesh_data.positions.iter().map(|p| (*p).into()).collect();
+    let aabb = match primitive
+        .get(&gltf::Semantic::Positions)
+        .and_then(|positions| get_gltf_accessor_aabb(&positions))
+    {
+        Some(aabb) => aabb,
+        None => bounds::Aabb::from_points(points.iter().cloned()),
+    };
+    Some(bounds::Bounds {
+        aabb,
+        sphere: bounds::BoundingSphere::from_points(&points[..]),
+    })
+}
 
-            mesh.index_buffer = Some(mesh_idx_buffer);
+//the smallest index format able to hold the indices, 0xffff is left out since
+//strips use it to restart the primitive
+pub fn pack_indices(indices: &[u32]) -> (wgpu::IndexFormat, Vec<u8>) {
+    if indices.iter().all(|index| *index < 0xffff) {
+        let indices: Vec<u16> = indices.iter().map(|index| *index as u16).collect();
+        (
+            wgpu::IndexFormat::Uint16,
+            bytemuck::cast_slice(&indices[..]).to_vec(),
+        )
+    } else {
+        (
+            wgpu::IndexFormat::Uint32,
+            bytemuck::cast_slice(indices).to_vec(),
+        )
+    }
+}
+
+//loops and fans are turned into lists by get_gltf_list_indices
+pub fn get_gltf_topology(mode: gltf::mesh::Mode) -> wgpu::PrimitiveTopology {
+    match mode {
+        gltf::mesh::Mode::Points => wgpu::PrimitiveTopology::PointList,
+        gltf::mesh::Mode::Lines | gltf::mesh::Mode::LineLoop => wgpu::PrimitiveTopology::LineList,
+        gltf::mesh::Mode::LineStrip => wgpu::PrimitiveTopology::LineStrip,
+        gltf::mesh::Mode::Triangles | gltf::mesh::Mode::TriangleFan => {
+            wgpu::PrimitiveTopology::TriangleList
         }
-        None => {}
+        gltf::mesh::Mode::TriangleStrip => wgpu::PrimitiveTopology::TriangleStrip,
     }
+}
 
-    mesh
+//indices drawing the primitive with the topology of get_gltf_topology, only loops
+//and fans change, a loop closes back on its first vertex
+pub fn get_gltf_list_indices(mode: gltf::mesh::Mode, indices: &[u32]) -> Vec<u32> {
+    match mode {
+        gltf::mesh::Mode::LineLoop => {
+            if indices.len() < 2 {
+                return Vec::new();
+            }
+            let mut list = Vec::with_capacity(indices.len() * 2);
+            for (start, end) in indices.iter().zip(indices.iter().cycle().skip(1)) {
+                list.push(*start);
+                list.push(*end);
+            }
+            list
+        }
+        gltf::mesh::Mode::TriangleFan => {
+            let mut list = Vec::with_capacity(indices.len().saturating_sub(2) * 3);
+            for pair in indices.windows(2).skip(1) {
+                list.push(indices[0]);
+                list.push(pair[0]);
+                list.push(pair[1]);
+            }
+            list
+        }
+        _ => indices.to_vec(),
+    }
 }
 
-fn load_gltf_mesh(
-    mesh: &gltf::Mesh,
-    gpu_raw_buffers: &mut HashMap<u32, wgpu::Buffer>,
+//the attribute as floats whatever its storage, with the components of a vertex in
+//consecutive values
+fn read_gltf_attribute_f32(
+    accessor: &gltf::Accessor,
     raw_buffers: &HashMap<u32, Vec<u8>>,
-    gpu_interfaces: &api::GPUInterfaces,
-) -> Vec<Mesh> {
-    let primitives = mesh.primitives();
-    let mut meshes = Vec::new();
-    for primitive in primitives {
-        let mesh =
-            load_gltf_mesh_primitive(&primitive, gpu_raw_buffers, raw_buffers, gpu_interfaces);
-        meshes.push(mesh);
-    }
+) -> Result<Vec<f32>, String> {
+    let data = read_gltf_accessor(accessor, raw_buffers)?;
+    Ok(convert_gltf_components_to_f32(
+        &data[..],
+        accessor.data_type(),
+        accessor.normalized(),
+    ))
+}
 
-    meshes
+//indices of the accessor with the format they are stored in. wgpu has no 8 bit
+//indices so those get 16 bits
+pub fn read_gltf_indices(
+    accessor: &gltf::Accessor,
+    raw_buffers: &HashMap<u32, Vec<u8>>,
+) -> Result<(wgpu::IndexFormat, Vec<u32>), String> {
+    use gltf::accessor::DataType;
+    if accessor.dimensions() != gltf::accessor::Dimensions::Scalar {
+        return Err(format!("index accessor {} is not scalar", accessor.index()));
+    }
+    let data = read_gltf_accessor(accessor, raw_buffers)?;
+    match accessor.data_type() {
+        DataType::U8 => Ok((
+            wgpu::IndexFormat::Uint16,
+            data.iter().map(|index| *index as u32).collect(),
+        )),
+        DataType::U16 => Ok((
+            wgpu::IndexFormat::Uint16,
+            data.chunks(2)
+                .map(|c| u16::from_le_bytes([c[0], c[1]]) as u32)
+                .collect(),
+        )),
+        DataType::U32 => Ok((
+            wgpu::IndexFormat::Uint32,
+            data.chunks(4)
+                .map(|c| u32::from_le_bytes([c[0], c[1], c[2], c[3]]))
+                .collect(),
+        )),
+        data_type => Err(format!(
+            "index accessor {} has the invalid type {:?}",
+            accessor.index(),
+            data_type
+        )),
+    }
 }
 
-pub async fn load_gltf_file(file_name: &str, gpu_interfaces: &api::GPUInterfaces) -> GltfFile {
-    let gltf_content = platform::file_system::load_file_u8(file_name)
-        .await
-        .unwrap();
-    let gltf = gltf::Gltf::from_slice(&gltf_content[..]).unwrap();
-    /*
-    for scene in gltf.scenes() {
-        //scene
-        for root in scene.nodes() {
-            let fmt_str = format!(
-                "Node #{} has {} children",
-                root.index(),
-                root.children().count(),
+//cpu copy of a primitive. Positions, normals, tangents and the first uv and color
+//sets are converted to floats, the other attributes are kept as streams in the format
+//get_gltf_attribute_format gives them. Loops and fans are turned into lists. The
+//index format is the one of the file
+pub fn load_gltf_mesh_data(
+    primitive: &gltf::Primitive,
+    default_weights: Option<&[f32]>,
+    raw_buffers: &HashMap<u32, Vec<u8>>,
+) -> Result<MeshData, String> {
+    let mut mesh_data = MeshData {
+        topology: get_gltf_topology(primitive.mode()),
+        material: primitive.material().index(),
+        ..Default::default()
+    };
+    for (semantic, accessor) in primitive.attributes() {
+        let (mesh_semantic, format) = match get_gltf_attribute_format(
+            &semantic,
+            accessor.data_type(),
+            accessor.dimensions(),
+            accessor.normalized(),
+        ) {
+            Ok(result) => result,
+            Err(message) => {
+                platform::core::to_console(
+                    &format!("{}, the attribute will be ignored...", message)[..],
+                );
+                continue;
+            }
+        };
+        match mesh_semantic {
+            MeshBufferSemantic::Positions => {
+                let values = read_gltf_attribute_f32(&accessor, raw_buffers)?;
+                mesh_data.positions = values.chunks(3).map(|v| [v[0], v[1], v[2]]).collect();
+            }
+            MeshBufferSemantic::Normals => {
+                let values = read_gltf_attribute_f32(&accessor, raw_buffers)?;
+                mesh_data.normals = values.chunks(3).map(|v| [v[0], v[1], v[2]]).collect();
+            }
+            MeshBufferSemantic::Tangents => {
+                let values = read_gltf_attribute_f32(&accessor, raw_buffers)?;
+                mesh_data.tangents = values.chunks(4).map(|v| [v[0], v[1], v[2], v[3]]).collect();
+            }
+            MeshBufferSemantic::TexCoords(0) => {
+                let values = read_gltf_attribute_f32(&accessor, raw_buffers)?;
+                mesh_data.uvs = values.chunks(2).map(|v| [v[0], v[1]]).collect();
+            }
+            MeshBufferSemantic::Colors(0) => {
+                let values = read_gltf_attribute_f32(&accessor, raw_buffers)?;
+                mesh_data.colors = match accessor.dimensions() {
+                    gltf::accessor::Dimensions::Vec3 => {
+                        values.chunks(3).map(|v| [v[0], v[1], v[2], 1.0]).collect()
+                    }
+                    _ => values.chunks(4).map(|v| [v[0], v[1], v[2], v[3]]).collect(),
+                };
+            }
+            _ => {
+                let data = get_gltf_attribute_data(&accessor, format, raw_buffers)?;
+                mesh_data.streams.push(VertexStreamData {
+                    semantic: mesh_semantic,
+                    format,
+                    data,
+                });
+            }
+        }
+    }
+    if mesh_data.positions.is_empty() {
+        return Err(String::from("primitive without positions"));
+    }
+    //attributes come in no particular order, sorting them keeps the layout stable
+    mesh_data
+        .streams
+        .sort_by_key(|stream| match stream.semantic {
+            MeshBufferSemantic::TexCoords(set) => (0, set),
+            MeshBufferSemantic::Colors(set) => (1, set),
+            MeshBufferSemantic::Joints(set) => (2, set),
+            MeshBufferSemantic::Weights(set) => (3, set),
+            _ => (4, 0),
+        });
+
+    let indices: Option<Vec<u32>> = match primitive.indices() {
+        Some(accessor) => {
+            let (index_format, indices) = read_gltf_indices(&accessor, raw_buffers)?;
+            mesh_data.index_format = index_format;
+            Some(indices)
+        }
+        None => None,
+    };
+    //loops and fans do not exist in wgpu, they are drawn as lists through indices
+    mesh_data.indices = match (primitive.mode(), indices) {
+        (gltf::mesh::Mode::LineLoop, indices) | (gltf::mesh::Mode::TriangleFan, indices) => {
+            let indices =
+                indices.unwrap_or_else(|| (0..mesh_data.positions.len() as u32).collect());
+            get_gltf_list_indices(primitive.mode(), &indices[..])
+        }
+        (_, indices) => indices.unwrap_or_default(),
+    };
+
+    mesh_data.morph_targets = load_gltf_morph_targets(primitive, default_weights, raw_buffers)
+        .unwrap_or_else(|message| {
+            platform::core::to_console(
+                &format!("{}, morph targets will be ignored...", message)[..],
             );
-            platform::core::to_console(&fmt_str[..]);
-
-            for child in root.children() {
-                println!("{}", child.index());
-                let mesh = child.mesh();
-                match mesh {
-                    Some(mesh_node) => println!("found mesh"),
-                    _ => println!("no mesh"),
-                }
+            None
+        });
+    mesh_data.bounds = get_gltf_primitive_bounds(primitive, &mesh_data);
+    mesh_data.validate()?;
+    Ok(mesh_data)
+}
+
+//a primitive that cannot be loaded is left out of its model, a failed processing step
+//leaves the data usable with only the steps after it missing
+fn load_gltf_model_data(
+    mesh: &gltf::Mesh,
+    processing_options: &processing::MeshProcessingOptions,
+    raw_buffers: &HashMap<u32, Vec<u8>>,
+) -> ModelData {
+    let mut model_data = ModelData::default();
+    for primitive in mesh.primitives() {
+        let mut mesh_data = match load_gltf_mesh_data(&primitive, mesh.weights(), raw_buffers) {
+            Ok(mesh_data) => mesh_data,
+            Err(message) => {
+                platform::core::to_console(
+                    &format!(
+                        "could not load primitive {} of mesh {}: {}",
+                        primitive.index(),
+                        mesh.index(),
+                        message
+                    )[..],
+                );
+                continue;
+            }
+        };
+        if processing_options.is_enabled() {
+            if let Err(message) = processing::process_mesh(&mut mesh_data, processing_options) {
+                platform::core::to_console(&format!("could not process the mesh: {}", message)[..]);
             }
         }
+        model_data.meshes.push(mesh_data);
     }
-    */
+    model_data
+}
 
-    let mut raw_buffers = HashMap::new();
-    let mut gpu_raw_buffers = HashMap::new();
+fn decode_hex_digit(digit: u8) -> Option<u8> {
+    match digit {
+        b'0'..=b'9' => Some(digit - b'0'),
+        b'a'..=b'f' => Some(digit - b'a' + 10),
+        b'A'..=b'F' => Some(digit - b'A' + 10),
+        _ => None,
+    }
+}
+
+//uris in a gltf are allowed to be percent encoded, "my%20model.bin" for example
+pub fn percent_decode(uri: &str) -> Result<Vec<u8>, String> {
+    let bytes = uri.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] != b'%' {
+            decoded.push(bytes[i]);
+            i += 1;
+            continue;
+        }
+        let high = bytes.get(i + 1).cloned().and_then(decode_hex_digit);
+        let low = bytes.get(i + 2).cloned().and_then(decode_hex_digit);
+        match (high, low) {
+            (Some(high), Some(low)) => decoded.push(high << 4 | low),
+            _ => return Err(format!("invalid percent encoding in uri {}", uri)),
+        }
+        i += 3;
+    }
+    Ok(decoded)
+}
+
+//decodes the content of a data uri in the form data:[<media type>][;base64],<data>
+pub fn decode_data_uri(uri: &str) -> Result<Vec<u8>, String> {
+    let content = match uri.strip_prefix("data:") {
+        Some(content) => content,
+        None => return Err(format!("{} is not a data uri", uri)),
+    };
+    let comma = match content.find(',') {
+        Some(comma) => comma,
+        None => return Err(String::from("data uri is missing the \",\" separator")),
+    };
+    let (header, data) = (&content[..comma], &content[comma + 1..]);
+    if header.ends_with(";base64") {
+        base64::decode(data).map_err(|e| format!("invalid base64 data uri: {}", e))
+    } else {
+        percent_decode(data)
+    }
+}
+
+//resolves the uri relative to the gltf file, the result goes through the platform
+//file system so it works both natively and on the web
+pub fn resolve_gltf_uri(file_name: &str, uri: &str) -> Result<String, String> {
+    let decoded = percent_decode(uri)?;
+    let relative_path = String::from_utf8(decoded)
+        .map_err(|_| format!("uri {} is not valid utf8 once decoded", uri))?;
+    let parent_folder = std::path::Path::new(file_name)
+        .parent()
+        .and_then(|parent| parent.to_str())
+        .unwrap_or("");
+    if parent_folder.is_empty() {
+        return Ok(relative_path);
+    }
+    Ok(String::from(parent_folder) + "/" + &relative_path[..])
+}
+
+pub async fn load_gltf_uri(file_name: &str, uri: &str) -> Result<Vec<u8>, String> {
+    if uri.starts_with("data:") {
+        return decode_data_uri(uri);
+    }
+    let path = resolve_gltf_uri(file_name, uri)?;
+    platform::file_system::load_file_u8(&path[..])
+        .await
+        .map_err(|e| format!("could not load {}: {:?}", path, e))
+}
 
-    //let us first load all the buffers
+//loads the content of all the buffers of the file, the binary chunk of a glb is
+//moved out of the gltf since it is only ever referenced by a single buffer
+pub async fn load_gltf_buffers(
+    gltf: &mut gltf::Gltf,
+    file_name: &str,
+) -> Result<HashMap<u32, Vec<u8>>, String> {
+    let mut blob = gltf.blob.take();
+    let mut raw_buffers = HashMap::new();
     for buffer in gltf.buffers() {
-        let buffer_idx = buffer.index();
+        let buffer_content = match buffer.source() {
+            gltf::buffer::Source::Uri(uri) => load_gltf_uri(file_name, uri).await?,
+            gltf::buffer::Source::Bin => match blob.take() {
+                Some(content) => content,
+                None => {
+                    return Err(format!(
+                        "buffer {} references a missing glb binary chunk",
+                        buffer.index()
+                    ))
+                }
+            },
+        };
+        //the glb chunk is padded to 4 bytes, so it can be longer than the buffer
+        if buffer_content.len() < buffer.length() {
+            return Err(format!(
+                "buffer {} is {} bytes long but {} were loaded",
+                buffer.index(),
+                buffer.length(),
+                buffer_content.len()
+            ));
+        }
+        raw_buffers.insert(buffer.index() as u32, buffer_content);
+    }
+    Ok(raw_buffers)
+}
 
-        let buffer_src = buffer.source();
+fn get_gltf_node_transform(node: &gltf::Node) -> scene::NodeTransform {
+    match node.transform() {
+        gltf::scene::Transform::Matrix { matrix } => {
+            scene::NodeTransform::Matrix(cgmath::Matrix4::from(matrix))
+        }
+        gltf::scene::Transform::Decomposed {
+            translation,
+            rotation,
+            scale,
+        } => scene::NodeTransform::Decomposed {
+            translation: translation.into(),
+            //gltf stores quaternions as x,y,z,w
+            rotation: cgmath::Quaternion::new(rotation[3], rotation[0], rotation[1], rotation[2]),
+            scale: scale.into(),
+        },
+    }
+}
 
-        let buffer_uri = match buffer_src {
-            gltf::buffer::Source::Uri(uri) => uri,
-            gltf::buffer::Source::Bin => panic!("gltf bin field of buffer is not supported yet"),
+//node and scene indices of the resulting graph match the ones in the file, and the
+//model of each node is the index of its gltf mesh
+pub fn load_gltf_scene_graph(document: &gltf::Document) -> Result<scene::SceneGraph, String> {
+    let mut scene_graph = scene::SceneGraph::default();
+    for node in document.nodes() {
+        let index = scene_graph.add_node(
+            node.name().map(String::from),
+            get_gltf_node_transform(&node),
+            node.mesh().map(|mesh| mesh.index()),
+        );
+        //weights on the node win over the default ones of the mesh
+        let weights = match (node.weights(), node.mesh()) {
+            (Some(weights), _) => Some(weights),
+            (None, Some(mesh)) => mesh.weights(),
+            (None, None) => None,
         };
+        scene_graph.nodes[index].skin = node.skin().map(|skin| skin.index());
+        scene_graph.nodes[index].weights = weights.map(|w| w.to_vec()).unwrap_or_default();
+    }
+    for node in document.nodes() {
+        for child in node.children() {
+            scene_graph.set_parent(child.index(), node.index())?;
+        }
+    }
 
-        let parent_folder = std::path::Path::new(file_name)
-            .parent()
-            .unwrap()
-            .to_str()
-            .unwrap();
-        let buffer_path = String::from(parent_folder) + "/" + buffer_uri;
+    for gltf_scene in document.scenes() {
+        let mut roots = Vec::new();
+        for node in gltf_scene.nodes() {
+            if scene_graph.nodes[node.index()].parent.is_some() {
+                return Err(format!(
+                    "node {} is a root of scene {} but has a parent",
+                    node.index(),
+                    gltf_scene.index()
+                ));
+            }
+            roots.push(node.index());
+        }
+        scene_graph.scenes.push(scene::Scene {
+            name: gltf_scene.name().map(String::from),
+            roots,
+        });
+    }
+    scene_graph.default_scene = document.default_scene().map(|s| s.index());
 
-        let buffer_content = platform::file_system::load_file_u8(&buffer_path[..])
-            .await
-            .unwrap();
+    scene_graph.update_world_matrices();
+    Ok(scene_graph)
+}
 
-        let wgpu_buffer = gpu_interfaces.device.create_buffer_with_data(
-            bytemuck::cast_slice(&buffer_content[..]),
-            wgpu::BufferUsage::INDEX | wgpu::BufferUsage::VERTEX,
-        );
+pub fn load_gltf_skin(
+    skin: &gltf::Skin,
+    raw_buffers: &HashMap<u32, Vec<u8>>,
+) -> Result<animation::Skin, String> {
+    let joints: Vec<usize> = skin.joints().map(|joint| joint.index()).collect();
+    let reader = skin.reader(|buffer| raw_buffers.get(&(buffer.index() as u32)).map(|b| &b[..]));
+    //without inverse bind matrices the joints are already in the space of the mesh
+    let inverse_bind_matrices: Vec<cgmath::Matrix4<f32>> = match reader.read_inverse_bind_matrices()
+    {
+        Some(matrices) => matrices.map(cgmath::Matrix4::from).collect(),
+        None if skin.inverse_bind_matrices().is_some() => {
+            return Err(format!(
+                "could not read the inverse bind matrices of skin {}",
+                skin.index()
+            ))
+        }
+        None => joints
+            .iter()
+            .map(|_| cgmath::SquareMatrix::identity())
+            .collect(),
+    };
+    if inverse_bind_matrices.len() != joints.len() {
+        return Err(format!(
+            "skin {} has {} joints but {} inverse bind matrices",
+            skin.index(),
+            joints.len(),
+            inverse_bind_matrices.len()
+        ));
+    }
+    Ok(animation::Skin {
+        name: skin.name().map(String::from),
+        joints,
+        inverse_bind_matrices,
+        skeleton: skin.skeleton().map(|node| node.index()),
+    })
+}
 
-        raw_buffers.insert(buffer_idx as u32, buffer_content);
-        gpu_raw_buffers.insert(buffer_idx as u32, wgpu_buffer);
+pub fn load_gltf_animation(
+    gltf_animation: &gltf::Animation,
+    raw_buffers: &HashMap<u32, Vec<u8>>,
+) -> Result<animation::AnimationClip, String> {
+    use gltf::animation::util::ReadOutputs;
+    let mut channels = Vec::new();
+    for channel in gltf_animation.channels() {
+        let node = channel.target().node().index();
+        let reader =
+            channel.reader(|buffer| raw_buffers.get(&(buffer.index() as u32)).map(|b| &b[..]));
+        let times: Vec<f32> = match reader.read_inputs() {
+            Some(inputs) => inputs.collect(),
+            None => {
+                return Err(format!(
+                    "could not read the keyframe times of node {}",
+                    node
+                ))
+            }
+        };
+        //integer rotations and weights are normalized, into_f32 brings them back
+        let (property, values): (animation::AnimationProperty, Vec<f32>) =
+            match reader.read_outputs() {
+                Some(ReadOutputs::Translations(values)) => (
+                    animation::AnimationProperty::Translation,
+                    values.flatten().collect(),
+                ),
+                Some(ReadOutputs::Rotations(values)) => (
+                    animation::AnimationProperty::Rotation,
+                    values.into_f32().flatten().collect(),
+                ),
+                Some(ReadOutputs::Scales(values)) => (
+                    animation::AnimationProperty::Scale,
+                    values.flatten().collect(),
+                ),
+                Some(ReadOutputs::MorphTargetWeights(values)) => (
+                    animation::AnimationProperty::Weights,
+                    values.into_f32().collect(),
+                ),
+                None => {
+                    return Err(format!(
+                        "could not read the keyframe values of node {}",
+                        node
+                    ))
+                }
+            };
+        let interpolation = match channel.sampler().interpolation() {
+            gltf::animation::Interpolation::Step => animation::Interpolation::Step,
+            gltf::animation::Interpolation::Linear => animation::Interpolation::Linear,
+            gltf::animation::Interpolation::CubicSpline => animation::Interpolation::CubicSpline,
+        };
+        channels.push(animation::AnimationChannel::new(
+            node,
+            property,
+            interpolation,
+            times,
+            values,
+        )?);
     }
+    Ok(animation::AnimationClip {
+        name: gltf_animation.name().map(String::from),
+        channels,
+    })
+}
 
-    let mut models = Vec::new();
-    for mesh in gltf.meshes() {
-        let meshes = load_gltf_mesh(&mesh, &mut gpu_raw_buffers, &raw_buffers, gpu_interfaces);
+pub fn get_gltf_sampler_settings(sampler: &gltf::texture::Sampler) -> texture::SamplerSettings {
+    use gltf::texture::{MagFilter, MinFilter, WrappingMode};
+    let address_mode = |mode: WrappingMode| match mode {
+        WrappingMode::ClampToEdge => wgpu::AddressMode::ClampToEdge,
+        WrappingMode::MirroredRepeat => wgpu::AddressMode::MirrorRepeat,
+        WrappingMode::Repeat => wgpu::AddressMode::Repeat,
+    };
+    let mag_filter = match sampler.mag_filter() {
+        Some(MagFilter::Nearest) => wgpu::FilterMode::Nearest,
+        Some(MagFilter::Linear) | None => wgpu::FilterMode::Linear,
+    };
+    let (min_filter, mipmap_filter) = match sampler.min_filter() {
+        Some(MinFilter::Nearest) | Some(MinFilter::NearestMipmapNearest) => {
+            (wgpu::FilterMode::Nearest, wgpu::FilterMode::Nearest)
+        }
+        Some(MinFilter::NearestMipmapLinear) => {
+            (wgpu::FilterMode::Nearest, wgpu::FilterMode::Linear)
+        }
+        Some(MinFilter::LinearMipmapNearest) => {
+            (wgpu::FilterMode::Linear, wgpu::FilterMode::Nearest)
+        }
+        Some(MinFilter::Linear) | Some(MinFilter::LinearMipmapLinear) | None => {
+            (wgpu::FilterMode::Linear, wgpu::FilterMode::Linear)
+        }
+    };
+    texture::SamplerSettings {
+        address_mode_u: address_mode(sampler.wrap_s()),
+        address_mode_v: address_mode(sampler.wrap_t()),
+        mag_filter,
+        min_filter,
+        mipmap_filter,
+    }
+}
 
-        let model = Model { meshes };
-        models.push(model);
+fn get_gltf_material_texture(info: &gltf::texture::Info) -> material::MaterialTexture {
+    material::MaterialTexture {
+        texture: info.texture().index(),
+        tex_coord: info.tex_coord(),
     }
+}
 
-    GltfFile {
-        models,
-        buffers: gpu_raw_buffers,
+pub fn load_gltf_material(gltf_material: &gltf::Material) -> material::Material {
+    let pbr = gltf_material.pbr_metallic_roughness();
+    let normal_texture = gltf_material.normal_texture();
+    let occlusion_texture = gltf_material.occlusion_texture();
+    material::Material {
+        name: gltf_material.name().map(String::from),
+        base_color_factor: pbr.base_color_factor(),
+        base_color_texture: pbr
+            .base_color_texture()
+            .map(|info| get_gltf_material_texture(&info)),
+        metallic_factor: pbr.metallic_factor(),
+        roughness_factor: pbr.roughness_factor(),
+        metallic_roughness_texture: pbr
+            .metallic_roughness_texture()
+            .map(|info| get_gltf_material_texture(&info)),
+        normal_texture: normal_texture
+            .as_ref()
+            .map(|normal| material::MaterialTexture {
+                texture: normal.texture().index(),
+                tex_coord: normal.tex_coord(),
+            }),
+        normal_scale: normal_texture.as_ref().map_or(1.0, |normal| normal.scale()),
+        occlusion_texture: occlusion_texture
+            .as_ref()
+            .map(|occlusion| material::MaterialTexture {
+                texture: occlusion.texture().index(),
+                tex_coord: occlusion.tex_coord(),
+            }),
+        occlusion_strength: occlusion_texture
+            .as_ref()
+            .map_or(1.0, |occlusion| occlusion.strength()),
+        emissive_texture: gltf_material
+            .emissive_texture()
+            .map(|info| get_gltf_material_texture(&info)),
+        emissive_factor: gltf_material.emissive_factor(),
+        alpha_mode: match gltf_material.alpha_mode() {
+            gltf::material::AlphaMode::Opaque => material::AlphaMode::Opaque,
+            gltf::material::AlphaMode::Mask => material::AlphaMode::Mask,
+            gltf::material::AlphaMode::Blend => material::AlphaMode::Blend,
+        },
+        alpha_cutoff: gltf_material.alpha_cutoff(),
+        double_sided: gltf_material.double_sided(),
+    }
+}
+
+//images in their own file are only referenced so the texture manager can share them
+//between files, embedded ones are decoded straight away
+fn load_gltf_texture_source(
+    image: &gltf::image::Image<'_>,
+    file_name: &str,
+    color_space: texture::ColorSpace,
+    raw_buffers: &HashMap<u32, Vec<u8>>,
+) -> Result<TextureSource, String> {
+    let content = match image.source() {
+        gltf::image::Source::Uri { uri, .. } if !uri.starts_with("data:") => {
+            return Ok(TextureSource::File(resolve_gltf_uri(file_name, uri)?));
+        }
+        gltf::image::Source::Uri { uri, .. } => decode_data_uri(uri)?,
+        gltf::image::Source::View { view, .. } => {
+            let buffer = match raw_buffers.get(&(view.buffer().index() as u32)) {
+                Some(buffer) => buffer,
+                None => return Err(format!("buffer {} is not loaded", view.buffer().index())),
+            };
+            match buffer.get(view.offset()..view.offset() + view.length()) {
+                Some(content) => content.to_vec(),
+                None => return Err(format!("image {} view is out of bounds", image.index())),
+            }
+        }
+    };
+    Ok(TextureSource::Data(texture::decode_image(
+        &content[..],
+        color_space,
+    )?))
+}
+
+fn create_gltf_gpu_buffer(size: u64, gpu_interfaces: &api::GPUInterfaces) -> wgpu::Buffer {
+    gpu_interfaces
+        .device
+        .create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            //copies are done in multiple of 4 bytes
+            size: upload::align_copy_size(size),
+            usage: wgpu::BufferUsage::INDEX
+                | wgpu::BufferUsage::VERTEX
+                | wgpu::BufferUsage::COPY_DST,
+        })
+}
+
+//everything in the file except the gpu resources, the buffers must come from
+//load_gltf_buffers. Broken parts are logged and left out or replaced so the rest of
+//the file can still be used
+pub fn import_gltf_document(
+    document: &gltf::Document,
+    raw_buffers: &HashMap<u32, Vec<u8>>,
+    file_name: &str,
+    processing_options: &processing::MeshProcessingOptions,
+) -> Result<ModelFileData, String> {
+    let mut scene_graph = load_gltf_scene_graph(document)?;
+    let materials: Vec<material::Material> = document
+        .materials()
+        .map(|m| load_gltf_material(&m))
+        .collect();
+
+    //a broken skin is dropped and leaves its nodes in bind pose, the nodes of the
+    //other skins follow them to their new index. A broken animation is dropped
+    let mut skins = Vec::new();
+    let mut skin_indices = Vec::new();
+    for skin in document.skins() {
+        match load_gltf_skin(&skin, raw_buffers) {
+            Ok(skin) => {
+                skin_indices.push(Some(skins.len()));
+                skins.push(skin);
+            }
+            Err(message) => {
+                platform::core::to_console(
+                    &format!("could not load skin of {}: {}", file_name, message)[..],
+                );
+                skin_indices.push(None);
+            }
+        }
     }
+    for node in scene_graph.nodes.iter_mut() {
+        node.skin = node.skin.and_then(|skin| skin_indices[skin]);
+    }
+    let mut animations = Vec::new();
+    for gltf_animation in document.animations() {
+        match load_gltf_animation(&gltf_animation, raw_buffers) {
+            Ok(clip) => animations.push(clip),
+            Err(message) => platform::core::to_console(
+                &format!("could not load animation of {}: {}", file_name, message)[..],
+            ),
+        }
+    }
+
+    //an image that cannot be decoded is replaced by a white one so the model can
+    //still be rendered
+    let mut textures = Vec::new();
+    for gltf_texture in document.textures() {
+        let image = gltf_texture.source();
+        let color_space = if materials
+            .iter()
+            .any(|m| m.is_srgb_texture(gltf_texture.index()))
+        {
+            texture::ColorSpace::Srgb
+        } else {
+            texture::ColorSpace::Linear
+        };
+        let source = load_gltf_texture_source(&image, file_name, color_space, raw_buffers)
+            .unwrap_or_else(|message| {
+                platform::core::to_console(
+                    &format!(
+                        "could not load image {} of {}: {}, using a white texture instead",
+                        image.index(),
+                        file_name,
+                        message
+                    )[..],
+                );
+                TextureSource::Data(texture::TextureData::from_color(
+                    [255, 255, 255, 255],
+                    color_space,
+                ))
+            });
+        textures.push(TextureImportData {
+            label: format!("{}-texture-{}", file_name, gltf_texture.index()),
+            source,
+            color_space,
+            sampler: get_gltf_sampler_settings(&gltf_texture.sampler()),
+        });
+    }
+
+    let models = document
+        .meshes()
+        .map(|mesh| load_gltf_model_data(&mesh, processing_options, raw_buffers))
+        .collect();
+
+    Ok(ModelFileData {
+        models,
+        scene_graph,
+        materials,
+        textures,
+        skins,
+        animations,
+    })
+}
+
+//loads and imports a .gltf or .glb file, nothing here needs a gpu
+pub async fn import_gltf_file(
+    file_name: &str,
+    processing_options: &processing::MeshProcessingOptions,
+) -> Result<ModelFileData, String> {
+    let gltf_content = platform::file_system::load_file_u8(file_name)
+        .await
+        .map_err(|e| format!("could not load {}: {:?}", file_name, e))?;
+    //from_slice detects on its own whether we are dealing with a .gltf or a .glb
+    let mut gltf = gltf::Gltf::from_slice(&gltf_content[..])
+        .map_err(|e| format!("could not parse {}: {}", file_name, e))?;
+    let raw_buffers = load_gltf_buffers(&mut gltf, file_name).await?;
+    import_gltf_document(&gltf, &raw_buffers, file_name, processing_options)
+}
+
+pub async fn load_gltf_file(
+    file_name: &str,
+    gpu_interfaces: &api::GPUInterfaces,
+    upload_manager: &mut upload::UploadManager,
+    texture_manager: &mut texture::TextureManager,
+    processing_options: &processing::MeshProcessingOptions,
+) -> Result<GltfFile, String> {
+    let file_data = import_gltf_file(file_name, processing_options).await?;
+    Ok(file_data
+        .upload(gpu_interfaces, upload_manager, texture_manager)
+        .await)
 }
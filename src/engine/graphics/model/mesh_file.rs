@@ -0,0 +1,511 @@
+use super::super::super::platform;
+use super::super::api;
+use super::super::bounds;
+use super::super::upload;
+use super::processing;
+use super::{
+    create_gltf_gpu_buffer, import_gltf_file, Mesh, MeshBufferMapper, MeshBufferSemantic,
+    MeshIndexBufferMapper, Model, ModelData,
+};
+use std::collections::HashMap;
+
+//.mesh files hold a model with its vertex streams already laid out the way the gpu
+//reads them, so the whole file is uploaded as a single buffer and only the tables at
+//its start are parsed. Everything is little endian and 4 bytes aligned:
+//  header: magic, version, mesh count, material count, file size
+//  material table: offset and length of the utf8 name of every material, an unnamed
+//  material has the offset NO_VALUE
+//  mesh table: MESH_RECORD_SIZE bytes per mesh, see write_mesh_record
+//  stream table: STREAM_RECORD_SIZE bytes per stream, the streams of a mesh follow
+//  each other starting from its first stream
+//  data: names, streams and indices, each starting on a 4 bytes boundary
+pub const MESH_FILE_MAGIC: [u8; 4] = *b"MESH";
+pub const MESH_FILE_VERSION: u32 = 1;
+
+const HEADER_SIZE: usize = 20;
+const MATERIAL_RECORD_SIZE: usize = 8;
+const MESH_RECORD_SIZE: usize = 76;
+const STREAM_RECORD_SIZE: usize = 20;
+//missing material or index buffer
+const NO_VALUE: u32 = 0xffff_ffff;
+
+//every format a stream can be stored in, the file keeps the position in this list
+const VERTEX_FORMATS: [wgpu::VertexFormat; 30] = [
+    wgpu::VertexFormat::Uchar2,
+    wgpu::VertexFormat::Uchar4,
+    wgpu::VertexFormat::Char2,
+    wgpu::VertexFormat::Char4,
+    wgpu::VertexFormat::Uchar2Norm,
+    wgpu::VertexFormat::Uchar4Norm,
+    wgpu::VertexFormat::Char2Norm,
+    wgpu::VertexFormat::Char4Norm,
+    wgpu::VertexFormat::Ushort2,
+    wgpu::VertexFormat::Ushort4,
+    wgpu::VertexFormat::Short2,
+    wgpu::VertexFormat::Short4,
+    wgpu::VertexFormat::Ushort2Norm,
+    wgpu::VertexFormat::Ushort4Norm,
+    wgpu::VertexFormat::Short2Norm,
+    wgpu::VertexFormat::Short4Norm,
+    wgpu::VertexFormat::Half2,
+    wgpu::VertexFormat::Half4,
+    wgpu::VertexFormat::Float,
+    wgpu::VertexFormat::Float2,
+    wgpu::VertexFormat::Float3,
+    wgpu::VertexFormat::Float4,
+    wgpu::VertexFormat::Uint,
+    wgpu::VertexFormat::Uint2,
+    wgpu::VertexFormat::Uint3,
+    wgpu::VertexFormat::Uint4,
+    wgpu::VertexFormat::Int,
+    wgpu::VertexFormat::Int2,
+    wgpu::VertexFormat::Int3,
+    wgpu::VertexFormat::Int4,
+];
+
+const TOPOLOGIES: [wgpu::PrimitiveTopology; 5] = [
+    wgpu::PrimitiveTopology::PointList,
+    wgpu::PrimitiveTopology::LineList,
+    wgpu::PrimitiveTopology::LineStrip,
+    wgpu::PrimitiveTopology::TriangleList,
+    wgpu::PrimitiveTopology::TriangleStrip,
+];
+
+fn get_semantic_code(semantic: MeshBufferSemantic) -> (u32, u32) {
+    match semantic {
+        MeshBufferSemantic::None => (0, 0),
+        MeshBufferSemantic::Positions => (1, 0),
+        MeshBufferSemantic::Normals => (2, 0),
+        MeshBufferSemantic::Tangents => (3, 0),
+        MeshBufferSemantic::TexCoords(set) => (4, set),
+        MeshBufferSemantic::Colors(set) => (5, set),
+        MeshBufferSemantic::Joints(set) => (6, set),
+        MeshBufferSemantic::Weights(set) => (7, set),
+    }
+}
+
+fn get_semantic_from_code(code: u32, set: u32) -> Result<MeshBufferSemantic, String> {
+    match code {
+        0 => Ok(MeshBufferSemantic::None),
+        1 => Ok(MeshBufferSemantic::Positions),
+        2 => Ok(MeshBufferSemantic::Normals),
+        3 => Ok(MeshBufferSemantic::Tangents),
+        4 => Ok(MeshBufferSemantic::TexCoords(set)),
+        5 => Ok(MeshBufferSemantic::Colors(set)),
+        6 => Ok(MeshBufferSemantic::Joints(set)),
+        7 => Ok(MeshBufferSemantic::Weights(set)),
+        _ => Err(format!("unknown stream semantic {}", code)),
+    }
+}
+
+fn align_to_4(data: &mut Vec<u8>) {
+    while data.len() & 3 != 0 {
+        data.push(0);
+    }
+}
+
+fn write_u32(data: &mut [u8], offset: usize, value: u32) {
+    data[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+}
+
+fn write_f32(data: &mut [u8], offset: usize, value: f32) {
+    data[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+}
+
+//bounds checked read, the tables are validated before anything is read from them
+fn read_u32(content: &[u8], offset: usize) -> Result<u32, String> {
+    match content.get(offset..offset + 4) {
+        Some(b) => Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]])),
+        None => Err(format!("mesh file is truncated at byte {}", offset)),
+    }
+}
+
+fn read_f32(content: &[u8], offset: usize) -> Result<f32, String> {
+    read_u32(content, offset).map(f32::from_bits)
+}
+
+//the model as a .mesh file, material indices of the meshes point into the given
+//names. Morph targets are blended on the cpu from data the file does not keep, so
+//meshes with them are refused
+pub fn write_mesh_file(
+    model_data: &ModelData,
+    material_names: &[Option<String>],
+) -> Result<Vec<u8>, String> {
+    //the data of every mesh is laid out first, its offsets are fixed once the size
+    //of the tables is known
+    let mut data: Vec<u8> = Vec::new();
+    let mut name_ranges = Vec::new();
+    for name in material_names.iter() {
+        name_ranges.push(name.as_ref().map(|name| {
+            let offset = data.len();
+            data.extend_from_slice(name.as_bytes());
+            align_to_4(&mut data);
+            (offset, name.len())
+        }));
+    }
+    let mut meshes = Vec::new();
+    let mut stream_count = 0;
+    for (mesh_index, mesh_data) in model_data.meshes.iter().enumerate() {
+        if mesh_data.morph_targets.is_some() {
+            return Err(format!(
+                "mesh {} has morph targets, they cannot be stored",
+                mesh_index
+            ));
+        }
+        if let Some(material) = mesh_data.material {
+            if material >= material_names.len() {
+                return Err(format!(
+                    "mesh {} uses material {} but there are {} materials",
+                    mesh_index,
+                    material,
+                    material_names.len()
+                ));
+            }
+        }
+        mesh_data.validate()?;
+        let mesh = mesh_data.create_mesh(|buffer| {
+            let offset = data.len() as u32;
+            data.extend_from_slice(&buffer[..]);
+            align_to_4(&mut data);
+            offset
+        });
+        stream_count += mesh.buffers.len();
+        meshes.push(mesh);
+    }
+
+    let data_offset = HEADER_SIZE
+        + material_names.len() * MATERIAL_RECORD_SIZE
+        + meshes.len() * MESH_RECORD_SIZE
+        + stream_count * STREAM_RECORD_SIZE;
+    let mut content = vec![0u8; data_offset];
+    content[0..4].copy_from_slice(&MESH_FILE_MAGIC);
+    write_u32(&mut content, 4, MESH_FILE_VERSION);
+    write_u32(&mut content, 8, meshes.len() as u32);
+    write_u32(&mut content, 12, material_names.len() as u32);
+    write_u32(&mut content, 16, (data_offset + data.len()) as u32);
+
+    let mut record = HEADER_SIZE;
+    for name_range in name_ranges.iter() {
+        let (offset, length) = match name_range {
+            Some((offset, length)) => ((data_offset + offset) as u32, *length as u32),
+            None => (NO_VALUE, 0),
+        };
+        write_u32(&mut content, record, offset);
+        write_u32(&mut content, record + 4, length);
+        record += MATERIAL_RECORD_SIZE;
+    }
+    let mut first_stream = 0;
+    for mesh in meshes.iter() {
+        write_mesh_record(&mut content[record..], mesh, first_stream, data_offset);
+        first_stream += mesh.buffers.len() as u32;
+        record += MESH_RECORD_SIZE;
+    }
+    for mesh in meshes.iter() {
+        for buffer in mesh.buffers.iter() {
+            let (code, set) = get_semantic_code(buffer.semantic);
+            let format = VERTEX_FORMATS
+                .iter()
+                .position(|format| *format == buffer.format)
+                .ok_or_else(|| format!("vertex format {:?} cannot be stored", buffer.format))?;
+            write_u32(&mut content, record, code);
+            write_u32(&mut content, record + 4, set);
+            write_u32(&mut content, record + 8, format as u32);
+            write_u32(
+                &mut content,
+                record + 12,
+                data_offset as u32 + buffer.buffer_idx,
+            );
+            write_u32(&mut content, record + 16, buffer.length);
+            record += STREAM_RECORD_SIZE;
+        }
+    }
+    content.extend_from_slice(&data[..]);
+    Ok(content)
+}
+
+//the buffer indices of the mesh are offsets in the data, the record gets offsets in
+//the file
+fn write_mesh_record(record: &mut [u8], mesh: &Mesh, first_stream: u32, data_offset: usize) {
+    let topology = TOPOLOGIES
+        .iter()
+        .position(|topology| *topology == mesh.topology)
+        .unwrap_or(3);
+    write_u32(record, 0, topology as u32);
+    write_u32(record, 4, mesh.vertex_count);
+    write_u32(record, 8, mesh.material.map_or(NO_VALUE, |m| m as u32));
+    write_u32(record, 12, first_stream);
+    write_u32(record, 16, mesh.buffers.len() as u32);
+    match mesh.index_buffer.as_ref() {
+        Some(index_buffer) => {
+            let format = match index_buffer.format {
+                wgpu::IndexFormat::Uint16 => 0,
+                wgpu::IndexFormat::Uint32 => 1,
+            };
+            write_u32(record, 20, format);
+            write_u32(record, 24, index_buffer.count);
+            write_u32(record, 28, data_offset as u32 + index_buffer.buffer_idx);
+            write_u32(record, 32, index_buffer.length);
+        }
+        None => write_u32(record, 20, NO_VALUE),
+    }
+    let aabb = &mesh.bounds.aabb;
+    let sphere = &mesh.bounds.sphere;
+    let floats = [
+        aabb.min.x,
+        aabb.min.y,
+        aabb.min.z,
+        aabb.max.x,
+        aabb.max.y,
+        aabb.max.z,
+        sphere.center.x,
+        sphere.center.y,
+        sphere.center.z,
+        sphere.radius,
+    ];
+    for (i, value) in floats.iter().enumerate() {
+        write_f32(record, 36 + i * 4, *value);
+    }
+}
+
+//what the file holds besides the gpu data
+pub struct MeshFile {
+    //every mesh reads from the buffer given to read_mesh_file
+    pub model: Model,
+    pub material_names: Vec<Option<String>>,
+}
+
+//reads the tables of the file, the meshes reference the whole content uploaded as
+//the buffer with the given index. Every range is checked against the file so a
+//broken one cannot make the gpu read past the buffer
+pub fn read_mesh_file(content: &[u8], buffer_idx: u32) -> Result<MeshFile, String> {
+    if content.len() < HEADER_SIZE || content[0..4] != MESH_FILE_MAGIC {
+        return Err(String::from("not a mesh file"));
+    }
+    let version = read_u32(content, 4)?;
+    if version != MESH_FILE_VERSION {
+        return Err(format!(
+            "mesh file version {} is not supported, expected {}",
+            version, MESH_FILE_VERSION
+        ));
+    }
+    let mesh_count = read_u32(content, 8)? as usize;
+    let material_count = read_u32(content, 12)? as usize;
+    let file_size = read_u32(content, 16)? as usize;
+    if file_size != content.len() {
+        return Err(format!(
+            "mesh file is {} bytes long but {} were loaded",
+            file_size,
+            content.len()
+        ));
+    }
+    let check_range = |offset: u32, length: u32| -> Result<(), String> {
+        let end = offset as u64 + length as u64;
+        if offset & 3 != 0 || end > content.len() as u64 {
+            return Err(format!(
+                "range {}..{} is misaligned or out of the file",
+                offset, end
+            ));
+        }
+        Ok(())
+    };
+    //the counts come from the file, the tables they describe have to fit in it before
+    //anything is allocated for them
+    let tables_size = HEADER_SIZE as u64
+        + material_count as u64 * MATERIAL_RECORD_SIZE as u64
+        + mesh_count as u64 * MESH_RECORD_SIZE as u64;
+    if tables_size > content.len() as u64 {
+        return Err(format!(
+            "{} materials and {} meshes do not fit in a {} bytes file",
+            material_count,
+            mesh_count,
+            content.len()
+        ));
+    }
+
+    let mut record = HEADER_SIZE;
+    let mut material_names = Vec::with_capacity(material_count);
+    for _ in 0..material_count {
+        let offset = read_u32(content, record)?;
+        let length = read_u32(content, record + 4)?;
+        record += MATERIAL_RECORD_SIZE;
+        if offset == NO_VALUE {
+            material_names.push(None);
+            continue;
+        }
+        check_range(offset, length)?;
+        let name = &content[offset as usize..(offset + length) as usize];
+        let name = std::str::from_utf8(name)
+            .map_err(|_| format!("material name at {} is not valid utf8", offset))?;
+        material_names.push(Some(String::from(name)));
+    }
+
+    let stream_table = record + mesh_count * MESH_RECORD_SIZE;
+    let mut meshes = Vec::with_capacity(mesh_count);
+    for _ in 0..mesh_count {
+        let topology = read_u32(content, record)? as usize;
+        let vertex_count = read_u32(content, record + 4)?;
+        let material = read_u32(content, record + 8)?;
+        let first_stream = read_u32(content, record + 12)? as usize;
+        let stream_count = read_u32(content, record + 16)? as usize;
+        let index_format = read_u32(content, record + 20)?;
+        let mut floats = [0.0f32; 10];
+        for (i, value) in floats.iter_mut().enumerate() {
+            *value = read_f32(content, record + 36 + i * 4)?;
+        }
+
+        let mut mesh = Mesh {
+            topology: *TOPOLOGIES
+                .get(topology)
+                .ok_or_else(|| format!("unknown topology {}", topology))?,
+            vertex_count,
+            material: match material {
+                NO_VALUE => None,
+                material if (material as usize) < material_count => Some(material as usize),
+                material => return Err(format!("material {} is out of the table", material)),
+            },
+            bounds: bounds::Bounds {
+                aabb: bounds::Aabb::new(
+                    cgmath::Point3::new(floats[0], floats[1], floats[2]),
+                    cgmath::Point3::new(floats[3], floats[4], floats[5]),
+                ),
+                sphere: bounds::BoundingSphere {
+                    center: cgmath::Point3::new(floats[6], floats[7], floats[8]),
+                    radius: floats[9],
+                },
+            },
+            ..Default::default()
+        };
+        let streams_end = stream_table as u64
+            + (first_stream as u64 + stream_count as u64) * STREAM_RECORD_SIZE as u64;
+        if streams_end > content.len() as u64 {
+            return Err(format!(
+                "{} streams from {} are out of the stream table",
+                stream_count, first_stream
+            ));
+        }
+        for stream in first_stream..first_stream + stream_count {
+            let stream_record = stream_table + stream * STREAM_RECORD_SIZE;
+            let semantic = get_semantic_from_code(
+                read_u32(content, stream_record)?,
+                read_u32(content, stream_record + 4)?,
+            )?;
+            let format = read_u32(content, stream_record + 8)? as usize;
+            let format = *VERTEX_FORMATS
+                .get(format)
+                .ok_or_else(|| format!("unknown vertex format {}", format))?;
+            let offset = read_u32(content, stream_record + 12)?;
+            let length = read_u32(content, stream_record + 16)?;
+            check_range(offset, length)?;
+            if (length as usize) < vertex_count as usize * super::get_vertex_format_size(format) {
+                return Err(format!("{:?} stream is too short", semantic));
+            }
+            mesh.buffers.push(MeshBufferMapper {
+                semantic,
+                format,
+                offset,
+                length,
+                buffer_idx,
+            });
+        }
+        if index_format != NO_VALUE {
+            let format = match index_format {
+                0 => wgpu::IndexFormat::Uint16,
+                1 => wgpu::IndexFormat::Uint32,
+                _ => return Err(format!("unknown index format {}", index_format)),
+            };
+            let count = read_u32(content, record + 24)?;
+            let offset = read_u32(content, record + 28)?;
+            let length = read_u32(content, record + 32)?;
+            check_range(offset, length)?;
+            let index_size = match format {
+                wgpu::IndexFormat::Uint16 => 2,
+                wgpu::IndexFormat::Uint32 => 4,
+            };
+            if (length as u64) < count as u64 * index_size {
+                return Err(String::from("index buffer is too short"));
+            }
+            let indices = &content[offset as usize..];
+            let max_index = match format {
+                wgpu::IndexFormat::Uint16 => indices
+                    .chunks_exact(2)
+                    .take(count as usize)
+                    .map(|index| u16::from_le_bytes([index[0], index[1]]) as u32)
+                    .max(),
+                wgpu::IndexFormat::Uint32 => indices
+                    .chunks_exact(4)
+                    .take(count as usize)
+                    .map(|index| u32::from_le_bytes([index[0], index[1], index[2], index[3]]))
+                    .max(),
+            };
+            if let Some(max_index) = max_index {
+                if max_index >= vertex_count {
+                    return Err(format!(
+                        "index {} is out of the {} vertices of the mesh",
+                        max_index, vertex_count
+                    ));
+                }
+            }
+            mesh.index_buffer = Some(MeshIndexBufferMapper {
+                offset,
+                length,
+                format,
+                buffer_idx,
+                count,
+            });
+        }
+        meshes.push(mesh);
+        record += MESH_RECORD_SIZE;
+    }
+    Ok(MeshFile {
+        model: Model::new(meshes),
+        material_names,
+    })
+}
+
+//one .mesh file per model of the gltf file, named after the materials of the file
+pub async fn convert_gltf_file(
+    file_name: &str,
+    processing_options: &processing::MeshProcessingOptions,
+) -> Result<Vec<Vec<u8>>, String> {
+    let file_data = import_gltf_file(file_name, processing_options).await?;
+    let material_names: Vec<Option<String>> = file_data
+        .materials
+        .iter()
+        .map(|material| material.name.clone())
+        .collect();
+    file_data
+        .models
+        .iter()
+        .map(|model_data| write_mesh_file(model_data, &material_names[..]))
+        .collect()
+}
+
+//the whole file goes up as a single buffer added to the given map, with an index
+//past the ones already there
+pub async fn load_mesh_file(
+    file_name: &str,
+    buffers: &mut HashMap<u32, wgpu::Buffer>,
+    gpu_interfaces: &api::GPUInterfaces,
+    upload_manager: &mut upload::UploadManager,
+) -> Result<MeshFile, String> {
+    let content = platform::file_system::load_file_u8(file_name)
+        .await
+        .map_err(|e| format!("could not load {}: {:?}", file_name, e))?;
+    let buffer_idx = buffers.keys().max().map_or(0, |idx| idx + 1);
+    let mesh_file =
+        read_mesh_file(&content[..], buffer_idx).map_err(|e| format!("{}: {}", file_name, e))?;
+
+    let wgpu_buffer = create_gltf_gpu_buffer(content.len() as u64, gpu_interfaces);
+    let mut encoder =
+        gpu_interfaces
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("mesh file upload encoder"),
+            });
+    let mut batch = upload_manager.begin_batch();
+    batch.write_buffer(&wgpu_buffer, 0, &content[..]);
+    batch.flush(&gpu_interfaces.device, &mut encoder);
+    gpu_interfaces.queue.submit(vec![encoder.finish()]);
+    buffers.insert(buffer_idx, wgpu_buffer);
+    Ok(mesh_file)
+}
@@ -0,0 +1,692 @@
+use super::super::super::platform;
+use super::super::api;
+use super::super::material;
+use super::super::scene;
+use super::super::texture;
+use super::super::upload;
+use super::processing;
+use super::{GltfFile, MeshData, ModelData, ModelFileData, TextureImportData, TextureSource};
+use cgmath::InnerSpace;
+use std::collections::HashMap;
+
+//the triangles of a mesh using a single material, None is the default material
+#[derive(Clone, Debug, PartialEq)]
+pub struct ObjMesh {
+    pub data: MeshData,
+    pub material: Option<String>,
+}
+
+//the faces following an o or g statement, split by material
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ObjModel {
+    pub name: Option<String>,
+    pub meshes: Vec<ObjMesh>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ObjData {
+    pub models: Vec<ObjModel>,
+    //mtl files named by mtllib, relative to the obj file
+    pub material_libraries: Vec<String>,
+}
+
+//the material and the texture files it uses, relative to the mtl file
+#[derive(Clone, Debug, PartialEq)]
+pub struct ObjMaterial {
+    pub material: material::Material,
+    pub base_color_map: Option<String>,
+    pub normal_map: Option<String>,
+    pub emissive_map: Option<String>,
+}
+
+impl Default for ObjMaterial {
+    fn default() -> Self {
+        Self {
+            //obj materials are dielectrics unless Pm says otherwise
+            material: material::Material {
+                metallic_factor: 0.0,
+                ..Default::default()
+            },
+            base_color_map: None,
+            normal_map: None,
+            emissive_map: None,
+        }
+    }
+}
+
+//a face corner, the indices in the v, vt and vn lists of the file
+type ObjCorner = (usize, Option<usize>, Option<usize>);
+
+//mesh being filled by the parser, vertices are unique combinations of v, vt and vn
+#[derive(Default)]
+struct ObjMeshBuilder {
+    material: Option<String>,
+    corners: Vec<ObjCorner>,
+    vertices: HashMap<ObjCorner, u32>,
+    indices: Vec<u32>,
+}
+
+impl ObjMeshBuilder {
+    //vertices without a normal get the average of the faces around their position,
+    //texcoords missing on some of the vertices are set to 0
+    fn build(&self, positions: &[[f32; 3]], uvs: &[[f32; 2]], normals: &[[f32; 3]]) -> MeshData {
+        let mut data = MeshData {
+            positions: self.corners.iter().map(|c| positions[c.0]).collect(),
+            indices: self.indices.clone(),
+            ..Default::default()
+        };
+        if self.corners.iter().any(|c| c.1.is_some()) {
+            data.uvs = self
+                .corners
+                .iter()
+                .map(|c| c.1.map_or([0.0, 0.0], |uv| uvs[uv]))
+                .collect();
+        }
+
+        let mut smooth_normals: HashMap<usize, cgmath::Vector3<f32>> = HashMap::new();
+        if self.corners.iter().any(|c| c.2.is_none()) {
+            for triangle in self.indices.chunks(3) {
+                let corner = |i: usize| self.corners[triangle[i] as usize].0;
+                let point = |i: usize| cgmath::Vector3::from(positions[corner(i)]);
+                //not normalized so bigger faces weigh more
+                let normal = (point(1) - point(0)).cross(point(2) - point(0));
+                for i in 0..3 {
+                    *smooth_normals
+                        .entry(corner(i))
+                        .or_insert_with(|| cgmath::Vector3::new(0.0, 0.0, 0.0)) += normal;
+                }
+            }
+        }
+        data.normals = self
+            .corners
+            .iter()
+            .map(|c| match c.2 {
+                Some(normal) => normals[normal],
+                None => {
+                    let normal = smooth_normals[&c.0];
+                    if normal.magnitude2() > 0.0 {
+                        normal.normalize().into()
+                    } else {
+                        [0.0, 1.0, 0.0]
+                    }
+                }
+            })
+            .collect();
+        data
+    }
+}
+
+struct ObjModelBuilder {
+    name: Option<String>,
+    meshes: Vec<ObjMeshBuilder>,
+    current: usize,
+}
+
+impl ObjModelBuilder {
+    fn new(name: Option<String>, material: Option<String>) -> Self {
+        Self {
+            name,
+            meshes: vec![ObjMeshBuilder {
+                material,
+                ..Default::default()
+            }],
+            current: 0,
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.meshes.iter().all(|mesh| mesh.indices.is_empty())
+    }
+
+    fn use_material(&mut self, material: Option<String>) {
+        self.current = match self.meshes.iter().position(|m| m.material == material) {
+            Some(mesh) => mesh,
+            None => {
+                self.meshes.push(ObjMeshBuilder {
+                    material,
+                    ..Default::default()
+                });
+                self.meshes.len() - 1
+            }
+        };
+    }
+}
+
+fn parse_floats(tokens: &[&str], count: usize, line: usize) -> Result<Vec<f32>, String> {
+    if tokens.len() < count {
+        return Err(format!("line {}: expected {} numbers", line, count));
+    }
+    tokens[..count]
+        .iter()
+        .map(|token| {
+            token
+                .parse()
+                .map_err(|_| format!("line {}: {} is not a number", line, token))
+        })
+        .collect()
+}
+
+fn parse_vec3(tokens: &[&str], line: usize) -> Result<[f32; 3], String> {
+    let values = parse_floats(tokens, 3, line)?;
+    Ok([values[0], values[1], values[2]])
+}
+
+fn parse_float(tokens: &[&str], line: usize) -> Result<f32, String> {
+    Ok(parse_floats(tokens, 1, line)?[0])
+}
+
+//obj indices start at 1, negative ones count back from the last element
+fn parse_obj_index(token: &str, count: usize, line: usize) -> Result<usize, String> {
+    let index: i64 = token
+        .parse()
+        .map_err(|_| format!("line {}: {} is not an index", line, token))?;
+    let resolved = if index < 0 {
+        count as i64 + index
+    } else {
+        index - 1
+    };
+    if index == 0 || resolved < 0 || resolved >= count as i64 {
+        return Err(format!(
+            "line {}: index {} is out of bounds, there are {} elements",
+            line, index, count
+        ));
+    }
+    Ok(resolved as usize)
+}
+
+//statements without a meaning for the engine (s, l, p, curves...) are ignored
+pub fn parse_obj(content: &str) -> Result<ObjData, String> {
+    let mut positions: Vec<[f32; 3]> = Vec::new();
+    let mut uvs: Vec<[f32; 2]> = Vec::new();
+    let mut normals: Vec<[f32; 3]> = Vec::new();
+    let mut material_libraries = Vec::new();
+    let mut material = None;
+    let mut finished_models: Vec<ObjModelBuilder> = Vec::new();
+    let mut model = ObjModelBuilder::new(None, None);
+
+    for (line_index, line) in join_continued_lines(content).iter().enumerate() {
+        let line_number = line_index + 1;
+        let line = line.split('#').next().unwrap_or("");
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        if tokens.is_empty() {
+            continue;
+        }
+        let arguments = &tokens[1..];
+        match tokens[0] {
+            //w and the vertex colors some exporters add are dropped
+            "v" => positions.push(parse_vec3(arguments, line_number)?),
+            //v goes down in the engine like in gltf
+            "vt" => {
+                let u = parse_float(arguments, line_number)?;
+                let v = match arguments.get(1) {
+                    Some(_) => parse_float(&arguments[1..], line_number)?,
+                    None => 0.0,
+                };
+                uvs.push([u, 1.0 - v]);
+            }
+            "vn" => normals.push(parse_vec3(arguments, line_number)?),
+            "f" => {
+                if arguments.len() < 3 {
+                    return Err(format!("line {}: a face needs 3 vertices", line_number));
+                }
+                let mut corners = Vec::with_capacity(arguments.len());
+                for argument in arguments.iter() {
+                    let mut parts = argument.split('/');
+                    let position =
+                        parse_obj_index(parts.next().unwrap(), positions.len(), line_number)?;
+                    let uv = match parts.next() {
+                        Some(uv) if !uv.is_empty() => {
+                            Some(parse_obj_index(uv, uvs.len(), line_number)?)
+                        }
+                        _ => None,
+                    };
+                    let normal = match parts.next() {
+                        Some(normal) if !normal.is_empty() => {
+                            Some(parse_obj_index(normal, normals.len(), line_number)?)
+                        }
+                        _ => None,
+                    };
+                    corners.push((position, uv, normal));
+                }
+                let points: Vec<[f32; 3]> = corners.iter().map(|c| positions[c.0]).collect();
+                let mesh = &mut model.meshes[model.current];
+                for triangle in triangulate_polygon(&points[..]).iter() {
+                    for corner in triangle.iter() {
+                        let key = corners[*corner];
+                        let next = mesh.corners.len() as u32;
+                        let index = *mesh.vertices.entry(key).or_insert(next);
+                        if index == next {
+                            mesh.corners.push(key);
+                        }
+                        mesh.indices.push(index);
+                    }
+                }
+            }
+            //a group right after an object is part of it
+            "o" | "g" => {
+                let name = if arguments.is_empty() {
+                    None
+                } else {
+                    Some(arguments.join(" "))
+                };
+                if tokens[0] == "g" && model.is_empty() && model.name.is_some() {
+                    continue;
+                }
+                let previous =
+                    std::mem::replace(&mut model, ObjModelBuilder::new(name, material.clone()));
+                finished_models.push(previous);
+            }
+            "usemtl" => {
+                material = Some(arguments.join(" "));
+                model.use_material(material.clone());
+            }
+            "mtllib" => {
+                material_libraries.extend(arguments.iter().map(|library| library.to_string()))
+            }
+            _ => {}
+        }
+    }
+    finished_models.push(model);
+
+    let models = finished_models
+        .iter()
+        .filter(|model| !model.is_empty())
+        .map(|model| ObjModel {
+            name: model.name.clone(),
+            meshes: model
+                .meshes
+                .iter()
+                .filter(|mesh| !mesh.indices.is_empty())
+                .map(|mesh| ObjMesh {
+                    data: mesh.build(&positions[..], &uvs[..], &normals[..]),
+                    material: mesh.material.clone(),
+                })
+                .collect(),
+        })
+        .collect();
+    Ok(ObjData {
+        models,
+        material_libraries,
+    })
+}
+
+//a backslash at the end of a line continues it on the next one, the lines joined
+//are left empty so the line numbers of the errors stay right
+fn join_continued_lines(content: &str) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut current = String::new();
+    let mut continued = 0;
+    for line in content.lines() {
+        match line.trim_end().strip_suffix('\\') {
+            Some(start) => {
+                current.push_str(start);
+                current.push(' ');
+                continued += 1;
+            }
+            None => {
+                current.push_str(line);
+                lines.push(std::mem::take(&mut current));
+                lines.extend((0..continued).map(|_| String::new()));
+                continued = 0;
+            }
+        }
+    }
+    if continued > 0 {
+        lines.push(current);
+    }
+    lines
+}
+
+//triangles covering a simple polygon, as indices in the given points and with the
+//winding of the polygon. Ears are clipped on the plane of the polygon so concave
+//faces work too, what is left when no ear can be found is closed as a fan
+pub fn triangulate_polygon(points: &[[f32; 3]]) -> Vec<[usize; 3]> {
+    if points.len() < 3 {
+        return Vec::new();
+    }
+    //newell normal, works for concave polygons
+    let mut normal = cgmath::Vector3::new(0.0, 0.0, 0.0);
+    for (i, a) in points.iter().enumerate() {
+        let b = points[(i + 1) % points.len()];
+        normal.x += (a[1] - b[1]) * (a[2] + b[2]);
+        normal.y += (a[2] - b[2]) * (a[0] + b[0]);
+        normal.z += (a[0] - b[0]) * (a[1] + b[1]);
+    }
+    //dropping the biggest axis of the normal keeps the polygon counter clockwise
+    //when looked at along it, flipped when that axis is negative
+    let (u, v, flip) = if normal.x.abs() >= normal.y.abs() && normal.x.abs() >= normal.z.abs() {
+        (1, 2, normal.x < 0.0)
+    } else if normal.y.abs() >= normal.z.abs() {
+        (2, 0, normal.y < 0.0)
+    } else {
+        (0, 1, normal.z < 0.0)
+    };
+    let projected: Vec<[f32; 2]> = points
+        .iter()
+        .map(|p| if flip { [p[v], p[u]] } else { [p[u], p[v]] })
+        .collect();
+    let cross = |o: usize, a: usize, b: usize| {
+        let (o, a, b) = (projected[o], projected[a], projected[b]);
+        (a[0] - o[0]) * (b[1] - o[1]) - (a[1] - o[1]) * (b[0] - o[0])
+    };
+
+    let mut remaining: Vec<usize> = (0..points.len()).collect();
+    let mut triangles = Vec::with_capacity(points.len() - 2);
+    while remaining.len() > 3 {
+        let count = remaining.len();
+        let ear = (0..count).find(|i| {
+            let previous = remaining[(i + count - 1) % count];
+            let current = remaining[*i];
+            let next = remaining[(i + 1) % count];
+            if cross(previous, current, next) <= 0.0 {
+                return false;
+            }
+            //no other corner may be inside or on the edges of the ear
+            remaining.iter().all(|other| {
+                *other == previous
+                    || *other == current
+                    || *other == next
+                    || projected[*other] == projected[previous]
+                    || projected[*other] == projected[current]
+                    || projected[*other] == projected[next]
+                    || cross(previous, current, *other) < 0.0
+                    || cross(current, next, *other) < 0.0
+                    || cross(next, previous, *other) < 0.0
+            })
+        });
+        //degenerate or self intersecting, the first corner will do
+        let i = ear.unwrap_or(0);
+        triangles.push([
+            remaining[(i + count - 1) % count],
+            remaining[i],
+            remaining[(i + 1) % count],
+        ]);
+        remaining.remove(i);
+    }
+    triangles.push([remaining[0], remaining[1], remaining[2]]);
+    triangles
+}
+
+//texture options come before the file name, -bm is kept as it is the normal scale
+fn parse_mtl_map(arguments: &[&str], line: usize) -> Result<(String, Option<f32>), String> {
+    let mut bump_multiplier = None;
+    let mut i = 0;
+    while i < arguments.len() && arguments[i].starts_with('-') {
+        let option = arguments[i];
+        i += 1;
+        if option == "-imfchan" {
+            i += 1;
+            continue;
+        }
+        let start = i;
+        while i < arguments.len()
+            && (arguments[i].parse::<f32>().is_ok()
+                || arguments[i] == "on"
+                || arguments[i] == "off")
+        {
+            i += 1;
+        }
+        if option == "-bm" && i > start {
+            bump_multiplier = arguments[start].parse().ok();
+        }
+    }
+    if i >= arguments.len() {
+        return Err(format!("line {}: texture map without a file", line));
+    }
+    Ok((arguments[i..].join(" "), bump_multiplier))
+}
+
+//the phong parameters are turned into their closest metallic-roughness values, the
+//Pr and Pm pbr extensions win when they are there
+pub fn parse_mtl(content: &str) -> Result<HashMap<String, ObjMaterial>, String> {
+    let mut materials = HashMap::new();
+    let mut current: Option<(String, ObjMaterial)> = None;
+    let mut has_roughness = false;
+
+    for (line_index, line) in join_continued_lines(content).iter().enumerate() {
+        let line_number = line_index + 1;
+        let line = line.split('#').next().unwrap_or("");
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        if tokens.is_empty() {
+            continue;
+        }
+        let arguments = &tokens[1..];
+        if tokens[0] == "newmtl" {
+            let name = arguments.join(" ");
+            let mut obj_material = ObjMaterial::default();
+            obj_material.material.name = Some(name.clone());
+            if let Some((name, finished)) = current.replace((name, obj_material)) {
+                materials.insert(name, finished);
+            }
+            has_roughness = false;
+            continue;
+        }
+        let obj_material = match current.as_mut() {
+            Some((_, obj_material)) => obj_material,
+            None => return Err(format!("line {}: {} before newmtl", line_number, tokens[0])),
+        };
+        let material = &mut obj_material.material;
+        match tokens[0] {
+            "Kd" => {
+                let color = parse_vec3(arguments, line_number)?;
+                material.base_color_factor[0..3].copy_from_slice(&color[..]);
+            }
+            "d" => material.base_color_factor[3] = parse_float(arguments, line_number)?,
+            "Tr" => material.base_color_factor[3] = 1.0 - parse_float(arguments, line_number)?,
+            "Ke" => material.emissive_factor = parse_vec3(arguments, line_number)?,
+            "Ns" if !has_roughness => {
+                let exponent = parse_float(arguments, line_number)?.max(0.0);
+                material.roughness_factor = (2.0 / (exponent + 2.0)).sqrt().clamp(0.0, 1.0);
+            }
+            "Pr" => {
+                material.roughness_factor = parse_float(arguments, line_number)?;
+                has_roughness = true;
+            }
+            "Pm" => material.metallic_factor = parse_float(arguments, line_number)?,
+            "map_Kd" => {
+                obj_material.base_color_map = Some(parse_mtl_map(arguments, line_number)?.0)
+            }
+            "map_Ke" => obj_material.emissive_map = Some(parse_mtl_map(arguments, line_number)?.0),
+            "map_Bump" | "map_bump" | "bump" | "norm" => {
+                let (map, scale) = parse_mtl_map(arguments, line_number)?;
+                obj_material.normal_map = Some(map);
+                if let Some(scale) = scale {
+                    material.normal_scale = scale;
+                }
+            }
+            _ => {}
+        }
+    }
+    if let Some((name, finished)) = current {
+        materials.insert(name, finished);
+    }
+
+    for obj_material in materials.values_mut() {
+        let material = &mut obj_material.material;
+        if material.base_color_factor[3] < 1.0 {
+            material.alpha_mode = material::AlphaMode::Blend;
+        }
+        //emissive maps multiply the factor, which is black when only the map is given
+        if obj_material.emissive_map.is_some() && material.emissive_factor == [0.0, 0.0, 0.0] {
+            material.emissive_factor = [1.0, 1.0, 1.0];
+        }
+    }
+    Ok(materials)
+}
+
+//paths in obj and mtl files are relative to the file naming them
+pub fn resolve_obj_path(file_name: &str, relative_path: &str) -> String {
+    let relative_path = relative_path.replace('\\', "/");
+    let parent_folder = std::path::Path::new(file_name)
+        .parent()
+        .and_then(|parent| parent.to_str())
+        .unwrap_or("");
+    if parent_folder.is_empty() || relative_path.starts_with('/') {
+        return relative_path;
+    }
+    String::from(parent_folder) + "/" + &relative_path[..]
+}
+
+//the models of the file are placed at the origin, a node each in a single scene.
+//Libraries are given with the path of their file, textures are relative to it. A
+//material defined by several libraries comes from the first one
+pub fn create_obj_file_data(
+    file_name: &str,
+    obj_data: ObjData,
+    libraries: &[(String, HashMap<String, ObjMaterial>)],
+    processing_options: &processing::MeshProcessingOptions,
+) -> ModelFileData {
+    //materials are referenced by name, texture files by path
+    let mut material_indices: HashMap<String, usize> = HashMap::new();
+    let mut materials = Vec::new();
+    let mut texture_paths: Vec<String> = Vec::new();
+    for (library_path, library_materials) in libraries.iter() {
+        let mut names: Vec<&String> = library_materials.keys().collect();
+        names.sort();
+        for name in names {
+            let obj_material = &library_materials[name];
+            let mut material = obj_material.material.clone();
+            let mut add_texture = |map: &Option<String>| {
+                map.as_ref().map(|map| {
+                    let path = resolve_obj_path(&library_path[..], &map[..]);
+                    let texture = match texture_paths.iter().position(|p| *p == path) {
+                        Some(texture) => texture,
+                        None => {
+                            texture_paths.push(path);
+                            texture_paths.len() - 1
+                        }
+                    };
+                    material::MaterialTexture {
+                        texture,
+                        tex_coord: 0,
+                    }
+                })
+            };
+            material.base_color_texture = add_texture(&obj_material.base_color_map);
+            material.normal_texture = add_texture(&obj_material.normal_map);
+            material.emissive_texture = add_texture(&obj_material.emissive_map);
+            if !material_indices.contains_key(name) {
+                material_indices.insert(name.clone(), materials.len());
+                materials.push(material);
+            }
+        }
+    }
+
+    let textures = texture_paths
+        .into_iter()
+        .enumerate()
+        .map(|(texture_index, path)| {
+            let color_space = if materials.iter().any(|m| m.is_srgb_texture(texture_index)) {
+                texture::ColorSpace::Srgb
+            } else {
+                texture::ColorSpace::Linear
+            };
+            TextureImportData {
+                label: path.clone(),
+                source: TextureSource::File(path),
+                color_space,
+                sampler: texture::SamplerSettings::default(),
+            }
+        })
+        .collect();
+
+    let mut models = Vec::new();
+    let mut scene_graph = scene::SceneGraph::default();
+    let mut roots = Vec::new();
+    for obj_model in obj_data.models.into_iter() {
+        let mut model_data = ModelData::default();
+        for obj_mesh in obj_model.meshes.into_iter() {
+            let mut mesh_data = obj_mesh.data;
+            if processing_options.is_enabled() {
+                if let Err(message) = processing::process_mesh(&mut mesh_data, processing_options) {
+                    platform::core::to_console(
+                        &format!("could not process a mesh of {}: {}", file_name, message)[..],
+                    );
+                }
+            }
+            mesh_data.material = match obj_mesh.material {
+                Some(name) => {
+                    let material = material_indices.get(&name).cloned();
+                    if material.is_none() {
+                        platform::core::to_console(
+                            &format!(
+                                "material {} of {} is not defined, using the default one",
+                                name, file_name
+                            )[..],
+                        );
+                    }
+                    material
+                }
+                None => None,
+            };
+            model_data.meshes.push(mesh_data);
+        }
+        roots.push(scene_graph.add_node(
+            obj_model.name,
+            scene::NodeTransform::identity(),
+            Some(models.len()),
+        ));
+        models.push(model_data);
+    }
+    scene_graph.scenes.push(scene::Scene { name: None, roots });
+    scene_graph.default_scene = Some(0);
+
+    ModelFileData {
+        models,
+        scene_graph,
+        materials,
+        textures,
+        skins: Vec::new(),
+        animations: Vec::new(),
+    }
+}
+
+//loads the file and its mtl libraries, missing ones are reported and their materials
+//replaced by the default one. Textures are only loaded at upload
+pub async fn import_obj_file(
+    file_name: &str,
+    processing_options: &processing::MeshProcessingOptions,
+) -> Result<ModelFileData, String> {
+    let content = platform::file_system::load_file_string(file_name)
+        .await
+        .map_err(|e| format!("could not load {}: {:?}", file_name, e))?;
+    let obj_data = parse_obj(&content[..]).map_err(|e| format!("{}: {}", file_name, e))?;
+
+    let mut libraries = Vec::new();
+    for library in obj_data.material_libraries.iter() {
+        let library_path = resolve_obj_path(file_name, &library[..]);
+        let library_materials =
+            match platform::file_system::load_file_string(&library_path[..]).await {
+                Ok(library_content) => parse_mtl(&library_content[..]),
+                Err(e) => Err(format!("could not load {:?}", e)),
+            };
+        match library_materials {
+            Ok(library_materials) => libraries.push((library_path, library_materials)),
+            Err(message) => platform::core::to_console(
+                &format!(
+                    "{}: {}, its materials will be missing",
+                    library_path, message
+                )[..],
+            ),
+        }
+    }
+    Ok(create_obj_file_data(
+        file_name,
+        obj_data,
+        &libraries[..],
+        processing_options,
+    ))
+}
+
+pub async fn load_obj_file(
+    file_name: &str,
+    gpu_interfaces: &api::GPUInterfaces,
+    upload_manager: &mut upload::UploadManager,
+    texture_manager: &mut texture::TextureManager,
+    processing_options: &processing::MeshProcessingOptions,
+) -> Result<GltfFile, String> {
+    let file_data = import_obj_file(file_name, processing_options).await?;
+    Ok(file_data
+        .upload(gpu_interfaces, upload_manager, texture_manager)
+        .await)
+}
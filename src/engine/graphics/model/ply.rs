@@ -0,0 +1,451 @@
+use super::super::super::platform;
+use super::super::api;
+use super::super::mipmap;
+use super::super::upload;
+use super::obj;
+use super::processing;
+use super::{Mesh, MeshData};
+use std::collections::HashMap;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PlyFormat {
+    Ascii,
+    BinaryLittleEndian,
+    BinaryBigEndian,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PlyScalarType {
+    Int8,
+    Uint8,
+    Int16,
+    Uint16,
+    Int32,
+    Uint32,
+    Float32,
+    Float64,
+}
+
+impl PlyScalarType {
+    pub fn from_name(name: &str) -> Result<Self, String> {
+        match name {
+            "char" | "int8" => Ok(PlyScalarType::Int8),
+            "uchar" | "uint8" => Ok(PlyScalarType::Uint8),
+            "short" | "int16" => Ok(PlyScalarType::Int16),
+            "ushort" | "uint16" => Ok(PlyScalarType::Uint16),
+            "int" | "int32" => Ok(PlyScalarType::Int32),
+            "uint" | "uint32" => Ok(PlyScalarType::Uint32),
+            "float" | "float32" => Ok(PlyScalarType::Float32),
+            "double" | "float64" => Ok(PlyScalarType::Float64),
+            _ => Err(format!("unknown ply type {}", name)),
+        }
+    }
+
+    pub fn get_size(&self) -> usize {
+        match self {
+            PlyScalarType::Int8 | PlyScalarType::Uint8 => 1,
+            PlyScalarType::Int16 | PlyScalarType::Uint16 => 2,
+            PlyScalarType::Int32 | PlyScalarType::Uint32 | PlyScalarType::Float32 => 4,
+            PlyScalarType::Float64 => 8,
+        }
+    }
+
+    //integer colors go from 0 to the biggest value of their type
+    fn get_color_scale(&self) -> f64 {
+        match self {
+            PlyScalarType::Int8 => 127.0,
+            PlyScalarType::Uint8 => 255.0,
+            PlyScalarType::Int16 => 32767.0,
+            PlyScalarType::Uint16 => 65535.0,
+            PlyScalarType::Int32 => 2147483647.0,
+            PlyScalarType::Uint32 => 4294967295.0,
+            PlyScalarType::Float32 | PlyScalarType::Float64 => 1.0,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum PlyProperty {
+    Scalar {
+        name: String,
+        data_type: PlyScalarType,
+    },
+    List {
+        name: String,
+        count_type: PlyScalarType,
+        item_type: PlyScalarType,
+    },
+}
+
+impl PlyProperty {
+    pub fn get_name(&self) -> &str {
+        match self {
+            PlyProperty::Scalar { name, .. } | PlyProperty::List { name, .. } => &name[..],
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct PlyElement {
+    pub name: String,
+    pub count: usize,
+    pub properties: Vec<PlyProperty>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct PlyHeader {
+    pub format: PlyFormat,
+    pub elements: Vec<PlyElement>,
+    //where the data of the elements starts
+    pub body_offset: usize,
+}
+
+pub fn parse_ply_header(content: &[u8]) -> Result<PlyHeader, String> {
+    let end = b"end_header";
+    let end_position = content
+        .windows(end.len())
+        .position(|window| window == end)
+        .ok_or("ply header has no end_header")?;
+    let mut body_offset = end_position + end.len();
+    //the line ending of the header can be \n or \r\n
+    while body_offset < content.len() && content[body_offset] != b'\n' {
+        body_offset += 1;
+    }
+    body_offset += 1;
+
+    let header = std::str::from_utf8(&content[..end_position])
+        .map_err(|_| String::from("ply header is not valid text"))?;
+    let mut lines = header.lines();
+    if lines.next().map(|line| line.trim()) != Some("ply") {
+        return Err(String::from("file does not start with ply"));
+    }
+
+    let mut format = None;
+    let mut elements: Vec<PlyElement> = Vec::new();
+    for line in lines {
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        match tokens.first() {
+            Some(&"format") => {
+                format = Some(match tokens.get(1) {
+                    Some(&"ascii") => PlyFormat::Ascii,
+                    Some(&"binary_little_endian") => PlyFormat::BinaryLittleEndian,
+                    Some(&"binary_big_endian") => PlyFormat::BinaryBigEndian,
+                    _ => return Err(format!("unknown ply format {}", line)),
+                })
+            }
+            Some(&"element") => {
+                if tokens.len() != 3 {
+                    return Err(format!("malformed ply element {}", line));
+                }
+                elements.push(PlyElement {
+                    name: String::from(tokens[1]),
+                    count: tokens[2]
+                        .parse()
+                        .map_err(|_| format!("ply element count {} is not a number", tokens[2]))?,
+                    properties: Vec::new(),
+                });
+            }
+            Some(&"property") => {
+                let element = elements
+                    .last_mut()
+                    .ok_or_else(|| format!("ply property {} outside of an element", line))?;
+                let property = match &tokens[1..] {
+                    ["list", count_type, item_type, name] => PlyProperty::List {
+                        name: String::from(*name),
+                        count_type: PlyScalarType::from_name(count_type)?,
+                        item_type: PlyScalarType::from_name(item_type)?,
+                    },
+                    [data_type, name] => PlyProperty::Scalar {
+                        name: String::from(*name),
+                        data_type: PlyScalarType::from_name(data_type)?,
+                    },
+                    _ => return Err(format!("malformed ply property {}", line)),
+                };
+                element.properties.push(property);
+            }
+            //comment, obj_info and empty lines
+            _ => {}
+        }
+    }
+
+    Ok(PlyHeader {
+        format: format.ok_or("ply header has no format")?,
+        elements,
+        body_offset,
+    })
+}
+
+//reads the values of the body one after the other, whatever the format
+struct PlyReader<'a> {
+    format: PlyFormat,
+    data: &'a [u8],
+    offset: usize,
+    tokens: std::str::SplitAsciiWhitespace<'a>,
+}
+
+impl<'a> PlyReader<'a> {
+    fn new(format: PlyFormat, data: &'a [u8]) -> Result<Self, String> {
+        let text = match format {
+            PlyFormat::Ascii => std::str::from_utf8(data)
+                .map_err(|_| String::from("ascii ply body is not valid text"))?,
+            _ => "",
+        };
+        Ok(Self {
+            format,
+            data,
+            offset: 0,
+            tokens: text.split_ascii_whitespace(),
+        })
+    }
+
+    fn read(&mut self, data_type: PlyScalarType) -> Result<f64, String> {
+        if self.format == PlyFormat::Ascii {
+            let token = self.tokens.next().ok_or("ply body ends too early")?;
+            return token
+                .parse()
+                .map_err(|_| format!("ply value {} is not a number", token));
+        }
+
+        let size = data_type.get_size();
+        let bytes = self
+            .data
+            .get(self.offset..self.offset + size)
+            .ok_or("ply body ends too early")?;
+        self.offset += size;
+        let mut buffer = [0u8; 8];
+        buffer[..size].copy_from_slice(bytes);
+        if self.format == PlyFormat::BinaryBigEndian {
+            buffer[..size].reverse();
+        }
+        let value = match data_type {
+            PlyScalarType::Int8 => buffer[0] as i8 as f64,
+            PlyScalarType::Uint8 => buffer[0] as f64,
+            PlyScalarType::Int16 => i16::from_le_bytes([buffer[0], buffer[1]]) as f64,
+            PlyScalarType::Uint16 => u16::from_le_bytes([buffer[0], buffer[1]]) as f64,
+            PlyScalarType::Int32 => {
+                i32::from_le_bytes([buffer[0], buffer[1], buffer[2], buffer[3]]) as f64
+            }
+            PlyScalarType::Uint32 => {
+                u32::from_le_bytes([buffer[0], buffer[1], buffer[2], buffer[3]]) as f64
+            }
+            PlyScalarType::Float32 => {
+                f32::from_le_bytes([buffer[0], buffer[1], buffer[2], buffer[3]]) as f64
+            }
+            PlyScalarType::Float64 => f64::from_le_bytes(buffer),
+        };
+        Ok(value)
+    }
+
+    //scalars are returned as they are, the items of a list are appended to list_items
+    //and its first item is returned, zero for empty lists
+    fn read_property(
+        &mut self,
+        property: &PlyProperty,
+        list_items: &mut Vec<f64>,
+    ) -> Result<f64, String> {
+        match property {
+            PlyProperty::Scalar { data_type, .. } => self.read(*data_type),
+            PlyProperty::List {
+                count_type,
+                item_type,
+                ..
+            } => {
+                let count = self.read(*count_type)?;
+                if count < 0.0 {
+                    return Err(format!("ply list has {} items", count));
+                }
+                let first = list_items.len();
+                for _ in 0..count as usize {
+                    let item = self.read(*item_type)?;
+                    list_items.push(item);
+                }
+                Ok(list_items.get(first).cloned().unwrap_or(0.0))
+            }
+        }
+    }
+}
+
+//the vertex element gives the positions and the optional normals, texcoords and
+//colors, the face element the polygons, which are triangulated. Files without
+//faces are point clouds. Colors are taken as srgb like any color meant to be
+//looked at and are stored linear
+pub fn parse_ply(content: &[u8]) -> Result<MeshData, String> {
+    let header = parse_ply_header(content)?;
+    let mut reader = PlyReader::new(
+        header.format,
+        &content[header.body_offset.min(content.len())..],
+    )?;
+
+    let mut mesh = MeshData::default();
+    let mut faces: Vec<Vec<u32>> = Vec::new();
+    //reused by every property, only the lists of faces end up in their own vectors
+    let mut list_items: Vec<f64> = Vec::new();
+    let mut has_vertices = false;
+    for element in header.elements.iter() {
+        let find = |names: &[&str]| {
+            element
+                .properties
+                .iter()
+                .position(|property| names.contains(&property.get_name()))
+        };
+        match &element.name[..] {
+            "vertex" => {
+                has_vertices = true;
+                let position = [find(&["x"]), find(&["y"]), find(&["z"])];
+                let normal = [find(&["nx"]), find(&["ny"]), find(&["nz"])];
+                let uv = [
+                    find(&["s", "u", "texture_u"]),
+                    find(&["t", "v", "texture_v"]),
+                ];
+                let color = [
+                    find(&["red", "diffuse_red"]),
+                    find(&["green", "diffuse_green"]),
+                    find(&["blue", "diffuse_blue"]),
+                ];
+                let alpha = find(&["alpha"]);
+                if position.iter().any(|p| p.is_none()) {
+                    return Err(String::from("ply vertices need x, y and z"));
+                }
+                let get_all = |indices: &[Option<usize>]| {
+                    let found: Vec<usize> = indices.iter().filter_map(|i| *i).collect();
+                    if found.len() == indices.len() {
+                        Some(found)
+                    } else {
+                        None
+                    }
+                };
+                let (position, normal, uv, color) = (
+                    get_all(&position[..]).unwrap(),
+                    get_all(&normal[..]),
+                    get_all(&uv[..]),
+                    get_all(&color[..]),
+                );
+                let color_scale = |index: usize| match &element.properties[index] {
+                    PlyProperty::Scalar { data_type, .. } => data_type.get_color_scale(),
+                    PlyProperty::List { .. } => 1.0,
+                };
+
+                let mut values = Vec::with_capacity(element.properties.len());
+                for _ in 0..element.count {
+                    values.clear();
+                    for property in element.properties.iter() {
+                        list_items.clear();
+                        values.push(reader.read_property(property, &mut list_items)?);
+                    }
+                    let get = |index: usize| values[index] as f32;
+                    mesh.positions
+                        .push([get(position[0]), get(position[1]), get(position[2])]);
+                    if let Some(normal) = normal.as_ref() {
+                        mesh.normals
+                            .push([get(normal[0]), get(normal[1]), get(normal[2])]);
+                    }
+                    //v goes down in the engine like in gltf
+                    if let Some(uv) = uv.as_ref() {
+                        mesh.uvs.push([get(uv[0]), 1.0 - get(uv[1])]);
+                    }
+                    if let Some(color) = color.as_ref() {
+                        let channel = |index: usize| {
+                            (values[index] / color_scale(index)).clamp(0.0, 1.0) as f32
+                        };
+                        mesh.colors.push([
+                            mipmap::srgb_to_linear(channel(color[0])),
+                            mipmap::srgb_to_linear(channel(color[1])),
+                            mipmap::srgb_to_linear(channel(color[2])),
+                            alpha.map_or(1.0, channel),
+                        ]);
+                    }
+                }
+            }
+            "face" => {
+                let indices = find(&["vertex_indices", "vertex_index"])
+                    .ok_or("ply faces need vertex_indices")?;
+                for _ in 0..element.count {
+                    for (index, property) in element.properties.iter().enumerate() {
+                        list_items.clear();
+                        reader.read_property(property, &mut list_items)?;
+                        if index == indices {
+                            //the list can be of floats, casting them would clamp negative
+                            //values to zero and drop fractions
+                            if let Some(value) = list_items.iter().find(|value| {
+                                !(**value >= 0.0
+                                    && value.fract() == 0.0
+                                    && **value <= u32::MAX as f64)
+                            }) {
+                                return Err(format!("ply face index {} is invalid", value));
+                            }
+                            faces.push(list_items.iter().map(|value| *value as u32).collect());
+                        }
+                    }
+                }
+            }
+            //other elements, like edges or materials, are read and dropped
+            _ => {
+                for _ in 0..element.count {
+                    for property in element.properties.iter() {
+                        list_items.clear();
+                        reader.read_property(property, &mut list_items)?;
+                    }
+                }
+            }
+        }
+    }
+    if !has_vertices {
+        return Err(String::from("ply file has no vertex element"));
+    }
+
+    if faces.is_empty() {
+        mesh.topology = wgpu::PrimitiveTopology::PointList;
+        return Ok(mesh);
+    }
+    for face in faces.iter().filter(|face| face.len() >= 3) {
+        if let Some(index) = face.iter().find(|i| **i as usize >= mesh.positions.len()) {
+            return Err(format!(
+                "ply face index {} is out of the {} vertices",
+                index,
+                mesh.positions.len()
+            ));
+        }
+        let points: Vec<[f32; 3]> = face.iter().map(|i| mesh.positions[*i as usize]).collect();
+        for triangle in obj::triangulate_polygon(&points[..]).iter() {
+            mesh.indices
+                .extend(triangle.iter().map(|corner| face[*corner]));
+        }
+    }
+    if mesh.normals.is_empty() {
+        processing::generate_normals(&mut mesh)?;
+    }
+    Ok(mesh)
+}
+
+//loads and processes the mesh, nothing here needs a gpu
+pub async fn import_ply_file(
+    file_name: &str,
+    processing_options: &processing::MeshProcessingOptions,
+) -> Result<MeshData, String> {
+    let content = platform::file_system::load_file_u8(file_name)
+        .await
+        .map_err(|e| format!("could not load {}: {:?}", file_name, e))?;
+    let mut mesh_data = parse_ply(&content[..]).map_err(|e| format!("{}: {}", file_name, e))?;
+    if processing_options.is_enabled() {
+        if let Err(message) = processing::process_mesh(&mut mesh_data, processing_options) {
+            platform::core::to_console(
+                &format!("could not process {}: {}", file_name, message)[..],
+            );
+        }
+    }
+    Ok(mesh_data)
+}
+
+//imports and uploads the mesh, its buffers are added to the given map.
+//Point clouds need a pipeline built with the pointList topology
+pub async fn load_ply_file(
+    file_name: &str,
+    buffers: &mut HashMap<u32, wgpu::Buffer>,
+    gpu_interfaces: &api::GPUInterfaces,
+    upload_manager: &mut upload::UploadManager,
+    processing_options: &processing::MeshProcessingOptions,
+) -> Result<Mesh, String> {
+    import_ply_file(file_name, processing_options)
+        .await?
+        .upload(buffers, gpu_interfaces, upload_manager)
+}
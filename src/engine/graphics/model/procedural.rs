@@ -0,0 +1,390 @@
+use super::super::super::platform;
+use super::MeshData;
+use std::f32::consts::PI;
+
+//meshes built on the cpu, handy to test pipelines without an asset on disk. They are
+//all centered on the origin with counter clockwise front faces, the uv origin is the
+//top left corner like in gltf
+
+fn normalize(vector: [f32; 3]) -> [f32; 3] {
+    let length = dot(vector, vector).sqrt();
+    if length > 0.0 {
+        [vector[0] / length, vector[1] / length, vector[2] / length]
+    } else {
+        vector
+    }
+}
+
+fn dot(a: [f32; 3], b: [f32; 3]) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn cross(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+fn scale(a: [f32; 3], value: f32) -> [f32; 3] {
+    [a[0] * value, a[1] * value, a[2] * value]
+}
+
+fn add(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[0] + b[0], a[1] + b[1], a[2] + b[2]]
+}
+
+//the generated meshes never mirror their uvs so the tangents only add vertices where
+//triangles around a vertex do not share edges, like the side of the cone
+fn with_tangents(mut mesh: MeshData) -> MeshData {
+    if let Err(error) = super::processing::generate_tangents(&mut mesh) {
+        platform::core::to_console(&format!("procedural mesh without tangents: {}", error));
+    }
+    mesh
+}
+
+//a grid of (columns + 1) * (rows + 1) vertices stored row by row, u goes along the
+//columns and v along the rows. Quads touching a collapsed first or last row, like
+//the poles of a sphere, only get the triangle that is not degenerate
+fn add_grid_indices(
+    indices: &mut Vec<u32>,
+    first_vertex: u32,
+    columns: u32,
+    rows: u32,
+    collapsed_first_row: bool,
+    collapsed_last_row: bool,
+) {
+    for row in 0..rows {
+        for column in 0..columns {
+            let top_left = first_vertex + row * (columns + 1) + column;
+            let top_right = top_left + 1;
+            let bottom_left = top_left + columns + 1;
+            let bottom_right = bottom_left + 1;
+            if !(collapsed_last_row && row == rows - 1) {
+                indices.extend_from_slice(&[top_left, bottom_left, bottom_right]);
+            }
+            if !(collapsed_first_row && row == 0) {
+                indices.extend_from_slice(&[top_left, bottom_right, top_right]);
+            }
+        }
+    }
+}
+
+//24 vertices so every face gets its own normals and uvs
+pub fn cube(size: f32) -> MeshData {
+    let half = size * 0.5;
+    //normal and the direction of increasing u, up is cross(normal, right)
+    let faces: [([f32; 3], [f32; 3]); 6] = [
+        ([1.0, 0.0, 0.0], [0.0, 0.0, -1.0]),
+        ([-1.0, 0.0, 0.0], [0.0, 0.0, 1.0]),
+        ([0.0, 1.0, 0.0], [1.0, 0.0, 0.0]),
+        ([0.0, -1.0, 0.0], [1.0, 0.0, 0.0]),
+        ([0.0, 0.0, 1.0], [1.0, 0.0, 0.0]),
+        ([0.0, 0.0, -1.0], [-1.0, 0.0, 0.0]),
+    ];
+    let mut mesh = MeshData::default();
+    for (normal, right) in faces.iter() {
+        let up = cross(*normal, *right);
+        let first = mesh.positions.len() as u32;
+        for (x, y) in [(-1.0, 1.0), (1.0, 1.0), (-1.0, -1.0), (1.0, -1.0)].iter() {
+            let position = add(
+                scale(*normal, half),
+                add(scale(*right, x * half), scale(up, y * half)),
+            );
+            mesh.positions.push(position);
+            mesh.normals.push(*normal);
+            mesh.uvs.push([(x + 1.0) * 0.5, (1.0 - y) * 0.5]);
+        }
+        add_grid_indices(&mut mesh.indices, first, 1, 1, false, false);
+    }
+    with_tangents(mesh)
+}
+
+//latitude and longitude sphere, u goes around the y axis and v from the top pole to
+//the bottom one. The seam and the poles have duplicated vertices
+pub fn uv_sphere(radius: f32, segments: u32, rings: u32) -> MeshData {
+    let segments = segments.max(3);
+    let rings = rings.max(2);
+    let mut mesh = MeshData::default();
+    for ring in 0..=rings {
+        let v = ring as f32 / rings as f32;
+        let theta = v * PI;
+        for segment in 0..=segments {
+            let u = segment as f32 / segments as f32;
+            let phi = u * 2.0 * PI;
+            let normal = [
+                theta.sin() * phi.cos(),
+                theta.cos(),
+                -theta.sin() * phi.sin(),
+            ];
+            mesh.positions.push(scale(normal, radius));
+            mesh.normals.push(normal);
+            mesh.uvs.push([u, v]);
+        }
+    }
+    add_grid_indices(&mut mesh.indices, 0, segments, rings, true, true);
+    with_tangents(mesh)
+}
+
+//subdivided icosahedron, the triangles are much more even than the ones of a uv
+//sphere. Vertices on the uv seam are duplicated so no triangle wraps around
+pub fn icosphere(radius: f32, subdivisions: u32) -> MeshData {
+    let t = (1.0 + 5.0f32.sqrt()) * 0.5;
+    let mut positions: Vec<[f32; 3]> = [
+        [-1.0, t, 0.0],
+        [1.0, t, 0.0],
+        [-1.0, -t, 0.0],
+        [1.0, -t, 0.0],
+        [0.0, -1.0, t],
+        [0.0, 1.0, t],
+        [0.0, -1.0, -t],
+        [0.0, 1.0, -t],
+        [t, 0.0, -1.0],
+        [t, 0.0, 1.0],
+        [-t, 0.0, -1.0],
+        [-t, 0.0, 1.0],
+    ]
+    .iter()
+    .map(|p| normalize(*p))
+    .collect();
+    let mut triangles: Vec<[u32; 3]> = vec![
+        [0, 11, 5],
+        [0, 5, 1],
+        [0, 1, 7],
+        [0, 7, 10],
+        [0, 10, 11],
+        [1, 5, 9],
+        [5, 11, 4],
+        [11, 10, 2],
+        [10, 7, 6],
+        [7, 1, 8],
+        [3, 9, 4],
+        [3, 4, 2],
+        [3, 2, 6],
+        [3, 6, 8],
+        [3, 8, 9],
+        [4, 9, 5],
+        [2, 4, 11],
+        [6, 2, 10],
+        [8, 6, 7],
+        [9, 8, 1],
+    ];
+
+    for _ in 0..subdivisions {
+        //edges are shared by two triangles, the midpoint is only added once
+        let mut midpoints = std::collections::HashMap::new();
+        let mut get_midpoint = |a: u32, b: u32, positions: &mut Vec<[f32; 3]>| -> u32 {
+            let key = (a.min(b), a.max(b));
+            *midpoints.entry(key).or_insert_with(|| {
+                let midpoint = scale(add(positions[a as usize], positions[b as usize]), 0.5);
+                positions.push(normalize(midpoint));
+                positions.len() as u32 - 1
+            })
+        };
+        let mut subdivided = Vec::with_capacity(triangles.len() * 4);
+        for [a, b, c] in triangles.iter().cloned() {
+            let ab = get_midpoint(a, b, &mut positions);
+            let bc = get_midpoint(b, c, &mut positions);
+            let ca = get_midpoint(c, a, &mut positions);
+            subdivided.push([a, ab, ca]);
+            subdivided.push([b, bc, ab]);
+            subdivided.push([c, ca, bc]);
+            subdivided.push([ab, bc, ca]);
+        }
+        triangles = subdivided;
+    }
+
+    //same mapping as the uv sphere
+    let mut uvs: Vec<[f32; 2]> = positions
+        .iter()
+        .map(|p| {
+            let u = (-p[2]).atan2(p[0]) / (2.0 * PI);
+            let u = if u < 0.0 { u + 1.0 } else { u };
+            [u, p[1].clamp(-1.0, 1.0).acos() / PI]
+        })
+        .collect();
+    let mut seam_copies = std::collections::HashMap::new();
+    for triangle in triangles.iter_mut() {
+        let us: Vec<f32> = triangle.iter().map(|v| uvs[*v as usize][0]).collect();
+        let max = us.iter().cloned().fold(f32::MIN, f32::max);
+        let min = us.iter().cloned().fold(f32::MAX, f32::min);
+        if max - min <= 0.5 {
+            continue;
+        }
+        for vertex in triangle.iter_mut() {
+            if uvs[*vertex as usize][0] < 0.5 {
+                let original = *vertex as usize;
+                *vertex = *seam_copies.entry(original).or_insert_with(|| {
+                    positions.push(positions[original]);
+                    uvs.push([uvs[original][0] + 1.0, uvs[original][1]]);
+                    positions.len() as u32 - 1
+                });
+            }
+        }
+    }
+
+    let mesh = MeshData {
+        positions: positions.iter().map(|p| scale(*p, radius)).collect(),
+        normals: positions,
+        uvs,
+        indices: triangles.iter().flat_map(|t| t.iter().cloned()).collect(),
+        ..Default::default()
+    };
+    with_tangents(mesh)
+}
+
+//on the xz plane facing +y, u goes along x and v along z
+pub fn grid(width: f32, depth: f32, x_segments: u32, z_segments: u32) -> MeshData {
+    let x_segments = x_segments.max(1);
+    let z_segments = z_segments.max(1);
+    let mut mesh = MeshData::default();
+    for row in 0..=z_segments {
+        let v = row as f32 / z_segments as f32;
+        for column in 0..=x_segments {
+            let u = column as f32 / x_segments as f32;
+            mesh.positions
+                .push([(u - 0.5) * width, 0.0, (v - 0.5) * depth]);
+            mesh.normals.push([0.0, 1.0, 0.0]);
+            mesh.uvs.push([u, v]);
+        }
+    }
+    add_grid_indices(&mut mesh.indices, 0, x_segments, z_segments, false, false);
+    with_tangents(mesh)
+}
+
+pub fn plane(width: f32, depth: f32) -> MeshData {
+    grid(width, depth, 1, 1)
+}
+
+//the disc of a cylinder or cone at the given height, facing up or down
+fn add_cap(mesh: &mut MeshData, radius: f32, height: f32, segments: u32, up: bool) {
+    let normal = if up {
+        [0.0, 1.0, 0.0]
+    } else {
+        [0.0, -1.0, 0.0]
+    };
+    let center = mesh.positions.len() as u32;
+    mesh.positions.push([0.0, height, 0.0]);
+    mesh.normals.push(normal);
+    mesh.uvs.push([0.5, 0.5]);
+    for segment in 0..segments {
+        let phi = segment as f32 / segments as f32 * 2.0 * PI;
+        let (x, z) = (phi.cos(), -phi.sin());
+        mesh.positions.push([x * radius, height, z * radius]);
+        mesh.normals.push(normal);
+        //seen from the outside the texture is never mirrored
+        let v = if up { z } else { -z };
+        mesh.uvs.push([0.5 + x * 0.5, 0.5 + v * 0.5]);
+    }
+    for segment in 0..segments {
+        let current = center + 1 + segment;
+        let next = center + 1 + (segment + 1) % segments;
+        if up {
+            mesh.indices.extend_from_slice(&[center, current, next]);
+        } else {
+            mesh.indices.extend_from_slice(&[center, next, current]);
+        }
+    }
+}
+
+//along the y axis, the side has its own seam and the caps their own vertices
+pub fn cylinder(radius: f32, height: f32, segments: u32) -> MeshData {
+    let segments = segments.max(3);
+    let half = height * 0.5;
+    let mut mesh = MeshData::default();
+    for (row, y) in [half, -half].iter().enumerate() {
+        for segment in 0..=segments {
+            let u = segment as f32 / segments as f32;
+            let phi = u * 2.0 * PI;
+            let normal = [phi.cos(), 0.0, -phi.sin()];
+            mesh.positions
+                .push([normal[0] * radius, *y, normal[2] * radius]);
+            mesh.normals.push(normal);
+            mesh.uvs.push([u, row as f32]);
+        }
+    }
+    add_grid_indices(&mut mesh.indices, 0, segments, 1, false, false);
+    add_cap(&mut mesh, radius, half, segments, true);
+    add_cap(&mut mesh, radius, -half, segments, false);
+    with_tangents(mesh)
+}
+
+//along the y axis with the tip up, every side triangle has its own tip vertex so
+//the normals there point the right way. The side triangles share no edge, their base
+//vertices are split by the tangents
+pub fn cone(radius: f32, height: f32, segments: u32) -> MeshData {
+    let segments = segments.max(3);
+    let half = height * 0.5;
+    let slope_normal = |phi: f32| normalize([height * phi.cos(), radius, -height * phi.sin()]);
+    let mut mesh = MeshData::default();
+    for segment in 0..segments {
+        let u = (segment as f32 + 0.5) / segments as f32;
+        mesh.positions.push([0.0, half, 0.0]);
+        mesh.normals.push(slope_normal(u * 2.0 * PI));
+        mesh.uvs.push([u, 0.0]);
+    }
+    for segment in 0..=segments {
+        let u = segment as f32 / segments as f32;
+        let phi = u * 2.0 * PI;
+        mesh.positions
+            .push([phi.cos() * radius, -half, -phi.sin() * radius]);
+        mesh.normals.push(slope_normal(phi));
+        mesh.uvs.push([u, 1.0]);
+    }
+    for segment in 0..segments {
+        let base = segments + segment;
+        mesh.indices.extend_from_slice(&[segment, base, base + 1]);
+    }
+    add_cap(&mut mesh, radius, -half, segments, false);
+    with_tangents(mesh)
+}
+
+//ring around the y axis, u goes around the ring and v around the tube
+pub fn torus(
+    major_radius: f32,
+    minor_radius: f32,
+    major_segments: u32,
+    minor_segments: u32,
+) -> MeshData {
+    let major_segments = major_segments.max(3);
+    let minor_segments = minor_segments.max(3);
+    let mut mesh = MeshData::default();
+    for row in 0..=minor_segments {
+        let v = row as f32 / minor_segments as f32;
+        let theta = v * 2.0 * PI;
+        for column in 0..=major_segments {
+            let u = column as f32 / major_segments as f32;
+            let phi = u * 2.0 * PI;
+            let direction = [phi.cos(), 0.0, -phi.sin()];
+            let normal = add(scale(direction, theta.cos()), [0.0, -theta.sin(), 0.0]);
+            mesh.positions.push(add(
+                scale(direction, major_radius),
+                scale(normal, minor_radius),
+            ));
+            mesh.normals.push(normal);
+            mesh.uvs.push([u, v]);
+        }
+    }
+    add_grid_indices(
+        &mut mesh.indices,
+        0,
+        major_segments,
+        minor_segments,
+        false,
+        false,
+    );
+    with_tangents(mesh)
+}
+
+//a single triangle covering the whole viewport, positions are already in clip space
+//and there are no indices
+pub fn fullscreen_triangle() -> MeshData {
+    MeshData {
+        positions: vec![[-1.0, -1.0, 0.0], [3.0, -1.0, 0.0], [-1.0, 3.0, 0.0]],
+        normals: vec![[0.0, 0.0, 1.0]; 3],
+        uvs: vec![[0.0, 1.0], [2.0, 1.0], [0.0, -1.0]],
+        tangents: vec![[1.0, 0.0, 0.0, 1.0]; 3],
+        ..Default::default()
+    }
+}
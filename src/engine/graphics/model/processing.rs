@@ -0,0 +1,796 @@
+use super::MeshData;
+use cgmath::InnerSpace;
+use std::collections::HashMap;
+
+//cache size the vertex cache order is built for, bigger than most gpus have but the
+//order still works well on smaller caches
+const VERTEX_CACHE_SIZE: usize = 32;
+//fifo cache used to find where the vertex cache order restarts, the clusters the
+//overdraw optimization sorts begin there
+const OVERDRAW_CACHE_SIZE: usize = 16;
+
+//steps applied to imported meshes, all off by default so meshes are uploaded the way
+//they are stored
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct MeshProcessingOptions {
+    pub weld_vertices: bool,
+    //mikktspace tangents like the ones normal maps are baked against, only when the
+    //mesh has no tangents. It needs normals and uvs
+    pub generate_tangents: bool,
+    pub optimize_vertex_cache: bool,
+    pub optimize_overdraw: bool,
+}
+
+impl MeshProcessingOptions {
+    pub fn is_enabled(&self) -> bool {
+        self.weld_vertices
+            || self.generate_tangents
+            || self.optimize_vertex_cache
+            || self.optimize_overdraw
+    }
+}
+
+//runs the enabled steps in the order they work best in, welding first so tangents
+//are shared and the caches see the final vertices. Every step runs even if an
+//earlier one fails, the first error is returned
+pub fn process_mesh(mesh: &mut MeshData, options: &MeshProcessingOptions) -> Result<(), String> {
+    mesh.validate()?;
+    let mut result = Ok(());
+    if options.weld_vertices {
+        weld_vertices(mesh);
+    }
+    if options.generate_tangents && mesh.tangents.is_empty() {
+        result = generate_tangents(mesh);
+    }
+    let is_triangle_list = mesh.topology == wgpu::PrimitiveTopology::TriangleList;
+    if options.optimize_vertex_cache && is_triangle_list {
+        optimize_vertex_cache(&mut mesh.indices[..], mesh.positions.len());
+    }
+    if options.optimize_overdraw && is_triangle_list {
+        optimize_overdraw(&mesh.positions[..], &mut mesh.indices[..]);
+    }
+    result
+}
+
+//attributes compared bit by bit, -0.0 is turned into 0.0 first so it matches.
+//Streams are compared byte by byte and morph targets by their displacements
+fn get_vertex_key(mesh: &MeshData, vertex: usize) -> Vec<u32> {
+    let mut key = Vec::with_capacity(12);
+    let mut push = |values: &[f32]| {
+        key.extend(values.iter().map(|value| (value + 0.0).to_bits()));
+    };
+    push(&mesh.positions[vertex][..]);
+    if !mesh.normals.is_empty() {
+        push(&mesh.normals[vertex][..]);
+    }
+    if !mesh.uvs.is_empty() {
+        push(&mesh.uvs[vertex][..]);
+    }
+    if !mesh.tangents.is_empty() {
+        push(&mesh.tangents[vertex][..]);
+    }
+    if !mesh.colors.is_empty() {
+        push(&mesh.colors[vertex][..]);
+    }
+    if let Some(morph_targets) = mesh.morph_targets.as_ref() {
+        for target in morph_targets.targets.iter() {
+            for values in [&target.positions, &target.normals, &target.tangents].iter() {
+                if !values.is_empty() {
+                    push(&values[vertex][..]);
+                }
+            }
+        }
+    }
+    for stream in mesh.streams.iter() {
+        key.extend(stream.get_vertex(vertex).iter().map(|byte| *byte as u32));
+    }
+    key
+}
+
+//merges vertices with exactly the same attributes, meshes without indices get them.
+//The remaining vertices keep the order they are first used in
+pub fn weld_vertices(mesh: &mut MeshData) {
+    let indices: Vec<u32> = if mesh.indices.is_empty() {
+        (0..mesh.positions.len() as u32).collect()
+    } else {
+        mesh.indices.clone()
+    };
+
+    let mut sources: Vec<u32> = Vec::new();
+    let mut unique: HashMap<Vec<u32>, u32> = HashMap::new();
+    let mut remap: Vec<Option<u32>> = vec![None; mesh.positions.len()];
+    let mut welded_indices = Vec::with_capacity(indices.len());
+    for index in indices.iter() {
+        let vertex = *index as usize;
+        let new_index = match remap[vertex] {
+            Some(new_index) => new_index,
+            None => {
+                let next = sources.len() as u32;
+                let new_index = *unique.entry(get_vertex_key(mesh, vertex)).or_insert(next);
+                if new_index == next {
+                    sources.push(*index);
+                }
+                remap[vertex] = Some(new_index);
+                new_index
+            }
+        };
+        welded_indices.push(new_index);
+    }
+    mesh.remap_vertices(&sources[..]);
+    mesh.indices = welded_indices;
+}
+
+//fraction of the vertices of each triangle a fifo cache of the given size misses, 3
+//is the worst and 0.5 about the best a regular grid can get
+pub fn get_cache_miss_ratio(indices: &[u32], cache_size: usize) -> f32 {
+    let triangle_count = indices.len() / 3;
+    if triangle_count == 0 {
+        return 0.0;
+    }
+    let misses: usize = get_cache_misses(indices, cache_size).iter().sum();
+    misses as f32 / triangle_count as f32
+}
+
+//misses of every triangle with a fifo cache
+fn get_cache_misses(indices: &[u32], cache_size: usize) -> Vec<usize> {
+    let mut cache: std::collections::VecDeque<u32> = std::collections::VecDeque::new();
+    indices
+        .chunks(3)
+        .map(|triangle| {
+            let mut misses = 0;
+            for index in triangle.iter() {
+                if !cache.contains(index) {
+                    misses += 1;
+                    cache.push_back(*index);
+                    if cache.len() > cache_size {
+                        cache.pop_front();
+                    }
+                }
+            }
+            misses
+        })
+        .collect()
+}
+
+//score of tom forsyth's linear speed vertex cache optimization, vertices recently
+//used and vertices with few triangles left to draw are preferred
+fn get_vertex_score(cache_position: Option<usize>, remaining_triangles: usize) -> f32 {
+    if remaining_triangles == 0 {
+        return -1.0;
+    }
+    let cache_score = match cache_position {
+        None => 0.0,
+        //the last triangle gets a fixed score so it is not drawn again straight away
+        Some(position) if position < 3 => 0.75,
+        Some(position) => {
+            let scaler = 1.0 / (VERTEX_CACHE_SIZE - 3) as f32;
+            (1.0 - (position - 3) as f32 * scaler).powf(1.5)
+        }
+    };
+    cache_score + 2.0 * (remaining_triangles as f32).powf(-0.5)
+}
+
+//reorders the triangles so their vertices are found in the post transform cache as
+//often as possible, the triangles themselves and their winding do not change
+pub fn optimize_vertex_cache(indices: &mut [u32], vertex_count: usize) {
+    let triangle_count = indices.len() / 3;
+    if triangle_count == 0 {
+        return;
+    }
+
+    //triangles using each vertex, the drawn ones are swapped out of the live range
+    let mut remaining = vec![0usize; vertex_count];
+    for index in indices[..triangle_count * 3].iter() {
+        remaining[*index as usize] += 1;
+    }
+    let mut offsets = vec![0usize; vertex_count + 1];
+    for vertex in 0..vertex_count {
+        offsets[vertex + 1] = offsets[vertex] + remaining[vertex];
+    }
+    let mut vertex_triangles = vec![0usize; offsets[vertex_count]];
+    let mut filled = vec![0usize; vertex_count];
+    for triangle in 0..triangle_count {
+        for index in indices[triangle * 3..triangle * 3 + 3].iter() {
+            let vertex = *index as usize;
+            vertex_triangles[offsets[vertex] + filled[vertex]] = triangle;
+            filled[vertex] += 1;
+        }
+    }
+
+    let mut cache_positions: Vec<Option<usize>> = vec![None; vertex_count];
+    let mut vertex_scores: Vec<f32> = (0..vertex_count)
+        .map(|vertex| get_vertex_score(None, remaining[vertex]))
+        .collect();
+    let mut triangle_scores: Vec<f32> = (0..triangle_count)
+        .map(|triangle| {
+            indices[triangle * 3..triangle * 3 + 3]
+                .iter()
+                .map(|index| vertex_scores[*index as usize])
+                .sum()
+        })
+        .collect();
+    let mut emitted = vec![false; triangle_count];
+    let mut cache: Vec<u32> = Vec::with_capacity(VERTEX_CACHE_SIZE + 3);
+    let mut output = Vec::with_capacity(triangle_count * 3);
+    //first triangle not drawn yet in input order, used when the cache has no candidate
+    let mut cursor = 0;
+
+    let mut best = (0..triangle_count)
+        .max_by(|a, b| {
+            triangle_scores[*a]
+                .partial_cmp(&triangle_scores[*b])
+                .unwrap()
+        })
+        .unwrap();
+    for _ in 0..triangle_count {
+        emitted[best] = true;
+        let triangle: Vec<u32> = indices[best * 3..best * 3 + 3].to_vec();
+        output.extend_from_slice(&triangle[..]);
+
+        for index in triangle.iter() {
+            let vertex = *index as usize;
+            let live = &mut vertex_triangles[offsets[vertex]..offsets[vertex] + remaining[vertex]];
+            if let Some(position) = live.iter().position(|t| *t == best) {
+                let last = live.len() - 1;
+                live.swap(position, last);
+                remaining[vertex] -= 1;
+            }
+        }
+
+        //the vertices of the triangle move to the front of the lru cache
+        let mut new_cache = triangle.clone();
+        new_cache.extend(cache.iter().filter(|v| !triangle.contains(v)));
+        for (position, vertex) in new_cache.iter().enumerate() {
+            cache_positions[*vertex as usize] = if position < VERTEX_CACHE_SIZE {
+                Some(position)
+            } else {
+                None
+            };
+        }
+
+        //scores only change for the vertices that are or were in the cache
+        for vertex in new_cache.iter() {
+            let vertex = *vertex as usize;
+            let score = get_vertex_score(cache_positions[vertex], remaining[vertex]);
+            let delta = score - vertex_scores[vertex];
+            vertex_scores[vertex] = score;
+            for triangle in
+                vertex_triangles[offsets[vertex]..offsets[vertex] + remaining[vertex]].iter()
+            {
+                triangle_scores[*triangle] += delta;
+            }
+        }
+        new_cache.truncate(VERTEX_CACHE_SIZE);
+        cache = new_cache;
+
+        let mut best_score = -1.0;
+        let mut next = None;
+        for vertex in cache.iter() {
+            let vertex = *vertex as usize;
+            for triangle in
+                vertex_triangles[offsets[vertex]..offsets[vertex] + remaining[vertex]].iter()
+            {
+                if triangle_scores[*triangle] > best_score {
+                    best_score = triangle_scores[*triangle];
+                    next = Some(*triangle);
+                }
+            }
+        }
+        best = match next {
+            Some(triangle) => triangle,
+            None => {
+                while cursor < triangle_count && emitted[cursor] {
+                    cursor += 1;
+                }
+                if cursor == triangle_count {
+                    break;
+                }
+                cursor
+            }
+        };
+    }
+
+    indices[..triangle_count * 3].copy_from_slice(&output[..]);
+}
+
+//reorders groups of triangles so the ones facing away from the center of the mesh,
+//which are likely in front of the others, are drawn first. The groups start where the
+//vertex cache order restarts anyway so the cache efficiency is mostly kept
+pub fn optimize_overdraw(positions: &[[f32; 3]], indices: &mut [u32]) {
+    let triangle_count = indices.len() / 3;
+    if triangle_count == 0 {
+        return;
+    }
+    let point = |index: u32| cgmath::Vector3::from(positions[index as usize]);
+
+    //area weighted center and normal of every triangle
+    let triangles: Vec<(cgmath::Vector3<f32>, cgmath::Vector3<f32>)> = indices
+        [..triangle_count * 3]
+        .chunks(3)
+        .map(|t| {
+            let (a, b, c) = (point(t[0]), point(t[1]), point(t[2]));
+            ((a + b + c) / 3.0, (b - a).cross(c - a))
+        })
+        .collect();
+    let area_sum = |range: std::ops::Range<usize>| {
+        let mut center = cgmath::Vector3::new(0.0, 0.0, 0.0);
+        let mut normal = cgmath::Vector3::new(0.0, 0.0, 0.0);
+        let mut area = 0.0;
+        for (triangle_center, triangle_normal) in triangles[range.clone()].iter() {
+            let triangle_area = triangle_normal.magnitude();
+            center += triangle_center * triangle_area;
+            normal += *triangle_normal;
+            area += triangle_area;
+        }
+        if area > 0.0 {
+            center /= area;
+        } else {
+            //only degenerate triangles, the plain average will do
+            center = triangles[range.clone()]
+                .iter()
+                .fold(cgmath::Vector3::new(0.0, 0.0, 0.0), |sum, t| sum + t.0)
+                / range.len() as f32;
+        }
+        (center, normal)
+    };
+    let (mesh_center, _) = area_sum(0..triangle_count);
+
+    let misses = get_cache_misses(&indices[..triangle_count * 3], OVERDRAW_CACHE_SIZE);
+    let mut starts: Vec<usize> = (0..triangle_count)
+        .filter(|triangle| *triangle == 0 || misses[*triangle] == 3)
+        .collect();
+    starts.push(triangle_count);
+
+    let mut clusters: Vec<(f32, std::ops::Range<usize>)> = starts
+        .windows(2)
+        .map(|pair| {
+            let range = pair[0]..pair[1];
+            let (center, normal) = area_sum(range.clone());
+            let direction = if normal.magnitude2() > 0.0 {
+                normal.normalize()
+            } else {
+                normal
+            };
+            ((center - mesh_center).dot(direction), range)
+        })
+        .collect();
+    //stable so clusters facing the same way keep their order
+    clusters.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(std::cmp::Ordering::Equal));
+
+    let mut output = Vec::with_capacity(triangle_count * 3);
+    for (_, range) in clusters.iter() {
+        output.extend_from_slice(&indices[range.start * 3..range.end * 3]);
+    }
+    indices[..triangle_count * 3].copy_from_slice(&output[..]);
+}
+
+//smooth normals averaged from the faces around each vertex, bigger faces weigh more.
+//Vertices only used by degenerate triangles point up
+pub fn generate_normals(mesh: &mut MeshData) -> Result<(), String> {
+    mesh.validate()?;
+    if mesh.topology != wgpu::PrimitiveTopology::TriangleList {
+        return Err(format!(
+            "cannot generate normals for {:?} meshes",
+            mesh.topology
+        ));
+    }
+    let indices: Vec<u32> = if mesh.indices.is_empty() {
+        (0..mesh.positions.len() as u32).collect()
+    } else {
+        mesh.indices.clone()
+    };
+    let mut normals = vec![cgmath::Vector3::new(0.0, 0.0, 0.0); mesh.positions.len()];
+    for triangle in indices.chunks(3).filter(|t| t.len() == 3) {
+        let point = |i: usize| cgmath::Vector3::from(mesh.positions[triangle[i] as usize]);
+        let normal = (point(1) - point(0)).cross(point(2) - point(0));
+        for index in triangle.iter() {
+            normals[*index as usize] += normal;
+        }
+    }
+    mesh.normals = normals
+        .iter()
+        .map(|normal| {
+            if normal.magnitude2() > 0.0 {
+                normal.normalize().into()
+            } else {
+                [0.0, 1.0, 0.0]
+            }
+        })
+        .collect();
+    Ok(())
+}
+
+//per triangle data of generate_tangents
+#[derive(Clone)]
+struct TangentTriangle {
+    //triangle of the mesh, its corners are 3 * source + 0..3
+    source: usize,
+    //triangle across the edge starting at each corner
+    neighbours: [Option<usize>; 3],
+    //group of each corner
+    groups: [Option<usize>; 3],
+    //directions of increasing u and v, named like in the reference implementation
+    os: cgmath::Vector3<f32>,
+    ot: cgmath::Vector3<f32>,
+    orientation_preserving: bool,
+    //no uv gradient, the triangle joins the groups of its neighbours
+    group_with_any: bool,
+}
+
+//triangles sharing a welded vertex, connected by their edges and with the same
+//orientation
+struct TangentGroup {
+    vertex: u32,
+    orientation_preserving: bool,
+    triangles: Vec<usize>,
+}
+
+fn is_not_zero(value: f32) -> bool {
+    value.abs() > f32::MIN_POSITIVE
+}
+
+fn normalize_not_zero(vector: cgmath::Vector3<f32>) -> cgmath::Vector3<f32> {
+    if is_not_zero(vector.x) || is_not_zero(vector.y) || is_not_zero(vector.z) {
+        vector * (1.0 / vector.magnitude())
+    } else {
+        vector
+    }
+}
+
+//mikktspace tangents, a port of the reference implementation with its default 180
+//degree angular threshold, restricted to triangles. Corners with the same position,
+//normal and uv are treated as one vertex, so the result does not depend on how the
+//mesh is welded. Vertices whose corners end up with different tangents,
+//like the ones on a uv mirror, are split. The reference bitangent points towards
+//increasing v, the sign is flipped so it follows gltf and points towards decreasing v
+pub fn generate_tangents(mesh: &mut MeshData) -> Result<(), String> {
+    mesh.validate()?;
+    if mesh.topology != wgpu::PrimitiveTopology::TriangleList {
+        return Err(format!(
+            "cannot generate tangents for {:?} meshes",
+            mesh.topology
+        ));
+    }
+    let count = mesh.positions.len();
+    if mesh.normals.len() != count || mesh.uvs.len() != count {
+        return Err(String::from("tangents need normals and uvs"));
+    }
+    let indexed = !mesh.indices.is_empty();
+    let mut indices: Vec<u32> = if indexed {
+        mesh.indices.clone()
+    } else {
+        (0..count as u32).collect()
+    };
+    let triangle_count = indices.len() / 3;
+
+    let position = |vertex: u32| cgmath::Vector3::from(mesh.positions[vertex as usize]);
+    let normal = |vertex: u32| cgmath::Vector3::from(mesh.normals[vertex as usize]);
+    let uv = |vertex: u32| mesh.uvs[vertex as usize];
+
+    //every corner gets the first vertex with the same position, normal and uv
+    let mut welded_vertices: HashMap<[u32; 8], u32> = HashMap::new();
+    let mut welded: Vec<u32> = vec![0; count];
+    for (vertex, welded_vertex) in welded.iter_mut().enumerate() {
+        let mut key = [0u32; 8];
+        let values = mesh.positions[vertex]
+            .iter()
+            .chain(mesh.normals[vertex].iter())
+            .chain(mesh.uvs[vertex].iter());
+        for (bits, value) in key.iter_mut().zip(values) {
+            *bits = (value + 0.0).to_bits();
+        }
+        *welded_vertex = *welded_vertices.entry(key).or_insert(vertex as u32);
+    }
+
+    //degenerate triangles go after the others, the good ones keep their order
+    let is_degenerate = |triangle: usize| {
+        let p = |corner: usize| position(welded[indices[triangle * 3 + corner] as usize]);
+        p(0) == p(1) || p(0) == p(2) || p(1) == p(2)
+    };
+    let (good, degenerate): (Vec<usize>, Vec<usize>) =
+        (0..triangle_count).partition(|triangle| !is_degenerate(*triangle));
+    let mut triangles: Vec<TangentTriangle> = Vec::with_capacity(good.len());
+    let mut list: Vec<u32> = Vec::with_capacity(good.len() * 3);
+    for source in good.iter() {
+        list.extend((0..3).map(|corner| welded[indices[source * 3 + corner] as usize]));
+        triangles.push(TangentTriangle {
+            source: *source,
+            neighbours: [None; 3],
+            groups: [None; 3],
+            os: cgmath::Vector3::new(0.0, 0.0, 0.0),
+            ot: cgmath::Vector3::new(0.0, 0.0, 0.0),
+            orientation_preserving: false,
+            group_with_any: true,
+        });
+    }
+
+    //first order derivatives of every triangle
+    for (t, triangle) in triangles.iter_mut().enumerate() {
+        let (v1, v2, v3) = (list[t * 3], list[t * 3 + 1], list[t * 3 + 2]);
+        let (t1, t2, t3) = (uv(v1), uv(v2), uv(v3));
+        let (t21x, t21y) = (t2[0] - t1[0], t2[1] - t1[1]);
+        let (t31x, t31y) = (t3[0] - t1[0], t3[1] - t1[1]);
+        let d1 = position(v2) - position(v1);
+        let d2 = position(v3) - position(v1);
+        let signed_area = t21x * t31y - t21y * t31x;
+        let os = d1 * t31y - d2 * t21y;
+        let ot = d1 * -t31x + d2 * t21x;
+        triangle.orientation_preserving = signed_area > 0.0;
+        if is_not_zero(signed_area) {
+            let area = signed_area.abs();
+            let (length_os, length_ot) = (os.magnitude(), ot.magnitude());
+            let sign = if triangle.orientation_preserving {
+                1.0
+            } else {
+                -1.0
+            };
+            if is_not_zero(length_os) {
+                triangle.os = os * (sign / length_os);
+            }
+            if is_not_zero(length_ot) {
+                triangle.ot = ot * (sign / length_ot);
+            }
+            if is_not_zero(length_os / area) && is_not_zero(length_ot / area) {
+                triangle.group_with_any = false;
+            }
+        }
+    }
+
+    //triangles sharing an edge in opposite directions are neighbours, edges are paired
+    //in the order of their vertices and triangles
+    let mut edges: Vec<(u32, u32, usize)> = Vec::with_capacity(list.len());
+    for t in 0..triangles.len() {
+        for corner in 0..3 {
+            let (a, b) = (list[t * 3 + corner], list[t * 3 + (corner + 1) % 3]);
+            edges.push((a.min(b), a.max(b), t));
+        }
+    }
+    edges.sort_unstable();
+    let get_edge = |t: usize, a: u32, b: u32| {
+        let corners = &list[t * 3..t * 3 + 3];
+        if corners[0] == a || corners[0] == b {
+            if corners[1] == a || corners[1] == b {
+                (0, corners[0], corners[1])
+            } else {
+                (2, corners[2], corners[0])
+            }
+        } else {
+            (1, corners[1], corners[2])
+        }
+    };
+    for (i, (a, b, t)) in edges.iter().enumerate() {
+        let (edge, from, to) = get_edge(*t, *a, *b);
+        if triangles[*t].neighbours[edge].is_some() {
+            continue;
+        }
+        let other = edges[i + 1..]
+            .iter()
+            .take_while(|(other_a, other_b, _)| other_a == a && other_b == b)
+            .find_map(|(_, _, other)| {
+                let (other_edge, other_from, other_to) = get_edge(*other, *a, *b);
+                if other_from == to
+                    && other_to == from
+                    && triangles[*other].neighbours[other_edge].is_none()
+                {
+                    Some((*other, other_edge))
+                } else {
+                    None
+                }
+            });
+        if let Some((other, other_edge)) = other {
+            triangles[*t].neighbours[edge] = Some(other);
+            triangles[other].neighbours[other_edge] = Some(*t);
+        }
+    }
+
+    //groups grow from every corner of a good triangle through the neighbours around
+    //the vertex. Triangles without a uv gradient take the orientation of the first
+    //group reaching them
+    let mut groups: Vec<TangentGroup> = Vec::new();
+    for t in 0..triangles.len() {
+        for corner in 0..3 {
+            if triangles[t].group_with_any || triangles[t].groups[corner].is_some() {
+                continue;
+            }
+            let group_index = groups.len();
+            let mut group = TangentGroup {
+                vertex: list[t * 3 + corner],
+                orientation_preserving: triangles[t].orientation_preserving,
+                triangles: vec![t],
+            };
+            triangles[t].groups[corner] = Some(group_index);
+            let neighbours_of = |triangle: &TangentTriangle, corner: usize| {
+                [
+                    triangle.neighbours[corner],
+                    triangle.neighbours[(corner + 2) % 3],
+                ]
+            };
+            let mut pending: Vec<usize> = neighbours_of(&triangles[t], corner)
+                .iter()
+                .rev()
+                .filter_map(|n| *n)
+                .collect();
+            while let Some(other) = pending.pop() {
+                let triangle = &mut triangles[other];
+                let corner = match (0..3).find(|c| list[other * 3 + c] == group.vertex) {
+                    Some(corner) => corner,
+                    None => continue,
+                };
+                if triangle.groups[corner].is_some() {
+                    continue;
+                }
+                if triangle.group_with_any && triangle.groups.iter().all(|g| g.is_none()) {
+                    triangle.orientation_preserving = group.orientation_preserving;
+                }
+                if triangle.orientation_preserving != group.orientation_preserving {
+                    continue;
+                }
+                triangle.groups[corner] = Some(group_index);
+                group.triangles.push(other);
+                pending.extend(
+                    neighbours_of(triangle, corner)
+                        .iter()
+                        .rev()
+                        .filter_map(|n| *n),
+                );
+            }
+            groups.push(group);
+        }
+    }
+
+    //tangent of every corner of the good triangles, corners no group reached keep the
+    //default frame of the reference
+    let project = |n: cgmath::Vector3<f32>, vector: cgmath::Vector3<f32>| {
+        normalize_not_zero(vector - n * n.dot(vector))
+    };
+    let mut spaces: Vec<(cgmath::Vector3<f32>, bool)> =
+        vec![(cgmath::Vector3::unit_x(), false); triangle_count * 3];
+    for (group_index, group) in groups.iter().enumerate() {
+        let n = normal(group.vertex);
+        let mut subgroups: Vec<(Vec<usize>, cgmath::Vector3<f32>)> = Vec::new();
+        for t in group.triangles.iter() {
+            let corner = (0..3)
+                .find(|c| triangles[*t].groups[*c] == Some(group_index))
+                .unwrap();
+            let os = project(n, triangles[*t].os);
+            let ot = project(n, triangles[*t].ot);
+            let mut members: Vec<usize> = group
+                .triangles
+                .iter()
+                .cloned()
+                .filter(|other| {
+                    let other_os = project(n, triangles[*other].os);
+                    let other_ot = project(n, triangles[*other].ot);
+                    triangles[*t].group_with_any
+                        || triangles[*other].group_with_any
+                        || t == other
+                        || (os.dot(other_os) > -1.0 && ot.dot(other_ot) > -1.0)
+                })
+                .collect();
+            members.sort_unstable();
+            let tangent = match subgroups.iter().find(|(other, _)| *other == members) {
+                Some((_, tangent)) => *tangent,
+                None => {
+                    let tangent = get_group_tangent(
+                        &members[..],
+                        group.vertex,
+                        &list[..],
+                        &triangles[..],
+                        mesh,
+                    );
+                    subgroups.push((members, tangent));
+                    tangent
+                }
+            };
+            spaces[triangles[*t].source * 3 + corner] = (tangent, group.orientation_preserving);
+        }
+    }
+    //corners of degenerate triangles copy the first good corner of their vertex
+    let mut first_corners: HashMap<u32, usize> = HashMap::new();
+    for (corner, vertex) in list.iter().enumerate().rev() {
+        first_corners.insert(*vertex, triangles[corner / 3].source * 3 + corner % 3);
+    }
+    for source in degenerate.iter() {
+        for corner in source * 3..source * 3 + 3 {
+            if let Some(first) = first_corners.get(&welded[indices[corner] as usize]) {
+                spaces[corner] = spaces[*first];
+            }
+        }
+    }
+
+    //every vertex keeps the first tangent its corners got, the other ones go to copies
+    //appended in vertex order after the existing vertices
+    let corner_tangents: Vec<[f32; 4]> = spaces
+        .iter()
+        .map(|(tangent, orientation_preserving)| {
+            let sign = if *orientation_preserving { -1.0 } else { 1.0 };
+            [tangent.x, tangent.y, tangent.z, sign]
+        })
+        .collect();
+    //compared bit by bit so a nan does not make a copy for every corner
+    let same = |a: &[f32; 4], b: &[f32; 4]| {
+        a.iter()
+            .zip(b.iter())
+            .all(|(a, b)| a.to_bits() == b.to_bits())
+    };
+    let mut versions: Vec<Vec<[f32; 4]>> = vec![Vec::new(); count];
+    let mut corner_versions: Vec<usize> = Vec::with_capacity(corner_tangents.len());
+    for (corner, tangent) in corner_tangents.iter().enumerate() {
+        let vertex_versions = &mut versions[indices[corner] as usize];
+        match vertex_versions
+            .iter()
+            .position(|version| same(version, tangent))
+        {
+            Some(version) => corner_versions.push(version),
+            None => {
+                corner_versions.push(vertex_versions.len());
+                vertex_versions.push(*tangent);
+            }
+        }
+    }
+    let mut tangents: Vec<[f32; 4]> = Vec::with_capacity(count);
+    for (vertex, vertex_versions) in versions.iter().enumerate() {
+        tangents.push(vertex_versions.first().cloned().unwrap_or_else(|| {
+            //unused vertices, any direction on the plane of the normal will do
+            let n = normal(vertex as u32);
+            let axis = if n.x.abs() < 0.9 {
+                cgmath::Vector3::unit_x()
+            } else {
+                cgmath::Vector3::unit_y()
+            };
+            let tangent = n.cross(axis).cross(n).normalize();
+            [tangent.x, tangent.y, tangent.z, 1.0]
+        }));
+    }
+    let mut first_copies: Vec<u32> = vec![0; count];
+    let mut sources: Vec<u32> = (0..count as u32).collect();
+    for (vertex, vertex_versions) in versions.iter().enumerate() {
+        first_copies[vertex] = sources.len() as u32;
+        for tangent in vertex_versions.iter().skip(1) {
+            tangents.push(*tangent);
+            sources.push(vertex as u32);
+        }
+    }
+    if sources.len() > count {
+        mesh.remap_vertices(&sources[..]);
+    }
+    for (corner, version) in corner_versions.iter().enumerate() {
+        if *version > 0 {
+            let vertex = indices[corner] as usize;
+            indices[corner] = first_copies[vertex] + *version as u32 - 1;
+        }
+    }
+
+    mesh.tangents = tangents;
+    //meshes without indices have a vertex per corner and never need copies
+    if indexed {
+        mesh.indices = indices;
+    }
+    Ok(())
+}
+
+//angle weighted average of the tangents of the triangles around the vertex, the
+//triangles without a uv gradient do not contribute
+fn get_group_tangent(
+    members: &[usize],
+    vertex: u32,
+    list: &[u32],
+    triangles: &[TangentTriangle],
+    mesh: &MeshData,
+) -> cgmath::Vector3<f32> {
+    let position = |vertex: u32| cgmath::Vector3::from(mesh.positions[vertex as usize]);
+    let n = cgmath::Vector3::from(mesh.normals[vertex as usize]);
+    let project = |vector: cgmath::Vector3<f32>| normalize_not_zero(vector - n * n.dot(vector));
+    let mut sum = cgmath::Vector3::new(0.0, 0.0, 0.0);
+    for t in members.iter().filter(|t| !triangles[**t].group_with_any) {
+        let corner = (0..3).find(|c| list[t * 3 + c] == vertex).unwrap();
+        let previous = list[t * 3 + (corner + 2) % 3];
+        let next = list[t * 3 + (corner + 1) % 3];
+        let to_previous = project(position(previous) - position(vertex));
+        let to_next = project(position(next) - position(vertex));
+        let cosine = to_previous.dot(to_next).clamp(-1.0, 1.0);
+        let angle = (cosine as f64).acos() as f32;
+        sum += project(triangles[*t].os) * angle;
+    }
+    normalize_not_zero(sum)
+}
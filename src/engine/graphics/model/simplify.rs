@@ -0,0 +1,441 @@
+use super::super::bounds;
+use super::super::camera;
+use super::MeshData;
+use cgmath::InnerSpace;
+use std::collections::HashMap;
+
+//sum of the squared distances to a set of planes, weighted by the area of the
+//triangles they come from. Stored as the upper half of the symmetric 4x4 matrix
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct Quadric {
+    a: [f64; 6],
+    b: [f64; 3],
+    c: f64,
+    weight: f64,
+}
+
+impl Quadric {
+    fn from_plane(normal: [f64; 3], distance: f64, weight: f64) -> Self {
+        let [x, y, z] = normal;
+        Self {
+            a: [
+                x * x * weight,
+                x * y * weight,
+                x * z * weight,
+                y * y * weight,
+                y * z * weight,
+                z * z * weight,
+            ],
+            b: [
+                x * distance * weight,
+                y * distance * weight,
+                z * distance * weight,
+            ],
+            c: distance * distance * weight,
+            weight,
+        }
+    }
+
+    fn add(&mut self, other: &Quadric) {
+        for (value, other_value) in self.a.iter_mut().zip(other.a.iter()) {
+            *value += other_value;
+        }
+        for (value, other_value) in self.b.iter_mut().zip(other.b.iter()) {
+            *value += other_value;
+        }
+        self.c += other.c;
+        self.weight += other.weight;
+    }
+
+    fn evaluate(&self, point: [f32; 3]) -> f64 {
+        let [x, y, z] = [point[0] as f64, point[1] as f64, point[2] as f64];
+        let a = &self.a;
+        let quadratic = a[0] * x * x
+            + 2.0 * a[1] * x * y
+            + 2.0 * a[2] * x * z
+            + a[3] * y * y
+            + 2.0 * a[4] * y * z
+            + a[5] * z * z;
+        let linear = 2.0 * (self.b[0] * x + self.b[1] * y + self.b[2] * z);
+        (quadratic + linear + self.c).max(0.0)
+    }
+}
+
+//vertices with the same position, -0.0 is turned into 0.0 first so it matches
+fn get_position_groups(positions: &[[f32; 3]]) -> (Vec<usize>, Vec<Vec<u32>>) {
+    let mut group_indices: HashMap<[u32; 3], usize> = HashMap::new();
+    let mut vertex_groups = Vec::with_capacity(positions.len());
+    let mut groups: Vec<Vec<u32>> = Vec::new();
+    for (vertex, position) in positions.iter().enumerate() {
+        let key = [
+            (position[0] + 0.0).to_bits(),
+            (position[1] + 0.0).to_bits(),
+            (position[2] + 0.0).to_bits(),
+        ];
+        let next = groups.len();
+        let group = *group_indices.entry(key).or_insert(next);
+        if group == next {
+            groups.push(Vec::new());
+        }
+        groups[group].push(vertex as u32);
+        vertex_groups.push(group);
+    }
+    (vertex_groups, groups)
+}
+
+fn get_triangle_normal(a: [f32; 3], b: [f32; 3], c: [f32; 3]) -> cgmath::Vector3<f32> {
+    let a = cgmath::Vector3::from(a);
+    (cgmath::Vector3::from(b) - a).cross(cgmath::Vector3::from(c) - a)
+}
+
+//state of a simplification, triangles store vertex indices of the mesh and are set to
+//None once collapsed
+struct Simplifier<'a> {
+    positions: &'a [[f32; 3]],
+    vertex_groups: Vec<usize>,
+    groups: Vec<Vec<u32>>,
+    //quadrics are shared by the vertices of a position group
+    quadrics: Vec<Quadric>,
+    locked: Vec<bool>,
+    triangles: Vec<Option<[u32; 3]>>,
+    //live triangles around every vertex, rebuilt at the start of each pass
+    adjacency: Vec<Vec<usize>>,
+}
+
+impl<'a> Simplifier<'a> {
+    fn new(positions: &'a [[f32; 3]], indices: &[u32]) -> Self {
+        let (vertex_groups, groups) = get_position_groups(positions);
+        let mut quadrics = vec![Quadric::default(); groups.len()];
+        let mut edge_uses: HashMap<(usize, usize), u32> = HashMap::new();
+        let triangles: Vec<Option<[u32; 3]>> = indices
+            .chunks_exact(3)
+            .map(|t| Some([t[0], t[1], t[2]]))
+            .collect();
+        for triangle in triangles.iter().flatten() {
+            let corners = [
+                positions[triangle[0] as usize],
+                positions[triangle[1] as usize],
+                positions[triangle[2] as usize],
+            ];
+            let normal = get_triangle_normal(corners[0], corners[1], corners[2]);
+            let double_area = normal.magnitude();
+            if double_area > 0.0 {
+                let normal = normal / double_area;
+                let normal = [normal.x as f64, normal.y as f64, normal.z as f64];
+                let distance = -(normal[0] * corners[0][0] as f64
+                    + normal[1] * corners[0][1] as f64
+                    + normal[2] * corners[0][2] as f64);
+                let quadric = Quadric::from_plane(normal, distance, double_area as f64 * 0.5);
+                for vertex in triangle.iter() {
+                    quadrics[vertex_groups[*vertex as usize]].add(&quadric);
+                }
+            }
+            for corner in 0..3 {
+                let start = vertex_groups[triangle[corner] as usize];
+                let end = vertex_groups[triangle[(corner + 1) % 3] as usize];
+                *edge_uses
+                    .entry((start.min(end), start.max(end)))
+                    .or_insert(0) += 1;
+            }
+        }
+
+        //seams have more than a vertex per position, borders and non manifold edges
+        //do not have exactly two triangles. Neither ever move
+        let mut locked_groups: Vec<bool> = groups.iter().map(|group| group.len() > 1).collect();
+        for ((start, end), uses) in edge_uses.iter() {
+            if *uses != 2 {
+                locked_groups[*start] = true;
+                locked_groups[*end] = true;
+            }
+        }
+        let locked = vertex_groups
+            .iter()
+            .map(|group| locked_groups[*group])
+            .collect();
+        Self {
+            positions,
+            vertex_groups,
+            groups,
+            quadrics,
+            locked,
+            triangles,
+            adjacency: Vec::new(),
+        }
+    }
+
+    fn update_adjacency(&mut self) {
+        self.adjacency = vec![Vec::new(); self.positions.len()];
+        for (index, triangle) in self.triangles.iter().enumerate() {
+            if let Some(triangle) = triangle {
+                for vertex in triangle.iter() {
+                    self.adjacency[*vertex as usize].push(index);
+                }
+            }
+        }
+    }
+
+    fn get_cost(&self, from: u32, to: u32) -> f64 {
+        let mut quadric = self.quadrics[self.vertex_groups[from as usize]];
+        quadric.add(&self.quadrics[self.vertex_groups[to as usize]]);
+        quadric.evaluate(self.positions[to as usize])
+    }
+
+    //position groups around the vertices of the group
+    fn get_group_neighbours(&self, group: usize) -> Vec<usize> {
+        let mut neighbours = Vec::new();
+        for vertex in self.groups[group].iter() {
+            for triangle in self.adjacency[*vertex as usize].iter() {
+                if let Some(triangle) = self.triangles[*triangle] {
+                    for corner in triangle.iter() {
+                        let corner_group = self.vertex_groups[*corner as usize];
+                        if corner_group != group && !neighbours.contains(&corner_group) {
+                            neighbours.push(corner_group);
+                        }
+                    }
+                }
+            }
+        }
+        neighbours
+    }
+
+    //moving from onto to must keep the surface manifold, must not flip a triangle and
+    //must not mix the attributes of the two sides of a seam
+    fn can_collapse(&self, from: u32, to: u32) -> bool {
+        let from_group = self.vertex_groups[from as usize];
+        let to_group = self.vertex_groups[to as usize];
+        let mut shared_triangles = 0;
+        for triangle in self.adjacency[from as usize].iter() {
+            let triangle = match self.triangles[*triangle] {
+                Some(triangle) => triangle,
+                None => continue,
+            };
+            let touches_to = triangle
+                .iter()
+                .any(|corner| self.vertex_groups[*corner as usize] == to_group);
+            if touches_to {
+                if !triangle.contains(&to) {
+                    return false;
+                }
+                shared_triangles += 1;
+                continue;
+            }
+            let position = |corner: u32| self.positions[corner as usize];
+            let moved = |corner: u32| {
+                if corner == from {
+                    position(to)
+                } else {
+                    position(corner)
+                }
+            };
+            let before = get_triangle_normal(
+                position(triangle[0]),
+                position(triangle[1]),
+                position(triangle[2]),
+            );
+            let after =
+                get_triangle_normal(moved(triangle[0]), moved(triangle[1]), moved(triangle[2]));
+            if after.magnitude2() <= before.magnitude2() * 1e-6 || before.dot(after) <= 0.0 {
+                return false;
+            }
+        }
+
+        //the link condition, the only neighbours the two have in common are the
+        //opposite corners of the triangles on their edge
+        let to_neighbours = self.get_group_neighbours(to_group);
+        let common = self
+            .get_group_neighbours(from_group)
+            .iter()
+            .filter(|group| to_neighbours.contains(group))
+            .count();
+        shared_triangles > 0 && common <= shared_triangles
+    }
+
+    //the triangles of from touching to go away and the other ones use to in place of
+    //from, returns how many triangles were removed
+    fn collapse(&mut self, from: u32, to: u32) -> usize {
+        let to_group = self.vertex_groups[to as usize];
+        let mut removed = 0;
+        for triangle_index in self.adjacency[from as usize].clone().iter() {
+            let triangle = match self.triangles[*triangle_index].as_mut() {
+                Some(triangle) => triangle,
+                None => continue,
+            };
+            if triangle.contains(&to) {
+                self.triangles[*triangle_index] = None;
+                removed += 1;
+                continue;
+            }
+            for corner in triangle.iter_mut() {
+                if *corner == from {
+                    *corner = to;
+                }
+            }
+        }
+        let from_quadric = self.quadrics[self.vertex_groups[from as usize]];
+        self.quadrics[to_group].add(&from_quadric);
+        removed
+    }
+}
+
+//removes vertices with quadric error metric edge collapses until the mesh has no more
+//than the target triangles or nothing can be removed. Vertices only ever move onto a
+//neighbour so their attributes stay valid, and vertices on seams and borders never
+//move so uv islands and open edges keep their shape. Seams are only found between
+//vertices sharing a position, meshes need to be welded first. The returned error is
+//the distance to the original surface of the worst collapse, in the units of the mesh
+pub fn simplify_mesh(
+    mesh: &MeshData,
+    target_triangle_count: usize,
+) -> Result<(MeshData, f32), String> {
+    mesh.validate()?;
+    if mesh.topology != wgpu::PrimitiveTopology::TriangleList {
+        return Err(format!("cannot simplify {:?} meshes", mesh.topology));
+    }
+    let indices: Vec<u32> = if mesh.indices.is_empty() {
+        (0..mesh.positions.len() as u32).collect()
+    } else {
+        mesh.indices.clone()
+    };
+
+    let mut simplifier = Simplifier::new(&mesh.positions[..], &indices[..]);
+    let mut triangle_count = simplifier.triangles.len();
+    let mut error: f64 = 0.0;
+    //every pass collapses the cheapest edges first, a vertex is used at most once per
+    //pass since the costs of its neighbourhood are out of date after a collapse
+    while triangle_count > target_triangle_count {
+        simplifier.update_adjacency();
+        let mut candidates: Vec<(f64, u32, u32)> = Vec::new();
+        for triangle in simplifier.triangles.iter().flatten() {
+            for corner in 0..3 {
+                let from = triangle[corner];
+                if simplifier.locked[from as usize] {
+                    continue;
+                }
+                for to in [triangle[(corner + 1) % 3], triangle[(corner + 2) % 3]].iter() {
+                    //degenerate triangles list a vertex twice, collapsing it onto itself
+                    //would remove every triangle around it
+                    if *to != from {
+                        candidates.push((simplifier.get_cost(from, *to), from, *to));
+                    }
+                }
+            }
+        }
+        candidates.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal));
+
+        let mut touched = vec![false; mesh.positions.len()];
+        let mut collapsed = false;
+        for (cost, from, to) in candidates.iter() {
+            if triangle_count <= target_triangle_count {
+                break;
+            }
+            if touched[*from as usize]
+                || touched[*to as usize]
+                || !simplifier.can_collapse(*from, *to)
+            {
+                continue;
+            }
+            for triangle in simplifier.adjacency[*from as usize].iter() {
+                if let Some(triangle) = simplifier.triangles[*triangle] {
+                    for corner in triangle.iter() {
+                        touched[*corner as usize] = true;
+                    }
+                }
+            }
+            let to_group = simplifier.vertex_groups[*to as usize];
+            for vertex in simplifier.groups[to_group].iter() {
+                touched[*vertex as usize] = true;
+            }
+            let weight = simplifier.quadrics[simplifier.vertex_groups[*from as usize]].weight
+                + simplifier.quadrics[to_group].weight;
+            if weight > 0.0 {
+                error = error.max((cost / weight).sqrt());
+            }
+            triangle_count -= simplifier.collapse(*from, *to);
+            collapsed = true;
+        }
+        if !collapsed {
+            break;
+        }
+    }
+
+    //only the vertices still in use are kept, in the order they are first used in
+    let mut sources: Vec<u32> = Vec::new();
+    let mut remap: Vec<Option<u32>> = vec![None; mesh.positions.len()];
+    let mut simplified_indices = Vec::with_capacity(triangle_count * 3);
+    for triangle in simplifier.triangles.iter().flatten() {
+        for corner in triangle.iter() {
+            let new_index = *remap[*corner as usize].get_or_insert_with(|| {
+                sources.push(*corner);
+                sources.len() as u32 - 1
+            });
+            simplified_indices.push(new_index);
+        }
+    }
+    let mut simplified = mesh.clone();
+    simplified.remap_vertices(&sources[..]);
+    simplified.indices = simplified_indices;
+    Ok((simplified, error as f32))
+}
+
+//a level of detail of a mesh
+#[derive(Clone, Debug, PartialEq)]
+pub struct MeshLod {
+    pub mesh: MeshData,
+    //bound of the distance to the full detail surface relative to the radius of its
+    //bounding sphere, so it stays valid whatever the scale the mesh is drawn at
+    pub error: f32,
+}
+
+//the full detail mesh followed by a level for each ratio of its triangles, every level
+//simplified from the previous one. Ratios go from the most detailed level to the least
+//one, a level the simplifier cannot reduce any further ends the chain
+pub fn build_lod_chain(mesh: &MeshData, triangle_ratios: &[f32]) -> Result<Vec<MeshLod>, String> {
+    mesh.validate()?;
+    let triangle_count = if mesh.indices.is_empty() {
+        mesh.positions.len() / 3
+    } else {
+        mesh.indices.len() / 3
+    };
+    let radius = mesh.get_bounds().sphere.radius;
+    let mut lods = vec![MeshLod {
+        mesh: mesh.clone(),
+        error: 0.0,
+    }];
+    let mut previous_count = triangle_count;
+    for ratio in triangle_ratios.iter() {
+        if !(*ratio > 0.0 && *ratio <= 1.0) {
+            return Err(format!("lod triangle ratio {} is not in (0, 1]", ratio));
+        }
+        let target = (triangle_count as f32 * ratio).ceil() as usize;
+        let previous = lods.last().unwrap();
+        let (simplified, error) = simplify_mesh(&previous.mesh, target)?;
+        let count = simplified.indices.len() / 3;
+        if count >= previous_count {
+            break;
+        }
+        let error = if radius > 0.0 { error / radius } else { 0.0 };
+        lods.push(MeshLod {
+            //the distance to the previous level adds up to the one to the full detail
+            error: previous.error + error,
+            mesh: simplified,
+        });
+        previous_count = count;
+    }
+    Ok(lods)
+}
+
+//the least detailed level whose error covers no more than the given pixels, from the
+//radius the bounds cover on screen. The sphere is the one of the mesh where it is
+//drawn, the transform of the instance included
+pub fn select_lod(
+    lods: &[MeshLod],
+    sphere: &bounds::BoundingSphere,
+    camera: &camera::Camera,
+    screen_height: f32,
+    max_pixel_error: f32,
+) -> usize {
+    let projected_radius = camera.get_projected_radius(sphere, screen_height);
+    lods.iter()
+        .rposition(|lod| lod.error * projected_radius <= max_pixel_error)
+        .unwrap_or(0)
+}
@@ -0,0 +1,165 @@
+use super::super::super::platform;
+use super::super::api;
+use super::super::upload;
+use super::processing;
+use super::{Mesh, MeshData};
+use cgmath::InnerSpace;
+use std::collections::HashMap;
+
+//80 bytes of header and the triangle count
+const STL_BINARY_HEADER_SIZE: usize = 84;
+//normal, 3 corners and a 16 bit attribute
+const STL_BINARY_TRIANGLE_SIZE: usize = 50;
+
+//binary files can start with "solid" too, the size tells them apart
+pub fn is_binary_stl(content: &[u8]) -> bool {
+    if content.len() < STL_BINARY_HEADER_SIZE {
+        return false;
+    }
+    //computed in 64 bits, the size of the biggest count overflows a 32 bit usize
+    let count = u32::from_le_bytes([content[80], content[81], content[82], content[83]]) as u64;
+    match count.checked_mul(STL_BINARY_TRIANGLE_SIZE as u64) {
+        Some(size) => content.len() as u64 == STL_BINARY_HEADER_SIZE as u64 + size,
+        None => false,
+    }
+}
+
+//every triangle gets its own 3 vertices with the facet normal, the normal is
+//computed from the corners when the file leaves it at zero. Welding the vertices
+//is left to processing
+pub fn parse_stl(content: &[u8]) -> Result<MeshData, String> {
+    let triangles = if is_binary_stl(content) {
+        parse_binary_stl_triangles(content)
+    } else {
+        parse_ascii_stl_triangles(content)?
+    };
+
+    let mut mesh = MeshData::default();
+    for (normal, corners) in triangles.iter() {
+        let point = |i: usize| cgmath::Vector3::from(corners[i]);
+        let mut normal = cgmath::Vector3::from(*normal);
+        if normal.magnitude2() == 0.0 {
+            normal = (point(1) - point(0)).cross(point(2) - point(0));
+        }
+        let normal = if normal.magnitude2() > 0.0 {
+            normal.normalize().into()
+        } else {
+            [0.0, 1.0, 0.0]
+        };
+        mesh.positions.extend_from_slice(&corners[..]);
+        mesh.normals.extend_from_slice(&[normal; 3]);
+    }
+    Ok(mesh)
+}
+
+type StlTriangle = ([f32; 3], [[f32; 3]; 3]);
+
+fn parse_binary_stl_triangles(content: &[u8]) -> Vec<StlTriangle> {
+    let read_vector = |bytes: &[u8]| {
+        let mut vector = [0.0f32; 3];
+        for (value, value_bytes) in vector.iter_mut().zip(bytes.chunks_exact(4)) {
+            *value = f32::from_le_bytes([
+                value_bytes[0],
+                value_bytes[1],
+                value_bytes[2],
+                value_bytes[3],
+            ]);
+        }
+        vector
+    };
+    content[STL_BINARY_HEADER_SIZE..]
+        .chunks_exact(STL_BINARY_TRIANGLE_SIZE)
+        .map(|triangle| {
+            (
+                read_vector(&triangle[0..12]),
+                [
+                    read_vector(&triangle[12..24]),
+                    read_vector(&triangle[24..36]),
+                    read_vector(&triangle[36..48]),
+                ],
+            )
+        })
+        .collect()
+}
+
+fn parse_ascii_stl_triangles(content: &[u8]) -> Result<Vec<StlTriangle>, String> {
+    let text =
+        std::str::from_utf8(content).map_err(|_| String::from("ascii stl is not valid text"))?;
+    let mut lines = text.lines().enumerate();
+    match lines.next() {
+        Some((_, line)) if line.trim_start().starts_with("solid") => {}
+        _ => return Err(String::from("ascii stl does not start with solid")),
+    }
+
+    let parse_vector = |tokens: &[&str], line: usize| -> Result<[f32; 3], String> {
+        if tokens.len() != 3 {
+            return Err(format!("line {}: expected 3 numbers", line + 1));
+        }
+        let mut vector = [0.0; 3];
+        for (value, token) in vector.iter_mut().zip(tokens.iter()) {
+            *value = token
+                .parse()
+                .map_err(|_| format!("line {}: {} is not a number", line + 1, token))?;
+        }
+        Ok(vector)
+    };
+
+    let mut triangles = Vec::new();
+    let mut normal = [0.0; 3];
+    let mut corners: Vec<[f32; 3]> = Vec::with_capacity(3);
+    for (line_index, line) in lines {
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        match &tokens[..] {
+            ["facet", "normal", values @ ..] => {
+                normal = parse_vector(values, line_index)?;
+                corners.clear();
+            }
+            ["vertex", values @ ..] => corners.push(parse_vector(values, line_index)?),
+            ["endfacet"] => {
+                if corners.len() != 3 {
+                    return Err(format!(
+                        "line {}: facet with {} vertices",
+                        line_index + 1,
+                        corners.len()
+                    ));
+                }
+                triangles.push((normal, [corners[0], corners[1], corners[2]]));
+            }
+            //outer loop, endloop, endsolid and the solids following the first one
+            _ => {}
+        }
+    }
+    Ok(triangles)
+}
+
+//loads and processes the mesh, nothing here needs a gpu
+pub async fn import_stl_file(
+    file_name: &str,
+    processing_options: &processing::MeshProcessingOptions,
+) -> Result<MeshData, String> {
+    let content = platform::file_system::load_file_u8(file_name)
+        .await
+        .map_err(|e| format!("could not load {}: {:?}", file_name, e))?;
+    let mut mesh_data = parse_stl(&content[..]).map_err(|e| format!("{}: {}", file_name, e))?;
+    if processing_options.is_enabled() {
+        if let Err(message) = processing::process_mesh(&mut mesh_data, processing_options) {
+            platform::core::to_console(
+                &format!("could not process {}: {}", file_name, message)[..],
+            );
+        }
+    }
+    Ok(mesh_data)
+}
+
+//imports and uploads the mesh, its buffers are added to the given map
+pub async fn load_stl_file(
+    file_name: &str,
+    buffers: &mut HashMap<u32, wgpu::Buffer>,
+    gpu_interfaces: &api::GPUInterfaces,
+    upload_manager: &mut upload::UploadManager,
+    processing_options: &processing::MeshProcessingOptions,
+) -> Result<Mesh, String> {
+    import_stl_file(file_name, processing_options)
+        .await?
+        .upload(buffers, gpu_interfaces, upload_manager)
+}
@@ -0,0 +1,135 @@
+//displacements added to the base vertices, scaled by the weight of the target.
+//Attributes the target does not move are left empty
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MorphTarget {
+    pub positions: Vec<[f32; 3]>,
+    pub normals: Vec<[f32; 3]>,
+    pub tangents: Vec<[f32; 3]>,
+}
+
+//the vertex stream affected by morphing, empty attributes are not present in the mesh
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MorphVertices {
+    pub positions: Vec<[f32; 3]>,
+    pub normals: Vec<[f32; 3]>,
+    pub tangents: Vec<[f32; 4]>,
+}
+
+impl MorphVertices {
+    pub fn len(&self) -> usize {
+        self.positions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+}
+
+//cpu copy of the vertices of a primitive with morph targets, the gpu buffers of
+//the mesh keep the undeformed data
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MorphTargetSet {
+    pub base: MorphVertices,
+    pub targets: Vec<MorphTarget>,
+    //used when the node does not provide its own weights
+    pub default_weights: Vec<f32>,
+}
+
+impl MorphTargetSet {
+    pub fn new(
+        base: MorphVertices,
+        targets: Vec<MorphTarget>,
+        default_weights: Vec<f32>,
+    ) -> Result<Self, String> {
+        let count = base.len();
+        if (!base.normals.is_empty() && base.normals.len() != count)
+            || (!base.tangents.is_empty() && base.tangents.len() != count)
+        {
+            return Err(format!(
+                "morph base attributes do not have {} vertices each",
+                count
+            ));
+        }
+        if default_weights.len() != targets.len() {
+            return Err(format!(
+                "{} default weights for {} morph targets",
+                default_weights.len(),
+                targets.len()
+            ));
+        }
+        for (index, target) in targets.iter().enumerate() {
+            //a target can only move attributes the base has
+            let check = |len: usize, base_len: usize| len == 0 || len == base_len;
+            if !check(target.positions.len(), count)
+                || !check(target.normals.len(), base.normals.len())
+                || !check(target.tangents.len(), base.tangents.len())
+            {
+                return Err(format!(
+                    "morph target {} does not match the {} vertices of the base",
+                    index, count
+                ));
+            }
+        }
+        Ok(Self {
+            base,
+            targets,
+            default_weights,
+        })
+    }
+
+    //the deformed vertex stream, weights missing at the end of the slice count as zero.
+    //Normals and tangents are normalized again after blending
+    pub fn blend(&self, weights: &[f32]) -> MorphVertices {
+        let mut output = MorphVertices::default();
+        self.blend_into(weights, &mut output);
+        output
+    }
+
+    //same as blend but reuses the memory of the output, meant to run every frame
+    pub fn blend_into(&self, weights: &[f32], output: &mut MorphVertices) {
+        output.positions.clear();
+        output.positions.extend_from_slice(&self.base.positions[..]);
+        output.normals.clear();
+        output.normals.extend_from_slice(&self.base.normals[..]);
+        output.tangents.clear();
+        output.tangents.extend_from_slice(&self.base.tangents[..]);
+
+        for (target, weight) in self.targets.iter().zip(weights.iter()) {
+            if *weight == 0.0 {
+                continue;
+            }
+            add_scaled(&mut output.positions[..], &target.positions[..], *weight);
+            add_scaled(&mut output.normals[..], &target.normals[..], *weight);
+            for (tangent, delta) in output.tangents.iter_mut().zip(target.tangents.iter()) {
+                for i in 0..3 {
+                    tangent[i] += delta[i] * weight;
+                }
+            }
+        }
+
+        for normal in output.normals.iter_mut() {
+            normalize(&mut normal[..]);
+        }
+        for tangent in output.tangents.iter_mut() {
+            //w is the handedness and is not morphed
+            normalize(&mut tangent[0..3]);
+        }
+    }
+}
+
+fn add_scaled(values: &mut [[f32; 3]], deltas: &[[f32; 3]], weight: f32) {
+    for (value, delta) in values.iter_mut().zip(deltas.iter()) {
+        for i in 0..3 {
+            value[i] += delta[i] * weight;
+        }
+    }
+}
+
+fn normalize(vector: &mut [f32]) {
+    let length = vector.iter().map(|v| v * v).sum::<f32>().sqrt();
+    if length > 0.0 {
+        for v in vector.iter_mut() {
+            *v /= length;
+        }
+    }
+}
@@ -0,0 +1,188 @@
+use super::bounds;
+use cgmath::SquareMatrix;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum NodeTransform {
+    Matrix(cgmath::Matrix4<f32>),
+    //kept split so it can be animated one component at the time
+    Decomposed {
+        translation: cgmath::Vector3<f32>,
+        rotation: cgmath::Quaternion<f32>,
+        scale: cgmath::Vector3<f32>,
+    },
+}
+
+impl NodeTransform {
+    pub fn identity() -> Self {
+        NodeTransform::Matrix(cgmath::Matrix4::identity())
+    }
+
+    pub fn to_matrix(&self) -> cgmath::Matrix4<f32> {
+        match self {
+            NodeTransform::Matrix(matrix) => *matrix,
+            NodeTransform::Decomposed {
+                translation,
+                rotation,
+                scale,
+            } => {
+                cgmath::Matrix4::from_translation(*translation)
+                    * cgmath::Matrix4::from(*rotation)
+                    * cgmath::Matrix4::from_nonuniform_scale(scale.x, scale.y, scale.z)
+            }
+        }
+    }
+}
+
+pub struct SceneNode {
+    pub name: Option<String>,
+    pub parent: Option<usize>,
+    pub children: Vec<usize>,
+    pub transform: NodeTransform,
+    //only valid after a call to update_world_matrices
+    pub world_matrix: cgmath::Matrix4<f32>,
+    //index of the model rendered by this node, if any
+    pub model: Option<usize>,
+    //index of the skin deforming the model, if any
+    pub skin: Option<usize>,
+    //morph target weights, empty when the model has no targets
+    pub weights: Vec<f32>,
+}
+
+pub struct Scene {
+    pub name: Option<String>,
+    pub roots: Vec<usize>,
+}
+
+//a model drawn at the world transform of a node
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MeshInstance {
+    pub node: usize,
+    pub model: usize,
+}
+
+//nodes live in a flat array and reference each other by index, the same way the
+//gltf file does, so the indices of an imported file match the file ones
+#[derive(Default)]
+pub struct SceneGraph {
+    pub nodes: Vec<SceneNode>,
+    pub scenes: Vec<Scene>,
+    pub default_scene: Option<usize>,
+}
+
+impl SceneGraph {
+    pub fn add_node(
+        &mut self,
+        name: Option<String>,
+        transform: NodeTransform,
+        model: Option<usize>,
+    ) -> usize {
+        self.nodes.push(SceneNode {
+            name,
+            parent: None,
+            children: Vec::new(),
+            transform,
+            world_matrix: cgmath::Matrix4::identity(),
+            model,
+            skin: None,
+            weights: Vec::new(),
+        });
+        self.nodes.len() - 1
+    }
+
+    pub fn set_parent(&mut self, child: usize, parent: usize) -> Result<(), String> {
+        if child >= self.nodes.len() || parent >= self.nodes.len() {
+            return Err(format!(
+                "cannot parent node {} to node {}, out of bounds",
+                child, parent
+            ));
+        }
+        if self.nodes[child].parent.is_some() {
+            return Err(format!("node {} already has a parent", child));
+        }
+        //walking up from the parent, if we meet the child we are about to make a loop
+        let mut ancestor = Some(parent);
+        while let Some(index) = ancestor {
+            if index == child {
+                return Err(format!(
+                    "parenting node {} to node {} would create a cycle",
+                    child, parent
+                ));
+            }
+            ancestor = self.nodes[index].parent;
+        }
+        self.nodes[child].parent = Some(parent);
+        self.nodes[parent].children.push(child);
+        Ok(())
+    }
+
+    pub fn find_node(&self, name: &str) -> Option<usize> {
+        self.nodes
+            .iter()
+            .position(|node| node.name.as_deref() == Some(name))
+    }
+
+    //the scene to show when nobody asks for a specific one
+    pub fn get_active_scene(&self) -> Option<usize> {
+        match self.default_scene {
+            Some(scene) => Some(scene),
+            None if !self.scenes.is_empty() => Some(0),
+            None => None,
+        }
+    }
+
+    pub fn update_world_matrices(&mut self) {
+        //explicit stack instead of recursion, gltf hierarchies can get deep
+        let mut stack: Vec<(usize, cgmath::Matrix4<f32>)> = self
+            .nodes
+            .iter()
+            .enumerate()
+            .filter(|(_, node)| node.parent.is_none())
+            .map(|(index, _)| (index, cgmath::Matrix4::identity()))
+            .collect();
+
+        while let Some((index, parent_matrix)) = stack.pop() {
+            let node = &mut self.nodes[index];
+            node.world_matrix = parent_matrix * node.transform.to_matrix();
+            let world_matrix = node.world_matrix;
+            for child in node.children.iter() {
+                stack.push((*child, world_matrix));
+            }
+        }
+    }
+
+    //every model reachable from the roots of the scene, in depth first order
+    pub fn get_mesh_instances(&self, scene: usize) -> Vec<MeshInstance> {
+        let mut instances = Vec::new();
+        let mut stack: Vec<usize> = match self.scenes.get(scene) {
+            Some(scene) => scene.roots.iter().rev().cloned().collect(),
+            None => return instances,
+        };
+        while let Some(index) = stack.pop() {
+            let node = &self.nodes[index];
+            if let Some(model) = node.model {
+                instances.push(MeshInstance { node: index, model });
+            }
+            stack.extend(node.children.iter().rev());
+        }
+        instances
+    }
+
+    //world space bounds of every instance of the scene, model_bounds is indexed by the
+    //model of the instances. World matrices must be up to date
+    pub fn get_scene_bounds(
+        &self,
+        scene: usize,
+        model_bounds: &[bounds::Bounds],
+    ) -> bounds::Bounds {
+        self.get_mesh_instances(scene)
+            .iter()
+            .filter_map(|instance| {
+                let model = model_bounds.get(instance.model)?;
+                Some(model.transform(&self.nodes[instance.node].world_matrix))
+            })
+            .fold(
+                bounds::Bounds::default(),
+                |scene_bounds, instance_bounds| scene_bounds.union(&instance_bounds),
+            )
+    }
+}
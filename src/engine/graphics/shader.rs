@@ -94,3 +94,252 @@ impl ShaderManager {
     }
 }
 
+
+pub struct ShaderInput {
+    pub location: u32,
+    pub glsl_type: String,
+    pub name: String,
+}
+
+fn strip_glsl_comments(source: &str) -> String {
+    let mut out = String::with_capacity(source.len());
+    let mut chars = source.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '/' && chars.peek() == Some(&'/') {
+            //line comment, skip up to the new line but keep it
+            while let Some(&next) = chars.peek() {
+                if next == '\n' {
+                    break;
+                }
+                chars.next();
+            }
+        } else if c == '/' && chars.peek() == Some(&'*') {
+            //block comment, we replace it with a space so tokens don't merge
+            chars.next();
+            let mut prev = ' ';
+            for next in chars.by_ref() {
+                if prev == '*' && next == '/' {
+                    break;
+                }
+                prev = next;
+            }
+            out.push(' ');
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+//extracts all the "layout(location=N) in type name;" declarations of a glsl source,
+//this is not a full glsl parser, just enough to validate vertex inputs offline
+pub fn get_glsl_vertex_inputs(source: &str) -> Result<Vec<ShaderInput>, String> {
+    //preprocessor lines are not terminated by a semicolon, we drop them so they
+    //don't get merged with the following statement
+    let no_comments_source = strip_glsl_comments(source);
+    let clean_source: Vec<&str> = no_comments_source
+        .lines()
+        .filter(|line| !line.trim_start().starts_with('#'))
+        .collect();
+    let clean_source = clean_source.join("\n");
+    let mut inputs = Vec::new();
+    for statement in clean_source.split(';') {
+        let statement = statement.trim();
+        if !statement.starts_with("layout") {
+            continue;
+        }
+        let open = match statement.find('(') {
+            Some(idx) => idx,
+            None => continue,
+        };
+        let close = match statement.find(')') {
+            Some(idx) => idx,
+            None => continue,
+        };
+        let tokens: Vec<&str> = statement[close + 1..].split_whitespace().collect();
+        //we only care about inputs, qualifiers like flat can sit in between
+        let in_position = match tokens.iter().position(|token| *token == "in") {
+            Some(position) => position,
+            None => continue,
+        };
+        if tokens.len() < in_position + 3 {
+            return Err(format!("malformed shader input declaration \"{}\"", statement));
+        }
+
+        let mut location = None;
+        for qualifier in statement[open + 1..close].split(',') {
+            let mut key_value = qualifier.split('=');
+            let key = key_value.next().unwrap_or("").trim();
+            if key == "location" {
+                let value = key_value.next().unwrap_or("").trim();
+                location = Some(value.parse::<u32>().map_err(|_| {
+                    format!("could not parse input location \"{}\"", value)
+                })?);
+            }
+        }
+        let location = match location {
+            Some(location) => location,
+            None => {
+                return Err(format!(
+                    "shader input declaration without location \"{}\"",
+                    statement
+                ))
+            }
+        };
+
+        inputs.push(ShaderInput {
+            location,
+            glsl_type: String::from(tokens[in_position + 1]),
+            name: String::from(tokens[in_position + 2]),
+        });
+    }
+    Ok(inputs)
+}
+
+//returns the glsl type a vertex format is going to be seen as from the shader
+pub fn get_vertex_format_glsl_type(format: wgpu::VertexFormat) -> &'static str {
+    match format {
+        wgpu::VertexFormat::Uchar2 | wgpu::VertexFormat::Ushort2 | wgpu::VertexFormat::Uint2 => {
+            "uvec2"
+        }
+        wgpu::VertexFormat::Uchar4 | wgpu::VertexFormat::Ushort4 | wgpu::VertexFormat::Uint4 => {
+            "uvec4"
+        }
+        wgpu::VertexFormat::Uint => "uint",
+        wgpu::VertexFormat::Uint3 => "uvec3",
+        wgpu::VertexFormat::Char2 | wgpu::VertexFormat::Short2 | wgpu::VertexFormat::Int2 => {
+            "ivec2"
+        }
+        wgpu::VertexFormat::Char4 | wgpu::VertexFormat::Short4 | wgpu::VertexFormat::Int4 => {
+            "ivec4"
+        }
+        wgpu::VertexFormat::Int => "int",
+        wgpu::VertexFormat::Int3 => "ivec3",
+        wgpu::VertexFormat::Float => "float",
+        wgpu::VertexFormat::Uchar2Norm
+        | wgpu::VertexFormat::Char2Norm
+        | wgpu::VertexFormat::Ushort2Norm
+        | wgpu::VertexFormat::Short2Norm
+        | wgpu::VertexFormat::Half2
+        | wgpu::VertexFormat::Float2 => "vec2",
+        wgpu::VertexFormat::Float3 => "vec3",
+        wgpu::VertexFormat::Uchar4Norm
+        | wgpu::VertexFormat::Char4Norm
+        | wgpu::VertexFormat::Ushort4Norm
+        | wgpu::VertexFormat::Short4Norm
+        | wgpu::VertexFormat::Half4
+        | wgpu::VertexFormat::Float4 => "vec4",
+    }
+}
+
+//spir-v opcodes and decorations needed to reflect a block layout
+const SPIRV_MAGIC: u32 = 0x0723_0203;
+const SPIRV_OP_NAME: u32 = 5;
+const SPIRV_OP_MEMBER_NAME: u32 = 6;
+const SPIRV_OP_TYPE_STRUCT: u32 = 30;
+const SPIRV_OP_DECORATE: u32 = 71;
+const SPIRV_OP_MEMBER_DECORATE: u32 = 72;
+const SPIRV_DECORATION_ARRAY_STRIDE: u32 = 6;
+const SPIRV_DECORATION_MATRIX_STRIDE: u32 = 7;
+const SPIRV_DECORATION_OFFSET: u32 = 35;
+
+#[derive(Default)]
+pub struct ReflectedBlockMember {
+    pub name: String,
+    pub offset: u32,
+    pub array_stride: Option<u32>,
+    pub matrix_stride: Option<u32>,
+}
+
+fn read_spirv_string(words: &[u32]) -> String {
+    let mut bytes = Vec::new();
+    for word in words {
+        for byte in word.to_le_bytes().iter() {
+            if *byte == 0 {
+                return String::from_utf8_lossy(&bytes).into_owned();
+            }
+            bytes.push(*byte);
+        }
+    }
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+//finds the struct type named block_name and returns its members with the offsets
+//the compiler assigned, needs the debug names to be present in the binary
+pub fn reflect_spirv_block(
+    spirv: &[u32],
+    block_name: &str,
+) -> Result<Vec<ReflectedBlockMember>, String> {
+    if spirv.len() < 5 || spirv[0] != SPIRV_MAGIC {
+        return Err(String::from("not a valid spir-v binary"));
+    }
+
+    let mut names = HashMap::new();
+    let mut member_names = HashMap::new();
+    let mut member_decorations = Vec::new();
+    let mut array_strides = HashMap::new();
+    let mut struct_members = HashMap::new();
+
+    let mut idx = 5;
+    while idx < spirv.len() {
+        let word_count = (spirv[idx] >> 16) as usize;
+        let opcode = spirv[idx] & 0xffff;
+        if word_count == 0 || idx + word_count > spirv.len() {
+            return Err(String::from("malformed spir-v instruction"));
+        }
+        let operands = &spirv[idx + 1..idx + word_count];
+        match opcode {
+            SPIRV_OP_NAME if !operands.is_empty() => {
+                names.insert(operands[0], read_spirv_string(&operands[1..]));
+            }
+            SPIRV_OP_MEMBER_NAME if operands.len() >= 2 => {
+                member_names.insert((operands[0], operands[1]), read_spirv_string(&operands[2..]));
+            }
+            SPIRV_OP_TYPE_STRUCT if !operands.is_empty() => {
+                struct_members.insert(operands[0], operands[1..].to_vec());
+            }
+            SPIRV_OP_DECORATE
+                if operands.len() >= 3 && operands[1] == SPIRV_DECORATION_ARRAY_STRIDE =>
+            {
+                array_strides.insert(operands[0], operands[2]);
+            }
+            SPIRV_OP_MEMBER_DECORATE if operands.len() >= 4 => {
+                member_decorations.push((operands[0], operands[1], operands[2], operands[3]));
+            }
+            _ => {}
+        }
+        idx += word_count;
+    }
+
+    let struct_id = match names.iter().find(|(id, name)| {
+        *name == block_name && struct_members.contains_key(*id)
+    }) {
+        Some((id, _)) => *id,
+        None => return Err(format!("could not find block {} in spir-v", block_name)),
+    };
+
+    let member_types = &struct_members[&struct_id];
+    let mut members: Vec<ReflectedBlockMember> = (0..member_types.len())
+        .map(|member| ReflectedBlockMember {
+            name: member_names
+                .get(&(struct_id, member as u32))
+                .cloned()
+                .unwrap_or_default(),
+            array_stride: array_strides.get(&member_types[member]).cloned(),
+            ..Default::default()
+        })
+        .collect();
+
+    for (target, member, decoration, value) in member_decorations {
+        if target != struct_id || member as usize >= members.len() {
+            continue;
+        }
+        match decoration {
+            SPIRV_DECORATION_OFFSET => members[member as usize].offset = value,
+            SPIRV_DECORATION_MATRIX_STRIDE => members[member as usize].matrix_stride = Some(value),
+            _ => {}
+        }
+    }
+
+    Ok(members)
+}
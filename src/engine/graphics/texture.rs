@@ -1,4 +1,10 @@
+use std::collections::HashMap;
 
+use super::super::handle;
+use super::super::platform::file_system;
+use super::api;
+use super::mipmap;
+use super::upload;
 
 pub struct Texture {
     pub texture: wgpu::Texture,
@@ -43,4 +49,349 @@ pub fn create_depth_texture(device: &wgpu::Device, sc_desc: &wgpu::SwapChainDesc
 
     Self { texture, view, sampler }
 }
-}
\ No newline at end of file
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SamplerSettings {
+    pub address_mode_u: wgpu::AddressMode,
+    pub address_mode_v: wgpu::AddressMode,
+    pub mag_filter: wgpu::FilterMode,
+    pub min_filter: wgpu::FilterMode,
+    pub mipmap_filter: wgpu::FilterMode,
+}
+
+impl Default for SamplerSettings {
+    fn default() -> Self {
+        Self {
+            address_mode_u: wgpu::AddressMode::Repeat,
+            address_mode_v: wgpu::AddressMode::Repeat,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Linear,
+        }
+    }
+}
+
+impl SamplerSettings {
+    pub fn create_sampler(&self, device: &wgpu::Device, label: &str) -> wgpu::Sampler {
+        device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some(label),
+            address_mode_u: self.address_mode_u,
+            address_mode_v: self.address_mode_v,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: self.mag_filter,
+            min_filter: self.min_filter,
+            mipmap_filter: self.mipmap_filter,
+            lod_min_clamp: -100.0,
+            lod_max_clamp: 100.0,
+            compare: wgpu::CompareFunction::Undefined,
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ColorSpace {
+    //color data authored by artists, albedo, emissive and so on
+    Srgb,
+    //anything that is not a color, normals, roughness, masks
+    Linear,
+}
+
+pub fn get_texture_format_bytes_per_pixel(format: wgpu::TextureFormat) -> Result<u32, String> {
+    match format {
+        wgpu::TextureFormat::R8Unorm => Ok(1),
+        wgpu::TextureFormat::Rg8Unorm => Ok(2),
+        wgpu::TextureFormat::Rgba8Unorm
+        | wgpu::TextureFormat::Rgba8UnormSrgb
+        | wgpu::TextureFormat::Bgra8Unorm
+        | wgpu::TextureFormat::Bgra8UnormSrgb => Ok(4),
+        wgpu::TextureFormat::R16Float => Ok(2),
+        wgpu::TextureFormat::Rg16Float => Ok(4),
+        wgpu::TextureFormat::Rgba16Float => Ok(8),
+        wgpu::TextureFormat::R32Float => Ok(4),
+        wgpu::TextureFormat::Rg32Float => Ok(8),
+        wgpu::TextureFormat::Rgba32Float => Ok(16),
+        _ => Err(format!("texture format {:?} cannot be uploaded from the cpu", format)),
+    }
+}
+
+//half float bits for the 16 bit float formats, rounded to the nearest even value.
+//Values too large for a half become infinity
+pub fn f32_to_f16(value: f32) -> u16 {
+    let bits = value.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exponent = ((bits >> 23) & 0xff) as i32;
+    let mantissa = bits & 0x7f_ffff;
+    if exponent == 0xff {
+        //infinity stays infinity and nan stays nan
+        return sign | 0x7c00 | if mantissa != 0 { 0x200 } else { 0 };
+    }
+    let exponent = exponent - 127 + 15;
+    if exponent >= 0x1f {
+        return sign | 0x7c00;
+    }
+    let round = |half: u32, remainder: u32, halfway: u32| {
+        if remainder > halfway || (remainder == halfway && half & 1 == 1) {
+            half + 1
+        } else {
+            half
+        }
+    };
+    if exponent <= 0 {
+        //subnormal, the implicit one is shifted in with the mantissa
+        if exponent < -10 {
+            return sign;
+        }
+        let mantissa = mantissa | 0x80_0000;
+        let shift = (14 - exponent) as u32;
+        let half = round(
+            mantissa >> shift,
+            mantissa & ((1 << shift) - 1),
+            1 << (shift - 1),
+        );
+        return sign | half as u16;
+    }
+    //a carry out of the mantissa moves to the exponent, up to infinity
+    let half = round(
+        ((exponent as u32) << 10) | (mantissa >> 13),
+        mantissa & 0x1fff,
+        0x1000,
+    );
+    sign | half as u16
+}
+
+pub fn f16_to_f32(value: u16) -> f32 {
+    let sign = ((value & 0x8000) as u32) << 16;
+    let exponent = ((value >> 10) & 0x1f) as u32;
+    let mantissa = (value & 0x3ff) as u32;
+    match exponent {
+        0 => {
+            let magnitude = mantissa as f32 * (2.0f32).powi(-24);
+            if sign != 0 {
+                -magnitude
+            } else {
+                magnitude
+            }
+        }
+        0x1f => f32::from_bits(sign | 0x7f80_0000 | (mantissa << 13)),
+        _ => f32::from_bits(sign | ((exponent + 127 - 15) << 23) | (mantissa << 13)),
+    }
+}
+
+//tightly packed pixels in the given format
+pub struct TextureData {
+    pub width: u32,
+    pub height: u32,
+    pub format: wgpu::TextureFormat,
+    pub pixels: Vec<u8>,
+}
+
+impl TextureData {
+    pub fn from_rgba8(
+        width: u32,
+        height: u32,
+        pixels: Vec<u8>,
+        color_space: ColorSpace,
+    ) -> Result<Self, String> {
+        if pixels.len() != (width * height * 4) as usize {
+            return Err(format!(
+                "expected {} bytes for a {}x{} rgba8 texture but got {}",
+                width * height * 4,
+                width,
+                height,
+                pixels.len()
+            ));
+        }
+        Ok(Self {
+            width,
+            height,
+            format: match color_space {
+                ColorSpace::Srgb => wgpu::TextureFormat::Rgba8UnormSrgb,
+                ColorSpace::Linear => wgpu::TextureFormat::Rgba8Unorm,
+            },
+            pixels,
+        })
+    }
+
+    pub fn from_color(color: [u8; 4], color_space: ColorSpace) -> Self {
+        Self::from_rgba8(1, 1, color.to_vec(), color_space).unwrap()
+    }
+
+    pub fn bytes_per_pixel(&self) -> u32 {
+        get_texture_format_bytes_per_pixel(self.format).unwrap()
+    }
+}
+
+//decodes any format the image crate knows about, the format is guessed from the content.
+//Radiance hdr files keep their range in a half float texture and are always linear,
+//everything else is converted to rgba8, 16 bit images included
+pub fn decode_image(bytes: &[u8], color_space: ColorSpace) -> Result<TextureData, String> {
+    if let Ok(image::ImageFormat::Hdr) = image::guess_format(bytes) {
+        let decoder = image::codecs::hdr::HdrDecoder::new(std::io::Cursor::new(bytes))
+            .map_err(|e| format!("could not decode hdr image: {}", e))?;
+        let metadata = decoder.metadata();
+        let texels = decoder
+            .read_image_hdr()
+            .map_err(|e| format!("could not decode hdr image: {}", e))?;
+        let mut pixels = Vec::with_capacity(texels.len() * 8);
+        for texel in texels.iter() {
+            for channel in texel.0.iter().chain([1.0f32].iter()) {
+                pixels.extend_from_slice(&f32_to_f16(*channel).to_le_bytes());
+            }
+        }
+        return Ok(TextureData {
+            width: metadata.width,
+            height: metadata.height,
+            format: wgpu::TextureFormat::Rgba16Float,
+            pixels,
+        });
+    }
+
+    let image =
+        image::load_from_memory(bytes).map_err(|e| format!("could not decode image: {}", e))?;
+    let rgba = image.to_rgba8();
+    TextureData::from_rgba8(rgba.width(), rgba.height(), rgba.into_raw(), color_space)
+}
+
+impl Texture {
+    //the texture content still needs to be uploaded, see upload
+    pub fn create_texture(
+        device: &wgpu::Device,
+        width: u32,
+        height: u32,
+        format: wgpu::TextureFormat,
+        mip_level_count: u32,
+        sampler_settings: &SamplerSettings,
+        label: &str,
+    ) -> Self {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some(label),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth: 1,
+            },
+            mip_level_count,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsage::SAMPLED | wgpu::TextureUsage::COPY_DST,
+        });
+        let view = texture.create_default_view();
+        let sampler = sampler_settings.create_sampler(device, &format!("{}-sampler", label)[..]);
+
+        Self {
+            texture,
+            view,
+            sampler,
+        }
+    }
+
+    //one entry per mip level starting from the largest one
+    pub fn upload<'a>(&'a self, levels: &[TextureData], uploads: &mut upload::UploadBatch<'a>) {
+        for (mip_level, data) in levels.iter().enumerate() {
+            uploads.write_texture(
+                &self.texture,
+                mip_level as u32,
+                wgpu::Origin3d::ZERO,
+                wgpu::Extent3d {
+                    width: data.width,
+                    height: data.height,
+                    depth: 1,
+                },
+                data.width * data.bytes_per_pixel(),
+                &data.pixels[..],
+            );
+        }
+    }
+}
+
+//textures are created right away but their content is kept on the cpu until
+//upload_pending is called, so many textures can go up in the same batch
+#[derive(Default)]
+pub struct TextureManager {
+    texture_mapper: HashMap<u64, Texture>,
+    path_mapper: HashMap<(String, ColorSpace), u64>,
+    pending_uploads: Vec<(u64, Vec<TextureData>)>,
+    handle_counter: u64,
+    //used to build the mip chain of every texture created from now on
+    pub mip_filter: mipmap::MipFilter,
+}
+
+impl TextureManager {
+    //the same file loaded twice with the same color space gives back the same handle
+    pub async fn load_texture(
+        &mut self,
+        file_name: &str,
+        color_space: ColorSpace,
+        gpu_interfaces: &api::GPUInterfaces,
+    ) -> Result<handle::ResourceHandle, String> {
+        let key = (String::from(file_name), color_space);
+        if let Some(value) = self.path_mapper.get(&key) {
+            return Ok(handle::ResourceHandle::new(
+                handle::ResourceHandleType::Texture,
+                *value,
+            ));
+        }
+
+        let content = file_system::load_file_u8(file_name)
+            .await
+            .map_err(|e| format!("could not load texture {}: {:?}", file_name, e))?;
+        let data = decode_image(&content[..], color_space)
+            .map_err(|message| format!("{}: {}", file_name, message))?;
+        let texture_handle = self.create_texture_from_data(file_name, data, gpu_interfaces);
+        self.path_mapper.insert(key, texture_handle.get_value());
+        Ok(texture_handle)
+    }
+
+    pub fn create_texture_from_data(
+        &mut self,
+        label: &str,
+        data: TextureData,
+        gpu_interfaces: &api::GPUInterfaces,
+    ) -> handle::ResourceHandle {
+        //formats we cannot filter still get a texture, just without the smaller levels
+        let levels = match mipmap::generate_mip_chain(&data, self.mip_filter) {
+            Ok(levels) => levels,
+            Err(_) => vec![data],
+        };
+        let texture = Texture::create_texture(
+            &gpu_interfaces.device,
+            levels[0].width,
+            levels[0].height,
+            levels[0].format,
+            levels.len() as u32,
+            &SamplerSettings::default(),
+            label,
+        );
+
+        self.handle_counter += 1;
+        self.texture_mapper.insert(self.handle_counter, texture);
+        self.pending_uploads.push((self.handle_counter, levels));
+        handle::ResourceHandle::new(handle::ResourceHandleType::Texture, self.handle_counter)
+    }
+
+    //queues the content of all the textures created since the last call
+    pub fn upload_pending<'a>(&'a mut self, uploads: &mut upload::UploadBatch<'a>) {
+        let pending = std::mem::take(&mut self.pending_uploads);
+        let texture_mapper = &self.texture_mapper;
+        for (value, levels) in pending.iter() {
+            texture_mapper[value].upload(&levels[..], uploads);
+        }
+    }
+
+    pub fn has_pending_uploads(&self) -> bool {
+        !self.pending_uploads.is_empty()
+    }
+
+    pub fn get_texture_from_handle(
+        &self,
+        handle: &handle::ResourceHandle,
+    ) -> Result<&Texture, &'static str> {
+        let value = handle.get_value();
+        match self.texture_mapper.get(&value) {
+            Some(texture) => Ok(texture),
+            None => Err("could not find texture"),
+        }
+    }
+}
@@ -0,0 +1,144 @@
+use super::upload;
+
+//dynamic offsets must be aligned to this value when binding a uniform buffer
+pub const UNIFORM_OFFSET_ALIGNMENT: wgpu::BufferAddress = wgpu::BIND_BUFFER_ALIGNMENT;
+
+//alignment must be a power of two
+pub fn align_up(value: u64, alignment: u64) -> u64 {
+    (value + alignment - 1) & !(alignment - 1)
+}
+
+//pure bookkeeping of the ring, no gpu involved. The buffer is split in one region per
+//frame in flight, each frame only sub-allocates from its own region so we never write
+//data the gpu might still be reading from a previous frame
+pub struct RingAllocator {
+    frame_size: u64,
+    frames_in_flight: u32,
+    alignment: u64,
+    frame_index: u32,
+    cursor: u64,
+}
+
+impl RingAllocator {
+    pub fn new(frame_size: u64, frames_in_flight: u32, alignment: u64) -> Self {
+        assert!(frames_in_flight > 0, "ring allocator needs at least one frame");
+        assert!(alignment.is_power_of_two(), "alignment must be a power of two");
+        Self {
+            frame_size: align_up(frame_size, alignment),
+            frames_in_flight,
+            alignment,
+            //begin_frame moves to the next region, starting at the last one means
+            //the first frame uses region zero
+            frame_index: frames_in_flight - 1,
+            cursor: 0,
+        }
+    }
+
+    pub fn begin_frame(&mut self) {
+        self.frame_index = (self.frame_index + 1) % self.frames_in_flight;
+        self.cursor = 0;
+    }
+
+    //returns the absolute offset in the ring of the allocation, None if the current
+    //frame region is out of space
+    pub fn allocate(&mut self, size: u64) -> Option<u64> {
+        let local_offset = align_up(self.cursor, self.alignment);
+        if local_offset + size > self.frame_size {
+            return None;
+        }
+        self.cursor = local_offset + size;
+        Some(self.frame_offset() + local_offset)
+    }
+
+    pub fn frame_offset(&self) -> u64 {
+        self.frame_index as u64 * self.frame_size
+    }
+
+    pub fn frame_index(&self) -> u32 {
+        self.frame_index
+    }
+
+    pub fn used_bytes(&self) -> u64 {
+        self.cursor
+    }
+
+    pub fn frame_size(&self) -> u64 {
+        self.frame_size
+    }
+
+    pub fn total_size(&self) -> u64 {
+        self.frame_size * self.frames_in_flight as u64
+    }
+}
+
+//one big uniform buffer handing out aligned slices for per object data, the
+//returned offsets are meant to be used as dynamic offsets in set_bind_group.
+//All the data pushed in a frame is queued as a single copy in end_frame
+pub struct UniformRingBuffer {
+    allocator: RingAllocator,
+    buffer: wgpu::Buffer,
+    frame_data: Vec<u8>,
+}
+
+impl UniformRingBuffer {
+    pub fn new(
+        device: &wgpu::Device,
+        frame_size: u64,
+        frames_in_flight: u32,
+        label: &str,
+    ) -> Self {
+        let allocator = RingAllocator::new(frame_size, frames_in_flight, UNIFORM_OFFSET_ALIGNMENT);
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some(label),
+            size: allocator.total_size(),
+            usage: wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
+        });
+
+        Self {
+            allocator,
+            buffer,
+            frame_data: Vec::with_capacity(frame_size as usize),
+        }
+    }
+
+    pub fn begin_frame(&mut self) {
+        self.allocator.begin_frame();
+        self.frame_data.clear();
+    }
+
+    pub fn push<T: bytemuck::Pod>(&mut self, data: &T) -> Result<wgpu::DynamicOffset, &'static str> {
+        self.push_bytes(bytemuck::bytes_of(data))
+    }
+
+    //same as push for data that is already laid out, like a block_layout buffer
+    pub fn push_bytes(&mut self, bytes: &[u8]) -> Result<wgpu::DynamicOffset, &'static str> {
+        let offset = match self.allocator.allocate(bytes.len() as u64) {
+            Some(offset) => offset,
+            None => return Err("uniform ring buffer frame region is full"),
+        };
+
+        //the cpu copy only mirrors the current frame region
+        let local_offset = (offset - self.allocator.frame_offset()) as usize;
+        self.frame_data.resize(local_offset, 0);
+        self.frame_data.extend_from_slice(bytes);
+        Ok(offset as wgpu::DynamicOffset)
+    }
+
+    //queues the upload of everything pushed this frame, the frame regions are
+    //256 bytes aligned so the copy padding never spills in the next region
+    pub fn end_frame<'a>(&'a self, uploads: &mut upload::UploadBatch<'a>) {
+        uploads.write_buffer(
+            &self.buffer,
+            self.allocator.frame_offset(),
+            &self.frame_data[..],
+        );
+    }
+
+    pub fn buffer(&self) -> &wgpu::Buffer {
+        &self.buffer
+    }
+
+    pub fn used_bytes(&self) -> u64 {
+        self.allocator.used_bytes()
+    }
+}
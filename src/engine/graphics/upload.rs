@@ -0,0 +1,427 @@
+use super::uniform;
+use futures::FutureExt;
+use std::future::Future;
+use std::pin::Pin;
+
+//buffer to buffer copies offset and size must be a multiple of 4
+pub const COPY_BUFFER_ALIGNMENT: u64 = 4;
+//rows of a buffer to texture copy must start on a 256 bytes boundary
+pub const COPY_BYTES_PER_ROW_ALIGNMENT: u32 = 256;
+//staging buffers are never smaller than this, so small uploads of different sizes
+//all fit the same buffer
+pub const MIN_STAGING_BUFFER_SIZE: u64 = 64 * 1024;
+pub const DEFAULT_FRAMES_IN_FLIGHT: u32 = 3;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct UploadStats {
+    pub bytes_uploaded: u64,
+    pub buffer_copies: u32,
+    pub texture_copies: u32,
+    pub flushes: u32,
+    //flushes that could not reuse a staging buffer and had to create one
+    pub staging_allocations: u32,
+}
+
+pub fn align_copy_size(size: u64) -> u64 {
+    uniform::align_up(size, COPY_BUFFER_ALIGNMENT)
+}
+
+pub fn padded_bytes_per_row(bytes_per_row: u32) -> u32 {
+    uniform::align_up(bytes_per_row as u64, COPY_BYTES_PER_ROW_ALIGNMENT as u64) as u32
+}
+
+//cpu side of the staging memory, everything queued in a batch is packed in here
+//and then copied in a single staging buffer. The vector is never shrunk so after
+//the first few frames we stop allocating
+#[derive(Default)]
+pub struct StagingArena {
+    data: Vec<u8>,
+}
+
+impl StagingArena {
+    pub fn clear(&mut self) {
+        self.data.clear();
+    }
+
+    //copies the data at the first offset with the requested alignment, the data is
+    //zero padded to a multiple of 4 so it can be used as a copy source right away
+    pub fn push(&mut self, data: &[u8], alignment: u64) -> u64 {
+        let offset = uniform::align_up(self.data.len() as u64, alignment);
+        self.data.resize(offset as usize, 0);
+        self.data.extend_from_slice(data);
+        let padded_size = align_copy_size(self.data.len() as u64);
+        self.data.resize(padded_size as usize, 0);
+        offset
+    }
+
+    //same as push but re-packs the rows of an image so each of them starts on the
+    //alignment the copy to texture requires, returns offset and padded row size
+    pub fn push_rows(&mut self, data: &[u8], bytes_per_row: u32, row_count: u32) -> (u64, u32) {
+        assert!(
+            data.len() >= (bytes_per_row * row_count) as usize,
+            "not enough data for the requested rows"
+        );
+        let padded_row = padded_bytes_per_row(bytes_per_row);
+        let offset = uniform::align_up(self.data.len() as u64, COPY_BYTES_PER_ROW_ALIGNMENT as u64);
+        self.data.resize(offset as usize, 0);
+        for row in data.chunks(bytes_per_row as usize).take(row_count as usize) {
+            self.data.extend_from_slice(row);
+            let padding = (padded_row - bytes_per_row) as usize;
+            self.data.resize(self.data.len() + padding, 0);
+        }
+        (offset, padded_row)
+    }
+
+    pub fn len(&self) -> u64 {
+        self.data.len() as u64
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    pub fn capacity(&self) -> u64 {
+        self.data.capacity() as u64
+    }
+
+    pub fn as_slice(&self) -> &[u8] {
+        &self.data[..]
+    }
+}
+
+//bookkeeping of the staging buffers, no gpu involved. Every frame in flight has its
+//own buffers, a flush takes the smallest free one with enough room and it is free
+//again once its frame comes back around, so the gpu is done copying from it. Buffers
+//a frame did not use the last time around are dropped, a burst of uploads while
+//loading does not keep its memory forever
+pub struct StagingPool<T> {
+    frames: Vec<Vec<StagingBuffer<T>>>,
+    frame_index: usize,
+}
+
+pub struct StagingBuffer<T> {
+    pub size: u64,
+    pub resource: T,
+    is_used: bool,
+}
+
+impl<T> StagingPool<T> {
+    pub fn new(frames_in_flight: u32) -> Self {
+        assert!(
+            frames_in_flight > 0,
+            "staging pool needs at least one frame"
+        );
+        Self {
+            frames: (0..frames_in_flight).map(|_| Vec::new()).collect(),
+            frame_index: 0,
+        }
+    }
+
+    //moves to the next frame and gives back the buffers that are free again
+    pub fn begin_frame(&mut self) -> impl Iterator<Item = &mut T> {
+        self.frame_index = (self.frame_index + 1) % self.frames.len();
+        let buffers = &mut self.frames[self.frame_index];
+        buffers.retain(|buffer| buffer.is_used);
+        buffers.iter_mut().map(|buffer| {
+            buffer.is_used = false;
+            &mut buffer.resource
+        })
+    }
+
+    //the smallest free buffer of the frame with room for size that is_ready accepts,
+    //None means a new one has to be added
+    pub fn acquire<F: FnMut(&mut T) -> bool>(
+        &mut self,
+        size: u64,
+        mut is_ready: F,
+    ) -> Option<&mut T> {
+        let buffers = &mut self.frames[self.frame_index];
+        let mut candidates: Vec<usize> = (0..buffers.len())
+            .filter(|i| !buffers[*i].is_used && buffers[*i].size >= size)
+            .collect();
+        candidates.sort_by_key(|i| buffers[*i].size);
+        let found = candidates
+            .into_iter()
+            .find(|i| is_ready(&mut buffers[*i].resource))?;
+        buffers[found].is_used = true;
+        Some(&mut buffers[found].resource)
+    }
+
+    //the buffer is in use by the current frame straight away
+    pub fn add(&mut self, size: u64, resource: T) -> &mut T {
+        let buffers = &mut self.frames[self.frame_index];
+        buffers.push(StagingBuffer {
+            size,
+            resource,
+            is_used: true,
+        });
+        &mut buffers.last_mut().unwrap().resource
+    }
+
+    pub fn buffer_count(&self) -> usize {
+        self.frames.iter().map(|buffers| buffers.len()).sum()
+    }
+
+    pub fn total_size(&self) -> u64 {
+        self.frames
+            .iter()
+            .flat_map(|buffers| buffers.iter())
+            .map(|buffer| buffer.size)
+            .sum()
+    }
+}
+
+type StagingMapping =
+    Pin<Box<dyn Future<Output = Result<wgpu::BufferWriteMapping, wgpu::BufferAsyncErr>>>>;
+
+//a staging buffer is written through a mapping, when its frame comes back around it
+//asks to be mapped again and can be reused once the mapping is there
+pub struct StagingMemory {
+    buffer: wgpu::Buffer,
+    size: u64,
+    pending_mapping: Option<StagingMapping>,
+    mapping: Option<wgpu::BufferWriteMapping>,
+}
+
+impl StagingMemory {
+    fn request_mapping(&mut self) {
+        if self.mapping.is_none() && self.pending_mapping.is_none() {
+            self.pending_mapping = Some(Box::pin(self.buffer.map_write(0, self.size)));
+        }
+    }
+
+    //never waits, a buffer still in use by the gpu is simply not ready. A failed
+    //mapping is asked again the next time around
+    fn is_mapped(&mut self) -> bool {
+        if let Some(pending_mapping) = self.pending_mapping.as_mut() {
+            match pending_mapping.as_mut().now_or_never() {
+                Some(Ok(mapping)) => {
+                    self.mapping = Some(mapping);
+                    self.pending_mapping = None;
+                }
+                Some(Err(_)) => self.pending_mapping = None,
+                None => {}
+            }
+        }
+        self.mapping.is_some()
+    }
+
+    //dropping the mapping unmaps the buffer so the copies can use it
+    fn write(&mut self, data: &[u8]) {
+        let mut mapping = self.mapping.take().expect("staging buffer is not mapped");
+        mapping.as_slice()[..data.len()].copy_from_slice(data);
+    }
+}
+
+//engine level owner of the staging memory, it keeps track of how much we upload
+//each frame. Uploads are recorded through an UploadBatch which borrows the
+//destinations until it gets flushed in a command encoder
+pub struct UploadManager {
+    arena: StagingArena,
+    staging_pool: StagingPool<StagingMemory>,
+    frame_stats: UploadStats,
+    last_frame_stats: UploadStats,
+}
+
+impl Default for UploadManager {
+    fn default() -> Self {
+        Self::new(DEFAULT_FRAMES_IN_FLIGHT)
+    }
+}
+
+impl UploadManager {
+    pub fn new(frames_in_flight: u32) -> Self {
+        Self {
+            arena: StagingArena::default(),
+            staging_pool: StagingPool::new(frames_in_flight),
+            frame_stats: UploadStats::default(),
+            last_frame_stats: UploadStats::default(),
+        }
+    }
+
+    //closes the stats of the previous frame, the staging buffers of the frame that
+    //comes back around are mapped again
+    pub fn begin_frame(&mut self) {
+        self.last_frame_stats = self.frame_stats;
+        self.frame_stats = UploadStats::default();
+        for memory in self.staging_pool.begin_frame() {
+            memory.request_mapping();
+        }
+    }
+
+    pub fn begin_batch(&mut self) -> UploadBatch<'_> {
+        self.arena.clear();
+        UploadBatch {
+            manager: self,
+            buffer_copies: Vec::new(),
+            texture_copies: Vec::new(),
+        }
+    }
+
+    //stats of the frame currently being recorded
+    pub fn frame_stats(&self) -> UploadStats {
+        self.frame_stats
+    }
+
+    pub fn last_frame_stats(&self) -> UploadStats {
+        self.last_frame_stats
+    }
+
+    pub fn staging_capacity(&self) -> u64 {
+        self.arena.capacity()
+    }
+
+    pub fn staging_buffer_count(&self) -> usize {
+        self.staging_pool.buffer_count()
+    }
+}
+
+struct BufferCopy<'a> {
+    destination: &'a wgpu::Buffer,
+    destination_offset: wgpu::BufferAddress,
+    source_offset: wgpu::BufferAddress,
+    size: wgpu::BufferAddress,
+}
+
+struct TextureCopy<'a> {
+    destination: &'a wgpu::Texture,
+    mip_level: u32,
+    origin: wgpu::Origin3d,
+    size: wgpu::Extent3d,
+    source_offset: wgpu::BufferAddress,
+    bytes_per_row: u32,
+}
+
+pub struct UploadBatch<'a> {
+    manager: &'a mut UploadManager,
+    buffer_copies: Vec<BufferCopy<'a>>,
+    texture_copies: Vec<TextureCopy<'a>>,
+}
+
+impl<'a> UploadBatch<'a> {
+    //the destination must be created with COPY_DST and have room for the data
+    //rounded up to a multiple of 4
+    pub fn write_buffer(
+        &mut self,
+        destination: &'a wgpu::Buffer,
+        destination_offset: wgpu::BufferAddress,
+        data: &[u8],
+    ) {
+        assert!(
+            destination_offset & (COPY_BUFFER_ALIGNMENT - 1) == 0,
+            "buffer copy destination offset must be a multiple of 4"
+        );
+        if data.is_empty() {
+            return;
+        }
+        let source_offset = self.manager.arena.push(data, COPY_BUFFER_ALIGNMENT);
+        self.buffer_copies.push(BufferCopy {
+            destination,
+            destination_offset,
+            source_offset,
+            size: align_copy_size(data.len() as u64),
+        });
+    }
+
+    //data is tightly packed, bytes_per_row is the size of a single row of texels
+    pub fn write_texture(
+        &mut self,
+        destination: &'a wgpu::Texture,
+        mip_level: u32,
+        origin: wgpu::Origin3d,
+        size: wgpu::Extent3d,
+        bytes_per_row: u32,
+        data: &[u8],
+    ) {
+        let row_count = size.height * size.depth;
+        let (source_offset, padded_row) =
+            self.manager.arena.push_rows(data, bytes_per_row, row_count);
+        self.texture_copies.push(TextureCopy {
+            destination,
+            mip_level,
+            origin,
+            size,
+            source_offset,
+            bytes_per_row: padded_row,
+        });
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.buffer_copies.is_empty() && self.texture_copies.is_empty()
+    }
+
+    //uploads the whole batch with a single staging buffer and records all the
+    //copies in the given encoder. The buffer comes from the pool of the frame, a new
+    //one is only created when none of the free ones is mapped and big enough
+    pub fn flush(self, device: &wgpu::Device, encoder: &mut wgpu::CommandEncoder) {
+        if self.is_empty() {
+            return;
+        }
+        let UploadManager {
+            arena,
+            staging_pool,
+            frame_stats,
+            ..
+        } = self.manager;
+        let size = arena.len();
+        //lets the mappings asked for in begin_frame complete
+        device.poll(wgpu::Maintain::Poll);
+        let staging_memory = match staging_pool.acquire(size, |memory| memory.is_mapped()) {
+            Some(memory) => {
+                memory.write(arena.as_slice());
+                memory
+            }
+            None => {
+                let buffer_size = size.next_power_of_two().max(MIN_STAGING_BUFFER_SIZE);
+                let mapped_buffer = device.create_buffer_mapped(&wgpu::BufferDescriptor {
+                    label: Some("staging buffer"),
+                    size: buffer_size,
+                    usage: wgpu::BufferUsage::MAP_WRITE | wgpu::BufferUsage::COPY_SRC,
+                });
+                mapped_buffer.data[..size as usize].copy_from_slice(arena.as_slice());
+                frame_stats.staging_allocations += 1;
+                staging_pool.add(
+                    buffer_size,
+                    StagingMemory {
+                        buffer: mapped_buffer.finish(),
+                        size: buffer_size,
+                        pending_mapping: None,
+                        mapping: None,
+                    },
+                )
+            }
+        };
+        let staging_buffer = &staging_memory.buffer;
+
+        for copy in self.buffer_copies.iter() {
+            encoder.copy_buffer_to_buffer(
+                staging_buffer,
+                copy.source_offset,
+                copy.destination,
+                copy.destination_offset,
+                copy.size,
+            );
+        }
+        for copy in self.texture_copies.iter() {
+            encoder.copy_buffer_to_texture(
+                wgpu::BufferCopyView {
+                    buffer: staging_buffer,
+                    offset: copy.source_offset,
+                    bytes_per_row: copy.bytes_per_row,
+                    rows_per_image: copy.size.height,
+                },
+                wgpu::TextureCopyView {
+                    texture: copy.destination,
+                    mip_level: copy.mip_level,
+                    origin: copy.origin,
+                },
+                copy.size,
+            );
+        }
+
+        frame_stats.bytes_uploaded += size;
+        frame_stats.buffer_copies += self.buffer_copies.len() as u32;
+        frame_stats.texture_copies += self.texture_copies.len() as u32;
+        frame_stats.flushes += 1;
+    }
+}
@@ -15,6 +15,7 @@ pub const HANDLE_TYPE_BIT_COUNT: u64 = 10;
 const HANDLE_TYPE_MASK_BIT_RANGE: u64 = (1 << HANDLE_TYPE_BIT_COUNT) - 1;
 const HANDLE_TYPE_MASK_FLAG: u64 = HANDLE_TYPE_MASK_BIT_RANGE << (64 - HANDLE_TYPE_BIT_COUNT);
 
+#[derive(Clone, Copy)]
 pub struct ResourceHandle {
     data: u64,
 }
@@ -1,7 +1,25 @@
 #[cfg(test)]
 mod tests {
 
+    use super::super::graphics;
+    use super::super::graphics::animation;
+    use super::super::graphics::bind_group;
+    use super::super::graphics::bindings;
+    use super::super::graphics::block_layout;
+    use super::super::graphics::bounds;
+    #[cfg(not(target_arch = "wasm32"))]
+    use super::super::graphics::lint;
+    use super::super::graphics::material;
+    use super::super::graphics::mipmap;
+    use super::super::graphics::model;
+    use super::super::graphics::morph;
+    use super::super::graphics::scene;
+    use super::super::graphics::shader;
+    use super::super::graphics::texture;
+    use super::super::graphics::uniform;
+    use super::super::graphics::upload;
     use super::super::handle;
+    use super::super::platform;
 
     async fn get_adapter_info() {
         let _instance = wgpu::Instance::new();
@@ -45,4 +63,3398 @@ mod tests {
         );
         assert_eq!(test_handle_invalid.get_value(), 3243);
     }
+
+    //asset linting
+    #[test]
+    fn glsl_vertex_inputs_tests() {
+        let source = "
+            #version 450
+            /*
+            layout(location=3) in vec2 a_commented;
+            */
+            layout(location=0) in vec3 a_position;
+            // layout(location=2) in vec4 a_line_commented;
+            layout(location = 1) in vec3 a_normal;
+            layout(location=0) out vec3 v_color;
+            layout(set=0, binding=0)
+            uniform Uniforms {
+                mat4 u_view_proj;
+            };
+        ";
+        let inputs = shader::get_glsl_vertex_inputs(source).unwrap();
+        assert_eq!(inputs.len(), 2);
+        assert_eq!(inputs[0].location, 0);
+        assert_eq!(inputs[0].glsl_type, "vec3");
+        assert_eq!(inputs[0].name, "a_position");
+        assert_eq!(inputs[1].location, 1);
+        assert_eq!(inputs[1].name, "a_normal");
+
+        assert!(shader::get_glsl_vertex_inputs("layout(binding=0) in vec3 a;").is_err());
+    }
+
+    #[test]
+    fn pipeline_parsing_errors_tests() {
+        let pipe = serde_json::json!({
+            "rasterization_state": { "type": "custom", "front_facing": "ccw",
+                "cull_mode": "sideways", "depth_bias": 0, "slope_scale": 0.0, "bias_clamp": 0.0 },
+            "primitive_topology": "quadList",
+            "color_states": [{ "format": "swap_chain_native",
+                "color_blend": "add", "alpha_blend": "replace" }],
+            "depth_state": { "format": "default", "depth_write_enabled": true,
+                "depth_compare": "Bigger" },
+        });
+        let format = wgpu::TextureFormat::Bgra8Unorm;
+        assert!(bindings::get_pipeline_raster_state(&pipe).is_err());
+        assert!(bindings::get_primitive_topology(&pipe).is_err());
+        assert!(bindings::get_pipeline_color_states(&pipe, format).is_err());
+        assert!(bindings::get_depth_stencil_state(&pipe, format).is_err());
+        assert!(bindings::get_compare_function(&serde_json::json!("Greater")).is_ok());
+    }
+
+    #[test]
+    #[cfg(not(target_arch = "wasm32"))]
+    fn lint_repository_resources() {
+        let report = lint::lint_resource_root("resources");
+        for issue in report.issues.iter() {
+            println!("{}: {}", issue.file, issue.message);
+        }
+        assert!(report.files_checked > 0);
+        assert_eq!(report.issues.len(), 0);
+    }
+
+    //bind groups
+    #[test]
+    fn bind_group_slot_resolution_tests() {
+        let layout = serde_json::json!({
+            "bindings": [
+                { "slot": 0, "name": "frame_data", "visibility": ["vertex"],
+                    "type": "uniform", "dynamic": false },
+                { "slot": 1, "name": "albedo", "visibility": ["fragment"],
+                    "type": "sampled_texture" },
+                { "slot": 2, "visibility": ["fragment"], "type": "sampler" },
+            ]
+        });
+        let description = bindings::get_bind_group_layout_description(&layout).unwrap();
+        assert_eq!(description.entries.len(), 3);
+        assert_eq!(description.names["albedo"], 1);
+
+        let uniform = bind_group::BindGroupResourceKind::Buffer { offset: 0, size: 64 };
+        let texture = bind_group::BindGroupResourceKind::TextureView;
+        let sampler = bind_group::BindGroupResourceKind::Sampler;
+
+        let slots = bind_group::resolve_bind_group_slots(
+            &description,
+            &[
+                (bind_group::BindingSlot::Index(2), sampler),
+                (bind_group::BindingSlot::Name("frame_data"), uniform),
+                (bind_group::BindingSlot::Name("albedo"), texture),
+            ],
+        )
+        .unwrap();
+        assert_eq!(slots, vec![2, 0, 1]);
+
+        //wrong resource type
+        assert!(bind_group::resolve_bind_group_slots(
+            &description,
+            &[
+                (bind_group::BindingSlot::Index(0), texture),
+                (bind_group::BindingSlot::Index(1), texture),
+                (bind_group::BindingSlot::Index(2), sampler),
+            ],
+        )
+        .is_err());
+        //unknown name
+        assert!(bind_group::resolve_bind_group_slots(
+            &description,
+            &[(bind_group::BindingSlot::Name("normal_map"), texture)],
+        )
+        .is_err());
+        //missing binding
+        assert!(bind_group::resolve_bind_group_slots(
+            &description,
+            &[
+                (bind_group::BindingSlot::Index(0), uniform),
+                (bind_group::BindingSlot::Index(1), texture),
+            ],
+        )
+        .is_err());
+        //misaligned buffer offset
+        assert!(bind_group::resolve_bind_group_slots(
+            &description,
+            &[
+                (
+                    bind_group::BindingSlot::Index(0),
+                    bind_group::BindGroupResourceKind::Buffer { offset: 64, size: 64 }
+                ),
+                (bind_group::BindingSlot::Index(1), texture),
+                (bind_group::BindingSlot::Index(2), sampler),
+            ],
+        )
+        .is_err());
+    }
+
+    //uniform ring
+    #[test]
+    fn ring_allocator_tests() {
+        //frame size gets rounded up to the alignment
+        let mut ring = uniform::RingAllocator::new(1000, 3, 256);
+        assert_eq!(ring.frame_size(), 1024);
+        assert_eq!(ring.total_size(), 3072);
+
+        ring.begin_frame();
+        assert_eq!(ring.frame_index(), 0);
+        assert_eq!(ring.allocate(64), Some(0));
+        assert_eq!(ring.allocate(64), Some(256));
+        assert_eq!(ring.allocate(300), Some(512));
+        assert_eq!(ring.used_bytes(), 812);
+        //only 1024 - 1024 left after aligning the cursor
+        assert_eq!(ring.allocate(16), None);
+
+        //each frame in flight uses its own region
+        ring.begin_frame();
+        assert_eq!(ring.frame_index(), 1);
+        assert_eq!(ring.allocate(64), Some(1024));
+        ring.begin_frame();
+        assert_eq!(ring.allocate(64), Some(2048));
+        ring.begin_frame();
+        assert_eq!(ring.frame_index(), 0);
+        assert_eq!(ring.allocate(1024), Some(0));
+        assert_eq!(ring.allocate(1), None);
+    }
+
+    //block layouts
+    #[test]
+    fn block_layout_std140_tests() {
+        use block_layout::FieldType;
+        let layout = block_layout::BlockLayoutBuilder::new(block_layout::LayoutRule::Std140)
+            .field("view_proj", FieldType::Mat4)
+            .field("light_dir", FieldType::Vec3)
+            .field("intensity", FieldType::Float)
+            .field("uv_scale", FieldType::Vec2)
+            .array("weights", FieldType::Float, 3)
+            .field("normal_matrix", FieldType::Mat3)
+            .build();
+
+        assert_eq!(layout.offset_of("view_proj"), Some(0));
+        assert_eq!(layout.offset_of("light_dir"), Some(64));
+        //a float can pack right after a vec3
+        assert_eq!(layout.offset_of("intensity"), Some(76));
+        assert_eq!(layout.offset_of("uv_scale"), Some(80));
+        //std140 arrays elements are vec4 aligned
+        let weights = layout.get_field("weights").unwrap();
+        assert_eq!(weights.offset, 96);
+        assert_eq!(weights.array_stride, 16);
+        let normal_matrix = layout.get_field("normal_matrix").unwrap();
+        assert_eq!(normal_matrix.offset, 144);
+        assert_eq!(normal_matrix.matrix_stride, 16);
+        assert_eq!(layout.size, 192);
+    }
+
+    #[test]
+    fn block_layout_std430_tests() {
+        use block_layout::FieldType;
+        let layout = block_layout::BlockLayoutBuilder::new(block_layout::LayoutRule::Std430)
+            .field("count", FieldType::Uint)
+            .array("weights", FieldType::Float, 3)
+            .array("positions", FieldType::Vec3, 2)
+            .field("scale", FieldType::Vec2)
+            .build();
+
+        assert_eq!(layout.offset_of("count"), Some(0));
+        let weights = layout.get_field("weights").unwrap();
+        assert_eq!(weights.offset, 4);
+        assert_eq!(weights.array_stride, 4);
+        //vec3 arrays are still padded to vec4
+        let positions = layout.get_field("positions").unwrap();
+        assert_eq!(positions.offset, 16);
+        assert_eq!(positions.array_stride, 16);
+        assert_eq!(layout.offset_of("scale"), Some(48));
+        assert_eq!(layout.size, 64);
+    }
+
+    #[test]
+    fn block_layout_write_tests() {
+        use block_layout::{BlockValue, FieldType};
+        let layout = block_layout::BlockLayoutBuilder::new(block_layout::LayoutRule::Std140)
+            .field("light_dir", FieldType::Vec3)
+            .field("intensity", FieldType::Float)
+            .array("weights", FieldType::Float, 2)
+            .field("normal_matrix", FieldType::Mat3)
+            .build();
+        let mut data = layout.create_data();
+        layout
+            .set(&mut data, "light_dir", BlockValue::Vec3([1.0, 2.0, 3.0]))
+            .unwrap();
+        layout
+            .set(&mut data, "intensity", BlockValue::Float(4.0))
+            .unwrap();
+        layout
+            .set_element(&mut data, "weights", 1, BlockValue::Float(5.0))
+            .unwrap();
+        layout
+            .set(
+                &mut data,
+                "normal_matrix",
+                BlockValue::Mat3(cgmath::Matrix3::new(
+                    1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0, 9.0,
+                )),
+            )
+            .unwrap();
+
+        let floats: Vec<f32> = data
+            .chunks_exact(4)
+            .map(|c| f32::from_le_bytes([c[0], c[1], c[2], c[3]]))
+            .collect();
+        assert_eq!(&floats[0..4], &[1.0, 2.0, 3.0, 4.0]);
+        assert_eq!(floats[8], 5.0);
+        //matrix columns are padded to vec4
+        assert_eq!(&floats[12..15], &[1.0, 2.0, 3.0]);
+        assert_eq!(&floats[16..19], &[4.0, 5.0, 6.0]);
+        assert_eq!(&floats[20..23], &[7.0, 8.0, 9.0]);
+
+        assert!(layout
+            .set(&mut data, "intensity", BlockValue::Vec2([0.0, 0.0]))
+            .is_err());
+        assert!(layout
+            .set_element(&mut data, "weights", 2, BlockValue::Float(0.0))
+            .is_err());
+    }
+
+    #[test]
+    fn frame_data_matches_shader_block() {
+        use block_layout::FieldType;
+        let layout = graphics::FrameData::get_layout();
+        assert_eq!(
+            layout.size as usize,
+            graphics::FrameData::new().as_bytes().len()
+        );
+
+        let bytes = std::fs::read("resources/shader.vert.spv").unwrap();
+        let spirv: Vec<u32> = bytes
+            .chunks_exact(4)
+            .map(|c| u32::from_le_bytes([c[0], c[1], c[2], c[3]]))
+            .collect();
+        layout.check_against_spirv(&spirv, "Uniforms").unwrap();
+
+        let wrong_layout =
+            block_layout::BlockLayoutBuilder::new(block_layout::LayoutRule::Std140)
+                .field("padding", FieldType::Vec4)
+                .field("u_view_proj", FieldType::Mat4)
+                .build();
+        assert!(wrong_layout.check_against_spirv(&spirv, "Uniforms").is_err());
+        assert!(layout.check_against_spirv(&spirv, "Missing").is_err());
+
+        //the shader the sandbox draws with
+        let spirv = futures::executor::block_on(platform::shader::compile_shader(
+            "resources/scene.vert",
+            &shader::ShaderType::VERTEX,
+        ));
+        layout.check_against_spirv(&spirv, "Uniforms").unwrap();
+    }
+
+    #[test]
+    fn object_data_matches_shader_block() {
+        let layout = graphics::ObjectData::get_layout();
+        let mut object_data = graphics::ObjectData::new(cgmath::Matrix4::from_scale(1.0));
+        assert_eq!(layout.size as usize, object_data.as_bytes().len());
+        object_data.set_world(cgmath::Matrix4::from_translation(cgmath::Vector3::new(
+            1.0, 2.0, 3.0,
+        )));
+        let floats: Vec<f32> = object_data
+            .as_bytes()
+            .chunks_exact(4)
+            .map(|c| f32::from_le_bytes([c[0], c[1], c[2], c[3]]))
+            .collect();
+        assert_eq!(&floats[12..16], &[1.0, 2.0, 3.0, 1.0]);
+
+        let spirv = futures::executor::block_on(platform::shader::compile_shader(
+            "resources/scene.vert",
+            &shader::ShaderType::VERTEX,
+        ));
+        layout
+            .check_against_spirv(&spirv, "ObjectUniforms")
+            .unwrap();
+    }
+
+    //staging uploads
+    #[test]
+    fn staging_arena_tests() {
+        let mut arena = upload::StagingArena::default();
+        assert_eq!(arena.push(&[1, 2, 3], upload::COPY_BUFFER_ALIGNMENT), 0);
+        //data gets padded to a valid copy size
+        assert_eq!(arena.len(), 4);
+        assert_eq!(arena.push(&[4; 8], upload::COPY_BUFFER_ALIGNMENT), 4);
+        assert_eq!(arena.len(), 12);
+        assert_eq!(arena.push(&[5; 4], 256), 256);
+        assert_eq!(&arena.as_slice()[..5], &[1, 2, 3, 0, 4]);
+
+        //texture rows are re-packed on 256 bytes boundaries
+        arena.clear();
+        assert!(arena.is_empty());
+        let rows: Vec<u8> = (0..12).collect();
+        let (offset, padded_row) = arena.push_rows(&rows[..], 4, 3);
+        assert_eq!(offset, 0);
+        assert_eq!(padded_row, 256);
+        assert_eq!(arena.len(), 768);
+        assert_eq!(&arena.as_slice()[256..260], &[4, 5, 6, 7]);
+        assert_eq!(&arena.as_slice()[512..516], &[8, 9, 10, 11]);
+        assert_eq!(arena.as_slice()[260], 0);
+
+        //the memory is reused between batches
+        let capacity = arena.capacity();
+        arena.clear();
+        arena.push(&[0; 64], upload::COPY_BUFFER_ALIGNMENT);
+        assert_eq!(arena.capacity(), capacity);
+
+        assert_eq!(upload::padded_bytes_per_row(256), 256);
+        assert_eq!(upload::padded_bytes_per_row(257), 512);
+        assert_eq!(upload::align_copy_size(13), 16);
+    }
+
+    #[test]
+    fn upload_manager_stats_tests() {
+        let mut manager = upload::UploadManager::default();
+        assert_eq!(manager.frame_stats(), upload::UploadStats::default());
+        //an empty batch does not count as an upload
+        manager.begin_batch();
+        assert_eq!(manager.frame_stats().flushes, 0);
+        manager.begin_frame();
+        assert_eq!(manager.last_frame_stats(), upload::UploadStats::default());
+    }
+
+    #[test]
+    fn staging_pool_tests() {
+        let mut pool: upload::StagingPool<u32> = upload::StagingPool::new(2);
+        //nothing to reuse at first, and a buffer is not handed out twice in a frame
+        assert!(pool.acquire(16, |_| true).is_none());
+        pool.add(64, 0);
+        assert!(pool.acquire(16, |_| true).is_none());
+        pool.add(256, 1);
+        //the next frame has its own buffers
+        assert_eq!(pool.begin_frame().count(), 0);
+        assert!(pool.acquire(16, |_| true).is_none());
+        pool.add(64, 2);
+
+        //back to the first frame both buffers are free again
+        let free: Vec<u32> = pool.begin_frame().map(|buffer| *buffer).collect();
+        assert_eq!(free, vec![0, 1]);
+        //the smallest one that fits is taken, those that are not ready are skipped
+        assert_eq!(pool.acquire(100, |_| true).copied(), Some(1));
+        assert!(pool.acquire(32, |buffer| *buffer != 0).is_none());
+        assert_eq!(pool.buffer_count(), 3);
+
+        //buffers a frame did not use are dropped the next time around
+        assert_eq!(pool.begin_frame().count(), 1);
+        let free: Vec<u32> = pool.begin_frame().map(|buffer| *buffer).collect();
+        assert_eq!(free, vec![1]);
+        assert_eq!(pool.buffer_count(), 2);
+        assert_eq!(pool.begin_frame().count(), 0);
+        assert_eq!(pool.buffer_count(), 1);
+        assert_eq!(pool.total_size(), 256);
+    }
+
+    //writes and flushes need a device, without a gpu there is nothing to check
+    #[test]
+    fn upload_batch_flush_tests() {
+        let device = futures::executor::block_on(async {
+            let instance = wgpu::Instance::new();
+            let adapter = instance
+                .request_adapter(
+                    &wgpu::RequestAdapterOptions {
+                        power_preference: wgpu::PowerPreference::Default,
+                        compatible_surface: None,
+                    },
+                    wgpu::BackendBit::PRIMARY,
+                )
+                .await?;
+            adapter
+                .request_device(
+                    &wgpu::DeviceDescriptor {
+                        extensions: wgpu::Extensions {
+                            anisotropic_filtering: false,
+                        },
+                        limits: wgpu::Limits::default(),
+                    },
+                    None,
+                )
+                .await
+                .ok()
+        });
+        let (device, queue) = match device {
+            Some(device) => device,
+            None => return,
+        };
+        let destination = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("upload destination"),
+            size: 256,
+            usage: wgpu::BufferUsage::COPY_DST,
+        });
+
+        let mut manager = upload::UploadManager::new(2);
+        for _ in 0..4 {
+            manager.begin_frame();
+            let mut encoder =
+                device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+            let mut batch = manager.begin_batch();
+            batch.write_buffer(&destination, 0, &[1, 2, 3]);
+            batch.write_buffer(&destination, 128, &[4; 64]);
+            batch.flush(&device, &mut encoder);
+            queue.submit(vec![encoder.finish()]);
+            //3 bytes padded to 4 plus 64
+            let stats = manager.frame_stats();
+            assert_eq!(stats.bytes_uploaded, 68);
+            assert_eq!(stats.buffer_copies, 2);
+            assert_eq!(stats.flushes, 1);
+            //with the gpu idle the buffer of the frame is mapped again in time
+            device.poll(wgpu::Maintain::Wait);
+        }
+        manager.begin_frame();
+        let stats = manager.last_frame_stats();
+        assert_eq!((stats.bytes_uploaded, stats.flushes), (68, 1));
+        assert_eq!(stats.staging_allocations, 0);
+        assert_eq!(manager.staging_buffer_count(), 2);
+        assert_eq!(manager.frame_stats(), upload::UploadStats::default());
+    }
+
+    //gltf buffer sources
+    #[test]
+    fn gltf_uri_decoding_tests() {
+        assert_eq!(
+            model::percent_decode("my%20model%2Ebin").unwrap(),
+            b"my model.bin".to_vec()
+        );
+        assert!(model::percent_decode("broken%2").is_err());
+        assert!(model::percent_decode("broken%zz").is_err());
+
+        assert_eq!(
+            model::decode_data_uri("data:application/octet-stream;base64,AAECAw==").unwrap(),
+            vec![0, 1, 2, 3]
+        );
+        assert_eq!(
+            model::decode_data_uri("data:,hello%21").unwrap(),
+            b"hello!".to_vec()
+        );
+        assert!(model::decode_data_uri("data:application/octet-stream;base64").is_err());
+        assert!(model::decode_data_uri("Suzanne.bin").is_err());
+
+        assert_eq!(
+            model::resolve_gltf_uri("resources/model.gltf", "textures/a%20b.png").unwrap(),
+            "resources/textures/a b.png"
+        );
+        assert_eq!(
+            model::resolve_gltf_uri("model.gltf", "model.bin").unwrap(),
+            "model.bin"
+        );
+    }
+
+    fn load_suzanne_with_buffer(buffer_json: serde_json::Value) -> serde_json::Value {
+        let source =
+            std::fs::read_to_string("resources/examples/gltf-model/Suzanne.gltf").unwrap();
+        let mut json: serde_json::Value = serde_json::from_str(&source[..]).unwrap();
+        json["buffers"] = serde_json::json!([buffer_json]);
+        json
+    }
+
+    fn load_gltf_buffers_from_slice(content: &[u8]) -> Vec<u8> {
+        let mut gltf = gltf::Gltf::from_slice(content).unwrap();
+        let mut buffers = futures::executor::block_on(model::load_gltf_buffers(
+            &mut gltf,
+            "resources/examples/gltf-model/Suzanne.gltf",
+        ))
+        .unwrap();
+        assert_eq!(buffers.len(), 1);
+        buffers.remove(&0).unwrap()
+    }
+
+    #[test]
+    fn gltf_buffer_sources_tests() {
+        let bin = std::fs::read("resources/examples/gltf-model/Suzanne.bin").unwrap();
+
+        //percent encoded relative path
+        let json = load_suzanne_with_buffer(
+            serde_json::json!({"byteLength": bin.len(), "uri": "Suzanne%2Ebin"}),
+        );
+        let content = serde_json::to_vec(&json).unwrap();
+        assert_eq!(load_gltf_buffers_from_slice(&content[..]), bin);
+
+        //embedded base64 data uri
+        let uri = format!(
+            "data:application/octet-stream;base64,{}",
+            base64::encode(&bin[..])
+        );
+        let json =
+            load_suzanne_with_buffer(serde_json::json!({"byteLength": bin.len(), "uri": uri}));
+        let content = serde_json::to_vec(&json).unwrap();
+        assert_eq!(load_gltf_buffers_from_slice(&content[..]), bin);
+
+        //glb container, both chunks are padded to 4 bytes
+        let json = load_suzanne_with_buffer(serde_json::json!({"byteLength": bin.len()}));
+        let mut json_chunk = serde_json::to_vec(&json).unwrap();
+        while json_chunk.len() % 4 != 0 {
+            json_chunk.push(b' ');
+        }
+        let mut bin_chunk = bin.clone();
+        while bin_chunk.len() % 4 != 0 {
+            bin_chunk.push(0);
+        }
+        let total_length = 12 + 8 + json_chunk.len() + 8 + bin_chunk.len();
+        let mut glb = Vec::new();
+        glb.extend_from_slice(b"glTF");
+        glb.extend_from_slice(&2u32.to_le_bytes());
+        glb.extend_from_slice(&(total_length as u32).to_le_bytes());
+        glb.extend_from_slice(&(json_chunk.len() as u32).to_le_bytes());
+        glb.extend_from_slice(b"JSON");
+        glb.extend_from_slice(&json_chunk[..]);
+        glb.extend_from_slice(&(bin_chunk.len() as u32).to_le_bytes());
+        glb.extend_from_slice(b"BIN\0");
+        glb.extend_from_slice(&bin_chunk[..]);
+        assert_eq!(&load_gltf_buffers_from_slice(&glb[..])[..bin.len()], &bin[..]);
+    }
+
+    //scene graph
+    #[test]
+    fn scene_graph_hierarchy_tests() {
+        use cgmath::SquareMatrix;
+        let mut scene_graph = scene::SceneGraph::default();
+        let root = scene_graph.add_node(
+            Some(String::from("root")),
+            scene::NodeTransform::Decomposed {
+                translation: cgmath::Vector3::new(1.0, 0.0, 0.0),
+                rotation: cgmath::Quaternion::from(cgmath::Euler::new(
+                    cgmath::Deg(0.0),
+                    cgmath::Deg(90.0),
+                    cgmath::Deg(0.0),
+                )),
+                scale: cgmath::Vector3::new(2.0, 2.0, 2.0),
+            },
+            None,
+        );
+        let child = scene_graph.add_node(
+            Some(String::from("child")),
+            scene::NodeTransform::Matrix(cgmath::Matrix4::from_translation(cgmath::Vector3::new(
+                1.0, 0.0, 0.0,
+            ))),
+            Some(3),
+        );
+        let other_root = scene_graph.add_node(None, scene::NodeTransform::identity(), Some(1));
+        scene_graph.set_parent(child, root).unwrap();
+        assert!(scene_graph.set_parent(child, other_root).is_err());
+        assert!(scene_graph.set_parent(root, child).is_err());
+        assert!(scene_graph.set_parent(root, 10).is_err());
+
+        scene_graph.scenes.push(scene::Scene {
+            name: None,
+            roots: vec![root, other_root],
+        });
+        assert_eq!(scene_graph.get_active_scene(), Some(0));
+        assert_eq!(scene_graph.find_node("child"), Some(child));
+
+        scene_graph.update_world_matrices();
+        //child is one unit along x, scaled by two and rotated around y, then moved by the root
+        let position =
+            scene_graph.nodes[child].world_matrix * cgmath::Vector4::new(0.0, 0.0, 0.0, 1.0);
+        assert!((position.x - 1.0).abs() < 1e-5);
+        assert!((position.z + 2.0).abs() < 1e-5);
+        assert_eq!(
+            scene_graph.nodes[other_root].world_matrix,
+            cgmath::Matrix4::identity()
+        );
+
+        assert_eq!(
+            scene_graph.get_mesh_instances(0),
+            vec![
+                scene::MeshInstance {
+                    node: child,
+                    model: 3
+                },
+                scene::MeshInstance {
+                    node: other_root,
+                    model: 1
+                },
+            ]
+        );
+        assert!(scene_graph.get_mesh_instances(1).is_empty());
+    }
+
+    #[test]
+    fn gltf_scene_graph_import_tests() {
+        let content = std::fs::read("resources/cube/cube.gltf").unwrap();
+        let gltf = gltf::Gltf::from_slice(&content[..]).unwrap();
+        let scene_graph = model::load_gltf_scene_graph(&gltf).unwrap();
+
+        assert_eq!(scene_graph.nodes.len(), 2);
+        assert_eq!(scene_graph.default_scene, Some(0));
+        assert_eq!(scene_graph.scenes[0].roots, vec![0]);
+        assert_eq!(scene_graph.nodes[1].parent, Some(0));
+        assert_eq!(
+            scene_graph.get_mesh_instances(0),
+            vec![scene::MeshInstance { node: 1, model: 0 }]
+        );
+        //the root turns the z up cube into y up, the child inherits it
+        let up = scene_graph.nodes[1].world_matrix * cgmath::Vector4::new(0.0, 0.0, 1.0, 0.0);
+        assert!((up.y - 1.0).abs() < 1e-5);
+        assert_eq!(
+            scene_graph.nodes[1].world_matrix,
+            scene_graph.nodes[0].transform.to_matrix()
+        );
+
+        //a node listed as child of two parents is not a valid gltf hierarchy
+        let mut json: serde_json::Value = serde_json::from_slice(&content[..]).unwrap();
+        json["nodes"] = serde_json::json!([{"children": [2]}, {"children": [2]}, {"mesh": 0}]);
+        let content = serde_json::to_vec(&json).unwrap();
+        let gltf = gltf::Gltf::from_slice(&content[..]).unwrap();
+        assert!(model::load_gltf_scene_graph(&gltf).is_err());
+    }
+
+    //gltf vertex attributes
+    #[test]
+    fn gltf_attribute_format_tests() {
+        use gltf::accessor::{DataType, Dimensions};
+        use model::MeshBufferSemantic;
+
+        let check = |semantic, data_type, dimensions, normalized| {
+            model::get_gltf_attribute_format(&semantic, data_type, dimensions, normalized)
+        };
+        assert_eq!(
+            check(
+                gltf::Semantic::Positions,
+                DataType::F32,
+                Dimensions::Vec3,
+                false
+            )
+            .unwrap(),
+            (MeshBufferSemantic::Positions, wgpu::VertexFormat::Float3)
+        );
+        assert_eq!(
+            check(
+                gltf::Semantic::Tangents,
+                DataType::F32,
+                Dimensions::Vec4,
+                false
+            )
+            .unwrap(),
+            (MeshBufferSemantic::Tangents, wgpu::VertexFormat::Float4)
+        );
+        assert_eq!(
+            check(
+                gltf::Semantic::TexCoords(1),
+                DataType::U16,
+                Dimensions::Vec2,
+                true
+            )
+            .unwrap(),
+            (
+                MeshBufferSemantic::TexCoords(1),
+                wgpu::VertexFormat::Ushort2Norm
+            )
+        );
+        assert_eq!(
+            check(
+                gltf::Semantic::Colors(0),
+                DataType::U8,
+                Dimensions::Vec4,
+                true
+            )
+            .unwrap(),
+            (
+                MeshBufferSemantic::Colors(0),
+                wgpu::VertexFormat::Uchar4Norm
+            )
+        );
+        assert_eq!(
+            check(
+                gltf::Semantic::Colors(0),
+                DataType::F32,
+                Dimensions::Vec3,
+                false
+            )
+            .unwrap(),
+            (MeshBufferSemantic::Colors(0), wgpu::VertexFormat::Float3)
+        );
+        assert_eq!(
+            check(
+                gltf::Semantic::Joints(0),
+                DataType::U16,
+                Dimensions::Vec4,
+                false
+            )
+            .unwrap(),
+            (MeshBufferSemantic::Joints(0), wgpu::VertexFormat::Ushort4)
+        );
+        assert_eq!(
+            check(
+                gltf::Semantic::Weights(0),
+                DataType::U8,
+                Dimensions::Vec4,
+                true
+            )
+            .unwrap(),
+            (
+                MeshBufferSemantic::Weights(0),
+                wgpu::VertexFormat::Uchar4Norm
+            )
+        );
+
+        //layouts the spec does not allow
+        assert!(check(
+            gltf::Semantic::Normals,
+            DataType::U8,
+            Dimensions::Vec3,
+            true
+        )
+        .is_err());
+        assert!(check(
+            gltf::Semantic::Joints(0),
+            DataType::U8,
+            Dimensions::Vec4,
+            true
+        )
+        .is_err());
+        assert!(check(
+            gltf::Semantic::TexCoords(0),
+            DataType::U32,
+            Dimensions::Vec2,
+            false
+        )
+        .is_err());
+        assert!(check(
+            gltf::Semantic::Normals,
+            DataType::I16,
+            Dimensions::Vec3,
+            false
+        )
+        .is_err());
+
+        //allowed by the spec but with no matching wgpu format, converted to floats
+        assert_eq!(
+            check(
+                gltf::Semantic::Colors(0),
+                DataType::U8,
+                Dimensions::Vec3,
+                true
+            )
+            .unwrap(),
+            (MeshBufferSemantic::Colors(0), wgpu::VertexFormat::Float3)
+        );
+        //KHR_mesh_quantization layouts, integers that are not normalized become floats
+        assert_eq!(
+            check(
+                gltf::Semantic::TexCoords(0),
+                DataType::U8,
+                Dimensions::Vec2,
+                false
+            )
+            .unwrap(),
+            (MeshBufferSemantic::TexCoords(0), wgpu::VertexFormat::Float2)
+        );
+        assert_eq!(
+            check(
+                gltf::Semantic::Positions,
+                DataType::I16,
+                Dimensions::Vec3,
+                false
+            )
+            .unwrap(),
+            (MeshBufferSemantic::Positions, wgpu::VertexFormat::Float3)
+        );
+        assert_eq!(
+            check(
+                gltf::Semantic::Normals,
+                DataType::I8,
+                Dimensions::Vec3,
+                true
+            )
+            .unwrap(),
+            (MeshBufferSemantic::Normals, wgpu::VertexFormat::Float3)
+        );
+        assert_eq!(
+            check(
+                gltf::Semantic::Tangents,
+                DataType::I16,
+                Dimensions::Vec4,
+                true
+            )
+            .unwrap(),
+            (MeshBufferSemantic::Tangents, wgpu::VertexFormat::Short4Norm)
+        );
+        assert_eq!(
+            check(
+                gltf::Semantic::TexCoords(1),
+                DataType::I16,
+                Dimensions::Vec2,
+                true
+            )
+            .unwrap(),
+            (
+                MeshBufferSemantic::TexCoords(1),
+                wgpu::VertexFormat::Short2Norm
+            )
+        );
+    }
+
+    #[test]
+    fn mesh_buffer_semantic_lookup_tests() {
+        let mut mesh = model::Mesh::default();
+        mesh.buffers.push(model::MeshBufferMapper {
+            semantic: model::MeshBufferSemantic::TexCoords(1),
+            format: wgpu::VertexFormat::Float2,
+            offset: 0,
+            length: 64,
+            buffer_idx: 0,
+        });
+        assert!(mesh
+            .get_buffer_from_semantic(model::MeshBufferSemantic::TexCoords(1))
+            .is_some());
+        assert!(mesh
+            .get_buffer_from_semantic(model::MeshBufferSemantic::TexCoords(0))
+            .is_none());
+        assert!(mesh
+            .get_buffer_from_semantic(model::MeshBufferSemantic::Positions)
+            .is_none());
+    }
+
+    //materials
+    #[test]
+    fn gltf_material_import_tests() {
+        let content = std::fs::read("resources/cube/cube.gltf").unwrap();
+        let gltf = gltf::Gltf::from_slice(&content[..]).unwrap();
+        let red = model::load_gltf_material(&gltf.materials().next().unwrap());
+        assert_eq!(red.name.as_deref(), Some("Red"));
+        assert_eq!(red.base_color_factor, [0.8, 0.0, 0.0, 1.0]);
+        assert_eq!(red.metallic_factor, 0.0);
+        //not in the file, spec default
+        assert_eq!(red.roughness_factor, 1.0);
+        assert_eq!(red.alpha_mode, material::AlphaMode::Opaque);
+        assert!(!red.double_sided);
+        assert!(red.base_color_texture.is_none());
+
+        let content = std::fs::read("resources/examples/gltf-model/Suzanne.gltf").unwrap();
+        let mut json: serde_json::Value = serde_json::from_slice(&content[..]).unwrap();
+        json["materials"][0]["normalTexture"] = serde_json::json!({"index": 1, "scale": 0.5});
+        json["materials"][0]["alphaMode"] = serde_json::json!("MASK");
+        json["materials"][0]["alphaCutoff"] = serde_json::json!(0.25);
+        json["materials"][0]["doubleSided"] = serde_json::json!(true);
+        json["samplers"] = serde_json::json!([{
+            "magFilter": 9728,
+            "minFilter": 9985,
+            "wrapS": 33071,
+            "wrapT": 33648
+        }]);
+        let content = serde_json::to_vec(&json).unwrap();
+        let gltf = gltf::Gltf::from_slice(&content[..]).unwrap();
+        let suzanne = model::load_gltf_material(&gltf.materials().next().unwrap());
+        assert_eq!(
+            suzanne.base_color_texture,
+            Some(material::MaterialTexture {
+                texture: 0,
+                tex_coord: 0
+            })
+        );
+        assert_eq!(suzanne.metallic_roughness_texture.unwrap().texture, 1);
+        assert_eq!(suzanne.normal_texture.unwrap().texture, 1);
+        assert_eq!(suzanne.normal_scale, 0.5);
+        assert_eq!(suzanne.alpha_mode, material::AlphaMode::Mask);
+        assert_eq!(suzanne.alpha_cutoff, 0.25);
+        assert!(suzanne.double_sided);
+        assert!(suzanne.is_srgb_texture(0));
+        assert!(!suzanne.is_srgb_texture(1));
+
+        let sampler = model::get_gltf_sampler_settings(&gltf.textures().next().unwrap().sampler());
+        assert_eq!(
+            sampler,
+            texture::SamplerSettings {
+                address_mode_u: wgpu::AddressMode::ClampToEdge,
+                address_mode_v: wgpu::AddressMode::MirrorRepeat,
+                mag_filter: wgpu::FilterMode::Nearest,
+                min_filter: wgpu::FilterMode::Linear,
+                mipmap_filter: wgpu::FilterMode::Nearest,
+            }
+        );
+        let default_material = material::Material::default();
+        assert_eq!(default_material.base_color_factor, [1.0; 4]);
+        assert_eq!(default_material.alpha_cutoff, 0.5);
+    }
+
+    #[test]
+    fn decode_image_tests() {
+        let mut image = image::RgbImage::new(3, 2);
+        image.put_pixel(2, 1, image::Rgb([10, 20, 30]));
+        let mut encoded = Vec::new();
+        image::DynamicImage::ImageRgb8(image)
+            .write_to(&mut encoded, image::ImageOutputFormat::Png)
+            .unwrap();
+
+        let data = texture::decode_image(&encoded[..], texture::ColorSpace::Srgb).unwrap();
+        assert_eq!((data.width, data.height), (3, 2));
+        assert_eq!(data.format, wgpu::TextureFormat::Rgba8UnormSrgb);
+        assert_eq!(data.pixels.len(), 3 * 2 * 4);
+        //rgb images get an opaque alpha channel
+        assert_eq!(&data.pixels[20..24], &[10, 20, 30, 255]);
+        let data = texture::decode_image(&encoded[..], texture::ColorSpace::Linear).unwrap();
+        assert_eq!(data.format, wgpu::TextureFormat::Rgba8Unorm);
+        assert!(texture::decode_image(&[0, 1, 2, 3], texture::ColorSpace::Srgb).is_err());
+    }
+
+    #[test]
+    fn decode_hdr_image_tests() {
+        let texels = vec![
+            image::Rgb([0.0f32, 0.5, 1.0]),
+            image::Rgb([2.0f32, 4.0, 8.0]),
+        ];
+        let mut encoded = Vec::new();
+        image::codecs::hdr::HdrEncoder::new(&mut encoded)
+            .encode(&texels[..], 2, 1)
+            .unwrap();
+
+        //hdr images are always float, whatever the color space asked for
+        let data = texture::decode_image(&encoded[..], texture::ColorSpace::Srgb).unwrap();
+        assert_eq!((data.width, data.height), (2, 1));
+        assert_eq!(data.format, wgpu::TextureFormat::Rgba16Float);
+        assert_eq!(data.bytes_per_pixel(), 8);
+        let channels: Vec<f32> = data
+            .pixels
+            .chunks(2)
+            .map(|bytes| texture::f16_to_f32(u16::from_le_bytes([bytes[0], bytes[1]])))
+            .collect();
+        assert_eq!(channels.len(), 8);
+        //values above one survive
+        assert_eq!(&channels[4..], &[2.0, 4.0, 8.0, 1.0]);
+        assert_eq!(channels[3], 1.0);
+
+        //half floats round to the nearest even value and overflow to infinity
+        assert_eq!(texture::f32_to_f16(1.0), 0x3c00);
+        assert_eq!(texture::f32_to_f16(-2.0), 0xc000);
+        assert_eq!(texture::f32_to_f16(65504.0), 0x7bff);
+        assert_eq!(texture::f32_to_f16(65520.0), 0x7c00);
+        assert_eq!(texture::f32_to_f16(1.0e10), 0x7c00);
+        assert_eq!(texture::f32_to_f16(f32::NEG_INFINITY), 0xfc00);
+        assert!(texture::f16_to_f32(texture::f32_to_f16(f32::NAN)).is_nan());
+        assert_eq!(texture::f32_to_f16(1.0 + 1.0 / 2048.0), 0x3c00);
+        assert_eq!(texture::f32_to_f16(1.0 + 3.0 / 2048.0), 0x3c02);
+        //subnormals
+        assert_eq!(texture::f32_to_f16((2.0f32).powi(-24)), 0x0001);
+        assert_eq!(texture::f32_to_f16((2.0f32).powi(-26)), 0x0000);
+        assert_eq!(texture::f32_to_f16(-(2.0f32).powi(-15)), 0x8200);
+        assert_eq!(texture::f16_to_f32(0x0001), (2.0f32).powi(-24));
+        assert_eq!(texture::f16_to_f32(0x8200), -(2.0f32).powi(-15));
+        for bits in 0..0x7c00u16 {
+            assert_eq!(texture::f32_to_f16(texture::f16_to_f32(bits)), bits);
+            assert_eq!(texture::f32_to_f16(-texture::f16_to_f32(bits)), bits | 0x8000);
+        }
+    }
+
+    #[test]
+    fn texture_data_tests() {
+        assert!(
+            texture::TextureData::from_rgba8(2, 2, vec![0; 15], texture::ColorSpace::Srgb).is_err()
+        );
+        let data = texture::TextureData::from_rgba8(2, 2, vec![0; 16], texture::ColorSpace::Linear)
+            .unwrap();
+        assert_eq!(data.bytes_per_pixel(), 4);
+        let white = texture::TextureData::from_color([255; 4], texture::ColorSpace::Srgb);
+        assert_eq!((white.width, white.height), (1, 1));
+        assert_eq!(white.format, wgpu::TextureFormat::Rgba8UnormSrgb);
+        assert_eq!(
+            texture::get_texture_format_bytes_per_pixel(wgpu::TextureFormat::Rgba16Float),
+            Ok(8)
+        );
+        assert!(
+            texture::get_texture_format_bytes_per_pixel(wgpu::TextureFormat::Depth32Float).is_err()
+        );
+    }
+
+    fn rgba8_texture(
+        width: u32,
+        height: u32,
+        pixels: Vec<u8>,
+        color_space: texture::ColorSpace,
+    ) -> texture::TextureData {
+        texture::TextureData::from_rgba8(width, height, pixels, color_space).unwrap()
+    }
+
+    #[test]
+    fn mip_level_count_tests() {
+        assert_eq!(mipmap::get_mip_level_count(1, 1), 1);
+        assert_eq!(mipmap::get_mip_level_count(256, 256), 9);
+        assert_eq!(mipmap::get_mip_level_count(256, 4), 9);
+        //non power of two sizes are rounded down at each level
+        assert_eq!(mipmap::get_mip_level_count(5, 3), 3);
+        assert_eq!(mipmap::get_mip_size(5, 1), 2);
+        assert_eq!(mipmap::get_mip_size(5, 2), 1);
+        assert_eq!(mipmap::get_mip_size(3, 5), 1);
+    }
+
+    #[test]
+    fn mip_chain_box_tests() {
+        //2x2 checker of 0 and 200, every level in linear space averages to 100
+        let mut pixels = Vec::new();
+        for value in [0u8, 200, 200, 0].iter() {
+            pixels.extend_from_slice(&[*value, *value, *value, 255]);
+        }
+        let data = rgba8_texture(2, 2, pixels, texture::ColorSpace::Linear);
+        let levels = mipmap::generate_mip_chain(&data, mipmap::MipFilter::Box).unwrap();
+        assert_eq!(levels.len(), 2);
+        assert_eq!(levels[0].pixels, data.pixels);
+        assert_eq!((levels[1].width, levels[1].height), (1, 1));
+        assert_eq!(levels[1].format, wgpu::TextureFormat::Rgba8Unorm);
+        assert_eq!(levels[1].pixels, vec![100, 100, 100, 255]);
+
+        //a 3x1 image goes down to a single texel where each source texel has the same
+        //weight, the middle one is shared by both halves
+        let data = rgba8_texture(
+            3,
+            1,
+            vec![30, 0, 0, 0, 60, 0, 0, 0, 90, 0, 0, 0],
+            texture::ColorSpace::Linear,
+        );
+        let levels = mipmap::generate_mip_chain(&data, mipmap::MipFilter::Box).unwrap();
+        assert_eq!(levels.len(), 2);
+        assert_eq!(levels[1].pixels, vec![60, 0, 0, 0]);
+    }
+
+    #[test]
+    fn mip_chain_srgb_tests() {
+        //black and white averages to half the light, which is 188 in srgb and not 128
+        let data = rgba8_texture(
+            2,
+            1,
+            vec![0, 0, 0, 0, 255, 255, 255, 255],
+            texture::ColorSpace::Srgb,
+        );
+        let levels = mipmap::generate_mip_chain(&data, mipmap::MipFilter::Box).unwrap();
+        assert_eq!(levels[1].format, wgpu::TextureFormat::Rgba8UnormSrgb);
+        //alpha is linear in both cases
+        assert_eq!(levels[1].pixels, vec![188, 188, 188, 128]);
+
+        let data = rgba8_texture(
+            2,
+            1,
+            vec![0, 0, 0, 0, 255, 255, 255, 255],
+            texture::ColorSpace::Linear,
+        );
+        let levels = mipmap::generate_mip_chain(&data, mipmap::MipFilter::Box).unwrap();
+        assert_eq!(levels[1].pixels, vec![128, 128, 128, 128]);
+
+        for value in [0.0f32, 0.002, 0.2, 0.5, 1.0].iter() {
+            let round_trip = mipmap::linear_to_srgb(mipmap::srgb_to_linear(*value));
+            assert!((round_trip - value).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn mip_chain_kaiser_tests() {
+        //a constant image stays constant whatever the size
+        let data = rgba8_texture(
+            7,
+            5,
+            [10u8, 20, 30, 40].repeat(35),
+            texture::ColorSpace::Srgb,
+        );
+        let levels = mipmap::generate_mip_chain(&data, mipmap::MipFilter::Kaiser).unwrap();
+        assert_eq!(levels.len(), 3);
+        for (level, data) in levels.iter().enumerate() {
+            assert_eq!(data.width, mipmap::get_mip_size(7, level as u32));
+            assert_eq!(data.height, mipmap::get_mip_size(5, level as u32));
+            assert_eq!(
+                data.pixels,
+                [10u8, 20, 30, 40].repeat(data.pixels.len() / 4)
+            );
+        }
+
+        //a 4x1 ramp keeps its direction and averages to the middle value
+        let data = rgba8_texture(
+            4,
+            1,
+            vec![0, 0, 0, 0, 80, 0, 0, 0, 160, 0, 0, 0, 240, 0, 0, 0],
+            texture::ColorSpace::Linear,
+        );
+        let levels = mipmap::generate_mip_chain(&data, mipmap::MipFilter::Kaiser).unwrap();
+        assert_eq!(levels.len(), 3);
+        assert!(levels[1].pixels[0] < 60);
+        assert!(levels[1].pixels[4] > 180);
+        assert_eq!(levels[2].pixels[0], 120);
+    }
+
+    #[test]
+    fn mip_chain_float_tests() {
+        let mut pixels = Vec::new();
+        for value in [1.0f32, 1.0, 1.0, 1.0, 7.0, 7.0, 7.0, 1.0].iter() {
+            pixels.extend_from_slice(&value.to_le_bytes());
+        }
+        let data = texture::TextureData {
+            width: 2,
+            height: 1,
+            format: wgpu::TextureFormat::Rgba32Float,
+            pixels,
+        };
+        let levels = mipmap::generate_mip_chain(&data, mipmap::MipFilter::Box).unwrap();
+        assert_eq!(levels[1].format, wgpu::TextureFormat::Rgba32Float);
+        //values above one are not clamped
+        assert_eq!(&levels[1].pixels[0..4], &4.0f32.to_le_bytes());
+
+        //half floats, like the decoded hdr images
+        let mut pixels = Vec::new();
+        for value in [1.0f32, 1.0, 1.0, 1.0, 7.0, 7.0, 7.0, 1.0].iter() {
+            pixels.extend_from_slice(&texture::f32_to_f16(*value).to_le_bytes());
+        }
+        let data = texture::TextureData {
+            width: 2,
+            height: 1,
+            format: wgpu::TextureFormat::Rgba16Float,
+            pixels,
+        };
+        let levels = mipmap::generate_mip_chain(&data, mipmap::MipFilter::Box).unwrap();
+        assert_eq!(levels[1].format, wgpu::TextureFormat::Rgba16Float);
+        assert_eq!(levels[1].pixels.len(), 8);
+        assert_eq!(&levels[1].pixels[0..2], &texture::f32_to_f16(4.0).to_le_bytes());
+
+        let data = texture::TextureData {
+            width: 1,
+            height: 1,
+            format: wgpu::TextureFormat::Rg8Unorm,
+            pixels: vec![0, 0],
+        };
+        assert!(mipmap::generate_mip_chain(&data, mipmap::MipFilter::Box).is_err());
+        let data = rgba8_texture(2, 1, vec![0; 8], texture::ColorSpace::Linear);
+        let data = texture::TextureData {
+            pixels: vec![0; 4],
+            ..data
+        };
+        assert!(mipmap::generate_mip_chain(&data, mipmap::MipFilter::Box).is_err());
+    }
+
+    #[test]
+    fn animation_channel_sampling_tests() {
+        use animation::{AnimationChannel, AnimationProperty, Interpolation};
+        let close =
+            |a: &[f32], b: &[f32]| a.iter().zip(b.iter()).all(|(a, b)| (a - b).abs() < 1e-5);
+
+        let translation = |interpolation| {
+            AnimationChannel::new(
+                0,
+                AnimationProperty::Translation,
+                interpolation,
+                vec![1.0, 2.0, 4.0],
+                vec![0.0, 0.0, 0.0, 2.0, 4.0, 6.0, 2.0, 0.0, 0.0],
+            )
+            .unwrap()
+        };
+        let linear = translation(Interpolation::Linear);
+        assert_eq!(linear.get_component_count(), 3);
+        assert_eq!(linear.get_duration(), 4.0);
+        assert!(close(&linear.sample(1.5)[..], &[1.0, 2.0, 3.0]));
+        assert!(close(&linear.sample(3.0)[..], &[2.0, 2.0, 3.0]));
+        //outside of the keyframes the value is clamped
+        assert!(close(&linear.sample(-1.0)[..], &[0.0, 0.0, 0.0]));
+        assert!(close(&linear.sample(10.0)[..], &[2.0, 0.0, 0.0]));
+        let step = translation(Interpolation::Step);
+        assert!(close(&step.sample(1.99)[..], &[0.0, 0.0, 0.0]));
+        assert!(close(&step.sample(2.0)[..], &[2.0, 4.0, 6.0]));
+
+        //rotations take the shortest arc, the second key is the same rotation with
+        //a flipped sign
+        let half = std::f32::consts::FRAC_1_SQRT_2;
+        let rotation = AnimationChannel::new(
+            0,
+            AnimationProperty::Rotation,
+            Interpolation::Linear,
+            vec![0.0, 1.0],
+            vec![0.0, 0.0, 0.0, 1.0, 0.0, 0.0, -half, -half],
+        )
+        .unwrap();
+        let quarter = (std::f32::consts::PI / 8.0).sin();
+        let quarter_w = (std::f32::consts::PI / 8.0).cos();
+        assert!(close(
+            &rotation.sample(0.5)[..],
+            &[0.0, 0.0, quarter, quarter_w]
+        ));
+
+        //cubic spline keys are in tangent, value, out tangent. With zero tangents the
+        //curve eases in and out and goes through the middle at half time
+        let cubic = AnimationChannel::new(
+            0,
+            AnimationProperty::Weights,
+            Interpolation::CubicSpline,
+            vec![0.0, 2.0],
+            vec![0.0, 0.0, 0.0, 10.0, 0.0, 0.0, 0.0, 0.0, 0.0, 20.0, 0.0, 0.0],
+        )
+        .unwrap();
+        assert_eq!(cubic.get_component_count(), 2);
+        assert!(close(&cubic.sample(1.0)[..], &[0.0, 15.0]));
+        assert!(cubic.sample(0.5)[1] < 12.5);
+        //a tangent of one per second on a one second step is a straight line
+        let cubic = AnimationChannel::new(
+            0,
+            AnimationProperty::Weights,
+            Interpolation::CubicSpline,
+            vec![0.0, 1.0],
+            vec![1.0, 0.0, 1.0, 1.0, 1.0, 1.0],
+        )
+        .unwrap();
+        assert!(close(&cubic.sample(0.25)[..], &[0.25]));
+
+        assert!(AnimationChannel::new(
+            0,
+            AnimationProperty::Scale,
+            Interpolation::Linear,
+            vec![0.0, 1.0],
+            vec![1.0; 5]
+        )
+        .is_err());
+        assert!(AnimationChannel::new(
+            0,
+            AnimationProperty::Weights,
+            Interpolation::Linear,
+            vec![1.0, 0.0],
+            vec![1.0; 2]
+        )
+        .is_err());
+        assert!(AnimationChannel::new(
+            0,
+            AnimationProperty::Weights,
+            Interpolation::Step,
+            Vec::new(),
+            Vec::new()
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn joint_palette_tests() {
+        use cgmath::SquareMatrix;
+        let mut scene_graph = scene::SceneGraph::default();
+        let mesh_node = scene_graph.add_node(
+            None,
+            scene::NodeTransform::Matrix(cgmath::Matrix4::from_translation(cgmath::Vector3::new(
+                5.0, 0.0, 0.0,
+            ))),
+            Some(0),
+        );
+        let root = scene_graph.add_node(None, scene::NodeTransform::identity(), None);
+        let joint = scene_graph.add_node(
+            None,
+            scene::NodeTransform::Decomposed {
+                translation: cgmath::Vector3::new(0.0, 1.0, 0.0),
+                rotation: cgmath::Quaternion::new(1.0, 0.0, 0.0, 0.0),
+                scale: cgmath::Vector3::new(1.0, 1.0, 1.0),
+            },
+            None,
+        );
+        scene_graph.set_parent(joint, root).unwrap();
+        scene_graph.update_world_matrices();
+
+        let skin = animation::Skin {
+            name: None,
+            joints: vec![root, joint],
+            inverse_bind_matrices: vec![
+                cgmath::Matrix4::identity(),
+                cgmath::Matrix4::from_translation(cgmath::Vector3::new(0.0, -1.0, 0.0)),
+            ],
+            skeleton: Some(root),
+        };
+        //in bind pose the joints do not move the mesh, but the transform of the
+        //skinned node itself is taken out
+        let palette = skin.get_joint_palette(&scene_graph, mesh_node);
+        assert_eq!(palette.len(), 2);
+        let unskinned: animation::JointMatrix =
+            cgmath::Matrix4::from_translation(cgmath::Vector3::new(-5.0, 0.0, 0.0)).into();
+        assert_eq!(palette[0], unskinned);
+        assert_eq!(palette[1], unskinned);
+
+        let clip = animation::AnimationClip {
+            name: Some(String::from("raise")),
+            channels: vec![animation::AnimationChannel::new(
+                joint,
+                animation::AnimationProperty::Translation,
+                animation::Interpolation::Linear,
+                vec![0.0, 1.0],
+                vec![0.0, 1.0, 0.0, 0.0, 3.0, 0.0],
+            )
+            .unwrap()],
+        };
+        assert_eq!(clip.get_duration(), 1.0);
+        let palette =
+            animation::sample_joint_palette(&clip, 0.5, &skin, mesh_node, &mut scene_graph);
+        //the joint went up by one, so do the vertices bound to it
+        assert_eq!(palette[1][3], [-5.0, 1.0, 0.0, 1.0]);
+        assert_eq!(palette[0], unskinned);
+        //the palette is uploaded as a plain mat4 array
+        let bytes: &[u8] = bytemuck::cast_slice(&palette[..]);
+        assert_eq!(bytes.len(), 2 * 64);
+    }
+
+    #[test]
+    fn gltf_skin_animation_import_tests() {
+        let mut bin = Vec::new();
+        for value in [0.0f32, 1.0].iter() {
+            bin.extend_from_slice(&value.to_le_bytes());
+        }
+        for value in [0.0f32, 1.0, 0.0, 0.0, 2.0, 0.0].iter() {
+            bin.extend_from_slice(&value.to_le_bytes());
+        }
+        let mut inverse_bind = [0.0f32; 16];
+        for i in 0..4 {
+            inverse_bind[i * 5] = 1.0;
+        }
+        inverse_bind[13] = -1.0;
+        for value in inverse_bind.iter() {
+            bin.extend_from_slice(&value.to_le_bytes());
+        }
+        //rotations stored as normalized shorts
+        for value in [0i16, 0, 0, 32767, 0, 32767, 0, 0].iter() {
+            bin.extend_from_slice(&value.to_le_bytes());
+        }
+        let uri = format!(
+            "data:application/octet-stream;base64,{}",
+            base64::encode(&bin[..])
+        );
+        let json = serde_json::json!({
+            "asset": {"version": "2.0"},
+            "buffers": [{"byteLength": bin.len(), "uri": uri}],
+            "bufferViews": [
+                {"buffer": 0, "byteOffset": 0, "byteLength": 8},
+                {"buffer": 0, "byteOffset": 8, "byteLength": 24},
+                {"buffer": 0, "byteOffset": 32, "byteLength": 64},
+                {"buffer": 0, "byteOffset": 96, "byteLength": 16}
+            ],
+            "accessors": [
+                {"bufferView": 0, "componentType": 5126, "count": 2, "type": "SCALAR", "min": [0.0], "max": [1.0]},
+                {"bufferView": 1, "componentType": 5126, "count": 2, "type": "VEC3"},
+                {"bufferView": 2, "componentType": 5126, "count": 1, "type": "MAT4"},
+                {"bufferView": 3, "componentType": 5122, "normalized": true, "count": 2, "type": "VEC4"}
+            ],
+            "nodes": [
+                {"name": "skinned", "skin": 0},
+                {"name": "joint", "translation": [0.0, 1.0, 0.0]}
+            ],
+            "skins": [{"joints": [1], "inverseBindMatrices": 2, "skeleton": 1}],
+            "animations": [{
+                "name": "move",
+                "channels": [
+                    {"sampler": 0, "target": {"node": 1, "path": "translation"}},
+                    {"sampler": 1, "target": {"node": 1, "path": "rotation"}}
+                ],
+                "samplers": [
+                    {"input": 0, "output": 1, "interpolation": "LINEAR"},
+                    {"input": 0, "output": 3, "interpolation": "STEP"}
+                ]
+            }],
+            "scenes": [{"nodes": [0, 1]}]
+        });
+        let content = serde_json::to_vec(&json).unwrap();
+        let mut gltf = gltf::Gltf::from_slice(&content[..]).unwrap();
+        let raw_buffers =
+            futures::executor::block_on(model::load_gltf_buffers(&mut gltf, "skin.gltf")).unwrap();
+        let mut scene_graph = model::load_gltf_scene_graph(&gltf).unwrap();
+        assert_eq!(scene_graph.nodes[0].skin, Some(0));
+        assert_eq!(scene_graph.nodes[1].skin, None);
+
+        let skin = model::load_gltf_skin(&gltf.skins().next().unwrap(), &raw_buffers).unwrap();
+        assert_eq!(skin.joints, vec![1]);
+        assert_eq!(skin.skeleton, Some(1));
+        assert_eq!(skin.inverse_bind_matrices[0].w.y, -1.0);
+
+        let clip =
+            model::load_gltf_animation(&gltf.animations().next().unwrap(), &raw_buffers).unwrap();
+        assert_eq!(clip.name.as_deref(), Some("move"));
+        assert_eq!(clip.channels.len(), 2);
+        assert_eq!(
+            clip.channels[1].interpolation,
+            animation::Interpolation::Step
+        );
+        assert_eq!(&clip.channels[1].values[4..], &[0.0, 1.0, 0.0, 0.0]);
+
+        let palette = animation::sample_joint_palette(&clip, 0.5, &skin, 0, &mut scene_graph);
+        assert_eq!(palette[0][3], [0.0, 0.5, 0.0, 1.0]);
+
+        //one inverse bind matrix short for two joints
+        let mut json = json;
+        json["skins"] = serde_json::json!([{"joints": [0, 1], "inverseBindMatrices": 2}]);
+        let content = serde_json::to_vec(&json).unwrap();
+        let mut gltf = gltf::Gltf::from_slice(&content[..]).unwrap();
+        let raw_buffers =
+            futures::executor::block_on(model::load_gltf_buffers(&mut gltf, "skin.gltf")).unwrap();
+        assert!(model::load_gltf_skin(&gltf.skins().next().unwrap(), &raw_buffers).is_err());
+
+        //the broken skin is left out of the import, the node using the good one
+        //points at its new index and the one using the broken one at none
+        json["nodes"][1]["skin"] = serde_json::json!(1);
+        json["skins"] = serde_json::json!([
+            {"joints": [0, 1], "inverseBindMatrices": 2},
+            {"joints": [1], "inverseBindMatrices": 2}
+        ]);
+        let content = serde_json::to_vec(&json).unwrap();
+        let mut gltf = gltf::Gltf::from_slice(&content[..]).unwrap();
+        let raw_buffers =
+            futures::executor::block_on(model::load_gltf_buffers(&mut gltf, "skin.gltf")).unwrap();
+        let file_data =
+            model::import_gltf_document(&gltf, &raw_buffers, "skin.gltf", &Default::default())
+                .unwrap();
+        assert_eq!(file_data.skins.len(), 1);
+        assert_eq!(file_data.skins[0].joints, vec![1]);
+        assert_eq!(file_data.scene_graph.nodes[0].skin, None);
+        assert_eq!(file_data.scene_graph.nodes[1].skin, Some(0));
+    }
+
+    #[test]
+    fn morph_target_blend_tests() {
+        let base = morph::MorphVertices {
+            positions: vec![[0.0, 0.0, 0.0], [1.0, 0.0, 0.0]],
+            normals: vec![[0.0, 0.0, 1.0], [0.0, 0.0, 1.0]],
+            tangents: vec![[1.0, 0.0, 0.0, -1.0], [1.0, 0.0, 0.0, 1.0]],
+        };
+        let raise = morph::MorphTarget {
+            positions: vec![[0.0, 2.0, 0.0], [0.0, 0.0, 0.0]],
+            ..Default::default()
+        };
+        let tilt = morph::MorphTarget {
+            positions: vec![[1.0, 0.0, 0.0], [1.0, 0.0, 0.0]],
+            normals: vec![[0.0, 1.0, -1.0], [0.0, 0.0, 0.0]],
+            tangents: vec![[0.0, 1.0, 0.0], [0.0, 0.0, 0.0]],
+        };
+        let set =
+            morph::MorphTargetSet::new(base.clone(), vec![raise, tilt], vec![0.0, 0.0]).unwrap();
+
+        //no weight gives the base back
+        assert_eq!(set.blend(&[0.0, 0.0]), base);
+        assert_eq!(set.blend(&[]), base);
+
+        let blended = set.blend(&[0.5, 1.0]);
+        assert_eq!(blended.len(), 2);
+        assert_eq!(blended.positions, vec![[1.0, 1.0, 0.0], [2.0, 0.0, 0.0]]);
+        //the normal of the first vertex is now (0,1,0), normalized
+        assert_eq!(blended.normals[0], [0.0, 1.0, 0.0]);
+        assert_eq!(blended.normals[1], [0.0, 0.0, 1.0]);
+        let half = std::f32::consts::FRAC_1_SQRT_2;
+        assert!((blended.tangents[0][0] - half).abs() < 1e-6);
+        assert!((blended.tangents[0][1] - half).abs() < 1e-6);
+        //handedness is untouched
+        assert_eq!(blended.tangents[0][3], -1.0);
+
+        //blending into an existing stream gives the same result
+        let mut output = set.blend(&[1.0, 0.0]);
+        set.blend_into(&[0.5, 1.0], &mut output);
+        assert_eq!(output, blended);
+
+        //targets must match the base
+        let short = morph::MorphTarget {
+            positions: vec![[0.0; 3]],
+            ..Default::default()
+        };
+        assert!(morph::MorphTargetSet::new(base.clone(), vec![short], vec![0.0]).is_err());
+        assert!(morph::MorphTargetSet::new(
+            base.clone(),
+            vec![morph::MorphTarget::default()],
+            Vec::new()
+        )
+        .is_err());
+        let no_tangents = morph::MorphVertices {
+            tangents: Vec::new(),
+            ..base
+        };
+        let tilt = morph::MorphTarget {
+            tangents: vec![[0.0; 3]; 2],
+            ..Default::default()
+        };
+        assert!(morph::MorphTargetSet::new(no_tangents, vec![tilt], vec![0.0]).is_err());
+    }
+
+    //same layout as the AnimatedMorphCube sample, a primitive with two targets and
+    //default weights on the mesh, here on a single triangle
+    fn morph_triangle_gltf(weights: Option<Vec<f32>>) -> serde_json::Value {
+        let mut bin = Vec::new();
+        let floats: [f32; 27] = [
+            //positions
+            0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0, //
+            //first target positions
+            0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0, //
+            //second target positions
+            0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 2.0, 0.0,
+        ];
+        for value in floats.iter() {
+            bin.extend_from_slice(&value.to_le_bytes());
+        }
+        let uri = format!(
+            "data:application/octet-stream;base64,{}",
+            base64::encode(&bin[..])
+        );
+        let mut mesh = serde_json::json!({
+            "primitives": [{
+                "attributes": {"POSITION": 0},
+                "targets": [{"POSITION": 1}, {"POSITION": 2}]
+            }]
+        });
+        if let Some(weights) = weights {
+            mesh["weights"] = serde_json::json!(weights);
+        }
+        serde_json::json!({
+            "asset": {"version": "2.0"},
+            "buffers": [{"byteLength": bin.len(), "uri": uri}],
+            "bufferViews": [
+                {"buffer": 0, "byteOffset": 0, "byteLength": 36},
+                {"buffer": 0, "byteOffset": 36, "byteLength": 36},
+                {"buffer": 0, "byteOffset": 72, "byteLength": 36}
+            ],
+            "accessors": [
+                {"bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3", "min": [0.0, 0.0, 0.0], "max": [1.0, 1.0, 0.0]},
+                {"bufferView": 1, "componentType": 5126, "count": 3, "type": "VEC3", "min": [0.0, 0.0, 1.0], "max": [0.0, 0.0, 1.0]},
+                {"bufferView": 2, "componentType": 5126, "count": 3, "type": "VEC3", "min": [0.0, 0.0, 0.0], "max": [0.0, 2.0, 0.0]}
+            ],
+            "meshes": [mesh],
+            "nodes": [{"mesh": 0}],
+            "scenes": [{"nodes": [0]}]
+        })
+    }
+
+    #[test]
+    fn gltf_morph_target_import_tests() {
+        let json = morph_triangle_gltf(Some(vec![0.5, 0.0]));
+        let content = serde_json::to_vec(&json).unwrap();
+        let mut gltf = gltf::Gltf::from_slice(&content[..]).unwrap();
+        let raw_buffers =
+            futures::executor::block_on(model::load_gltf_buffers(&mut gltf, "morph.gltf")).unwrap();
+        let mesh = gltf.meshes().next().unwrap();
+        let primitive = mesh.primitives().next().unwrap();
+        let set = model::load_gltf_morph_targets(&primitive, mesh.weights(), &raw_buffers)
+            .unwrap()
+            .unwrap();
+        assert_eq!(set.targets.len(), 2);
+        assert_eq!(set.default_weights, vec![0.5, 0.0]);
+        assert!(set.base.normals.is_empty());
+        assert!(set.targets[0].normals.is_empty());
+
+        let blended = set.blend(&set.default_weights[..]);
+        assert_eq!(blended.positions[0], [0.0, 0.0, 0.5]);
+        assert_eq!(blended.positions[2], [0.0, 1.0, 0.5]);
+
+        //the node picks up the mesh weights, and animation weight tracks feed them
+        let mut scene_graph = model::load_gltf_scene_graph(&gltf).unwrap();
+        assert_eq!(scene_graph.nodes[0].weights, vec![0.5, 0.0]);
+        let clip = animation::AnimationClip {
+            name: None,
+            channels: vec![animation::AnimationChannel::new(
+                0,
+                animation::AnimationProperty::Weights,
+                animation::Interpolation::Linear,
+                vec![0.0, 1.0],
+                vec![0.0, 0.0, 1.0, 1.0],
+            )
+            .unwrap()],
+        };
+        clip.sample(0.5, &mut scene_graph);
+        let blended = set.blend(&scene_graph.nodes[0].weights[..]);
+        assert_eq!(blended.positions[2], [0.0, 2.0, 0.5]);
+
+        //without weights on the mesh they all start at zero
+        let json = morph_triangle_gltf(None);
+        let content = serde_json::to_vec(&json).unwrap();
+        let mut gltf = gltf::Gltf::from_slice(&content[..]).unwrap();
+        let raw_buffers =
+            futures::executor::block_on(model::load_gltf_buffers(&mut gltf, "morph.gltf")).unwrap();
+        let mesh = gltf.meshes().next().unwrap();
+        let primitive = mesh.primitives().next().unwrap();
+        let set = model::load_gltf_morph_targets(&primitive, mesh.weights(), &raw_buffers)
+            .unwrap()
+            .unwrap();
+        assert_eq!(set.default_weights, vec![0.0, 0.0]);
+
+        //primitives without targets have nothing to blend
+        let content = std::fs::read("resources/cube/cube.gltf").unwrap();
+        let mut gltf = gltf::Gltf::from_slice(&content[..]).unwrap();
+        let raw_buffers = futures::executor::block_on(model::load_gltf_buffers(
+            &mut gltf,
+            "resources/cube/cube.gltf",
+        ))
+        .unwrap();
+        let mesh = gltf.meshes().next().unwrap();
+        let primitive = mesh.primitives().next().unwrap();
+        assert_eq!(
+            model::load_gltf_morph_targets(&primitive, mesh.weights(), &raw_buffers).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn bounds_tests() {
+        use cgmath::Point3;
+        let empty = bounds::Aabb::empty();
+        assert!(empty.is_empty());
+        assert!(bounds::Bounds::default().is_empty());
+
+        let points = [
+            Point3::new(-1.0, 0.0, 0.0),
+            Point3::new(1.0, 0.0, 0.0),
+            Point3::new(0.0, 2.0, 0.0),
+        ];
+        let aabb = bounds::Aabb::from_points(points.iter().cloned());
+        assert_eq!(aabb.min, Point3::new(-1.0, 0.0, 0.0));
+        assert_eq!(aabb.max, Point3::new(1.0, 2.0, 0.0));
+        assert_eq!(aabb.get_center(), Point3::new(0.0, 1.0, 0.0));
+        assert_eq!(aabb.union(&empty), aabb);
+        assert_eq!(empty.union(&aabb), aabb);
+
+        //the point sphere is tighter than the one through the corners
+        let from_points = bounds::BoundingSphere::from_points(&points[..]);
+        let from_aabb = bounds::BoundingSphere::from_aabb(&aabb);
+        assert_eq!(from_points.center, Point3::new(0.0, 1.0, 0.0));
+        assert!((from_points.radius - 2.0f32.sqrt()).abs() < 1e-6);
+        assert!(from_aabb.radius >= from_points.radius);
+
+        //a rotated box grows, the sphere follows the biggest scale
+        let cube = bounds::Bounds::from_aabb(bounds::Aabb::new(
+            Point3::new(-1.0, -1.0, -1.0),
+            Point3::new(1.0, 1.0, 1.0),
+        ));
+        let rotation = cgmath::Matrix4::from_angle_y(cgmath::Deg(45.0));
+        let rotated = cube.transform(&rotation);
+        assert!((rotated.aabb.max.x - 2.0f32.sqrt()).abs() < 1e-5);
+        assert!((rotated.aabb.max.y - 1.0).abs() < 1e-5);
+        assert!((rotated.sphere.radius - cube.sphere.radius).abs() < 1e-5);
+        let moved = cube.transform(
+            &(cgmath::Matrix4::from_translation(cgmath::Vector3::new(10.0, 0.0, 0.0))
+                * cgmath::Matrix4::from_nonuniform_scale(1.0, 3.0, 1.0)),
+        );
+        assert_eq!(moved.aabb.min, Point3::new(9.0, -3.0, -1.0));
+        assert_eq!(moved.sphere.center, Point3::new(10.0, 0.0, 0.0));
+        assert!((moved.sphere.radius - 3.0 * cube.sphere.radius).abs() < 1e-5);
+
+        let union = cube.union(&moved);
+        assert_eq!(union.aabb.max, Point3::new(11.0, 3.0, 1.0));
+        assert_eq!(union.sphere.center, Point3::new(5.0, 0.0, 0.0));
+        assert_eq!(bounds::Bounds::default().union(&cube), cube);
+    }
+
+    #[test]
+    fn gltf_bounds_import_tests() {
+        let content = std::fs::read("resources/examples/gltf-model/Suzanne.gltf").unwrap();
+        let mut gltf = gltf::Gltf::from_slice(&content[..]).unwrap();
+        let raw_buffers = futures::executor::block_on(model::load_gltf_buffers(
+            &mut gltf,
+            "resources/examples/gltf-model/Suzanne.gltf",
+        ))
+        .unwrap();
+        let primitive = gltf.meshes().next().unwrap().primitives().next().unwrap();
+        let suzanne = model::load_gltf_mesh_data(&primitive, None, &raw_buffers)
+            .unwrap()
+            .get_bounds();
+        //straight from the accessor metadata
+        assert_eq!(
+            suzanne.aabb.min,
+            cgmath::Point3::new(-1.336914, -0.974609, -0.800781)
+        );
+        assert_eq!(
+            suzanne.aabb.max,
+            cgmath::Point3::new(1.336914, 0.950195, 0.825684)
+        );
+        assert!(suzanne.sphere.radius <= bounds::BoundingSphere::from_aabb(&suzanne.aabb).radius);
+
+        //morph targets grow the box by their displacements, whatever metadata they have
+        let mut json = morph_triangle_gltf(None);
+        for key in ["min", "max"].iter() {
+            json["accessors"][2].as_object_mut().unwrap().remove(*key);
+        }
+        let content = serde_json::to_vec(&json).unwrap();
+        let mut gltf = gltf::Gltf::from_slice(&content[..]).unwrap();
+        let raw_buffers =
+            futures::executor::block_on(model::load_gltf_buffers(&mut gltf, "morph.gltf")).unwrap();
+        assert!(model::get_gltf_accessor_aabb(&gltf.accessors().nth(2).unwrap()).is_none());
+        let primitive = gltf.meshes().next().unwrap().primitives().next().unwrap();
+        let mesh_data = model::load_gltf_mesh_data(&primitive, None, &raw_buffers).unwrap();
+        assert_eq!(mesh_data.bounds, None);
+        let morphed = mesh_data.get_bounds();
+        assert_eq!(morphed.aabb.min, cgmath::Point3::new(0.0, 0.0, 0.0));
+        assert_eq!(morphed.aabb.max, cgmath::Point3::new(1.0, 3.0, 1.0));
+    }
+
+    #[test]
+    fn scene_bounds_tests() {
+        use cgmath::InnerSpace;
+        let content = std::fs::read("resources/cube/cube.gltf").unwrap();
+        let mut gltf = gltf::Gltf::from_slice(&content[..]).unwrap();
+        let raw_buffers = futures::executor::block_on(model::load_gltf_buffers(
+            &mut gltf,
+            "resources/cube/cube.gltf",
+        ))
+        .unwrap();
+        let mut scene_graph = model::load_gltf_scene_graph(&gltf).unwrap();
+        let primitive = gltf.meshes().next().unwrap().primitives().next().unwrap();
+        let cube = model::load_gltf_mesh_data(&primitive, None, &raw_buffers)
+            .unwrap()
+            .get_bounds();
+        let model_bounds = vec![cube];
+
+        //the root only rotates, the cube stays where it is
+        let scene_bounds = scene_graph.get_scene_bounds(0, &model_bounds[..]);
+        assert!((scene_bounds.aabb.max - cgmath::Point3::new(0.5, 0.5, 0.5)).magnitude() < 1e-5);
+
+        scene_graph.nodes[0].transform = scene::NodeTransform::Matrix(
+            cgmath::Matrix4::from_translation(cgmath::Vector3::new(0.0, 5.0, 0.0)),
+        );
+        scene_graph.update_world_matrices();
+        let scene_bounds = scene_graph.get_scene_bounds(0, &model_bounds[..]);
+        assert!((scene_bounds.aabb.min.y - 4.5).abs() < 1e-5);
+        assert!((scene_bounds.sphere.center.y - 5.0).abs() < 1e-5);
+        //no model bounds, nothing to bound
+        assert!(scene_graph.get_scene_bounds(0, &[]).is_empty());
+
+        //framing keeps the direction and fits the sphere in the field of view
+        let mut camera = graphics::camera::Camera {
+            eye: (0.0, 0.0, 10.0).into(),
+            target: (0.0, 0.0, 0.0).into(),
+            up: cgmath::Vector3::unit_y(),
+            aspect: 2.0,
+            fovy: 90.0,
+            znear: 0.1,
+            zfar: 100.0,
+        };
+        camera.frame_bounding_sphere(&scene_bounds.sphere);
+        assert_eq!(camera.target, scene_bounds.sphere.center);
+        let distance = (camera.eye - camera.target).magnitude();
+        let expected = scene_bounds.sphere.radius / std::f32::consts::FRAC_PI_4.sin();
+        assert!((distance - expected).abs() < 1e-4);
+        assert!((camera.eye.x).abs() < 1e-5);
+        assert!(camera.eye.z > 0.0);
+    }
+
+    #[test]
+    fn gltf_accessor_read_tests() {
+        use gltf::accessor::DataType;
+        //interleaved positions and normalized short texcoords, with padding
+        let mut bin = Vec::new();
+        for i in 0..3 {
+            for value in [i as f32, 1.0, 2.0].iter() {
+                bin.extend_from_slice(&value.to_le_bytes());
+            }
+            for value in [32767i16, -32767].iter() {
+                bin.extend_from_slice(&value.to_le_bytes());
+            }
+            bin.extend_from_slice(&[0u8; 4]);
+        }
+        //sparse storage replacing the second position
+        bin.extend_from_slice(&1u16.to_le_bytes());
+        bin.extend_from_slice(&[0u8; 2]);
+        for value in [7.0f32, 8.0, 9.0].iter() {
+            bin.extend_from_slice(&value.to_le_bytes());
+        }
+        //u8 positions, not normalized
+        bin.extend_from_slice(&[1, 2, 3, 4, 5, 6, 7, 8, 9, 0, 0, 0]);
+        let uri = format!(
+            "data:application/octet-stream;base64,{}",
+            base64::encode(&bin[..])
+        );
+        let json = serde_json::json!({
+            "asset": {"version": "2.0"},
+            "extensionsUsed": ["KHR_mesh_quantization"],
+            "buffers": [{"byteLength": bin.len(), "uri": uri}],
+            "bufferViews": [
+                {"buffer": 0, "byteOffset": 0, "byteLength": 60, "byteStride": 20},
+                {"buffer": 0, "byteOffset": 60, "byteLength": 4},
+                {"buffer": 0, "byteOffset": 64, "byteLength": 12},
+                {"buffer": 0, "byteOffset": 76, "byteLength": 9}
+            ],
+            "accessors": [
+                {"bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3",
+                    "min": [0.0, 1.0, 2.0], "max": [2.0, 1.0, 2.0]},
+                {"bufferView": 0, "byteOffset": 12, "componentType": 5122, "normalized": true,
+                    "count": 3, "type": "VEC2"},
+                {"bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3",
+                    "min": [0.0, 1.0, 2.0], "max": [7.0, 8.0, 9.0],
+                    "sparse": {"count": 1,
+                        "indices": {"bufferView": 1, "componentType": 5123},
+                        "values": {"bufferView": 2}}},
+                {"bufferView": 3, "componentType": 5121, "count": 3, "type": "VEC3",
+                    "min": [1, 2, 3], "max": [7, 8, 9]},
+                {"componentType": 5126, "count": 2, "type": "VEC3", "min": [0.0, 0.0, 0.0], "max": [7.0, 8.0, 9.0],
+                    "sparse": {"count": 1,
+                        "indices": {"bufferView": 1, "componentType": 5123},
+                        "values": {"bufferView": 2}}}
+            ],
+            "meshes": [{"primitives": [{"attributes": {"POSITION": 0, "TEXCOORD_0": 1}}]}]
+        });
+        let content = serde_json::to_vec(&json).unwrap();
+        let mut gltf = gltf::Gltf::from_slice(&content[..]).unwrap();
+        let raw_buffers =
+            futures::executor::block_on(model::load_gltf_buffers(&mut gltf, "quantized.gltf"))
+                .unwrap();
+        let accessors: Vec<gltf::Accessor> = gltf.accessors().collect();
+        let to_floats = |data: Vec<u8>| -> Vec<f32> { bytemuck::cast_slice(&data[..]).to_vec() };
+
+        //the stride of the view is followed and the padding dropped
+        let positions = model::read_gltf_accessor(&accessors[0], &raw_buffers).unwrap();
+        assert_eq!(
+            to_floats(positions),
+            vec![0.0, 1.0, 2.0, 1.0, 1.0, 2.0, 2.0, 1.0, 2.0]
+        );
+        let texcoords = model::read_gltf_accessor(&accessors[1], &raw_buffers).unwrap();
+        assert_eq!(texcoords.len(), 3 * 4);
+        //normalized shorts can stay as they are, but the data must be packed
+        let data = model::get_gltf_attribute_data(
+            &accessors[1],
+            wgpu::VertexFormat::Short2Norm,
+            &raw_buffers,
+        )
+        .unwrap();
+        assert_eq!(data, texcoords.clone());
+        assert_eq!(
+            model::convert_gltf_components_to_f32(&texcoords[..], DataType::I16, true)[0..2],
+            [1.0, -1.0]
+        );
+
+        //sparse values replace the ones of the view
+        let sparse = model::read_gltf_accessor(&accessors[2], &raw_buffers).unwrap();
+        assert_eq!(&to_floats(sparse)[3..6], &[7.0, 8.0, 9.0]);
+        //without a view the accessor starts from zeros
+        let sparse = model::read_gltf_accessor(&accessors[4], &raw_buffers).unwrap();
+        assert_eq!(to_floats(sparse), vec![0.0, 0.0, 0.0, 7.0, 8.0, 9.0]);
+
+        //u8 positions have no wgpu format and are converted to floats
+        let data =
+            model::get_gltf_attribute_data(&accessors[3], wgpu::VertexFormat::Float3, &raw_buffers)
+                .unwrap();
+        assert_eq!(
+            to_floats(data),
+            vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0, 9.0]
+        );
+        assert_eq!(
+            model::convert_gltf_components_to_f32(&[255, 0], DataType::U8, true),
+            vec![1.0, 0.0]
+        );
+        assert_eq!(
+            model::convert_gltf_components_to_f32(&[0x81], DataType::I8, true),
+            vec![-1.0]
+        );
+
+        //a whole primitive with normalized short positions and byte normals, the min and
+        //max of the file are the stored integers and must not end up in the bounds
+        let mut bin = Vec::new();
+        for position in [[32767i16, 0, 0], [0, 32767, 0], [-32767, -32767, 0]].iter() {
+            for value in position.iter().chain([0i16].iter()) {
+                bin.extend_from_slice(&value.to_le_bytes());
+            }
+        }
+        for _ in 0..3 {
+            bin.extend_from_slice(&[0, 0, 127, 0]);
+        }
+        for displacement in [[0i16, 0, 32767], [0, 0, 0], [0, 0, 0]].iter() {
+            for value in displacement.iter().chain([0i16].iter()) {
+                bin.extend_from_slice(&value.to_le_bytes());
+            }
+        }
+        let uri = format!(
+            "data:application/octet-stream;base64,{}",
+            base64::encode(&bin[..])
+        );
+        let json = serde_json::json!({
+            "asset": {"version": "2.0"},
+            "extensionsUsed": ["KHR_mesh_quantization"],
+            "buffers": [{"byteLength": bin.len(), "uri": uri}],
+            "bufferViews": [
+                {"buffer": 0, "byteOffset": 0, "byteLength": 24, "byteStride": 8},
+                {"buffer": 0, "byteOffset": 24, "byteLength": 12, "byteStride": 4},
+                {"buffer": 0, "byteOffset": 36, "byteLength": 24, "byteStride": 8}
+            ],
+            "accessors": [
+                {"bufferView": 0, "componentType": 5122, "normalized": true, "count": 3,
+                    "type": "VEC3", "min": [-32767, -32767, 0], "max": [32767, 32767, 0]},
+                {"bufferView": 1, "componentType": 5120, "normalized": true, "count": 3,
+                    "type": "VEC3"},
+                {"bufferView": 2, "componentType": 5122, "normalized": true, "count": 3,
+                    "type": "VEC3", "min": [0, 0, 0], "max": [0, 0, 32767]}
+            ],
+            "meshes": [{"primitives": [
+                {"attributes": {"POSITION": 0, "NORMAL": 1}},
+                {"attributes": {"POSITION": 0, "NORMAL": 1}, "targets": [{"POSITION": 2}]}
+            ]}]
+        });
+        let content = serde_json::to_vec(&json).unwrap();
+        let mut gltf = gltf::Gltf::from_slice(&content[..]).unwrap();
+        let raw_buffers =
+            futures::executor::block_on(model::load_gltf_buffers(&mut gltf, "quantized.gltf"))
+                .unwrap();
+        let mesh = gltf.meshes().next().unwrap();
+        let primitives: Vec<gltf::Primitive> = mesh.primitives().collect();
+        let mesh_data = model::load_gltf_mesh_data(&primitives[0], None, &raw_buffers).unwrap();
+        assert_eq!(
+            mesh_data.positions,
+            vec![[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [-1.0, -1.0, 0.0]]
+        );
+        assert_eq!(mesh_data.normals, vec![[0.0, 0.0, 1.0]; 3]);
+        let bounds = mesh_data.get_bounds();
+        assert_eq!(bounds.aabb.min, cgmath::Point3::new(-1.0, -1.0, 0.0));
+        assert_eq!(bounds.aabb.max, cgmath::Point3::new(1.0, 1.0, 0.0));
+
+        //quantized targets are read the same way as the vertices
+        let mesh_data =
+            model::load_gltf_mesh_data(&primitives[1], Some(&[1.0]), &raw_buffers).unwrap();
+        let set = mesh_data.morph_targets.as_ref().unwrap();
+        assert_eq!(set.base.positions, mesh_data.positions);
+        assert_eq!(set.base.normals, mesh_data.normals);
+        assert_eq!(set.targets[0].positions[0], [0.0, 0.0, 1.0]);
+        let blended = set.blend(&set.default_weights[..]);
+        assert_eq!(blended.positions[0], [1.0, 0.0, 1.0]);
+        let bounds = mesh_data.get_bounds();
+        assert_eq!(bounds.aabb.min, cgmath::Point3::new(-1.0, -1.0, 0.0));
+        assert_eq!(bounds.aabb.max, cgmath::Point3::new(1.0, 1.0, 1.0));
+    }
+
+    #[test]
+    fn gltf_topology_tests() {
+        use gltf::mesh::Mode;
+        assert_eq!(
+            model::get_gltf_topology(Mode::Points),
+            wgpu::PrimitiveTopology::PointList
+        );
+        assert_eq!(
+            model::get_gltf_topology(Mode::Lines),
+            wgpu::PrimitiveTopology::LineList
+        );
+        assert_eq!(
+            model::get_gltf_topology(Mode::LineLoop),
+            wgpu::PrimitiveTopology::LineList
+        );
+        assert_eq!(
+            model::get_gltf_topology(Mode::LineStrip),
+            wgpu::PrimitiveTopology::LineStrip
+        );
+        assert_eq!(
+            model::get_gltf_topology(Mode::Triangles),
+            wgpu::PrimitiveTopology::TriangleList
+        );
+        assert_eq!(
+            model::get_gltf_topology(Mode::TriangleStrip),
+            wgpu::PrimitiveTopology::TriangleStrip
+        );
+        assert_eq!(
+            model::get_gltf_topology(Mode::TriangleFan),
+            wgpu::PrimitiveTopology::TriangleList
+        );
+
+        //loops close on the first vertex, fans share it in every triangle
+        let indices = [4, 5, 6, 7];
+        assert_eq!(
+            model::get_gltf_list_indices(Mode::LineLoop, &indices),
+            vec![4, 5, 5, 6, 6, 7, 7, 4]
+        );
+        assert_eq!(
+            model::get_gltf_list_indices(Mode::TriangleFan, &indices),
+            vec![4, 5, 6, 4, 6, 7]
+        );
+        assert_eq!(
+            model::get_gltf_list_indices(Mode::Triangles, &indices),
+            indices.to_vec()
+        );
+        //not enough vertices for a single line or triangle
+        assert!(model::get_gltf_list_indices(Mode::LineLoop, &[1]).is_empty());
+        assert!(model::get_gltf_list_indices(Mode::TriangleFan, &[1, 2]).is_empty());
+
+        //pipeline files use the same names get_primitive_topology reads
+        for topology in [
+            wgpu::PrimitiveTopology::PointList,
+            wgpu::PrimitiveTopology::LineList,
+            wgpu::PrimitiveTopology::LineStrip,
+            wgpu::PrimitiveTopology::TriangleList,
+            wgpu::PrimitiveTopology::TriangleStrip,
+        ]
+        .iter()
+        {
+            let json = serde_json::json!({
+                "primitive_topology": bindings::get_primitive_topology_name(*topology)
+            });
+            assert_eq!(bindings::get_primitive_topology(&json).unwrap(), *topology);
+        }
+
+        let mesh = model::Mesh::default();
+        assert_eq!(mesh.topology, wgpu::PrimitiveTopology::TriangleList);
+        assert_eq!(mesh.vertex_count, 0);
+    }
+
+    #[test]
+    fn mesh_vertex_stream_tests() {
+        use model::MeshBufferSemantic;
+        //stream descriptions follow the buffers of the vertex state they describe
+        for name in ["position_normal", "none"].iter() {
+            let streams = bindings::get_vertex_stream_descriptions(name).unwrap();
+            let descriptors = bindings::get_vertex_attrbibute_descriptor(name).unwrap();
+            assert_eq!(streams.len(), descriptors.len());
+            for (slot, (stream, descriptor)) in streams.iter().zip(descriptors.iter()).enumerate() {
+                assert_eq!(descriptor.attributes[0].shader_location, slot as u32);
+                assert_eq!(descriptor.attributes[0].format, stream.format);
+            }
+        }
+        assert!(bindings::get_vertex_stream_descriptions("missing").is_err());
+
+        let mapper = |semantic: MeshBufferSemantic, format: wgpu::VertexFormat, buffer_idx: u32| {
+            model::MeshBufferMapper {
+                semantic,
+                format,
+                offset: 0,
+                length: 36,
+                buffer_idx,
+            }
+        };
+        //buffers are returned in the order of the pipeline, not of the mesh
+        let mesh = model::Mesh {
+            buffers: vec![
+                mapper(
+                    MeshBufferSemantic::TexCoords(0),
+                    wgpu::VertexFormat::Float2,
+                    0,
+                ),
+                mapper(MeshBufferSemantic::Normals, wgpu::VertexFormat::Float3, 1),
+                mapper(MeshBufferSemantic::Positions, wgpu::VertexFormat::Float3, 2),
+            ],
+            ..Default::default()
+        };
+        let streams = bindings::get_vertex_stream_descriptions("position_normal").unwrap();
+        let buffers = mesh.get_vertex_streams(&streams[..]).unwrap();
+        let indices: Vec<u32> = buffers.iter().map(|buffer| buffer.buffer_idx).collect();
+        assert_eq!(indices, vec![2, 1]);
+        assert!(mesh.get_vertex_streams(&[]).unwrap().is_empty());
+
+        //a missing semantic or a quantized buffer the pipeline cannot read are errors
+        let mesh = model::Mesh {
+            buffers: vec![mapper(
+                MeshBufferSemantic::Positions,
+                wgpu::VertexFormat::Float3,
+                0,
+            )],
+            ..Default::default()
+        };
+        let message = mesh.get_vertex_streams(&streams[..]).err().unwrap();
+        assert!(message.contains("Normals"));
+        let mesh = model::Mesh {
+            buffers: vec![
+                mapper(MeshBufferSemantic::Positions, wgpu::VertexFormat::Float3, 0),
+                mapper(
+                    MeshBufferSemantic::Normals,
+                    wgpu::VertexFormat::Char4Norm,
+                    1,
+                ),
+            ],
+            ..Default::default()
+        };
+        let message = mesh.get_vertex_streams(&streams[..]).err().unwrap();
+        assert!(message.contains("Char4Norm"));
+    }
+
+    #[test]
+    fn gltf_index_data_tests() {
+        //small indices are packed in 16 bits, 0xffff is the strip restart value
+        let (format, data) = model::pack_indices(&[0, 1, 0xfffe]);
+        assert_eq!(format, wgpu::IndexFormat::Uint16);
+        assert_eq!(data, vec![0, 0, 1, 0, 0xfe, 0xff]);
+        let (format, data) = model::pack_indices(&[0, 0xffff]);
+        assert_eq!(format, wgpu::IndexFormat::Uint32);
+        assert_eq!(data.len(), 8);
+
+        //u16, u32, u8 and a u16 accessor not aligned to its size
+        let mut bin = Vec::new();
+        for index in [0u16, 1, 2].iter() {
+            bin.extend_from_slice(&index.to_le_bytes());
+        }
+        bin.extend_from_slice(&[0u8; 2]);
+        for index in [2u32, 1, 0].iter() {
+            bin.extend_from_slice(&index.to_le_bytes());
+        }
+        bin.extend_from_slice(&[1u8, 2, 0, 0]);
+        bin.extend_from_slice(&[0u8]);
+        for index in [2u16, 0, 1].iter() {
+            bin.extend_from_slice(&index.to_le_bytes());
+        }
+        let uri = format!(
+            "data:application/octet-stream;base64,{}",
+            base64::encode(&bin[..])
+        );
+        let json = serde_json::json!({
+            "asset": {"version": "2.0"},
+            "buffers": [{"byteLength": bin.len(), "uri": uri}],
+            "bufferViews": [
+                {"buffer": 0, "byteOffset": 0, "byteLength": 6},
+                {"buffer": 0, "byteOffset": 8, "byteLength": 12},
+                {"buffer": 0, "byteOffset": 20, "byteLength": 3},
+                {"buffer": 0, "byteOffset": 24, "byteLength": 7}
+            ],
+            "accessors": [
+                {"bufferView": 0, "componentType": 5123, "count": 3, "type": "SCALAR"},
+                {"bufferView": 1, "componentType": 5125, "count": 3, "type": "SCALAR"},
+                {"bufferView": 2, "componentType": 5121, "count": 3, "type": "SCALAR"},
+                {"bufferView": 3, "byteOffset": 1, "componentType": 5123, "count": 3,
+                    "type": "SCALAR"},
+                {"bufferView": 0, "componentType": 5123, "count": 1, "type": "VEC2"}
+            ]
+        });
+        let content = serde_json::to_vec(&json).unwrap();
+        let mut gltf = gltf::Gltf::from_slice(&content[..]).unwrap();
+        let raw_buffers =
+            futures::executor::block_on(model::load_gltf_buffers(&mut gltf, "indices.gltf"))
+                .unwrap();
+        let accessors: Vec<gltf::Accessor> = gltf.accessors().collect();
+
+        //16 and 32 bit indices keep their format
+        let (format, indices) = model::read_gltf_indices(&accessors[0], &raw_buffers).unwrap();
+        assert_eq!(format, wgpu::IndexFormat::Uint16);
+        assert_eq!(indices, vec![0, 1, 2]);
+        let (format, indices) = model::read_gltf_indices(&accessors[1], &raw_buffers).unwrap();
+        assert_eq!(format, wgpu::IndexFormat::Uint32);
+        assert_eq!(indices, vec![2, 1, 0]);
+        //bytes are widened to 16 bits
+        let (format, indices) = model::read_gltf_indices(&accessors[2], &raw_buffers).unwrap();
+        assert_eq!(format, wgpu::IndexFormat::Uint16);
+        assert_eq!(indices, vec![1, 2, 0]);
+        let (format, indices) = model::read_gltf_indices(&accessors[3], &raw_buffers).unwrap();
+        assert_eq!(format, wgpu::IndexFormat::Uint16);
+        assert_eq!(indices, vec![2, 0, 1]);
+        assert!(model::read_gltf_indices(&accessors[4], &raw_buffers).is_err());
+
+        //the mesh keeps 32 bit indices, and widens 16 bit ones only when it has to
+        let mut mesh = model::MeshData {
+            positions: vec![[0.0; 3]; 3],
+            indices: vec![0, 1, 2],
+            index_format: wgpu::IndexFormat::Uint32,
+            ..Default::default()
+        };
+        let gpu_mesh = mesh.create_mesh(|_| 0);
+        assert_eq!(
+            gpu_mesh.index_buffer.unwrap().format,
+            wgpu::IndexFormat::Uint32
+        );
+        mesh.index_format = wgpu::IndexFormat::Uint16;
+        let gpu_mesh = mesh.create_mesh(|_| 0);
+        assert_eq!(gpu_mesh.index_buffer.unwrap().length, 6);
+        mesh.positions.resize(0x10001, [0.0; 3]);
+        mesh.indices.push(0x10000);
+        let gpu_mesh = mesh.create_mesh(|_| 0);
+        assert_eq!(
+            gpu_mesh.index_buffer.unwrap().format,
+            wgpu::IndexFormat::Uint32
+        );
+
+        //pipeline files name the index format the same way it is read back
+        for format in [wgpu::IndexFormat::Uint16, wgpu::IndexFormat::Uint32].iter() {
+            let json = serde_json::json!({
+                "index_format": bindings::get_index_format_name(*format)
+            });
+            assert_eq!(bindings::get_index_format(&json).unwrap(), *format);
+        }
+        let json = serde_json::json!({});
+        assert_eq!(
+            bindings::get_index_format(&json).unwrap(),
+            wgpu::IndexFormat::Uint32
+        );
+        assert!(bindings::get_index_format(&serde_json::json!({"index_format": "uint8"})).is_err());
+
+        //meshes are drawn with the pipeline matching their indices
+        let mut mesh = model::Mesh {
+            topology: wgpu::PrimitiveTopology::LineStrip,
+            ..Default::default()
+        };
+        assert_eq!(
+            mesh.get_pipeline_variant(),
+            bindings::PipelineVariant {
+                topology: wgpu::PrimitiveTopology::LineStrip,
+                index_format: wgpu::IndexFormat::Uint32,
+            }
+        );
+        mesh.index_buffer = Some(model::MeshIndexBufferMapper {
+            offset: 0,
+            length: 6,
+            format: wgpu::IndexFormat::Uint16,
+            buffer_idx: 0,
+            count: 3,
+        });
+        assert_eq!(
+            mesh.get_pipeline_variant().index_format,
+            wgpu::IndexFormat::Uint16
+        );
+    }
+
+    #[test]
+    fn procedural_mesh_tests() {
+        use model::procedural;
+        //checks every generator has to pass, returns the number of triangles
+        let check_mesh = |name: &str, mesh: &model::MeshData| -> usize {
+            mesh.validate().unwrap();
+            let count = mesh.get_vertex_count();
+            assert_eq!(mesh.normals.len(), count, "{}", name);
+            assert_eq!(mesh.uvs.len(), count, "{}", name);
+            assert_eq!(mesh.tangents.len(), count, "{}", name);
+            for (normal, tangent) in mesh.normals.iter().zip(mesh.tangents.iter()) {
+                let normal = cgmath::Vector3::from(*normal);
+                let tangent_xyz = cgmath::Vector3::new(tangent[0], tangent[1], tangent[2]);
+                assert!((normal.magnitude() - 1.0).abs() < 1e-4, "{}", name);
+                assert!((tangent_xyz.magnitude() - 1.0).abs() < 1e-4, "{}", name);
+                assert!(normal.dot(tangent_xyz).abs() < 1e-4, "{}", name);
+                //none of the uv mappings is mirrored
+                assert_eq!(tangent[3], 1.0, "{}", name);
+            }
+            //counter clockwise seen from the side the normals point to
+            for triangle in mesh.indices.chunks(3) {
+                let p: Vec<cgmath::Point3<f32>> = triangle
+                    .iter()
+                    .map(|i| mesh.positions[*i as usize].into())
+                    .collect();
+                let face_normal = (p[1] - p[0]).cross(p[2] - p[0]);
+                assert!(
+                    face_normal.magnitude() > 0.0,
+                    "{} degenerate triangle",
+                    name
+                );
+                let vertex_normal = triangle
+                    .iter()
+                    .map(|i| cgmath::Vector3::from(mesh.normals[*i as usize]))
+                    .fold(cgmath::Vector3::new(0.0, 0.0, 0.0), |a, b| a + b);
+                assert!(face_normal.dot(vertex_normal) > 0.0, "{} winding", name);
+            }
+            mesh.indices.len() / 3
+        };
+        use cgmath::InnerSpace;
+
+        let cube = procedural::cube(2.0);
+        assert_eq!(cube.get_vertex_count(), 24);
+        assert_eq!(check_mesh("cube", &cube), 12);
+        let bounds = cube.get_bounds();
+        assert_eq!(bounds.aabb.min, cgmath::Point3::new(-1.0, -1.0, -1.0));
+        assert_eq!(bounds.aabb.max, cgmath::Point3::new(1.0, 1.0, 1.0));
+
+        let sphere = procedural::uv_sphere(1.5, 16, 8);
+        assert_eq!(sphere.get_vertex_count(), 17 * 9);
+        assert_eq!(check_mesh("uv sphere", &sphere), 16 * 7 * 2);
+        for (position, normal) in sphere.positions.iter().zip(sphere.normals.iter()) {
+            let position = cgmath::Vector3::from(*position);
+            assert!((position.magnitude() - 1.5).abs() < 1e-4);
+            assert!((position / 1.5 - cgmath::Vector3::from(*normal)).magnitude() < 1e-4);
+        }
+
+        for subdivisions in 0..3 {
+            let sphere = procedural::icosphere(2.0, subdivisions);
+            let faces = 20 * 4usize.pow(subdivisions);
+            assert_eq!(check_mesh("icosphere", &sphere), faces);
+            //seam vertices come on top of the ones of the icosahedron
+            assert!(sphere.get_vertex_count() >= faces / 2 + 2);
+            for position in sphere.positions.iter() {
+                assert!((cgmath::Vector3::from(*position).magnitude() - 2.0).abs() < 1e-4);
+            }
+            //no triangle wraps around the texture
+            for triangle in sphere.indices.chunks(3) {
+                let us: Vec<f32> = triangle
+                    .iter()
+                    .map(|i| sphere.uvs[*i as usize][0])
+                    .collect();
+                let max = us.iter().cloned().fold(f32::MIN, f32::max);
+                let min = us.iter().cloned().fold(f32::MAX, f32::min);
+                assert!(max - min <= 0.5);
+            }
+        }
+
+        let grid = procedural::grid(4.0, 2.0, 4, 2);
+        assert_eq!(grid.get_vertex_count(), 15);
+        assert_eq!(check_mesh("grid", &grid), 16);
+        for tangent in grid.tangents.iter() {
+            assert_eq!(*tangent, [1.0, 0.0, 0.0, 1.0]);
+        }
+        assert_eq!(check_mesh("plane", &procedural::plane(1.0, 1.0)), 2);
+
+        let cylinder = procedural::cylinder(1.0, 2.0, 12);
+        assert_eq!(cylinder.get_vertex_count(), 4 * 12 + 4);
+        assert_eq!(check_mesh("cylinder", &cylinder), 12 * 4);
+
+        let cone = procedural::cone(1.0, 2.0, 12);
+        //the base vertices between two side triangles get one tangent per triangle
+        assert_eq!(cone.get_vertex_count(), 3 * 12 + 2 + 11);
+        assert_eq!(check_mesh("cone", &cone), 12 * 2);
+
+        let torus = procedural::torus(2.0, 0.5, 24, 12);
+        assert_eq!(torus.get_vertex_count(), 25 * 13);
+        assert_eq!(check_mesh("torus", &torus), 24 * 12 * 2);
+        let bounds = torus.get_bounds();
+        assert!((bounds.aabb.max.x - 2.5).abs() < 1e-4);
+        assert!((bounds.aabb.max.y - 0.5).abs() < 1e-4);
+
+        //covers the whole clip space square, counter clockwise
+        let triangle = procedural::fullscreen_triangle();
+        assert!(triangle.indices.is_empty());
+        assert_eq!(triangle.get_vertex_count(), 3);
+        triangle.validate().unwrap();
+        let p = &triangle.positions;
+        let area =
+            (p[1][0] - p[0][0]) * (p[2][1] - p[0][1]) - (p[2][0] - p[0][0]) * (p[1][1] - p[0][1]);
+        assert!(area > 0.0);
+
+        //segment counts too small to make a closed shape are raised, to 3 segments
+        //and 2 rings for a sphere
+        assert_eq!(check_mesh("clamped", &procedural::uv_sphere(1.0, 1, 1)), 6);
+
+        let broken = model::MeshData {
+            positions: vec![[0.0; 3]; 2],
+            normals: vec![[0.0, 1.0, 0.0]],
+            ..Default::default()
+        };
+        assert!(broken.validate().is_err());
+        let broken = model::MeshData {
+            positions: vec![[0.0; 3]; 2],
+            indices: vec![0, 1, 2],
+            ..Default::default()
+        };
+        assert!(broken.validate().is_err());
+    }
+
+    #[test]
+    fn mesh_processing_tests() {
+        use model::procedural;
+        use model::processing;
+
+        //triangles with their smallest index first, the winding is kept
+        let canonical_triangles = |indices: &[u32]| {
+            let mut triangles: Vec<[u32; 3]> = indices
+                .chunks(3)
+                .map(|t| {
+                    let first = (0..3).min_by_key(|i| t[*i]).unwrap();
+                    [t[first], t[(first + 1) % 3], t[(first + 2) % 3]]
+                })
+                .collect();
+            triangles.sort();
+            triangles
+        };
+        let unweld = |mesh: &model::MeshData| model::MeshData {
+            topology: mesh.topology,
+            positions: mesh
+                .indices
+                .iter()
+                .map(|i| mesh.positions[*i as usize])
+                .collect(),
+            normals: mesh
+                .indices
+                .iter()
+                .map(|i| mesh.normals[*i as usize])
+                .collect(),
+            uvs: mesh.indices.iter().map(|i| mesh.uvs[*i as usize]).collect(),
+            tangents: mesh
+                .indices
+                .iter()
+                .map(|i| mesh.tangents[*i as usize])
+                .collect(),
+            ..Default::default()
+        };
+
+        //a corner per triangle vertex goes back to the 4 vertices of each face
+        let cube = procedural::cube(1.0);
+        let mut welded = unweld(&cube);
+        assert_eq!(welded.get_vertex_count(), 36);
+        processing::weld_vertices(&mut welded);
+        assert_eq!(welded.get_vertex_count(), 24);
+        assert_eq!(welded.indices.len(), 36);
+        welded.validate().unwrap();
+        //without the other attributes only the corners of the cube are left
+        let mut positions_only = model::MeshData {
+            positions: welded.positions.clone(),
+            indices: welded.indices.clone(),
+            ..Default::default()
+        };
+        processing::weld_vertices(&mut positions_only);
+        assert_eq!(positions_only.get_vertex_count(), 8);
+        //-0.0 and 0.0 are the same vertex
+        let mut signed_zero = model::MeshData {
+            positions: vec![[0.0, 1.0, 0.0], [-0.0, 1.0, 0.0], [1.0, 0.0, 0.0]],
+            ..Default::default()
+        };
+        processing::weld_vertices(&mut signed_zero);
+        assert_eq!(signed_zero.indices, vec![0, 0, 1]);
+
+        //a grid drawn in a scrambled order, the optimized order has to miss less and
+        //draw the same triangles
+        let grid = procedural::grid(1.0, 1.0, 24, 24);
+        let triangle_count = grid.indices.len() / 3;
+        let mut scrambled = Vec::new();
+        for i in 0..triangle_count {
+            let triangle = (i * 389) % triangle_count;
+            scrambled.extend_from_slice(&grid.indices[triangle * 3..triangle * 3 + 3]);
+        }
+        let mut optimized = scrambled.clone();
+        processing::optimize_vertex_cache(&mut optimized[..], grid.get_vertex_count());
+        let before = processing::get_cache_miss_ratio(&scrambled[..], 16);
+        let after = processing::get_cache_miss_ratio(&optimized[..], 16);
+        assert!(before > 2.0);
+        assert!(after < 0.9);
+        assert_eq!(
+            canonical_triangles(&scrambled[..]),
+            canonical_triangles(&optimized[..])
+        );
+        assert_eq!(processing::get_cache_miss_ratio(&[], 16), 0.0);
+
+        //two quads facing +z, the outer one has to be drawn first
+        let positions = vec![
+            [-1.0, -1.0, -0.2],
+            [1.0, -1.0, -0.2],
+            [1.0, 1.0, -0.2],
+            [-1.0, 1.0, -0.2],
+            [-1.0, -1.0, 1.0],
+            [1.0, -1.0, 1.0],
+            [1.0, 1.0, 1.0],
+            [-1.0, 1.0, 1.0],
+        ];
+        let mut indices = vec![0, 1, 2, 0, 2, 3, 4, 5, 6, 4, 6, 7];
+        processing::optimize_overdraw(&positions[..], &mut indices[..]);
+        assert_eq!(indices, vec![4, 5, 6, 4, 6, 7, 0, 1, 2, 0, 2, 3]);
+
+        //the uvs of the right half are mirrored, the vertices in the middle end up
+        //with a copy for each handedness
+        let mut mirrored = model::MeshData {
+            positions: vec![
+                [0.0, 0.0, 0.0],
+                [1.0, 0.0, 0.0],
+                [2.0, 0.0, 0.0],
+                [0.0, 1.0, 0.0],
+                [1.0, 1.0, 0.0],
+                [2.0, 1.0, 0.0],
+            ],
+            normals: vec![[0.0, 0.0, 1.0]; 6],
+            uvs: vec![
+                [0.0, 1.0],
+                [1.0, 1.0],
+                [0.0, 1.0],
+                [0.0, 0.0],
+                [1.0, 0.0],
+                [0.0, 0.0],
+            ],
+            indices: vec![0, 1, 4, 0, 4, 3, 1, 2, 5, 1, 5, 4],
+            ..Default::default()
+        };
+        processing::generate_tangents(&mut mirrored).unwrap();
+        mirrored.validate().unwrap();
+        assert_eq!(mirrored.get_vertex_count(), 8);
+        for (triangle, expected) in mirrored
+            .indices
+            .chunks(3)
+            .zip([1.0, 1.0, -1.0, -1.0].iter())
+        {
+            for index in triangle.iter() {
+                let tangent = mirrored.tangents[*index as usize];
+                assert_eq!(tangent[3], *expected);
+                assert!((tangent[0] - *expected).abs() < 1e-5);
+            }
+        }
+
+        //a bent strip with the uvs of the right half mirrored, the expected tangents of
+        //every corner come from the reference mikktspace implementation with the sign
+        //flipped to the gltf convention
+        let mut bent = model::MeshData {
+            positions: vec![
+                [0.0, 0.0, 0.0],
+                [1.0, 0.0, 0.3],
+                [2.0, 0.0, 0.0],
+                [0.0, 1.0, 0.2],
+                [1.0, 1.0, 0.6],
+                [2.0, 1.0, 0.1],
+            ],
+            normals: vec![
+                [0.09759, -0.19518, 0.9759],
+                [0.0, -0.287348, 0.957826],
+                [-0.196116, 0.0, 0.980581],
+                [0.286039, 0.095346, 0.953463],
+                [0.0, 0.196116, 0.980581],
+                [-0.099015, 0.099015, 0.990148],
+            ],
+            uvs: vec![
+                [0.0, 1.0],
+                [1.0, 0.9],
+                [0.0, 1.0],
+                [0.1, 0.0],
+                [1.0, 0.0],
+                [0.0, 0.1],
+            ],
+            indices: vec![0, 1, 4, 0, 4, 3, 1, 2, 5, 1, 5, 4],
+            ..Default::default()
+        };
+        let expected = [
+            [0.995194, 0.027075, -0.094104, 1.0],
+            [0.999556, -0.028530, -0.008559, 1.0],
+            [0.992715, -0.118146, 0.023629, 1.0],
+            [0.995194, 0.027075, -0.094104, 1.0],
+            [0.992715, -0.118146, 0.023629, 1.0],
+            [0.957155, -0.075282, -0.279619, 1.0],
+            [-0.999997, 0.002315, 0.000695, -1.0],
+            [-0.973566, -0.119400, -0.194713, -1.0],
+            [-0.983410, -0.161716, -0.082170, -1.0],
+            [-0.999997, 0.002315, 0.000695, -1.0],
+            [-0.983410, -0.161716, -0.082170, -1.0],
+            [-0.980491, -0.192746, 0.038549, -1.0],
+        ];
+        processing::generate_tangents(&mut bent).unwrap();
+        assert_eq!(bent.get_vertex_count(), 8);
+        for (index, expected) in bent.indices.iter().zip(expected.iter()) {
+            let tangent = bent.tangents[*index as usize];
+            assert_eq!(tangent[3], expected[3]);
+            for (value, expected) in tangent[..3].iter().zip(expected[..3].iter()) {
+                assert!((value - expected).abs() < 1e-5);
+            }
+        }
+        //the same mesh without indices gives the same tangents, mikktspace welds the
+        //corners by their attributes
+        let mut unwelded = model::MeshData {
+            positions: bent.indices.iter().map(|i| bent.positions[*i as usize]).collect(),
+            normals: bent.indices.iter().map(|i| bent.normals[*i as usize]).collect(),
+            uvs: bent.indices.iter().map(|i| bent.uvs[*i as usize]).collect(),
+            ..Default::default()
+        };
+        processing::generate_tangents(&mut unwelded).unwrap();
+        for (tangent, index) in unwelded.tangents.iter().zip(bent.indices.iter()) {
+            assert_eq!(*tangent, bent.tangents[*index as usize]);
+        }
+
+        //the tangents of a torus are known: u runs around the y axis so the tangent is
+        //the derivative of the ring direction, and the bitangent points towards
+        //decreasing v. Vertices on the seam only see one side, half a segment off
+        use cgmath::InnerSpace;
+        let mut torus = procedural::torus(1.0, 0.25, 64, 32);
+        torus.tangents.clear();
+        processing::generate_tangents(&mut torus).unwrap();
+        assert_eq!(torus.get_vertex_count(), 65 * 33);
+        let half_segment = (std::f32::consts::PI / 64.0).cos();
+        for (uv, tangent) in torus.uvs.iter().zip(torus.tangents.iter()) {
+            let phi = uv[0] * 2.0 * std::f32::consts::PI;
+            let theta = uv[1] * 2.0 * std::f32::consts::PI;
+            let expected = cgmath::Vector3::new(-phi.sin(), 0.0, -phi.cos());
+            let generated = cgmath::Vector3::new(tangent[0], tangent[1], tangent[2]);
+            assert!(expected.dot(generated) >= half_segment - 1e-4);
+            let normal = cgmath::Vector3::new(
+                phi.cos() * theta.cos(),
+                -theta.sin(),
+                -phi.sin() * theta.cos(),
+            );
+            let along_v = cgmath::Vector3::new(
+                -phi.cos() * theta.sin(),
+                -theta.cos(),
+                phi.sin() * theta.sin(),
+            );
+            assert!(normal.cross(generated * tangent[3]).dot(along_v) < 0.0);
+        }
+
+        //tangents need normals and uvs
+        let mut bare = model::MeshData {
+            positions: vec![[0.0; 3]; 3],
+            indices: vec![0, 1, 2],
+            ..Default::default()
+        };
+        assert!(processing::generate_tangents(&mut bare).is_err());
+
+        //nothing is done without options, everything with all of them
+        let options = processing::MeshProcessingOptions::default();
+        assert!(!options.is_enabled());
+        let mut untouched = unweld(&cube);
+        processing::process_mesh(&mut untouched, &options).unwrap();
+        assert_eq!(untouched, unweld(&cube));
+        let options = processing::MeshProcessingOptions {
+            weld_vertices: true,
+            generate_tangents: true,
+            optimize_vertex_cache: true,
+            optimize_overdraw: true,
+        };
+        let mut processed = unweld(&cube);
+        processed.tangents.clear();
+        processing::process_mesh(&mut processed, &options).unwrap();
+        processed.validate().unwrap();
+        assert_eq!(processed.get_vertex_count(), 24);
+        assert_eq!(processed.tangents.len(), 24);
+        assert!(processed.tangents.iter().all(|t| t[3] == 1.0));
+        //lines are welded but get no tangents, the error comes back after the steps ran
+        let mut lines = model::MeshData {
+            topology: wgpu::PrimitiveTopology::LineList,
+            positions: vec![[0.0; 3], [1.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0; 3]],
+            normals: vec![[0.0, 1.0, 0.0]; 4],
+            uvs: vec![[0.0; 2]; 4],
+            ..Default::default()
+        };
+        assert!(processing::process_mesh(&mut lines, &options).is_err());
+        assert_eq!(lines.indices, vec![0, 1, 1, 0]);
+
+        //every stream and the indices get a buffer of their own
+        let mut next_buffer = 7;
+        let mut sizes = Vec::new();
+        let mesh = processed.create_mesh(|data| {
+            sizes.push(data.len());
+            next_buffer += 1;
+            next_buffer - 1
+        });
+        assert_eq!(sizes, vec![24 * 12, 24 * 12, 24 * 8, 24 * 16, 36 * 2]);
+        let buffer_indices: Vec<u32> = mesh.buffers.iter().map(|b| b.buffer_idx).collect();
+        assert_eq!(buffer_indices, vec![7, 8, 9, 10]);
+        let index_buffer = mesh.index_buffer.as_ref().unwrap();
+        assert_eq!(index_buffer.buffer_idx, 11);
+        assert_eq!(index_buffer.format, wgpu::IndexFormat::Uint16);
+        assert_eq!(index_buffer.count, 36);
+        assert_eq!(mesh.vertex_count, 24);
+    }
+
+    #[test]
+    fn obj_import_tests() {
+        use model::obj;
+
+        let content = "
+# two objects, the first one with two materials
+mtllib shapes.mtl
+o Square
+v 0 0 0
+v 1 0 0
+v 1 1 0
+v 0 1 0
+vt 0 0
+vt 1 0
+vt 1 1
+vt 0 1
+vn 0 0 1
+usemtl red
+f 1/1/1 2/2/1 3/3/1 4/4/1
+usemtl blue
+f -4/-4/-1 -2/-2/-1 \\
+  -1/-1/-1
+o Triangle
+g ignored_group
+v 0 0 1
+v 1 0 1
+v 0 0 2
+f 5 6 7
+";
+        let data = obj::parse_obj(content).unwrap();
+        assert_eq!(data.material_libraries, vec![String::from("shapes.mtl")]);
+        assert_eq!(data.models.len(), 2);
+
+        let square = &data.models[0];
+        assert_eq!(square.name.as_deref(), Some("Square"));
+        assert_eq!(square.meshes.len(), 2);
+        let red = &square.meshes[0];
+        assert_eq!(red.material.as_deref(), Some("red"));
+        red.data.validate().unwrap();
+        //the quad is split in two triangles sharing its 4 corners
+        assert_eq!(red.data.get_vertex_count(), 4);
+        assert_eq!(red.data.indices.len(), 6);
+        //v is flipped so the uv origin is the top left corner
+        let uv_at = |position: [f32; 3]| {
+            let vertex = red.data.positions.iter().position(|p| *p == position);
+            red.data.uvs[vertex.unwrap()]
+        };
+        assert_eq!(uv_at([0.0, 0.0, 0.0]), [0.0, 1.0]);
+        assert_eq!(uv_at([1.0, 1.0, 0.0]), [1.0, 0.0]);
+        assert!(red.data.normals.iter().all(|n| *n == [0.0, 0.0, 1.0]));
+        //relative indices and a continued line
+        let blue = &square.meshes[1];
+        assert_eq!(blue.material.as_deref(), Some("blue"));
+        assert_eq!(
+            blue.data.positions,
+            vec![[0.0, 0.0, 0.0], [1.0, 1.0, 0.0], [0.0, 1.0, 0.0]]
+        );
+
+        //a group right after the object keeps its name, the missing normals come from
+        //the faces
+        let triangle = &data.models[1];
+        assert_eq!(triangle.name.as_deref(), Some("Triangle"));
+        assert_eq!(triangle.meshes.len(), 1);
+        //the material of the previous object is still in use
+        assert_eq!(triangle.meshes[0].material.as_deref(), Some("blue"));
+        let triangle = &triangle.meshes[0].data;
+        assert!(triangle.uvs.is_empty());
+        for normal in triangle.normals.iter() {
+            assert!((normal[1] + 1.0).abs() < 1e-6);
+        }
+
+        //an l shape, the fan from the first corner would go outside of it
+        let l_shape = [
+            [0.0, 0.0, 0.0],
+            [2.0, 0.0, 0.0],
+            [2.0, 1.0, 0.0],
+            [1.0, 1.0, 0.0],
+            [1.0, 2.0, 0.0],
+            [0.0, 2.0, 0.0],
+        ];
+        let triangles = obj::triangulate_polygon(&l_shape[..]);
+        assert_eq!(triangles.len(), 4);
+        let mut area = 0.0;
+        for triangle in triangles.iter() {
+            let [a, b, c] = [
+                l_shape[triangle[0]],
+                l_shape[triangle[1]],
+                l_shape[triangle[2]],
+            ];
+            let signed = (b[0] - a[0]) * (c[1] - a[1]) - (c[0] - a[0]) * (b[1] - a[1]);
+            //same winding as the polygon
+            assert!(signed > 0.0);
+            area += signed * 0.5;
+        }
+        assert!((area - 3.0f32).abs() < 1e-5);
+        //clockwise seen from +z, the triangles have to be clockwise too
+        let mut reversed = l_shape;
+        reversed.reverse();
+        for triangle in obj::triangulate_polygon(&reversed[..]).iter() {
+            let [a, b, c] = [
+                reversed[triangle[0]],
+                reversed[triangle[1]],
+                reversed[triangle[2]],
+            ];
+            assert!((b[0] - a[0]) * (c[1] - a[1]) - (c[0] - a[0]) * (b[1] - a[1]) < 0.0);
+        }
+
+        assert!(obj::parse_obj("v 0 0 0\nf 1 2 3").is_err());
+        assert!(obj::parse_obj("v 0 0 0\nv 1 0 0\nv 0 1 0\nf 0 1 2").is_err());
+        assert!(obj::parse_obj("v 0 zero 0").is_err());
+        let error = obj::parse_obj("v 0 0 0\n\nf 1 1").unwrap_err();
+        assert!(error.starts_with("line 3"));
+
+        let materials = obj::parse_mtl(
+            "
+newmtl red
+Kd 1 0 0
+Ns 0
+d 0.5
+map_Kd -s 2 2 1 textures/red.png
+newmtl blue
+Kd 0 0 1
+Pr 0.25
+Pm 1
+Ns 1000
+bump -bm 0.5 normal.png
+map_Ke glow.png
+",
+        )
+        .unwrap();
+        let red = &materials["red"];
+        assert_eq!(red.material.name.as_deref(), Some("red"));
+        assert_eq!(red.material.base_color_factor, [1.0, 0.0, 0.0, 0.5]);
+        assert_eq!(red.material.alpha_mode, material::AlphaMode::Blend);
+        assert_eq!(red.material.roughness_factor, 1.0);
+        assert_eq!(red.material.metallic_factor, 0.0);
+        assert_eq!(red.base_color_map.as_deref(), Some("textures/red.png"));
+        let blue = &materials["blue"];
+        assert_eq!(blue.material.alpha_mode, material::AlphaMode::Opaque);
+        //Pr wins over Ns even when Ns comes after it
+        assert_eq!(blue.material.roughness_factor, 0.25);
+        assert_eq!(blue.material.metallic_factor, 1.0);
+        assert_eq!(blue.normal_map.as_deref(), Some("normal.png"));
+        assert_eq!(blue.material.normal_scale, 0.5);
+        assert_eq!(blue.emissive_map.as_deref(), Some("glow.png"));
+        assert_eq!(blue.material.emissive_factor, [1.0, 1.0, 1.0]);
+        assert!(obj::parse_mtl("Kd 1 1 1").is_err());
+        assert!(obj::parse_mtl("newmtl empty\nmap_Kd -s 1 1 1").is_err());
+
+        assert_eq!(
+            obj::resolve_obj_path("resources/models/cube.obj", "cube.mtl"),
+            "resources/models/cube.mtl"
+        );
+        assert_eq!(
+            obj::resolve_obj_path("resources/models/cube.mtl", "textures\\wood.png"),
+            "resources/models/textures/wood.png"
+        );
+        assert_eq!(obj::resolve_obj_path("cube.obj", "cube.mtl"), "cube.mtl");
+    }
+
+    #[test]
+    fn ply_stl_import_tests() {
+        use model::{ply, stl};
+
+        //a colored quad, the face is a polygon and there is an element to skip
+        let ascii = "ply\r
+format ascii 1.0\r
+comment made by hand\r
+element vertex 4\r
+property float x\r
+property float y\r
+property float z\r
+property uchar red\r
+property uchar green\r
+property uchar blue\r
+element face 1\r
+property list uchar int vertex_indices\r
+element edge 1\r
+property int vertex1\r
+property int vertex2\r
+end_header\r
+0 0 0 255 0 0\r
+1 0 0 0 255 0\r
+1 1 0 0 0 255\r
+0 1 0 255 255 255\r
+4 0 1 2 3\r
+0 1\r
+";
+        let quad = ply::parse_ply(ascii.as_bytes()).unwrap();
+        quad.validate().unwrap();
+        assert_eq!(quad.topology, wgpu::PrimitiveTopology::TriangleList);
+        assert_eq!(quad.get_vertex_count(), 4);
+        assert_eq!(quad.indices.len(), 6);
+        assert_eq!(quad.colors[0], [1.0, 0.0, 0.0, 1.0]);
+        assert_eq!(quad.colors[3], [1.0, 1.0, 1.0, 1.0]);
+        //normals come from the faces when the file has none
+        assert!(quad.normals.iter().all(|n| *n == [0.0, 0.0, 1.0]));
+
+        //the same point cloud in both byte orders, with a srgb grey and alpha
+        let binary_cloud = |format: &str, big_endian: bool| {
+            let mut content = format!(
+                "ply\nformat {} 1.0\nelement vertex 2\nproperty float x\nproperty float y\n\
+                 property float z\nproperty ushort red\nproperty ushort green\n\
+                 property ushort blue\nproperty uchar alpha\nend_header\n",
+                format
+            )
+            .into_bytes();
+            for (position, color, alpha) in [
+                ([1.0f32, 2.0, 3.0], 0u16, 0u8),
+                ([-1.0, 0.5, 0.0], 65535, 255),
+            ]
+            .iter()
+            {
+                for value in position.iter() {
+                    if big_endian {
+                        content.extend_from_slice(&value.to_be_bytes());
+                    } else {
+                        content.extend_from_slice(&value.to_le_bytes());
+                    }
+                }
+                for _ in 0..3 {
+                    if big_endian {
+                        content.extend_from_slice(&color.to_be_bytes());
+                    } else {
+                        content.extend_from_slice(&color.to_le_bytes());
+                    }
+                }
+                content.push(*alpha);
+            }
+            content
+        };
+        let little = ply::parse_ply(&binary_cloud("binary_little_endian", false)[..]).unwrap();
+        let big = ply::parse_ply(&binary_cloud("binary_big_endian", true)[..]).unwrap();
+        assert_eq!(little, big);
+        assert_eq!(little.topology, wgpu::PrimitiveTopology::PointList);
+        assert!(little.indices.is_empty());
+        assert!(little.normals.is_empty());
+        assert_eq!(little.positions, vec![[1.0, 2.0, 3.0], [-1.0, 0.5, 0.0]]);
+        assert_eq!(
+            little.colors,
+            vec![[0.0, 0.0, 0.0, 0.0], [1.0, 1.0, 1.0, 1.0]]
+        );
+
+        //the point cloud pipeline gets the streams it needs from the mesh
+        let streams = bindings::get_vertex_stream_descriptions("position_color").unwrap();
+        let buffers = bindings::get_vertex_attrbibute_descriptor("position_color").unwrap();
+        assert_eq!(streams.len(), buffers.len());
+        let mut next_buffer = 0;
+        let mesh = little.create_mesh(|_| {
+            next_buffer += 1;
+            next_buffer - 1
+        });
+        assert_eq!(mesh.get_vertex_streams(&streams[..]).unwrap().len(), 2);
+        assert_eq!(
+            mesh.get_pipeline_variant().topology,
+            wgpu::PrimitiveTopology::PointList
+        );
+
+        assert!(ply::parse_ply(
+            b"ply\nformat ascii 1.0\nelement vertex 1\nproperty float x\nend_header\n0\n"
+        )
+        .is_err());
+        assert!(ply::parse_ply(b"ply\nformat ascii 1.0\nelement vertex 2\nproperty float x\nproperty float y\nproperty float z\nend_header\n0 0 0\n").is_err());
+        assert!(ply::parse_ply(b"format ascii 1.0\nend_header\n").is_err());
+        //face indices are checked before they are cast
+        for face in ["3 0 -1 2", "3 0 1.5 2"].iter() {
+            let content = ascii
+                .replace("list uchar int", "list uchar float")
+                .replace("4 0 1 2 3", face);
+            assert!(ply::parse_ply(content.as_bytes()).is_err());
+        }
+        let header = ply::parse_ply_header(ascii.as_bytes()).unwrap();
+        assert_eq!(header.format, ply::PlyFormat::Ascii);
+        assert_eq!(header.elements.len(), 3);
+        assert_eq!(
+            &ascii.as_bytes()[header.body_offset..header.body_offset + 5],
+            b"0 0 0"
+        );
+
+        //a facet without normal gets the one of its winding
+        let ascii_stl = "solid test
+  facet normal 0 0 1
+    outer loop
+      vertex 0 0 0
+      vertex 1 0 0
+      vertex 0 1 0
+    endloop
+  endfacet
+  facet normal 0 0 0
+    outer loop
+      vertex 0 0 0
+      vertex 0 1 0
+      vertex 1 0 0
+    endloop
+  endfacet
+endsolid test
+";
+        let triangles = stl::parse_stl(ascii_stl.as_bytes()).unwrap();
+        triangles.validate().unwrap();
+        assert_eq!(triangles.get_vertex_count(), 6);
+        assert!(triangles.indices.is_empty());
+        assert_eq!(triangles.normals[0], [0.0, 0.0, 1.0]);
+        assert_eq!(triangles.normals[5], [0.0, 0.0, -1.0]);
+
+        //the same triangles in binary, with a header starting with solid to be mean
+        let mut binary_stl = b"solid but binary".to_vec();
+        binary_stl.resize(80, 0);
+        binary_stl.extend_from_slice(&2u32.to_le_bytes());
+        for triangle in 0..2 {
+            let normal = if triangle == 0 { 1.0f32 } else { 0.0 };
+            let corners: [f32; 9] = if triangle == 0 {
+                [0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0]
+            } else {
+                [0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 1.0, 0.0, 0.0]
+            };
+            for value in [0.0, 0.0, normal].iter().chain(corners.iter()) {
+                binary_stl.extend_from_slice(&value.to_le_bytes());
+            }
+            binary_stl.extend_from_slice(&[0, 0]);
+        }
+        assert!(stl::is_binary_stl(&binary_stl[..]));
+        assert!(!stl::is_binary_stl(ascii_stl.as_bytes()));
+        let mut huge_count = binary_stl[..80].to_vec();
+        huge_count.extend_from_slice(&u32::MAX.to_le_bytes());
+        assert!(!stl::is_binary_stl(&huge_count[..]));
+        assert_eq!(stl::parse_stl(&binary_stl[..]).unwrap(), triangles);
+
+        let broken = ascii_stl.replace("      vertex 0 1 0\n    endloop", "    endloop");
+        assert!(stl::parse_stl(broken.as_bytes()).is_err());
+        assert!(stl::parse_stl(b"not an stl").is_err());
+    }
+
+    #[test]
+    fn model_data_import_tests() {
+        use model::{obj, processing};
+
+        //the whole import runs without a gpu
+        let cube = futures::executor::block_on(model::import_gltf_file(
+            "resources/cube/cube.gltf",
+            &Default::default(),
+        ))
+        .unwrap();
+        assert_eq!(cube.models.len(), 1);
+        assert_eq!(cube.scene_graph.nodes.len(), 2);
+        assert_eq!(cube.materials.len(), 1);
+        let mesh = &cube.models[0].meshes[0];
+        assert_eq!(mesh.topology, wgpu::PrimitiveTopology::TriangleList);
+        assert_eq!(mesh.get_vertex_count(), 24);
+        assert_eq!(mesh.normals.len(), 24);
+        assert!(mesh.uvs.is_empty() && mesh.streams.is_empty());
+        assert_eq!(mesh.indices.len(), 36);
+        assert_eq!(mesh.material, Some(0));
+        let aabb = cube.models[0].get_bounds().aabb;
+        assert_eq!(aabb.min, cgmath::Point3::new(-0.5, -0.5, -0.5));
+        assert_eq!(aabb.max, cgmath::Point3::new(0.5, 0.5, 0.5));
+        //the box comes from the min and max of the accessor, not from the vertices
+        let mut shrunk = mesh.clone();
+        shrunk.positions.truncate(3);
+        shrunk.normals.truncate(3);
+        shrunk.indices = vec![0, 1, 2];
+        assert_eq!(shrunk.get_bounds().aabb, aabb);
+        shrunk.bounds = None;
+        assert_ne!(shrunk.get_bounds().aabb, aabb);
+
+        //external images are only referenced, the color space comes from the material
+        let suzanne = futures::executor::block_on(model::import_gltf_file(
+            "resources/examples/gltf-model/Suzanne.gltf",
+            &Default::default(),
+        ))
+        .unwrap();
+        assert_eq!(suzanne.models[0].meshes[0].tangents.len(), 11808);
+        assert_eq!(suzanne.textures.len(), 2);
+        match &suzanne.textures[0].source {
+            model::TextureSource::File(path) => assert_eq!(
+                &path[..],
+                "resources/examples/gltf-model/Suzanne_BaseColor.png"
+            ),
+            model::TextureSource::Data(_) => panic!("the image is in its own file"),
+        }
+        assert_eq!(suzanne.textures[0].color_space, texture::ColorSpace::Srgb);
+        assert_eq!(suzanne.textures[1].color_space, texture::ColorSpace::Linear);
+
+        //a fan with 8 bit indices, skinning data and an image that cannot be decoded
+        let mut bytes: Vec<u8> = Vec::new();
+        let positions: [f32; 12] = [0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 1.0, 0.0, 0.0, 1.0, 0.0];
+        bytes.extend_from_slice(bytemuck::cast_slice(&positions[..]));
+        bytes.extend_from_slice(&[0, 1, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0]);
+        let weights: [f32; 16] = [
+            1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.5, 0.5, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0,
+        ];
+        bytes.extend_from_slice(bytemuck::cast_slice(&weights[..]));
+        bytes.extend_from_slice(&[0, 1, 2, 3]);
+        let json = serde_json::json!({
+            "asset": {"version": "2.0"},
+            "buffers": [{
+                "byteLength": bytes.len(),
+                "uri": format!("data:application/octet-stream;base64,{}", base64::encode(&bytes))
+            }],
+            "bufferViews": [
+                {"buffer": 0, "byteOffset": 0, "byteLength": 48},
+                {"buffer": 0, "byteOffset": 48, "byteLength": 16},
+                {"buffer": 0, "byteOffset": 64, "byteLength": 64},
+                {"buffer": 0, "byteOffset": 128, "byteLength": 4}
+            ],
+            "accessors": [
+                {
+                    "bufferView": 0, "componentType": 5126, "count": 4, "type": "VEC3",
+                    "min": [0.0, 0.0, 0.0], "max": [1.0, 1.0, 0.0]
+                },
+                {"bufferView": 1, "componentType": 5121, "count": 4, "type": "VEC4"},
+                {"bufferView": 2, "componentType": 5126, "count": 4, "type": "VEC4"},
+                {"bufferView": 3, "componentType": 5121, "count": 4, "type": "SCALAR"}
+            ],
+            "images": [{"uri": "data:image/png;base64,AAAA"}],
+            "textures": [{"source": 0}],
+            "meshes": [{"primitives": [{
+                "attributes": {"POSITION": 0, "JOINTS_0": 1, "WEIGHTS_0": 2},
+                "indices": 3,
+                "mode": 6
+            }]}],
+            "nodes": [{"mesh": 0}],
+            "scenes": [{"nodes": [0]}]
+        });
+        let import = |json: &serde_json::Value| {
+            let content = serde_json::to_vec(json).unwrap();
+            let mut gltf = gltf::Gltf::from_slice(&content[..]).unwrap();
+            let raw_buffers =
+                futures::executor::block_on(model::load_gltf_buffers(&mut gltf, "fan.gltf"))
+                    .unwrap();
+            model::import_gltf_document(&gltf, &raw_buffers, "fan.gltf", &Default::default())
+                .unwrap()
+        };
+        let file_data = import(&json);
+        match &file_data.textures[0].source {
+            model::TextureSource::Data(data) => assert_eq!((data.width, data.height), (1, 1)),
+            model::TextureSource::File(_) => panic!("the image is embedded"),
+        }
+        let mesh = &file_data.models[0].meshes[0];
+        assert_eq!(mesh.topology, wgpu::PrimitiveTopology::TriangleList);
+        assert_eq!(mesh.indices, vec![0, 1, 2, 0, 2, 3]);
+        assert_eq!(mesh.material, None);
+        assert_eq!(mesh.streams.len(), 2);
+        let joints = mesh
+            .streams
+            .iter()
+            .find(|s| s.semantic == model::MeshBufferSemantic::Joints(0))
+            .unwrap();
+        assert_eq!(joints.format, wgpu::VertexFormat::Uchar4);
+        assert_eq!(joints.get_vertex(2), &[1, 0, 0, 0]);
+        let weights_stream = mesh
+            .streams
+            .iter()
+            .find(|s| s.semantic == model::MeshBufferSemantic::Weights(0))
+            .unwrap();
+        assert_eq!(weights_stream.format, wgpu::VertexFormat::Float4);
+        assert_eq!(weights_stream.data.len(), 64);
+
+        //every stream gets its own buffer, the indices come last
+        let mut buffers = Vec::new();
+        let gpu_mesh = mesh.create_mesh(|data| {
+            buffers.push(data);
+            buffers.len() as u32 - 1
+        });
+        assert_eq!(gpu_mesh.buffers.len(), 3);
+        assert_eq!(
+            gpu_mesh.buffers[1].semantic,
+            model::MeshBufferSemantic::Joints(0)
+        );
+        assert_eq!(buffers[1].len(), 16);
+        let index_buffer = gpu_mesh.index_buffer.as_ref().unwrap();
+        assert_eq!(index_buffer.format, wgpu::IndexFormat::Uint16);
+        assert_eq!(index_buffer.count, 6);
+
+        //a primitive whose indices cannot be read is left out instead of drawn without
+        let mut broken_json = json.clone();
+        broken_json["meshes"][0]["primitives"][0]["indices"] = serde_json::json!(1);
+        assert!(import(&broken_json).models[0].meshes.is_empty());
+
+        //streams are part of what makes two vertices the same
+        let mut mesh = model::MeshData {
+            positions: vec![[0.0; 3], [0.0; 3], [0.0; 3]],
+            streams: vec![model::VertexStreamData {
+                semantic: model::MeshBufferSemantic::Joints(0),
+                format: wgpu::VertexFormat::Uchar4,
+                data: vec![0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0],
+            }],
+            ..Default::default()
+        };
+        processing::weld_vertices(&mut mesh);
+        assert_eq!(mesh.get_vertex_count(), 2);
+        assert_eq!(mesh.indices, vec![0, 1, 0]);
+        assert_eq!(mesh.streams[0].data, vec![0, 0, 0, 0, 1, 0, 0, 0]);
+        mesh.streams[0].data.pop();
+        assert!(mesh.validate().is_err());
+
+        //obj materials are resolved to indices and their maps to texture files
+        let obj_data = obj::parse_obj("v 0 0 0\nv 1 0 0\nv 0 1 0\nusemtl red\nf 1 2 3\n").unwrap();
+        let library = obj::parse_mtl("newmtl red\nKd 1 0 0\nmap_Kd red.png\n").unwrap();
+        let file_data = obj::create_obj_file_data(
+            "models/triangle.obj",
+            obj_data,
+            &[(String::from("models/materials/red.mtl"), library)],
+            &Default::default(),
+        );
+        assert_eq!(file_data.materials.len(), 1);
+        assert_eq!(file_data.models[0].meshes[0].material, Some(0));
+        assert_eq!(file_data.textures[0].color_space, texture::ColorSpace::Srgb);
+        match &file_data.textures[0].source {
+            model::TextureSource::File(path) => {
+                assert_eq!(&path[..], "models/materials/red.png")
+            }
+            model::TextureSource::Data(_) => panic!("obj textures are files"),
+        }
+        assert_eq!(file_data.scene_graph.scenes[0].roots, vec![0]);
+    }
+
+    #[test]
+    fn mesh_file_tests() {
+        use model::mesh_file;
+
+        let files = futures::executor::block_on(mesh_file::convert_gltf_file(
+            "resources/examples/gltf-model/Suzanne.gltf",
+            &Default::default(),
+        ))
+        .unwrap();
+        assert_eq!(files.len(), 1);
+        let content = &files[0];
+        assert_eq!(&content[0..4], b"MESH");
+        assert_eq!(content.len() % 4, 0);
+
+        //the streams in the file are the ones create_mesh lays out
+        let file_data = futures::executor::block_on(model::import_gltf_file(
+            "resources/examples/gltf-model/Suzanne.gltf",
+            &Default::default(),
+        ))
+        .unwrap();
+        let mesh_data = &file_data.models[0].meshes[0];
+        let mut buffers = Vec::new();
+        let expected = mesh_data.create_mesh(|data| {
+            buffers.push(data);
+            buffers.len() as u32 - 1
+        });
+        let mesh_file = mesh_file::read_mesh_file(&content[..], 7).unwrap();
+        assert_eq!(
+            mesh_file.material_names,
+            vec![file_data.materials[0].name.clone()]
+        );
+        assert_eq!(mesh_file.model.meshes.len(), 1);
+        let mesh = &mesh_file.model.meshes[0];
+        assert_eq!(mesh.vertex_count, 11808);
+        assert_eq!(mesh.material, Some(0));
+        assert_eq!(mesh.bounds, expected.bounds);
+        assert_eq!(mesh.buffers.len(), expected.buffers.len());
+        for (buffer, expected_buffer) in mesh.buffers.iter().zip(expected.buffers.iter()) {
+            assert_eq!(buffer.semantic, expected_buffer.semantic);
+            assert_eq!(buffer.format, expected_buffer.format);
+            assert_eq!(buffer.buffer_idx, 7);
+            assert_eq!(buffer.offset % 4, 0);
+            let range = buffer.offset as usize..(buffer.offset + buffer.length) as usize;
+            assert_eq!(
+                &content[range],
+                &buffers[expected_buffer.buffer_idx as usize][..]
+            );
+        }
+        let index_buffer = mesh.index_buffer.as_ref().unwrap();
+        assert_eq!(index_buffer.format, wgpu::IndexFormat::Uint16);
+        assert_eq!(index_buffer.count, 11808);
+        let range =
+            index_buffer.offset as usize..(index_buffer.offset + index_buffer.length) as usize;
+        assert_eq!(
+            &content[range],
+            &buffers[expected.index_buffer.as_ref().unwrap().buffer_idx as usize][..]
+        );
+
+        //unnamed materials, meshes without indices and the checks of the reader
+        let model_data = model::ModelData {
+            meshes: vec![model::MeshData {
+                topology: wgpu::PrimitiveTopology::PointList,
+                positions: vec![[0.0, 0.0, 0.0], [1.0, 2.0, 3.0]],
+                colors: vec![[1.0, 0.0, 0.0, 1.0]; 2],
+                material: Some(1),
+                ..Default::default()
+            }],
+        };
+        let names = [Some(String::from("é")), None];
+        let content = mesh_file::write_mesh_file(&model_data, &names[..]).unwrap();
+        let mesh_file = mesh_file::read_mesh_file(&content[..], 0).unwrap();
+        assert_eq!(mesh_file.material_names, names.to_vec());
+        let mesh = &mesh_file.model.meshes[0];
+        assert_eq!(mesh.topology, wgpu::PrimitiveTopology::PointList);
+        assert_eq!(mesh.material, Some(1));
+        assert!(mesh.index_buffer.is_none());
+        assert_eq!(
+            mesh.buffers[1].semantic,
+            model::MeshBufferSemantic::Colors(0)
+        );
+        assert_eq!(mesh.bounds.aabb.max, cgmath::Point3::new(1.0, 2.0, 3.0));
+
+        assert!(mesh_file::write_mesh_file(&model_data, &names[..1]).is_err());
+        assert!(mesh_file::read_mesh_file(&content[..content.len() - 4], 0).is_err());
+        let mut wrong_version = content.clone();
+        wrong_version[4] = 2;
+        assert!(mesh_file::read_mesh_file(&wrong_version[..], 0).is_err());
+        //a stream pointing past the end of the file
+        let mut broken = content.clone();
+        let stream_table = 20 + 2 * 8 + 76;
+        broken[stream_table + 16..stream_table + 20].copy_from_slice(&4096u32.to_le_bytes());
+        assert!(mesh_file::read_mesh_file(&broken[..], 0).is_err());
+        //counts too large for the file are refused before the tables are read
+        let mut broken = content.clone();
+        broken[8..12].copy_from_slice(&0x1000_0000u32.to_le_bytes());
+        assert!(mesh_file::read_mesh_file(&broken[..], 0).is_err());
+        let mut broken = content.clone();
+        broken[12..16].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(mesh_file::read_mesh_file(&broken[..], 0).is_err());
+        //streams of a mesh past the end of the stream table
+        let mut broken = content.clone();
+        let mesh_record = 20 + 2 * 8;
+        broken[mesh_record + 12..mesh_record + 16].copy_from_slice(&0x1000_0000u32.to_le_bytes());
+        assert!(mesh_file::read_mesh_file(&broken[..], 0).is_err());
+        //indices past the vertices of the mesh
+        let indexed = model::ModelData {
+            meshes: vec![model::MeshData {
+                positions: vec![[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]],
+                indices: vec![0, 1, 2],
+                ..Default::default()
+            }],
+        };
+        let content = mesh_file::write_mesh_file(&indexed, &[]).unwrap();
+        let mesh_file = mesh_file::read_mesh_file(&content[..], 0).unwrap();
+        let index_buffer = mesh_file.model.meshes[0].index_buffer.as_ref().unwrap();
+        assert_eq!(index_buffer.count, 3);
+        let mut broken = content.clone();
+        let last_index = index_buffer.offset as usize + 4;
+        broken[last_index..last_index + 2].copy_from_slice(&3u16.to_le_bytes());
+        assert!(mesh_file::read_mesh_file(&broken[..], 0).is_err());
+        let mut morphed = model_data;
+        morphed.meshes[0].morph_targets = Some(Default::default());
+        assert!(mesh_file::write_mesh_file(&morphed, &names[..]).is_err());
+    }
+
+    #[test]
+    fn mesh_simplification_tests() {
+        use cgmath::InnerSpace;
+        use model::{procedural, processing, simplify};
+
+        //a flat grid loses its inner vertices without moving away from the plane, the
+        //border is left alone
+        let grid = procedural::grid(2.0, 2.0, 8, 8);
+        let (simplified, error) = simplify::simplify_mesh(&grid, 16).unwrap();
+        assert!(simplified.indices.len() / 3 < grid.indices.len() / 3);
+        assert!(error < 1e-4);
+        assert!(simplified.validate().is_ok());
+        for position in grid.positions.iter() {
+            let on_border = position[0].abs() == 1.0 || position[2].abs() == 1.0;
+            assert_eq!(simplified.positions.contains(position), on_border);
+        }
+        for triangle in simplified.indices.chunks(3) {
+            let p = |i: usize| cgmath::Vector3::from(simplified.positions[triangle[i] as usize]);
+            //counter clockwise seen from above, nothing flipped
+            assert!((p(2) - p(0)).cross(p(1) - p(0)).y < 0.0);
+        }
+
+        //the seam of a sphere keeps all of its vertices with their uvs
+        let sphere = procedural::uv_sphere(1.0, 32, 16);
+        let (simplified, error) = simplify::simplify_mesh(&sphere, 200).unwrap();
+        assert!(simplified.indices.len() / 3 < sphere.indices.len() / 3);
+        assert!(error > 0.0 && error < 0.5);
+        for (position, uv) in sphere.positions.iter().zip(sphere.uvs.iter()) {
+            //the poles have a vertex per segment, not all of them used
+            if (uv[0] == 0.0 || uv[0] == 1.0) && position[1].abs() < 1.0 {
+                assert!(simplified
+                    .positions
+                    .iter()
+                    .zip(simplified.uvs.iter())
+                    .any(|(p, u)| p == position && u == uv));
+            }
+        }
+        //the tangents follow the vertices they belong to
+        assert_eq!(simplified.tangents.len(), simplified.positions.len());
+
+        //only triangle lists
+        let mut points = sphere.clone();
+        points.topology = wgpu::PrimitiveTopology::PointList;
+        assert!(simplify::simplify_mesh(&points, 10).is_err());
+
+        //degenerate triangles list a vertex twice, it must not collapse onto itself and
+        //take the whole fan around it. Two of them keep every edge used twice so none of
+        //their vertices is locked, the grid must keep its area
+        let mut degenerate = grid.clone();
+        degenerate.indices.extend_from_slice(&[40, 40, 20, 40, 40, 60]);
+        let (simplified, _) = simplify::simplify_mesh(&degenerate, 16).unwrap();
+        assert!(simplified.validate().is_ok());
+        let area: f32 = simplified
+            .indices
+            .chunks(3)
+            .map(|triangle| {
+                let p = |i: usize| {
+                    cgmath::Vector3::from(simplified.positions[triangle[i] as usize])
+                };
+                (p(1) - p(0)).cross(p(2) - p(0)).magnitude() / 2.0
+            })
+            .sum();
+        assert!((area - 4.0).abs() < 1e-4);
+
+        //suzanne is stored with a tangent per corner, without them the corners weld
+        //back into a smooth mesh the simplifier can work on
+        let file_data = futures::executor::block_on(model::import_gltf_file(
+            "resources/examples/gltf-model/Suzanne.gltf",
+            &Default::default(),
+        ))
+        .unwrap();
+        let mut suzanne = file_data.models[0].meshes[0].clone();
+        suzanne.tangents.clear();
+        processing::weld_vertices(&mut suzanne);
+        assert_eq!(suzanne.get_vertex_count(), 2012);
+        let triangle_count = suzanne.indices.len() / 3;
+        let lods = simplify::build_lod_chain(&suzanne, &[0.5, 0.25]).unwrap();
+        assert_eq!(lods.len(), 3);
+        assert_eq!(lods[0].mesh, suzanne);
+        assert_eq!(lods[0].error, 0.0);
+        assert!(lods[1].mesh.indices.len() / 3 <= triangle_count / 2 + 1);
+        assert!(lods[2].mesh.indices.len() < lods[1].mesh.indices.len());
+        assert!(lods[1].error > 0.0 && lods[2].error > lods[1].error);
+        assert!(lods[2].error < 0.1);
+        assert!(simplify::build_lod_chain(&suzanne, &[1.5]).is_err());
+
+        //far away the coarsest level is enough, up close the full detail one is needed
+        let mut camera = graphics::camera::Camera {
+            eye: (0.0, 0.0, 10.0).into(),
+            target: (0.0, 0.0, 0.0).into(),
+            up: cgmath::Vector3::unit_y(),
+            aspect: 1.0,
+            fovy: 90.0,
+            znear: 0.1,
+            zfar: 1000.0,
+        };
+        let sphere_bounds = bounds::BoundingSphere {
+            center: cgmath::Point3::new(0.0, 0.0, 0.0),
+            radius: 1.0,
+        };
+        //the tangent of the angular radius over the one of the half field of view
+        let radius = camera.get_projected_radius(&sphere_bounds, 1000.0);
+        assert!((radius - (1.0f32 / 10.0).asin().tan() * 500.0).abs() < 1e-3);
+        camera.eye = (0.0, 0.0, 0.5).into();
+        assert_eq!(
+            camera.get_projected_radius(&sphere_bounds, 1000.0),
+            f32::INFINITY
+        );
+        assert_eq!(
+            simplify::select_lod(&lods, &sphere_bounds, &camera, 1000.0, 1.0),
+            0
+        );
+        camera.eye = (0.0, 0.0, 900.0).into();
+        assert_eq!(
+            simplify::select_lod(&lods, &sphere_bounds, &camera, 1000.0, 1.0),
+            2
+        );
+        //somewhere in between the middle level is picked, the error in pixels is the
+        //relative error times about 500 over the distance
+        let distance_for = |error: f32| 500.0 * error;
+        camera.eye = (
+            0.0,
+            0.0,
+            (distance_for(lods[1].error) + distance_for(lods[2].error)) * 0.5,
+        )
+            .into();
+        assert_eq!(
+            simplify::select_lod(&lods, &sphere_bounds, &camera, 1000.0, 1.0),
+            1
+        );
+    }
 }
@@ -7,14 +7,20 @@ use rust_sandbox::engine::platform;
 
 pub struct Sandbox {
     engine_runtime: platform::EngineRuntime,
-    render_pipeline_handle: handle::ResourceHandle,
+    //one pipeline per topology and index format used by the meshes of the file
+    render_pipeline_handles: Vec<(graphics::bindings::PipelineVariant, handle::ResourceHandle)>,
     camera: graphics::camera::Camera,
-    uniform_buffer: wgpu::Buffer,
-    uniform_bind_group: wgpu::BindGroup,
+    uniform_ring: graphics::uniform::UniformRingBuffer,
+    frame_data_offset: wgpu::DynamicOffset,
+    //one per mesh instance of the scene, in get_mesh_instances order
+    object_data_offsets: Vec<wgpu::DynamicOffset>,
+    uniform_bind_group_handle: handle::ResourceHandle,
     size: winit::dpi::PhysicalSize<u32>,
     color: f64,
     camera_controller: graphics::camera::CameraControllerFPS,
     per_frame_data: graphics::FrameData,
+    //refilled for every mesh instance before it is pushed in the ring
+    object_data: graphics::ObjectData,
     time_stamp: u64,
     delta_time: u64,
     gltf_file: graphics::model::GltfFile,
@@ -48,58 +54,94 @@ impl platform::Application for Sandbox {
         let mut per_frame_data = graphics::FrameData::new();
         per_frame_data.update_view_proj(&camera);
 
-        let uniform_buffer = gpu_interfaces.device.create_buffer_with_data(
-            bytemuck::cast_slice(&[per_frame_data]),
-            wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
+        let gltf_file = graphics::model::load_gltf_file(
+            "resources/examples/gltf-model/Suzanne.gltf",
+            &gpu_interfaces,
+            &mut engine_runtime.resource_managers.upload_manager,
+            &mut engine_runtime.resource_managers.texture_manager,
+            &graphics::model::processing::MeshProcessingOptions {
+                weld_vertices: true,
+                generate_tangents: true,
+                optimize_vertex_cache: true,
+                optimize_overdraw: true,
+            },
+        )
+        .await
+        .unwrap();
+
+        //per frame and per object uniforms are sub-allocated from this ring, three frames
+        //in flight. A frame holds the frame data and the object data of every mesh
+        //instance of the scene, and never less than 64KB
+        let align = |size: u64| {
+            graphics::uniform::align_up(size, graphics::uniform::UNIFORM_OFFSET_ALIGNMENT)
+        };
+        let scene_graph = &gltf_file.scene_graph;
+        let instance_count = match scene_graph.get_active_scene() {
+            Some(scene) => scene_graph.get_mesh_instances(scene).len() as u64,
+            None => 0,
+        };
+        let frame_size = align(per_frame_data.as_bytes().len() as u64)
+            + instance_count * align(graphics::ObjectData::get_layout().size as u64);
+        let uniform_ring = graphics::uniform::UniformRingBuffer::new(
+            &gpu_interfaces.device,
+            frame_size.max(64 * 1024),
+            3,
+            "sandbox_uniform_ring",
         );
 
         let layout_handle = engine_runtime
             .resource_managers
             .pipeline_manager
-            .load_binding_group("resources/examples/gltf-model/gltf_model.bg", gpu_interfaces)
+            .load_binding_group("resources/scene.bg", gpu_interfaces)
             .await;
 
         let default_depth_format = wgpu::TextureFormat::Depth32Float;
 
-        let render_pipeline_handle = engine_runtime
-            .resource_managers
-            .pipeline_manager
-            .load_pipeline(
-                "resources/examples/gltf-model/gltf_model.pipeline",
-                &mut engine_runtime.resource_managers.shader_manager,
-                &engine_runtime.gpu_interfaces,
-                default_depth_format,
-            )
-            .await;
-
         platform::core::to_console("NEW3!");
 
-        let bg_layout = engine_runtime
+        let uniform_bind_group_handle = engine_runtime
             .resource_managers
-            .pipeline_manager
-            .get_bind_group_from_handle(layout_handle);
-
-        let uniform_bind_group =
-            gpu_interfaces
-                .device
-                .create_bind_group(&wgpu::BindGroupDescriptor {
-                    layout: &bg_layout.unwrap(),
-                    bindings: &[wgpu::Binding {
-                        binding: 0,
-                        resource: wgpu::BindingResource::Buffer {
-                            buffer: &uniform_buffer,
-                            // FYI: you can share a single buffer between bindings.
-                            range: 0..std::mem::size_of_val(&per_frame_data) as wgpu::BufferAddress,
+            .bind_group_manager
+            .create_bind_group(
+                "uniform_bind_group",
+                &layout_handle,
+                &[
+                    graphics::bind_group::BindGroupEntry {
+                        slot: graphics::bind_group::BindingSlot::Name("frame_data"),
+                        resource: graphics::bind_group::BindGroupResource::Buffer {
+                            buffer: uniform_ring.buffer(),
+                            range: 0..per_frame_data.as_bytes().len() as wgpu::BufferAddress,
                         },
-                    }],
-                    label: Some("uniform_bind_group"),
-                });
+                    },
+                    graphics::bind_group::BindGroupEntry {
+                        slot: graphics::bind_group::BindingSlot::Name("object_data"),
+                        resource: graphics::bind_group::BindGroupResource::Buffer {
+                            buffer: uniform_ring.buffer(),
+                            range: 0..graphics::ObjectData::get_layout().size
+                                as wgpu::BufferAddress,
+                        },
+                    },
+                ],
+                &engine_runtime.resource_managers.pipeline_manager,
+                &engine_runtime.gpu_interfaces,
+            )
+            .unwrap();
 
-        let gltf_file = graphics::model::load_gltf_file(
-            "resources/examples/gltf-model/Suzanne.gltf",
-            &gpu_interfaces,
-        )
-        .await;
+        let mut render_pipeline_handles = Vec::new();
+        for variant in gltf_file.get_pipeline_variants() {
+            let pipeline_handle = engine_runtime
+                .resource_managers
+                .pipeline_manager
+                .load_pipeline_variant(
+                    "resources/scene.pipeline",
+                    variant,
+                    &mut engine_runtime.resource_managers.shader_manager,
+                    &engine_runtime.gpu_interfaces,
+                    default_depth_format,
+                )
+                .await;
+            render_pipeline_handles.push((variant, pipeline_handle));
+        }
 
         let depth_texture = graphics::texture::Texture::create_depth_texture(
             &engine_runtime.gpu_interfaces.device,
@@ -109,14 +151,17 @@ impl platform::Application for Sandbox {
 
         Self {
             engine_runtime,
-            render_pipeline_handle,
+            render_pipeline_handles,
             camera,
-            uniform_buffer,
-            uniform_bind_group,
+            uniform_ring,
+            frame_data_offset: 0,
+            object_data_offsets: Vec::new(),
+            uniform_bind_group_handle,
             size,
             color,
             camera_controller,
             per_frame_data,
+            object_data: graphics::ObjectData::new(cgmath::Matrix4::from_scale(1.0)),
             time_stamp: platform::core::get_time_in_micro(),
             delta_time: 0,
             gltf_file,
@@ -145,6 +190,28 @@ impl platform::Application for Sandbox {
             .update_camera(&mut self.camera, self.delta_time);
         self.per_frame_data.update_view_proj(&self.camera);
 
+        self.uniform_ring.begin_frame();
+        self.frame_data_offset = self
+            .uniform_ring
+            .push_bytes(self.per_frame_data.as_bytes())
+            .unwrap();
+
+        //nothing is animated yet, but the world matrices are pushed every frame anyway
+        //so the render loop does not care where they come from
+        let scene_graph = &self.gltf_file.scene_graph;
+        self.object_data_offsets.clear();
+        if let Some(scene) = scene_graph.get_active_scene() {
+            for instance in scene_graph.get_mesh_instances(scene) {
+                let world = scene_graph.nodes[instance.node].world_matrix;
+                self.object_data.set_world(world);
+                let offset = self
+                    .uniform_ring
+                    .push_bytes(self.object_data.as_bytes())
+                    .unwrap();
+                self.object_data_offsets.push(offset);
+            }
+        }
+
         let mut encoder = self
             .engine_runtime
             .gpu_interfaces
@@ -153,22 +220,11 @@ impl platform::Application for Sandbox {
                 label: Some("update encoder"),
             });
 
-        let staging_buffer = self
-            .engine_runtime
-            .gpu_interfaces
-            .device
-            .create_buffer_with_data(
-                bytemuck::cast_slice(&[self.per_frame_data]),
-                wgpu::BufferUsage::COPY_SRC,
-            );
-
-        encoder.copy_buffer_to_buffer(
-            &staging_buffer,
-            0,
-            &self.uniform_buffer,
-            0,
-            std::mem::size_of::<graphics::FrameData>() as wgpu::BufferAddress,
-        );
+        let upload_manager = &mut self.engine_runtime.resource_managers.upload_manager;
+        upload_manager.begin_frame();
+        let mut uploads = upload_manager.begin_batch();
+        self.uniform_ring.end_frame(&mut uploads);
+        uploads.flush(&self.engine_runtime.gpu_interfaces.device, &mut encoder);
 
         command_buffers.push(encoder.finish());
     }
@@ -217,40 +273,45 @@ impl platform::Application for Sandbox {
                 depth_stencil_attachment,
             });
 
-            let render_pipeline = self
+            let uniform_bind_group = self
                 .engine_runtime
                 .resource_managers
-                .pipeline_manager
-                .get_pipeline_from_handle(&self.render_pipeline_handle);
-            render_pass.set_pipeline(&render_pipeline.unwrap());
-            render_pass.set_bind_group(0, &self.uniform_bind_group, &[]);
-
-            let model = self.gltf_file.models.get(0).unwrap();
-            let mesh = model.meshes.get(0).unwrap();
-            let pos_mapper =
-                mesh.get_buffer_from_semantic(graphics::model::MeshBufferSemantic::Positions);
-            let pos_idx = pos_mapper.buffer_idx;
-            let pos_buff = self.gltf_file.buffers.get(&pos_idx).unwrap();
-
-            let n_mapper =
-                mesh.get_buffer_from_semantic(graphics::model::MeshBufferSemantic::Normals);
-            let n_idx = n_mapper.buffer_idx;
-            let n_buff = self.gltf_file.buffers.get(&n_idx).unwrap();
-
-            let mut idx_count = 0;
-            match &mesh.index_buffer {
-                Some(idx_buff_map) => {
-                    let idx = idx_buff_map.buffer_idx;
-                    let idx_buff = self.gltf_file.buffers.get(&idx).unwrap();
-                    render_pass.set_index_buffer(idx_buff, idx_buff_map.offset as u64, 0);
-                    idx_count = idx_buff_map.count;
+                .bind_group_manager
+                .get_bind_group_from_handle(&self.uniform_bind_group_handle)
+                .unwrap();
+
+            let scene_graph = &self.gltf_file.scene_graph;
+            let instances = match scene_graph.get_active_scene() {
+                Some(scene) => scene_graph.get_mesh_instances(scene),
+                None => Vec::new(),
+            };
+            for (instance, object_offset) in instances.iter().zip(self.object_data_offsets.iter())
+            {
+                render_pass.set_bind_group(
+                    0,
+                    uniform_bind_group,
+                    &[self.frame_data_offset, *object_offset],
+                );
+
+                for (variant, pipeline_handle) in self.render_pipeline_handles.iter() {
+                    let pipeline_manager = &self.engine_runtime.resource_managers.pipeline_manager;
+                    let render_pipeline = pipeline_manager
+                        .get_pipeline_from_handle(pipeline_handle)
+                        .unwrap();
+                    let vertex_streams = pipeline_manager
+                        .get_pipeline_vertex_streams_from_handle(pipeline_handle)
+                        .unwrap();
+                    render_pass.set_pipeline(render_pipeline);
+                    if let Err(message) = self.gltf_file.draw_model(
+                        instance.model,
+                        *variant,
+                        &mut render_pass,
+                        vertex_streams,
+                    ) {
+                        platform::core::to_console(&message[..]);
+                    }
                 }
-                None => {}
             }
-
-            render_pass.set_vertex_buffer(0, pos_buff, pos_mapper.offset as u64, 0);
-            render_pass.set_vertex_buffer(1, n_buff, n_mapper.offset as u64, 0);
-            render_pass.draw_indexed(0..idx_count, 0, 0..1);
         }
 
         self.color += 0.001;